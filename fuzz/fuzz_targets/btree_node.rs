@@ -0,0 +1,19 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use marmelade::{
+    serialization::SerialReadStorage,
+    filesys::hfs::fuzzing::{
+        BTreeNode,
+        BTreeHeaderNode,
+        BTreeLeafNode,
+        CatKeyRec,
+        CatDataRec
+    }
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = BTreeNode::new(&mut SerialReadStorage::from(data.to_vec()));
+    let _ = BTreeHeaderNode::new(&mut SerialReadStorage::from(data.to_vec()));
+    let _ = BTreeLeafNode::<CatKeyRec, CatDataRec>::new(&mut SerialReadStorage::from(data.to_vec()));
+});
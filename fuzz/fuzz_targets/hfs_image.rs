@@ -0,0 +1,44 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use marmelade::{
+    serialization::SerialAdaptor,
+    filesys::hfs::{
+        HfsImage,
+        HfsObjRef,
+        HfsDirIter
+    }
+};
+
+use std::io::{Cursor, Read};
+
+// Corrupt catalogs may contain directories that are their own parents, so
+// limit depth instead of following them forever
+const MAX_DEPTH : usize = 32;
+
+fn walk(dir: HfsDirIter, depth: usize) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    for obj in dir {
+        let _ = obj.get_name();
+        match obj {
+            HfsObjRef::FileRef(file) => {
+                let _ = file.get_size();
+                let mut content = vec![];
+                let _ = file.open().read_to_end(&mut content);
+                let mut content = vec![];
+                let _ = file.open_rsrc().read_to_end(&mut content);
+            },
+            HfsObjRef::DirRef(dir) => {
+                walk(dir.open(), depth + 1);
+            }
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(img) = HfsImage::from(SerialAdaptor::new(Cursor::new(data.to_vec()))) {
+        walk(img.open_root(), 0);
+    }
+});
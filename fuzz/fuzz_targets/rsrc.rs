@@ -0,0 +1,23 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use marmelade::{
+    serialization::SerialAdaptor,
+    filesys::rsrc::Rsrc
+};
+
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(rsrc) = Rsrc::new(SerialAdaptor::new(Cursor::new(data.to_vec()))) {
+        for rsrc_type in rsrc.types() {
+            for info in rsrc.resources(rsrc_type) {
+                let _ = info.size();
+                let _ = info.open();
+                if let Some(name) = info.name() {
+                    let _ = rsrc.get_named(rsrc_type, &name.to_string());
+                }
+            }
+        }
+    }
+});
@@ -0,0 +1,28 @@
+#!/bin/sh
+# Seed the fuzzing corpus from the reference disk image in ref/, instead of
+# keeping copies of the image and the applications on it in the repository.
+# Run from the fuzz directory: ./seed-corpus.sh && cargo fuzz run rsrc
+set -e
+
+IMAGE=../ref/refdisk.dmg
+
+# Extract COUNT bytes at byte OFFSET of the image
+extract() {
+    tail -c +$(($1 + 1)) "$IMAGE" | head -c "$2" > "$3"
+}
+
+mkdir -p corpus/hfs_image corpus/btree_node corpus/rsrc
+
+cp "$IMAGE" corpus/hfs_image/refdisk.dmg
+
+# The catalog file, one 512 byte node per seed
+for node in 0 1 2 3 4 5 6 7; do
+    extract $((10240 + node * 512)) 512 corpus/btree_node/catalog-$node
+done
+
+# Resource forks of files on the image
+extract 90112 120392 corpus/rsrc/SimpleText
+extract 210944 414 corpus/rsrc/OpenFolderListDF_
+extract 211456 332 corpus/rsrc/file
+extract 212480 332 corpus/rsrc/another_file
+extract 213504 19045 corpus/rsrc/TeachText
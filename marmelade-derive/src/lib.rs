@@ -1,79 +1,200 @@
 extern crate proc_macro;
 extern crate syn;
+extern crate proc_macro2;
 #[macro_use]
 extern crate quote;
 
 use proc_macro::TokenStream;
-use syn::export::TokenStream2;
+use syn::export::{Span, TokenStream2};
+
+// Attributes that controls how a collection is read, rather than being a
+// modifier on the reader, and are therefore not passed on to the reader
+const COLLECTION_ATTRS : [&str; 5] = [
+    "count",
+    "count_minus_one",
+    "count_prefix",
+    "count_prefix_minus_one",
+    "seek"
+];
+
+fn attr_name(attr: &syn::Attribute) -> String {
+    attr.path.segments.iter()
+        .map(|seg| seg.ident.to_string())
+        .collect::<Vec<String>>()
+        .join("::")
+}
+
+fn find_attr<'a>(attrs: &'a Vec<syn::Attribute>, name: &str) -> Option<&'a syn::Attribute> {
+    attrs.iter().find(|attr| attr_name(attr) == name)
+}
+
+// Arguments of an attribute, without the surrounding parenthesis
+fn attr_args(attr: &syn::Attribute) -> TokenStream2 {
+    let mut toks = attr.tts.clone().into_iter();
+    match (toks.next(), toks.next()) {
+        (Some(proc_macro2::TokenTree::Group(group)), None) => group.stream(),
+        _ => attr.tts.clone()
+    }
+}
 
 fn format_attrs(attrs: &Vec<syn::Attribute>) -> TokenStream2 {
     let mut attr_mod = quote!{};
     for attr in attrs {
-        let attr_name = &attr.parse_meta().unwrap().name();
+        if COLLECTION_ATTRS.contains(&attr_name(attr).as_str()) {
+            continue;
+        }
+        let attr_name = &attr.path;
         let attr_toks = &attr.tts;
 
         attr_mod.extend( quote!{
-            .#attr_name #attr_toks
+            .#attr_name #attr_toks?
         });
     }
     attr_mod
 }
 
-fn reader_for_field(f : &syn::Field) -> TokenStream2 {
+// The seek attribute is evaluated before any other modifiers, so that a
+// field can be located at an offset given by a previously read field.
+fn seek_for_field(f : &syn::Field) -> TokenStream2 {
+    if let Some(attr) = find_attr(&f.attrs, "seek") {
+        let offset = attr_args(attr);
+        quote!{ rdr.seek((#offset) as u64)?; }
+    } else {
+        quote!{}
+    }
+}
+
+// Returns an expression evaluating to the number of elements in a
+// collection, if the field has a count attribute
+fn count_for_field(f : &syn::Field) -> Option<TokenStream2> {
+    if let Some(attr) = find_attr(&f.attrs, "count") {
+        let count = attr_args(attr);
+        Some(quote!{ ((#count) as usize) })
+    } else if let Some(attr) = find_attr(&f.attrs, "count_minus_one") {
+        let count = attr_args(attr);
+        Some(quote!{ (((#count) as i64 + 1).max(0) as usize) })
+    } else if let Some(attr) = find_attr(&f.attrs, "count_prefix") {
+        let count_type = attr_args(attr);
+        Some(quote!{ ({ let __count : #count_type = SerialRead::read(rdr)?; __count as usize }) })
+    } else if let Some(attr) = find_attr(&f.attrs, "count_prefix_minus_one") {
+        let count_type = attr_args(attr);
+        Some(quote!{ ({ let __count : #count_type = SerialRead::read(rdr)?; (__count as i64 + 1).max(0) as usize }) })
+    } else {
+        None
+    }
+}
+
+fn is_vec(ty : &syn::Type) -> bool {
+    if let syn::Type::Path(path) = ty {
+        if let Some(seg) = path.path.segments.iter().last() {
+            return seg.ident == "Vec";
+        }
+    }
+    false
+}
+
+// Errors are annotated with the structure and field that was being read, and
+// the offset where reading the field started
+fn reader_for_field(name : &syn::Ident, field : &str, f : &syn::Field) -> TokenStream2 {
     let attr_mod = format_attrs(&f.attrs);
-    if let syn::Type::Array(arr) = &f.ty {
+    let seek = seek_for_field(f);
+    let count = count_for_field(f);
+
+    let reader = if let syn::Type::Array(arr) = &f.ty {
         let mut reader = quote!{SerialRead::read(rdr#attr_mod)?,};
-        let len = if let syn::Expr::Lit(lit) = &arr.len {
-            match &lit.lit {
-                syn::Lit::Int(i) => i.value(),
-                _ => unimplemented!()
-            }
+        let len = if let syn::Expr::Lit(syn::ExprLit{lit: syn::Lit::Int(i), ..}) = &arr.len {
+            i.value()
         } else {
-            unimplemented!();
+            return syn::Error::new_spanned(&arr.len, "SerialRead only supports arrays of literal length, use a Vec with a count attribute instead")
+                .to_compile_error();
         };
         for _ in 1..len {
             reader.extend(quote!{SerialRead::read(rdr)?,});
         }
-        quote! {[#reader]}
+        quote! {Ok([#reader])}
+    } else if is_vec(&f.ty) {
+        let count = if let Some(count) = count {
+            count
+        } else {
+            return syn::Error::new_spanned(&f.ty, "SerialRead requires a count attribute for Vec fields")
+                .to_compile_error();
+        };
+        let modifiers = if attr_mod.is_empty() {
+            quote!{}
+        } else {
+            quote!{ rdr#attr_mod; }
+        };
+        quote! {{
+            #modifiers
+            let __count = #count;
+            let mut __elems = Vec::new();
+            for _ in 0..__count {
+                __elems.push(SerialRead::read(rdr)?);
+            }
+            Ok(__elems)
+        }}
+    } else if count.is_some() {
+        return syn::Error::new_spanned(&f.ty, "count attributes are only supported on Vec fields")
+            .to_compile_error();
     } else {
-        quote! {SerialRead::read(rdr#attr_mod)?}
-    }
+        quote! {SerialRead::read(rdr#attr_mod)}
+    };
+
+    let structure = name.to_string();
+    quote! {{
+        let __offset = rdr.pos();
+        let __read_field = |rdr : &mut SerialReadStorage| -> crate::Result<_> {
+            #seek
+            #reader
+        };
+        __read_field(rdr).map_err(|err| err.in_field(#structure, #field, __offset))?
+    }}
+}
+
+// Fields are read into local variables in order, so that attributes can refer
+// to previously read fields, for example as length of a collection.
+fn field_locals<'a>(fields : &'a syn::Fields) -> Vec<(syn::Ident, String, &'a syn::Field)> {
+    fields.iter().enumerate().map(|(i, f)| {
+        if let Some(ident) = &f.ident {
+            (ident.clone(), ident.to_string(), f)
+        } else {
+            (syn::Ident::new(&format!("field_{}", i), Span::call_site()), i.to_string(), f)
+        }
+    }).collect()
 }
 
-#[proc_macro_derive(SerialRead, attributes(length_start, length_end, align, pad))]
+#[proc_macro_derive(SerialRead, attributes(length_start, length_end, align, pad, count, count_minus_one, count_prefix, count_prefix_minus_one, seek))]
 pub fn file_readable(input: TokenStream) -> TokenStream {
     let ast : syn::DeriveInput = syn::parse(input).unwrap();
-    
+
     let name = &ast.ident;
 
 
     let read_func = match ast.data {
         syn::Data::Struct(data) => {
+            let mut ftoks = quote! {};
+            let locals = field_locals(&data.fields);
+            for (local, field, f) in locals.iter() {
+                let reader = reader_for_field(name, field, f);
+                ftoks.extend(quote! {
+                    let #local = #reader;
+                });
+            }
+            let names = locals.iter().map(|(local, _, _)| local);
             match data.fields {
-                fields @ syn::Fields::Named(_) => {
-                    let mut ftoks = quote! {};
-                    for f in fields.iter() {
-                        let fid = &f.ident;
-                        let reader = reader_for_field(f);
-                        ftoks.extend(quote! {
-                            #fid: #reader,
-                        });
-                    }
+                syn::Fields::Named(_) => {
                     quote! {
+                        #ftoks
                         Ok(#name {
-                            #ftoks
+                            #(#names),*
                         })
                     }
                 },
-                fields @ syn::Fields::Unnamed(_) => {
-                    let mut ftoks = quote! {};
-                    for f in fields.iter() {
-                        let reader = reader_for_field(f);
-                        ftoks.extend(quote! { #reader, });
-                    }
+                syn::Fields::Unnamed(_) => {
                     quote! {
+                        #ftoks
                         Ok(#name (
-                            #ftoks
+                            #(#names),*
                         ))
                     }
                 },
@@ -85,7 +206,8 @@ pub fn file_readable(input: TokenStream) -> TokenStream {
 
     let gen = quote! {
         impl SerialRead for #name {
-            fn read( rdr : &mut SerialReadStorage ) -> std::io::Result<#name> {
+            #[allow(non_snake_case)] // Locals are named after the fields
+            fn read( rdr : &mut SerialReadStorage ) -> crate::Result<#name> {
                 #read_func
             }
         }
@@ -94,4 +216,4 @@ pub fn file_readable(input: TokenStream) -> TokenStream {
     // println!("");
     // println!("");
     gen.into()
-}
\ No newline at end of file
+}
@@ -3,130 +3,948 @@ extern crate clap;
 
 use marmelade::{
     serialization::{
-        SerialAdaptor,
-        SerialReadStorage,
-        SerialRead
+        SerialAdaptor
     },
-    filesys::hfs::{
-        self,
-        HfsObjRef,
-        HfsDirIter
+    filesys::{
+        export::{
+            self,
+            host_name,
+            ExportFormat,
+            ExportSummary
+        },
+        import,
+        hfs::{
+            self,
+            Desktop,
+            HfsBuilder,
+            HfsObjRef,
+            HfsDirIter,
+            HfsFileRef
+        }
     },
     filesys::rsrc::{
-        Rsrc
+        self,
+        Rsrc,
+        RsrcFork,
+        RsrcInfo,
+        decode::{
+            self,
+            CodeAnalysis,
+            Decoded,
+            Field,
+            Font,
+            NewFont,
+            Picture,
+            Sound,
+            Template,
+            TypedRsrc
+        }
     },
     types::{
-        OSType
+        OSType,
+        Encoding,
+        PString
     },
-    tools::hexdump
+    tools::{
+        hexdump,
+        Image,
+        Json
+    },
+    Error
 };
 
-use std::io::Read;
+use clap::ArgMatches;
+use chrono::Utc;
+
+use std::convert::TryFrom;
+
+use std::io::{Read, Write};
 use std::fs;
+use std::path::{Path, PathBuf};
+
+// Exit codes, for scripts. Invalid arguments exit with 1, as reported by clap.
+const EXIT_ERROR: i32 = 1;
+const EXIT_NOT_FOUND: i32 = 2;
+const EXIT_BAD_IMAGE: i32 = 3;
+
+// Text for font previews
+const FONT_SAMPLE: &[u8] = b"The quick brown fox jumps over the lazy dog 0123456789";
+
+// Error to report, with the exit code to use
+struct Failure {
+    code: i32,
+    message: String
+}
+
+impl Failure {
+    fn new(code: i32, message: impl std::fmt::Display) -> Failure {
+        Failure { code, message: message.to_string() }
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(err: Error) -> Failure {
+        let code = match err.root_cause() {
+            Error::NotFound => EXIT_NOT_FOUND,
+            _ => EXIT_ERROR
+        };
+        Failure::new(code, err)
+    }
+}
+
+impl From<std::io::Error> for Failure {
+    fn from(err: std::io::Error) -> Failure {
+        Failure::new(EXIT_ERROR, err)
+    }
+}
+
+type CmdResult = std::result::Result<(), Failure>;
 
 fn main() {
-    let matches = clap_app!(myapp =>
+    let matches = clap_app!(marmelade_hfs =>
         (version: "0.1")
         (author: "Max Sikström <max@pengi.se>")
-        (about: "Makes toasters fly - run old stuff on new machines")
+        (about: "Inspect, extract and create HFS disk images")
+        (after_help: "Paths are relative to the root folder, separated by ':'.\n\n\
+            Exit codes: 0 on success, 1 on errors, 2 if a file, folder or resource isn't found, \
+            and 3 if the image can't be read.")
+        (@setting SubcommandRequiredElseHelp)
         (@arg img: +required -i --image +takes_value "Image file")
-        (@arg file: -f --file +takes_value "File to read")
-        (@arg rsrc: -r --rsrc "Open resource fork instead of data")
-        (@arg type: -T --type +takes_value "hexdump given rsrc type")
-        (@arg id: -I --id +takes_value "hexdump given rsrc id")
+        (@arg encoding: -e --encoding +takes_value "Encoding of names on volume (default: macroman)")
+        (@arg json: --json "Print output as JSON")
+        (@arg follow: -L --("follow-aliases") "Follow alias files in paths, as the Finder does")
+        (@subcommand info =>
+            (about: "Show volume information")
+        )
+        (@subcommand ls =>
+            (about: "List contents of a folder")
+            (@arg path: "Folder or file (default: root)")
+            (@arg long: -l --long "Show type, creator, sizes and dates")
+            (@arg recursive: -R --recursive "List subfolders")
+        )
+        (@subcommand tree =>
+            (about: "Show folder hierarchy")
+            (@arg path: "Folder (default: root)")
+        )
+        (@subcommand cat =>
+            (about: "Write contents of a file to stdout")
+            (@arg path: +required "File")
+            (@arg rsrc: -r --rsrc "Resource fork instead of data fork")
+        )
+        (@subcommand extract =>
+            (about: "Extract files with both forks and Finder information, folders recursively")
+            (@arg path: +required "File or folder, or ':' for the whole volume")
+            (@arg output: -o --output +takes_value "Output file or directory (default: name of file or folder)")
+            (@arg format: -F --format +takes_value possible_value[macbinary appledouble applesingle sidecar raw]
+                default_value("macbinary")
+                "Output format. Sidecar keeps resource fork and Finder info in .rsrc and .finf folders, \
+                raw writes resource fork to a .rsrc file")
+        )
+        (@subcommand rsrc =>
+            (about: "Inspect resource forks")
+            (@setting SubcommandRequiredElseHelp)
+            (@subcommand list =>
+                (about: "List resources")
+                (@arg path: +required "File")
+            )
+            (@subcommand dump =>
+                (about: "Show decoded resource and hexdump")
+                (@arg path: +required "File")
+                (@arg type: +required "Resource type")
+                (@arg id: +required "Resource ID")
+                (@arg template: --template +takes_value "File with TMPL resources, such as ResEdit")
+            )
+            (@subcommand export =>
+                (about: "Save a resource in a host format")
+                (@arg path: +required "File")
+                (@arg type: +required "Resource type")
+                (@arg id: +required "Resource ID")
+                (@arg output: -o --output +required +takes_value "Output file")
+                (@arg format: -F --format +takes_value possible_value[png wav text raw]
+                    "Output format (default: depending on type)")
+                (@arg template: --template +takes_value "File with TMPL resources, such as ResEdit")
+            )
+            (@subcommand derez =>
+                (about: "Decompile resource fork to Rez source")
+                (@arg path: +required "File")
+                (@arg output: -o --output +takes_value "Output file (default: stdout)")
+            )
+            (@subcommand code =>
+                (about: "List jump table and disassemble CODE resources")
+                (@arg path: +required "File")
+            )
+            (@subcommand check =>
+                (about: "Check resource fork structure")
+                (@arg path: +required "File")
+                (@arg salvage: --salvage +takes_value "Save readable resources of a damaged fork")
+            )
+        )
+        (@subcommand create =>
+            (about: "Create a new image from a host directory, in any format written by extract")
+            (@arg source: +required "Directory with the content of the volume")
+            (@arg name: -n --name +takes_value "Volume name (default: name of directory)")
+            (@arg size: -s --size +takes_value
+                "Image size in bytes, or with suffix K or M (default: smallest floppy size that fits, \
+                or just large enough)")
+            (@arg force: -f --force "Overwrite an existing image")
+        )
+        (@subcommand find =>
+            (about: "Find files and folders")
+            (@arg path: "Folder to search (default: root)")
+            (@arg name: -n --name +takes_value "Name, with * and ? as wildcards")
+            (@arg type: -t --type +takes_value "File type")
+            (@arg creator: -c --creator +takes_value "File creator")
+        )
     ).get_matches();
 
     let imgfile = matches.value_of("img").unwrap();
-    let img = fs::File::open(imgfile).unwrap();
+    let encoding = match matches.value_of("encoding").map(|name| (name, Encoding::from_name(name))) {
+        None => Encoding::default(),
+        Some((_, Some(encoding))) => encoding,
+        Some((name, None)) => {
+            eprintln!("Unknown encoding: {}", name);
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    let json = matches.is_present("json");
+
+    // Creating an image is the only command without an existing image
+    if let ("create", Some(args)) = matches.subcommand() {
+        if let Err(failure) = cmd_create(Path::new(imgfile), encoding, args, json) {
+            eprintln!("Error: {}", failure.message);
+            std::process::exit(failure.code);
+        }
+        return;
+    }
+
+    let img = match fs::File::open(imgfile) {
+        Ok(img) => img,
+        Err(err) => {
+            eprintln!("Can't open {}: {}", imgfile, err);
+            std::process::exit(EXIT_BAD_IMAGE);
+        }
+    };
     let fa = SerialAdaptor::new(img);
-    let fs = hfs::HfsImage::from(fa).unwrap();
+    let mut fs = match hfs::HfsImage::from(fa) {
+        Ok(fs) => fs,
+        Err(err) => {
+            eprintln!("Can't read image {}: {}", imgfile, err);
+            std::process::exit(EXIT_BAD_IMAGE);
+        }
+    };
+    fs.set_encoding(encoding);
+    fs.set_follow_aliases(matches.is_present("follow"));
 
-    if let Some(file) = matches.value_of("file") {
-        let use_rsrc = matches.occurrences_of("rsrc") > 0;
+    let result = match matches.subcommand() {
+        ("info", Some(_)) => cmd_info(&fs, json),
+        ("ls", Some(args)) => cmd_ls(&fs, args, json),
+        ("tree", Some(args)) => cmd_tree(&fs, args, json),
+        ("cat", Some(args)) => cmd_cat(&fs, args),
+        ("extract", Some(args)) => cmd_extract(&fs, args, json),
+        ("rsrc", Some(args)) => match args.subcommand() {
+            ("list", Some(args)) => cmd_rsrc_list(&fs, args, json),
+            ("dump", Some(args)) => cmd_rsrc_dump(&fs, args, json),
+            ("export", Some(args)) => cmd_rsrc_export(&fs, args),
+            ("derez", Some(args)) => cmd_rsrc_derez(&fs, args),
+            ("code", Some(args)) => cmd_rsrc_code(&fs, args),
+            ("check", Some(args)) => cmd_rsrc_check(&fs, args, json),
+            _ => unreachable!()
+        },
+        ("find", Some(args)) => cmd_find(&fs, args, json),
+        _ => unreachable!()
+    };
 
-        let mut type_id = None;
+    if let Err(failure) = result {
+        eprintln!("Error: {}", failure.message);
+        std::process::exit(failure.code);
+    }
+}
 
-        if let Some(rsrc_type) = matches.value_of("type") {
-            if let Some(rsrc_id) = matches.value_of("id") {
-                type_id = Some((
-                    OSType::from(rsrc_type.as_bytes()),
-                    i16::from_str_radix(rsrc_id, 10).unwrap()
-                ));
-            }
+
+fn locate<'img>(fs: &'img hfs::HfsImage, path: &str) -> std::result::Result<HfsObjRef<'img>, Failure> {
+    fs.locate(path).ok_or_else(|| Failure::new(EXIT_NOT_FOUND, format!("{}: no such file or folder", path)))
+}
+
+fn locate_file<'img>(fs: &'img hfs::HfsImage, path: &str) -> std::result::Result<HfsFileRef<'img>, Failure> {
+    locate(fs, path)?.to_file().ok_or_else(|| Failure::new(EXIT_ERROR, format!("{}: is a folder", path)))
+}
+
+// An empty path, or a single ':', is the root folder
+fn open_dir<'img>(fs: &'img hfs::HfsImage, path: Option<&str>) -> std::result::Result<HfsDirIter<'img>, Failure> {
+    match path {
+        None | Some("") | Some(":") => Ok(fs.open_root()),
+        Some(path) => match locate(fs, path)? {
+            HfsObjRef::DirRef(dir) => Ok(dir.open()),
+            HfsObjRef::FileRef(_) => Err(Failure::new(EXIT_ERROR, format!("{}: is a file", path)))
         }
+    }
+}
 
-        if let Err(err) = open_file(&fs, file, use_rsrc, type_id) {
-            eprintln!("Error: {}", err);
+fn open_rsrc(fs: &hfs::HfsImage, path: &str) -> std::result::Result<Rsrc, Failure> {
+    let file = locate_file(fs, path)?;
+    Ok(Rsrc::new(SerialAdaptor::new(file.open_rsrc()))?)
+}
+
+fn parse_type(text: &str) -> std::result::Result<OSType, Failure> {
+    // Types are often written with trailing spaces left out, such as 'snd'
+    let mut bytes = Encoding::MacRoman.encode(text).map_err(|err| Failure::new(EXIT_ERROR, format!("Invalid type: {}", err)))?;
+    if bytes.len() < 4 {
+        bytes.resize(4, b' ');
+    }
+    OSType::try_from(&bytes[..]).map_err(|err| Failure::new(EXIT_ERROR, format!("Invalid type: {}", err)))
+}
+
+fn parse_id(text: &str) -> std::result::Result<i16, Failure> {
+    text.parse().map_err(|err| Failure::new(EXIT_ERROR, format!("Invalid id: {}", err)))
+}
+
+fn type_string(os_type: &OSType) -> String {
+    Encoding::MacRoman.decode(&os_type.0)
+}
+
+fn format_time(time: &chrono::DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn json_time(time: &chrono::DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn join_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}:{}", prefix, name)
+    }
+}
+
+
+fn cmd_info(fs: &hfs::HfsImage, json: bool) -> CmdResult {
+    let (blocks, free_blocks) = fs.block_count();
+    let (files, folders) = fs.object_count();
+    let block_size = fs.block_size() as u64;
+    if json {
+        println!("{}", Json::object()
+            .with("name", fs.get_name())
+            .with("created", json_time(&fs.get_created()))
+            .with("modified", json_time(&fs.get_modified()))
+            .with("backup", json_time(&fs.get_backup()))
+            .with("block_size", fs.block_size())
+            .with("blocks", blocks)
+            .with("free_blocks", free_blocks)
+            .with("files", files)
+            .with("folders", folders));
+    } else {
+        println!("Name:       {}", fs.get_name());
+        println!("Created:    {}", format_time(&fs.get_created()));
+        println!("Modified:   {}", format_time(&fs.get_modified()));
+        println!("Backup:     {}", format_time(&fs.get_backup()));
+        println!("Block size: {} bytes", block_size);
+        println!("Size:       {} bytes, {} blocks", blocks as u64 * block_size, blocks);
+        println!("Free:       {} bytes, {} blocks", free_blocks as u64 * block_size, free_blocks);
+        println!("Files:      {}", files);
+        println!("Folders:    {}", folders);
+    }
+    Ok(())
+}
+
+
+// List a folder, with paths relative to the folder listed
+fn collect<'img>(dir: HfsDirIter<'img>, prefix: &str, recursive: bool, out: &mut Vec<(String, HfsObjRef<'img>)>) {
+    for obj in dir {
+        let path = join_path(prefix, &obj.get_name());
+        let sub = match &obj {
+            HfsObjRef::DirRef(dir) if recursive => Some(dir.open()),
+            _ => None
+        };
+        out.push((path.clone(), obj));
+        if let Some(sub) = sub {
+            collect(sub, &path, recursive, out);
         }
+    }
+}
+
+// Get Info comment, from the Finder database
+fn entry_comment(fs: &hfs::HfsImage, desktop: Option<&Desktop>, obj: &HfsObjRef) -> Option<String> {
+    desktop?.comment(obj).map(|comment| comment.decode(fs.encoding()))
+}
+
+// Path of the target of an alias file, when found on the volume
+fn entry_alias(fs: &hfs::HfsImage, obj: &HfsObjRef) -> Option<String> {
+    let alias = match obj {
+        HfsObjRef::FileRef(file) if file.is_alias() => file.read_alias().ok()?,
+        _ => return None
+    };
+    let target = fs.resolve_alias(&alias)?;
+    Some(join_path(&fs.folder_path(target.get_parent_id())?, &target.get_name()))
+}
+
+// Path of the application opening a document, from the Finder database
+fn entry_application(fs: &hfs::HfsImage, desktop: Option<&Desktop>, obj: &HfsObjRef) -> Option<String> {
+    let file = match obj {
+        HfsObjRef::FileRef(file) if file.get_type() != &OSType::from(b"APPL") => file,
+        _ => return None
+    };
+    let app = desktop?.application(file.get_creator())?;
+    let folder = fs.folder_path(app.parent)?;
+    Some(join_path(&folder, &app.name.decode(fs.encoding())))
+}
+
+fn entry_json(fs: &hfs::HfsImage, desktop: Option<&Desktop>, path: &str, obj: &HfsObjRef) -> Json {
+    let json = Json::object()
+        .with("name", obj.get_name())
+        .with("path", path)
+        .with("comment", entry_comment(fs, desktop, obj))
+        .with("application", entry_application(fs, desktop, obj))
+        .with("alias_target", entry_alias(fs, obj));
+    match obj {
+        HfsObjRef::FileRef(file) => {
+            let (data_size, rsrc_size) = file.get_size();
+            json.with("kind", "file")
+                .with("id", file.get_id())
+                .with("type", type_string(file.get_type()))
+                .with("creator", type_string(file.get_creator()))
+                .with("flags", file.get_finder_info().fdFlags)
+                .with("data_size", data_size)
+                .with("rsrc_size", rsrc_size)
+                .with("created", json_time(&file.get_created()))
+                .with("modified", json_time(&file.get_modified()))
+        },
+        HfsObjRef::DirRef(dir) => {
+            json.with("kind", "folder")
+                .with("id", dir.get_id())
+                .with("items", dir.get_valence())
+                .with("created", json_time(&dir.get_created()))
+                .with("modified", json_time(&dir.get_modified()))
+        }
+    }
+}
+
+fn entry_line(fs: &hfs::HfsImage, desktop: Option<&Desktop>, path: &str, obj: &HfsObjRef, long: bool) -> String {
+    let name = match obj {
+        HfsObjRef::FileRef(_) => path.to_string(),
+        HfsObjRef::DirRef(_) => format!("{}:", path)
+    };
+    if !long {
+        return name;
+    }
+    let mut line = match obj {
+        HfsObjRef::FileRef(file) => {
+            let (data_size, rsrc_size) = file.get_size();
+            format!("{} {}  {:>9} {:>9}  {}  {}",
+                file.get_type(), file.get_creator(), data_size, rsrc_size, format_time(&file.get_modified()), name)
+        },
+        HfsObjRef::DirRef(dir) => {
+            format!("{:13}  {:>19}  {}  {}",
+                "folder", format!("{} items", dir.get_valence()), format_time(&dir.get_modified()), name)
+        }
+    };
+    if let Some(target) = entry_alias(fs, obj) {
+        line.push_str(&format!("\n    Alias to: {}", target));
+    }
+    if let Some(application) = entry_application(fs, desktop, obj) {
+        line.push_str(&format!("\n    Opens with: {}", application));
+    }
+    if let Some(comment) = entry_comment(fs, desktop, obj) {
+        line.push_str(&format!("\n    Comment: {}", comment.replace('\r', "\n             ")));
+    }
+    line
+}
+
+fn print_entries(fs: &hfs::HfsImage, entries: &[(String, HfsObjRef)], long: bool, json: bool) {
+    // A damaged Finder database only loses the comments and applications
+    let desktop = if long || json {
+        fs.desktop().unwrap_or_else(|err| {
+            eprintln!("Can't read the Finder database: {}", err);
+            None
+        })
     } else {
-        let prefix = String::from("");
-        print_files(fs.open_root(), &prefix);
-    }
-}
-
-fn open_file(fs: &hfs::HfsImage, filename: &str, use_rsrc: bool, type_id: Option<(OSType, i16)>) -> std::io::Result<()> {
-    if let Some(file) = fs.locate(filename) {
-        println!("File: {:#?}", file);
-        if let HfsObjRef::FileRef(file) = file {
-            if use_rsrc {
-                let content = file.open_rsrc();
-                let rsrc_adaptor = SerialAdaptor::new(content);
-                let rsrc = Rsrc::new(rsrc_adaptor)?;
-                println!("Content: {:#?}", rsrc);
-                if let Some((rsrc_type, rsrc_id)) = type_id {
-                    if let Ok(storage) = rsrc.open(rsrc_type, rsrc_id) {
-                        hexdump::hexdump(&storage.to_vec());
-                    }
-                } else {
-                    if let Ok(mut storage) = rsrc.open(OSType::from(b"ICN#"), 128) {
-                        println!("Icon:");
-                        icon_render(&mut storage);
-                    }
-                }
-            } else {
-                let mut s = String::new();
-                file.open().read_to_string(&mut s)?;
-                println!("Content: {:#?}", s);
-            };
+        None
+    };
+    if json {
+        println!("{}", Json::Array(entries.iter().map(|(path, obj)| entry_json(fs, desktop.as_ref(), path, obj)).collect()));
+    } else {
+        for (path, obj) in entries {
+            println!("{}", entry_line(fs, desktop.as_ref(), path, obj, long));
         }
     }
+}
+
+fn cmd_ls(fs: &hfs::HfsImage, args: &ArgMatches, json: bool) -> CmdResult {
+    let path = args.value_of("path");
+    let mut entries = vec![];
+    match path.map(|path| (path, fs.locate(path))) {
+        Some((path, Some(obj @ HfsObjRef::FileRef(_)))) => entries.push((path.to_string(), obj)),
+        _ => collect(open_dir(fs, path)?, "", args.is_present("recursive"), &mut entries)
+    }
+    print_entries(fs, &entries, args.is_present("long"), json);
     Ok(())
 }
 
 
-fn print_files(dir: HfsDirIter, prefix: &String) {
-    for obj in dir {
+fn tree_json(dir: HfsDirIter) -> Json {
+    Json::Array(dir.map(|obj| {
+        let json = Json::object().with("name", obj.get_name());
         match obj {
+            HfsObjRef::FileRef(_) => json.with("kind", "file"),
+            HfsObjRef::DirRef(dir) => json.with("kind", "folder").with("children", tree_json(dir.open()))
+        }
+    }).collect())
+}
+
+fn print_tree(dir: HfsDirIter, indent: &str) {
+    let objs: Vec<HfsObjRef> = dir.collect();
+    for (index, obj) in objs.iter().enumerate() {
+        let last = index + 1 == objs.len();
+        println!("{}{}{}", indent, if last { "`-- " } else { "|-- " }, obj.get_name());
+        if let HfsObjRef::DirRef(dir) = obj {
+            let sub_indent = format!("{}{}", indent, if last { "    " } else { "|   " });
+            print_tree(dir.open(), &sub_indent);
+        }
+    }
+}
+
+fn cmd_tree(fs: &hfs::HfsImage, args: &ArgMatches, json: bool) -> CmdResult {
+    let path = args.value_of("path");
+    let dir = open_dir(fs, path)?;
+    if json {
+        println!("{}", tree_json(dir));
+    } else {
+        println!("{}", path.unwrap_or(&fs.get_name()));
+        print_tree(dir, "");
+    }
+    Ok(())
+}
+
+
+fn cmd_cat(fs: &hfs::HfsImage, args: &ArgMatches) -> CmdResult {
+    let file = locate_file(fs, args.value_of("path").unwrap())?;
+    let mut content = vec![];
+    if args.is_present("rsrc") {
+        file.open_rsrc().read_to_end(&mut content)?;
+    } else {
+        file.open().read_to_end(&mut content)?;
+    }
+    std::io::stdout().write_all(&content)?;
+    Ok(())
+}
+
+
+fn cmd_extract(fs: &hfs::HfsImage, args: &ArgMatches, json: bool) -> CmdResult {
+    let path = args.value_of("path").unwrap();
+    let format = ExportFormat::from_name(args.value_of("format").unwrap()).unwrap();
+    let output = args.value_of("output").map(PathBuf::from);
+
+    let summary = match path {
+        "" | ":" => {
+            let dest = output.unwrap_or_else(|| PathBuf::from(host_name(&fs.get_name())));
+            export::export_volume(fs, &dest, format)?
+        },
+        _ => match locate(fs, path)? {
             HfsObjRef::FileRef(file) => {
-                let (data_size, rsrc_size) = file.get_size();
-                println!("{}:{} (size: {}/{})", prefix, file.get_name(), data_size, rsrc_size);
+                let dest = output.unwrap_or_else(|| PathBuf::from(format.file_name(&host_name(&file.get_name()))));
+                ExportSummary {
+                    written: export::export_file(&file, &dest, format)?,
+                    files: 1,
+                    ..ExportSummary::default()
+                }
             },
             HfsObjRef::DirRef(dir) => {
-                let sub_prefix = format!("{}:{}", prefix, dir.get_name());
-                println!("{} (dir)", sub_prefix);
-                print_files(dir.open(), &sub_prefix);
+                let dest = output.unwrap_or_else(|| PathBuf::from(host_name(&dir.get_name())));
+                export::export_dir(dir.open(), &dest, format)?
+            }
+        }
+    };
+
+    if json {
+        let written: Vec<String> = summary.written.iter().map(|path| path.display().to_string()).collect();
+        let failed: Vec<Json> = summary.failed.iter().map(|(path, err)| Json::object()
+            .with("path", path.as_str())
+            .with("message", err.to_string())
+        ).collect();
+        println!("{}", Json::object()
+            .with("files", summary.files)
+            .with("folders", summary.folders)
+            .with("written", written)
+            .with("failed", Json::Array(failed)));
+    } else {
+        for path in summary.written.iter() {
+            println!("{}", path.display());
+        }
+        for (path, err) in summary.failed.iter() {
+            eprintln!("Can't extract {}: {}", path, err);
+        }
+    }
+    if !summary.failed.is_empty() {
+        return Err(Failure::new(EXIT_ERROR, format!("{} of {} files not extracted",
+            summary.failed.len(), summary.failed.len() + summary.files)));
+    }
+    Ok(())
+}
+
+
+// Size in bytes, with an optional K or M suffix for kilobytes and megabytes
+fn parse_size(size: &str) -> std::result::Result<u64, Failure> {
+    let lower = size.to_lowercase();
+    let (digits, unit) = match lower.chars().last() {
+        Some('k') => (&lower[..lower.len() - 1], 1024),
+        Some('m') => (&lower[..lower.len() - 1], 1024 * 1024),
+        _ => (&lower[..], 1)
+    };
+    digits.parse::<u64>().ok()
+        .and_then(|value| value.checked_mul(unit))
+        .ok_or_else(|| Failure::new(EXIT_ERROR, format!("Invalid size: {}", size)))
+}
+
+fn cmd_create(imgfile: &Path, encoding: Encoding, args: &ArgMatches, json: bool) -> CmdResult {
+    let source = Path::new(args.value_of("source").unwrap());
+    if !source.is_dir() {
+        return Err(Failure::new(EXIT_NOT_FOUND, format!("{}: no such directory", source.display())));
+    }
+    if imgfile.exists() && !args.is_present("force") {
+        return Err(Failure::new(EXIT_ERROR, format!("{}: already exists, use --force to overwrite", imgfile.display())));
+    }
+
+    let name = match args.value_of("name") {
+        Some(name) => name.to_string(),
+        None => source.canonicalize()?.file_name()
+            .map(|name| export::mac_name(&name.to_string_lossy()))
+            .unwrap_or_else(|| "Untitled".to_string())
+    };
+    let mut builder = HfsBuilder::new(PString::encode(&name, encoding)?)?;
+    if let Some(size) = args.value_of("size") {
+        builder.set_size(parse_size(size)?)?;
+    }
+
+    let summary = import::import_volume(&mut builder, source, encoding)?;
+    let image = builder.build()?;
+    fs::write(imgfile, &image)?;
+
+    if json {
+        let failed: Vec<Json> = summary.failed.iter().map(|(path, err)| Json::object()
+            .with("path", path.display().to_string())
+            .with("message", err.to_string())
+        ).collect();
+        println!("{}", Json::object()
+            .with("name", name)
+            .with("size", image.len())
+            .with("files", summary.files)
+            .with("folders", summary.folders)
+            .with("system_folder", summary.system_folder.map(|path| path.display().to_string()))
+            .with("failed", Json::Array(failed)));
+    } else {
+        println!("{}: {} files, {} folders, {}K", imgfile.display(), summary.files, summary.folders, image.len() / 1024);
+        if let Some(path) = summary.system_folder.as_ref() {
+            println!("Blessed {}", path.display());
+        }
+        for (path, err) in summary.failed.iter() {
+            eprintln!("Can't import {}: {}", path.display(), err);
+        }
+    }
+    if !summary.failed.is_empty() {
+        return Err(Failure::new(EXIT_ERROR, format!("{} files not imported", summary.failed.len())));
+    }
+    Ok(())
+}
+
+
+fn attribute_names(info: &RsrcInfo) -> Vec<&'static str> {
+    let attributes = info.attributes();
+    let mut names = vec![];
+    if attributes.sys_heap() { names.push("sysheap"); }
+    if attributes.purgeable() { names.push("purgeable"); }
+    if attributes.locked() { names.push("locked"); }
+    if attributes.protected() { names.push("protected"); }
+    if attributes.preload() { names.push("preload"); }
+    if attributes.compressed() { names.push("compressed"); }
+    names
+}
+
+fn rsrc_json(info: &RsrcInfo) -> std::result::Result<Json, Failure> {
+    Ok(Json::object()
+        .with("type", type_string(info.rsrc_type()))
+        .with("id", info.id())
+        .with("name", info.name().map(|n| n.to_string()))
+        .with("size", info.size()?)
+        .with("attributes", attribute_names(info)))
+}
+
+fn cmd_rsrc_list(fs: &hfs::HfsImage, args: &ArgMatches, json: bool) -> CmdResult {
+    let rsrc = open_rsrc(fs, args.value_of("path").unwrap())?;
+    if json {
+        let mut list = vec![];
+        for rsrc_type in rsrc.types() {
+            for info in rsrc.resources(rsrc_type) {
+                list.push(rsrc_json(&info)?);
+            }
+        }
+        println!("{}", Json::Array(list));
+        return Ok(());
+    }
+    for rsrc_type in rsrc.types() {
+        println!("{} ({} resources)", rsrc_type, rsrc.count(rsrc_type));
+        for info in rsrc.resources(rsrc_type) {
+            let name = info.name().map(|n| n.to_string()).unwrap_or_default();
+            println!("    {:6} {:8} {:24} {}", info.id(), info.size()?, format!("{:?}", name), attribute_names(&info).join(" "));
+        }
+    }
+    Ok(())
+}
+
+fn lookup<'rsrc>(rsrc: &'rsrc Rsrc, rsrc_type: &'rsrc OSType, id: i16) -> std::result::Result<RsrcInfo<'rsrc>, Failure> {
+    rsrc.get(rsrc_type, id).ok_or_else(|| Failure::new(EXIT_NOT_FOUND, format!("{} {}: no such resource", rsrc_type, id)))
+}
+
+// Types without a decoder are shown using a template, either from the file
+// itself or from a file given on the command line
+fn template_fields(fs: &hfs::HfsImage, rsrc: &Rsrc, info: &RsrcInfo, template: Option<&str>) -> std::result::Result<Option<Vec<Field>>, Failure> {
+    let tmpl_rsrc = match template {
+        Some(path) => Some(open_rsrc(fs, path)?),
+        None => None
+    };
+    match Template::find(tmpl_rsrc.as_ref().unwrap_or(rsrc), info.rsrc_type())? {
+        Some(tmpl) => Ok(Some(tmpl.decode(&mut info.open()?)?)),
+        None => Ok(None)
+    }
+}
+
+// Resource decoded as fields, using a template for types without a decoder.
+// Failing to decode is reported, but the raw data is still shown.
+fn decoded_fields(fs: &hfs::HfsImage, rsrc: &Rsrc, info: &RsrcInfo, template: Option<&str>) -> Option<Vec<Field>> {
+    let rsrc_type = info.rsrc_type();
+    let id = info.id();
+    match info.decode() {
+        Ok(Some(decoded)) => Some(decoded.fields()),
+        Ok(None) => match template_fields(fs, rsrc, info, template) {
+            Ok(fields) => fields,
+            Err(failure) => {
+                eprintln!("Can't decode {} {} using template: {}", rsrc_type, id, failure.message);
+                None
+            }
+        },
+        Err(err) => {
+            eprintln!("Can't decode {} {}: {}", rsrc_type, id, err);
+            None
+        }
+    }
+}
+
+fn cmd_rsrc_dump(fs: &hfs::HfsImage, args: &ArgMatches, json: bool) -> CmdResult {
+    let rsrc = open_rsrc(fs, args.value_of("path").unwrap())?;
+    let rsrc_type = parse_type(args.value_of("type").unwrap())?;
+    let id = parse_id(args.value_of("id").unwrap())?;
+    let info = lookup(&rsrc, &rsrc_type, id)?;
+    let data = info.open()?.to_vec();
+    let fields = decoded_fields(fs, &rsrc, &info, args.value_of("template"));
+
+    if json {
+        let hex: String = data.iter().map(|b| format!("{:02x}", b)).collect();
+        println!("{}", rsrc_json(&info)?
+            .with("decoded", fields.map(|fields| decode::fields_json(&fields)))
+            .with("data", hex));
+        return Ok(());
+    }
+
+    println!("{} {} {:?}: {} bytes {}", rsrc_type, id, info.name().map(|n| n.to_string()).unwrap_or_default(),
+        data.len(), attribute_names(&info).join(" "));
+    if let Some(fields) = fields {
+        print!("{}", decode::format_fields(&fields));
+    }
+    if let Ok(Some(image)) = decode::icon_image(&rsrc, &rsrc_type, id) {
+        icon_render(&image);
+    }
+    hexdump::hexdump(&data);
+    Ok(())
+}
+
+fn render_image(rsrc: &Rsrc, rsrc_type: &OSType, id: i16) -> std::result::Result<Option<Image>, Failure> {
+    Ok(if *rsrc_type == Picture::RSRC_TYPE {
+        Some(rsrc.load::<Picture>(id)?.render()?)
+    } else if *rsrc_type == Font::RSRC_TYPE {
+        Some(rsrc.load::<Font>(id)?.draw_string(FONT_SAMPLE).to_image())
+    } else if *rsrc_type == NewFont::RSRC_TYPE {
+        Some(rsrc.load::<NewFont>(id)?.draw_string(FONT_SAMPLE).to_image())
+    } else {
+        decode::icon_image(rsrc, rsrc_type, id)?
+    })
+}
+
+// Strings and text are saved as plain text, other decoded types as their
+// fields
+fn decoded_text(decoded: &Decoded) -> String {
+    match decoded {
+        Decoded::Str(s) => format!("{}\n", s.0),
+        Decoded::StrList(list) => list.strings.iter().map(|s| format!("{}\n", s)).collect(),
+        Decoded::Text(text) => text.decode(Encoding::MacRoman),
+        decoded => decode::format_fields(&decoded.fields())
+    }
+}
+
+fn cmd_rsrc_export(fs: &hfs::HfsImage, args: &ArgMatches) -> CmdResult {
+    let rsrc = open_rsrc(fs, args.value_of("path").unwrap())?;
+    let rsrc_type = parse_type(args.value_of("type").unwrap())?;
+    let id = parse_id(args.value_of("id").unwrap())?;
+    let info = lookup(&rsrc, &rsrc_type, id)?;
+    let output = args.value_of("output").unwrap();
+
+    let format = match args.value_of("format") {
+        Some(format) => format,
+        None if rsrc_type == Sound::RSRC_TYPE => "wav",
+        None if render_image(&rsrc, &rsrc_type, id).map(|i| i.is_some()).unwrap_or(false) => "png",
+        None if info.decode().map(|d| d.is_some()).unwrap_or(false) => "text",
+        None => "raw"
+    };
+    let content = match format {
+        "png" => match render_image(&rsrc, &rsrc_type, id)? {
+            Some(image) => image.to_png(),
+            None => return Err(Failure::new(EXIT_ERROR, format!("Not an icon, picture or font: {}", rsrc_type)))
+        },
+        "wav" => {
+            if rsrc_type != Sound::RSRC_TYPE {
+                return Err(Failure::new(EXIT_ERROR, format!("Not a sound: {}", rsrc_type)));
+            }
+            rsrc.load::<Sound>(id)?.to_pcm()?.to_wav()?
+        },
+        "text" => match info.decode()? {
+            Some(decoded) => decoded_text(&decoded).into_bytes(),
+            None => match template_fields(fs, &rsrc, &info, args.value_of("template"))? {
+                Some(fields) => decode::format_fields(&fields).into_bytes(),
+                None => return Err(Failure::new(EXIT_ERROR, format!("No decoder or template for {}", rsrc_type)))
+            }
+        },
+        _ => info.open()?.to_vec()
+    };
+    fs::write(output, content)?;
+    Ok(())
+}
+
+fn cmd_rsrc_derez(fs: &hfs::HfsImage, args: &ArgMatches) -> CmdResult {
+    let rsrc = open_rsrc(fs, args.value_of("path").unwrap())?;
+    let source = rsrc::derez(&RsrcFork::from_rsrc(&rsrc)?);
+    match args.value_of("output") {
+        Some(output) => fs::write(output, source)?,
+        None => print!("{}", source)
+    }
+    Ok(())
+}
+
+fn cmd_rsrc_code(fs: &hfs::HfsImage, args: &ArgMatches) -> CmdResult {
+    let rsrc = open_rsrc(fs, args.value_of("path").unwrap())?;
+    print_code(&CodeAnalysis::new(&rsrc)?)?;
+    Ok(())
+}
+
+// Damaged forks may not open, so the raw fork is checked
+fn cmd_rsrc_check(fs: &hfs::HfsImage, args: &ArgMatches, json: bool) -> CmdResult {
+    let file = locate_file(fs, args.value_of("path").unwrap())?;
+    let mut data = vec![];
+    file.open_rsrc().read_to_end(&mut data)?;
+    let report = match args.value_of("salvage") {
+        Some(salvage) => {
+            let (fork, report) = rsrc::salvage(&data);
+            fs::write(salvage, fork.to_bytes()?)?;
+            report
+        },
+        None => rsrc::validate(&data)
+    };
+    if json {
+        let problems: Vec<Json> = report.problems.iter().map(|problem| Json::object()
+            .with("severity", format!("{:?}", problem.severity()).to_lowercase())
+            .with("message", problem.to_string())
+        ).collect();
+        println!("{}", Json::object()
+            .with("valid", report.is_valid())
+            .with("readable", report.is_readable())
+            .with("resources", report.resources)
+            .with("salvageable", report.salvageable)
+            .with("problems", Json::Array(problems)));
+    } else {
+        print!("{}", report);
+    }
+    if !report.is_valid() {
+        return Err(Failure::new(EXIT_ERROR, "resource fork is damaged"));
+    }
+    Ok(())
+}
+
+
+// Case insensitive match, with '*' matching any number of characters and '?'
+// matching one
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => glob_match(&pattern[1..], name) || (!name.is_empty() && glob_match(pattern, &name[1..])),
+        (Some('?'), Some(_)) => glob_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => glob_match(&pattern[1..], &name[1..]),
+        _ => false
+    }
+}
+
+fn cmd_find(fs: &hfs::HfsImage, args: &ArgMatches, json: bool) -> CmdResult {
+    let lower_chars = |s: &str| -> Vec<char> { s.to_lowercase().chars().collect() };
+    let name = args.value_of("name").map(lower_chars);
+    let file_type = args.value_of("type").map(parse_type).transpose()?;
+    let creator = args.value_of("creator").map(parse_type).transpose()?;
+
+    let mut entries = vec![];
+    collect(open_dir(fs, args.value_of("path"))?, "", true, &mut entries);
+    entries.retain(|(_, obj)| {
+        if let Some(name) = &name {
+            if !glob_match(name, &lower_chars(&obj.get_name())) {
+                return false;
             }
         }
+        if file_type.is_none() && creator.is_none() {
+            return true;
+        }
+        match obj {
+            HfsObjRef::FileRef(file) =>
+                file_type.as_ref().map(|t| t == file.get_type()).unwrap_or(true) &&
+                creator.as_ref().map(|c| c == file.get_creator()).unwrap_or(true),
+            HfsObjRef::DirRef(_) => false
+        }
+    });
+
+    print_entries(fs, &entries, true, json);
+    if entries.is_empty() {
+        return Err(Failure::new(EXIT_NOT_FOUND, "no matches"));
     }
+    Ok(())
 }
 
 
-fn icon_render(rdr: &mut SerialReadStorage) {
-    let mut bytes = [0 as u8; 256];
-    for i in 0..256 {
-        bytes[i] = u8::read(rdr).unwrap();
+fn print_code(code: &CodeAnalysis) -> CmdResult {
+    let table = code.jump_table();
+    println!("Jump table: {} entries, A5 world ${:x} above, ${:x} below", table.entries.len(), table.header.above_a5, table.header.below_a5);
+    for (index, entry) in table.entries.iter().enumerate() {
+        match entry.segment() {
+            Some(segment) => println!("    ${:04x}(A5)  CODE {:3} + ${:04x}  {}",
+                table.a5_offset(index), segment, entry.offset, code.entry_name(index).unwrap_or_default()),
+            None => println!("    ${:04x}(A5)  (not a LoadSeg entry)", table.a5_offset(index))
+        }
     }
+    for (id, name, segment) in code.segments() {
+        let name = name.map(|n| n.to_string()).unwrap_or_default();
+        println!();
+        println!("CODE {} {:?}: {} bytes, {} entries", id, name, segment.code.len(), segment.header.entry_count);
+        for line in code.disassemble(id)? {
+            println!("{}", line);
+        }
+    }
+    Ok(())
+}
 
-    for y in 0..32 {
-        for x in 0..32 {
-            let pxlidx = y*32 + x;
-            let pxlbyte = pxlidx / 8;
-            let pxlbit = 1<<(7-(pxlidx%8));
-            let mask = bytes[pxlbyte+128]&pxlbit != 0;
-            let col = bytes[pxlbyte]&pxlbit != 0;
-            let chr = if !mask {'.'} else if col {'#'} else {' '};
+
+fn icon_render(image: &Image) {
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            let chr = match image.get(x, y) {
+                Some([_, _, _, 0]) | None => '.',
+                Some([0, 0, 0, _]) => '#',
+                Some(_) => ' '
+            };
             print!("{}{}", chr, chr);
         }
-        println!("");
+        println!();
     }
-}
\ No newline at end of file
+}
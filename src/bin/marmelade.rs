@@ -5,40 +5,49 @@ use marmelade::{
     serialization::SerialAdaptor,
     filesys::hfs::HfsImage,
     filesys::rsrc::Rsrc,
-    toolbox::Toolbox
+    phy::debug::Debugger,
+    phy::mem::log::LOG_DATA,
+    toolbox::Toolbox,
+    Error,
+    Result
 };
 
-use std::io::{
-    ErrorKind
-};
 use std::fs;
+use std::io;
 
-fn main() -> std::io::Result<()> {
+fn main() -> Result<()> {
     let matches = clap_app!(myapp =>
         (version: "0.1")
         (author: "Max Sikström <max@pengi.se>")
         (about: "Makes toasters fly - run old stuff on new machines")
         (@arg img: +required -i --image +takes_value "Image file")
         (@arg file: +required -f --file +takes_value "File to load")
+        (@arg debug: -d --debug "Run in the debugger instead of tracing each instruction")
     ).get_matches();
 
-    let file_os_path = matches.value_of("img").ok_or(ErrorKind::from(ErrorKind::InvalidInput))?;
-    let file_img_path = matches.value_of("file").ok_or(ErrorKind::from(ErrorKind::InvalidInput))?;
+    let file_os_path = matches.value_of("img").ok_or(Error::NotFound)?;
+    let file_img_path = matches.value_of("file").ok_or(Error::NotFound)?;
     let (fs, rsrc) = load_file(file_os_path, file_img_path)?;
 
     let toolbox = Toolbox::new(fs, rsrc)?;
-    let mut phy = Toolbox::into_phy(&toolbox)?;
 
-    phy.run();
+    if matches.is_present("debug") {
+        let mut phy = Toolbox::into_phy(&toolbox, 0)?;
+        let stdin = io::stdin();
+        Debugger::new().repl(&mut phy, &mut stdin.lock(), &mut io::stdout())?;
+    } else {
+        let mut phy = Toolbox::into_phy(&toolbox, LOG_DATA)?;
+        phy.run();
+    }
 
     Ok(())
 }
 
-fn load_file(file_os_path: &str, file_img_path: &str) -> std::io::Result<(HfsImage, Rsrc)> {
+fn load_file(file_os_path: &str, file_img_path: &str) -> Result<(HfsImage, Rsrc)> {
     let img_file = fs::File::open(file_os_path)?;
     let fs = HfsImage::from(SerialAdaptor::new(img_file))?;
-    let rsrc_objref = fs.locate(file_img_path).ok_or(ErrorKind::from(ErrorKind::NotFound))?;
-    let rsrc_fileref = rsrc_objref.to_file().ok_or(ErrorKind::from(ErrorKind::InvalidData))?;
+    let rsrc_objref = fs.locate(file_img_path).ok_or(Error::NotFound)?;
+    let rsrc_fileref = rsrc_objref.to_file().ok_or(Error::NotFound)?;
     let rsrc = Rsrc::new(SerialAdaptor::new(rsrc_fileref.open_rsrc()))?;
 
     Ok((fs, rsrc))
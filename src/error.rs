@@ -0,0 +1,144 @@
+use std::fmt;
+use std::io;
+
+// Errors are annotated on the way up with the structure and field being read,
+// so a failure deep inside a nested structure can be traced back to where in
+// the image it originated.
+#[derive(Debug)]
+pub enum Error {
+    // Error from the underlying storage
+    Io(io::Error),
+
+    // Tried to access data outside of the available buffer
+    UnexpectedEof {
+        offset: u64,
+        len: u64,
+        size: u64
+    },
+
+    // A field contained a value which isn't valid for the structure
+    InvalidValue {
+        offset: u64,
+        structure: &'static str,
+        field: &'static str,
+        expected: String,
+        actual: String
+    },
+
+    // The requested object doesn't exist
+    NotFound,
+
+    // An object with the same identifier already exists
+    AlreadyExists,
+
+    // Error occured while reading a field of a structure
+    InField {
+        offset: u64,
+        structure: &'static str,
+        field: &'static str,
+        error: Box<Error>
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn invalid_value(
+        offset: u64,
+        structure: &'static str,
+        field: &'static str,
+        expected: impl fmt::Display,
+        actual: impl fmt::Display
+    ) -> Error {
+        Error::InvalidValue {
+            offset,
+            structure,
+            field,
+            expected: expected.to_string(),
+            actual: actual.to_string()
+        }
+    }
+
+    pub fn in_field(self, structure: &'static str, field: &'static str, offset: u64) -> Error {
+        Error::InField {
+            offset,
+            structure,
+            field,
+            error: Box::new(self)
+        }
+    }
+
+    // Get the innermost error, without field annotations
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Error::InField { error, .. } => error.root_cause(),
+            err => err
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::UnexpectedEof { offset, len, size } =>
+                write!(f, "unexpected end of data: {} bytes at offset {} exceeds size {}", len, offset, size),
+            Error::InvalidValue { offset, structure, field, expected, actual } =>
+                write!(f, "invalid value in {}.{} at offset {}: expected {}, got {}", structure, field, offset, expected, actual),
+            Error::NotFound => write!(f, "not found"),
+            Error::AlreadyExists => write!(f, "already exists"),
+            Error::InField { offset, structure, field, error } =>
+                write!(f, "{}.{} at offset {}: {}", structure, field, offset, error)
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::InField { error, .. } => Some(error.as_ref()),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+// Needed to pass errors through std::io traits, like Read for FileIO
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        match err {
+            Error::Io(err) => err,
+            Error::UnexpectedEof { .. } => io::Error::new(io::ErrorKind::UnexpectedEof, err),
+            Error::NotFound => io::Error::new(io::ErrorKind::NotFound, err),
+            Error::AlreadyExists => io::Error::new(io::ErrorKind::AlreadyExists, err),
+            err => io::Error::new(io::ErrorKind::InvalidData, err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    #[test]
+    fn display_in_field() {
+        let err = Error::UnexpectedEof { offset: 10, len: 2, size: 11 }
+            .in_field("Inner", "b", 10)
+            .in_field("Outer", "a", 4);
+        assert_eq!(
+            format!("{}", err),
+            "Outer.a at offset 4: Inner.b at offset 10: unexpected end of data: 2 bytes at offset 10 exceeds size 11"
+        );
+        if let Error::UnexpectedEof { offset, .. } = err.root_cause() {
+            assert_eq!(*offset, 10);
+        } else {
+            panic!("wrong root cause");
+        }
+    }
+}
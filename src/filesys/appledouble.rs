@@ -0,0 +1,201 @@
+use crate::{Error, Result};
+use crate::serialization::{
+    SerialReadStorage,
+    SerialRead,
+    SerialWriteStorage,
+    SerialWrite
+};
+use crate::types::{
+    DateTime,
+    OSType,
+    PString
+};
+
+use super::MacFile;
+use super::hfs::{
+    FInfo,
+    FXInfo
+};
+
+// AppleSingle and AppleDouble version 2 (RFC 1740). AppleSingle keeps both
+// forks in one file. AppleDouble keeps the data fork as a plain file, and
+// everything else in a header file, by convention named "._" followed by the
+// name of the data file.
+
+const APPLE_SINGLE_MAGIC: u32 = 0x00051600;
+const APPLE_DOUBLE_MAGIC: u32 = 0x00051607;
+const VERSION: u32 = 0x00020000;
+
+const ENTRY_DATA_FORK: u32 = 1;
+const ENTRY_RSRC_FORK: u32 = 2;
+const ENTRY_REAL_NAME: u32 = 3;
+const ENTRY_DATES: u32 = 8;
+const ENTRY_FINDER_INFO: u32 = 9;
+const ENTRY_MAC_INFO: u32 = 10;
+
+// Dates are seconds relative to 2000-01-01, and this marks an unknown date
+const EPOCH_2000: i64 = 3029529600;
+const UNKNOWN_DATE: u32 = 0x80000000;
+
+fn to_date(time: &DateTime) -> u32 {
+    (time.mac_secs() - EPOCH_2000) as i32 as u32
+}
+
+fn from_date(date: u32) -> Result<Option<DateTime>> {
+    if date == UNKNOWN_DATE {
+        return Ok(None);
+    }
+    DateTime::from_mac_secs(date as i32 as i64 + EPOCH_2000).map(Some)
+}
+
+fn encode_entries(magic: u32, file: &MacFile, data: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut finder_info = SerialWriteStorage::new();
+    file.info.write(&mut finder_info)?;
+    file.ext_info.write(&mut finder_info)?;
+    let finder_info = finder_info.to_vec();
+
+    let mut dates = SerialWriteStorage::new();
+    dates.write_u32(to_date(&file.created))?;
+    dates.write_u32(to_date(&file.modified))?;
+    dates.write_u32(UNKNOWN_DATE)?;
+    dates.write_u32(UNKNOWN_DATE)?;
+    let dates = dates.to_vec();
+
+    let mac_info = (file.locked as u32).to_be_bytes();
+
+    // The forks are placed last, so they can be extended in place
+    let mut entries: Vec<(u32, &[u8])> = vec![
+        (ENTRY_REAL_NAME, file.name.as_bytes()),
+        (ENTRY_DATES, &dates),
+        (ENTRY_FINDER_INFO, &finder_info),
+        (ENTRY_MAC_INFO, &mac_info),
+        (ENTRY_RSRC_FORK, &file.rsrc)
+    ];
+    if let Some(data) = data {
+        entries.push((ENTRY_DATA_FORK, data));
+    }
+
+    let mut wtr = SerialWriteStorage::new();
+    wtr.write_u32(magic)?;
+    wtr.write_u32(VERSION)?;
+    wtr.pad(16)?;
+    wtr.write_u16(entries.len() as u16)?;
+    let mut offset = 26 + 12 * entries.len() as u32;
+    for (id, content) in entries.iter() {
+        wtr.write_u32(*id)?;
+        wtr.write_u32(offset)?;
+        wtr.write_u32(content.len() as u32)?;
+        offset += content.len() as u32;
+    }
+    for (_, content) in entries.iter() {
+        wtr.write_bytes(content)?;
+    }
+    Ok(wtr.to_vec())
+}
+
+// Header file for AppleDouble, with everything but the data fork
+pub fn encode(file: &MacFile) -> Result<Vec<u8>> {
+    encode_entries(APPLE_DOUBLE_MAGIC, file, None)
+}
+
+pub fn encode_single(file: &MacFile) -> Result<Vec<u8>> {
+    encode_entries(APPLE_SINGLE_MAGIC, file, Some(&file.data))
+}
+
+// Name of the AppleDouble header file for a data file
+pub fn header_name(name: &str) -> String {
+    format!("._{}", name)
+}
+
+// Decode an AppleSingle file or an AppleDouble header file. The data fork of
+// an AppleDouble file is stored separately, and is left empty. Entries which
+// are missing, such as the name, are left at their defaults.
+pub fn decode(header: &[u8]) -> Result<MacFile> {
+    let mut rdr = SerialReadStorage::from(header.to_vec());
+    let magic = rdr.read_u32()?;
+    if magic != APPLE_SINGLE_MAGIC && magic != APPLE_DOUBLE_MAGIC {
+        return Err(Error::invalid_value(0, "AppleDouble", "magic", format!("${:08x}", APPLE_DOUBLE_MAGIC), format!("${:08x}", magic)));
+    }
+    rdr.seek(24)?;
+    let count = rdr.read_u16()?;
+
+    let mut file = MacFile::new(PString::from(""), OSType::from(0), OSType::from(0));
+    for index in 0..count as u64 {
+        let entry_offset = 26 + 12 * index;
+        rdr.seek(entry_offset)?;
+        let id = rdr.read_u32()?;
+        let offset = rdr.read_u32()? as u64;
+        let len = rdr.read_u32()? as u64;
+        let mut content = rdr.sub_reader(offset, len)
+            .map_err(|err| err.in_field("AppleDouble", "entry", entry_offset))?;
+        match id {
+            ENTRY_DATA_FORK => file.data = content.to_vec(),
+            ENTRY_RSRC_FORK => file.rsrc = content.to_vec(),
+            ENTRY_REAL_NAME => file.name = PString::from(&content.to_vec()[..]),
+            ENTRY_DATES => {
+                if let Some(created) = from_date(content.read_u32()?)? {
+                    file.created = created;
+                }
+                if let Some(modified) = from_date(content.read_u32()?)? {
+                    file.modified = modified;
+                }
+            },
+            ENTRY_FINDER_INFO => {
+                file.info = FInfo::read(&mut content)?;
+                // Some writers only store the first 16 bytes
+                if len >= 32 {
+                    file.ext_info = FXInfo::read(&mut content)?;
+                }
+            },
+            ENTRY_MAC_INFO => file.locked = content.read_u32()? & 0x01 != 0,
+            _ => ()
+        }
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, encode_single};
+    use crate::filesys::MacFile;
+    use crate::types::{DateTime, OSType, PString};
+
+    fn sample() -> MacFile {
+        let mut file = MacFile::new(PString::from("Read Me"), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+        file.created = DateTime::from_mac_u32(0xa61e6e80);
+        file.modified = DateTime::from_mac_u32(0xb61e6e80);
+        file.ext_info.fdComment = 3;
+        file.locked = true;
+        file.data = b"Hello".to_vec();
+        file.rsrc = vec![1, 2, 3];
+        file
+    }
+
+    #[test]
+    fn double() {
+        let file = sample();
+        let header = encode(&file).unwrap();
+        assert_eq!(&header[0..8], &[0, 5, 0x16, 7, 0, 2, 0, 0]);
+        let decoded = decode(&header).unwrap();
+        assert_eq!(decoded.name, file.name);
+        assert_eq!(decoded.info.fdType, file.info.fdType);
+        assert_eq!(decoded.ext_info.fdComment, 3);
+        assert_eq!((decoded.created, decoded.modified), (file.created, file.modified));
+        assert!(decoded.locked);
+        assert_eq!(decoded.data, vec![]);
+        assert_eq!(decoded.rsrc, file.rsrc);
+    }
+
+    #[test]
+    fn single() {
+        let file = sample();
+        let decoded = decode(&encode_single(&file).unwrap()).unwrap();
+        assert_eq!(decoded.data, file.data);
+        assert_eq!(decoded.rsrc, file.rsrc);
+        assert!(decode(b"\x00\x05\x16\x08").is_err());
+        // Entry pointing outside of the file
+        let mut header = encode(&file).unwrap();
+        header[30] = 0xff;
+        assert!(decode(&header).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use std::fs::{self, FileTimes};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::Utc;
+
+use crate::{Error, Result};
+use crate::serialization::{SerialWrite, SerialWriteStorage};
+
+use super::{appledouble, macbinary, MacFile};
+use super::hfs::{HfsDirIter, HfsFileRef, HfsImage, HfsObjRef};
+
+// How the resource fork and Finder information are stored on the host
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum ExportFormat {
+    // Single .bin file
+    MacBinary,
+    // Single file, with both forks
+    AppleSingle,
+    // Data fork as the file, and the rest in a "._" header file next to it
+    AppleDouble,
+    // Data fork as the file, with the resource fork and Finder information in
+    // .rsrc and .finf folders next to it, as used by the Basilisk II and
+    // SheepShaver host file system
+    Sidecar,
+    // Data fork as the file, and the resource fork in a .rsrc file next to
+    // it. Finder information is lost.
+    Raw
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        match name.to_lowercase().as_str() {
+            "macbinary" | "bin" => Some(ExportFormat::MacBinary),
+            "applesingle" => Some(ExportFormat::AppleSingle),
+            "appledouble" => Some(ExportFormat::AppleDouble),
+            "sidecar" => Some(ExportFormat::Sidecar),
+            "raw" => Some(ExportFormat::Raw),
+            _ => None
+        }
+    }
+
+    // Host name of the main file, for a file with the given host name
+    pub fn file_name(&self, name: &str) -> String {
+        match self {
+            ExportFormat::MacBinary => format!("{}.bin", name),
+            _ => name.to_string()
+        }
+    }
+}
+
+// Map a Mac name to a name usable on the host. Mac names may contain '/',
+// which is swapped with ':' as the Finder does, since ':' can't occur in Mac
+// names. Control characters, '%' and a leading '.' are written as %XX, to
+// avoid hidden files and clashes with AppleDouble headers. The mapping is
+// reversed by mac_name.
+pub fn host_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for (index, c) in name.chars().enumerate() {
+        match c {
+            '/' => out.push(':'),
+            ':' | '%' | '\u{7f}' => out.push_str(&format!("%{:02X}", c as u32)),
+            '.' if index == 0 => out.push_str("%2E"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("%{:02X}", c as u32)),
+            c => out.push(c)
+        }
+    }
+    out
+}
+
+pub fn mac_name(host: &str) -> String {
+    let mut out = String::with_capacity(host.len());
+    let mut chars = host.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ':' => out.push('/'),
+            '%' => {
+                let hex: String = chars.clone().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) if hex.len() == 2 => {
+                        out.push(byte as char);
+                        chars.nth(1);
+                    },
+                    _ => out.push('%')
+                }
+            },
+            c => out.push(c)
+        }
+    }
+    out
+}
+
+fn sidecar_path(path: &Path, folder: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default();
+    path.with_file_name(folder).join(name)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+// Write a file in the given format, where path is the main file. Returns the
+// paths written, starting with the main file.
+pub fn write_mac_file(file: &MacFile, path: &Path, format: ExportFormat) -> Result<Vec<PathBuf>> {
+    let mut written: Vec<(PathBuf, Vec<u8>)> = vec![];
+    match format {
+        ExportFormat::MacBinary => written.push((path.to_path_buf(), macbinary::encode(file)?)),
+        ExportFormat::AppleSingle => written.push((path.to_path_buf(), appledouble::encode_single(file)?)),
+        ExportFormat::AppleDouble => {
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            written.push((path.to_path_buf(), file.data.clone()));
+            written.push((path.with_file_name(appledouble::header_name(&name)), appledouble::encode(file)?));
+        },
+        ExportFormat::Sidecar => {
+            let mut finder_info = SerialWriteStorage::new();
+            file.info.write(&mut finder_info)?;
+            file.ext_info.write(&mut finder_info)?;
+            written.push((path.to_path_buf(), file.data.clone()));
+            if !file.rsrc.is_empty() {
+                written.push((sidecar_path(path, ".rsrc"), file.rsrc.clone()));
+            }
+            written.push((sidecar_path(path, ".finf"), finder_info.to_vec()));
+        },
+        ExportFormat::Raw => {
+            written.push((path.to_path_buf(), file.data.clone()));
+            if !file.rsrc.is_empty() {
+                written.push((with_suffix(path, ".rsrc"), file.rsrc.clone()));
+            }
+        }
+    }
+
+    for (path, content) in written.iter() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+    }
+    Ok(written.into_iter().map(|(path, _)| path).collect())
+}
+
+fn system_time(time: &chrono::DateTime<Utc>) -> SystemTime {
+    let secs = time.timestamp();
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::from_secs(secs as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+    }
+}
+
+// The creation date can only be set on macOS and Windows. Other hosts only
+// get the modification date, and the creation date is only kept by formats
+// which store it.
+#[cfg_attr(not(any(target_os = "macos", windows)), allow(unused_variables))]
+fn file_times(created: &chrono::DateTime<Utc>, modified: &chrono::DateTime<Utc>) -> FileTimes {
+    let times = FileTimes::new().set_modified(system_time(modified));
+    #[cfg(target_os = "macos")]
+    let times = {
+        use std::os::macos::fs::FileTimesExt;
+        times.set_created(system_time(created))
+    };
+    #[cfg(windows)]
+    let times = {
+        use std::os::windows::fs::FileTimesExt;
+        times.set_created(system_time(created))
+    };
+    times
+}
+
+// Setting the times needs write access to files on Windows, while folders
+// can only be opened for reading on other hosts
+fn set_times(path: &Path, created: &chrono::DateTime<Utc>, modified: &chrono::DateTime<Utc>) -> Result<()> {
+    let file = if path.is_dir() {
+        fs::File::open(path)?
+    } else {
+        fs::OpenOptions::new().write(true).open(path)?
+    };
+    file.set_times(file_times(created, modified))?;
+    Ok(())
+}
+
+// Export a file to the given path, with the dates of the file on all files
+// written
+pub fn export_file(file: &HfsFileRef, path: &Path, format: ExportFormat) -> Result<Vec<PathBuf>> {
+    let written = write_mac_file(&file.read_mac_file()?, path, format)?;
+    for path in written.iter() {
+        set_times(path, &file.get_created(), &file.get_modified())?;
+    }
+    Ok(written)
+}
+
+#[derive(Debug)]
+#[derive(Default)]
+pub struct ExportSummary {
+    // Host paths written, for both files and folders
+    pub written: Vec<PathBuf>,
+    pub files: usize,
+    pub folders: usize,
+    // Files which couldn't be exported, by Mac path. The export continues
+    // with the remaining files.
+    pub failed: Vec<(String, Error)>
+}
+
+fn export_walk(dir: HfsDirIter, dest: &Path, prefix: &str, format: ExportFormat, summary: &mut ExportSummary) -> Result<()> {
+    for obj in dir {
+        let mac_path = if prefix.is_empty() { obj.get_name() } else { format!("{}:{}", prefix, obj.get_name()) };
+        let name = host_name(&obj.get_name());
+        match obj {
+            HfsObjRef::FileRef(file) => match export_file(&file, &dest.join(format.file_name(&name)), format) {
+                Ok(written) => {
+                    summary.written.extend(written);
+                    summary.files += 1;
+                },
+                Err(err) => summary.failed.push((mac_path, err))
+            },
+            HfsObjRef::DirRef(dir) => {
+                let sub_dest = dest.join(&name);
+                fs::create_dir_all(&sub_dest)?;
+                summary.written.push(sub_dest.clone());
+                summary.folders += 1;
+                export_walk(dir.open(), &sub_dest, &mac_path, format, summary)?;
+                // Not all hosts allow setting folder dates, which is ignored
+                let _ = set_times(&sub_dest, &dir.get_created(), &dir.get_modified());
+            }
+        }
+    }
+    Ok(())
+}
+
+// Export the contents of a folder recursively to a host directory, which is
+// created if needed. Errors creating folders stops the export, while files
+// that can't be read are listed in the summary.
+pub fn export_dir(dir: HfsDirIter, dest: &Path, format: ExportFormat) -> Result<ExportSummary> {
+    let mut summary = ExportSummary::default();
+    fs::create_dir_all(dest)?;
+    export_walk(dir, dest, "", format, &mut summary)?;
+    Ok(summary)
+}
+
+pub fn export_volume(img: &HfsImage, dest: &Path, format: ExportFormat) -> Result<ExportSummary> {
+    export_dir(img.open_root(), dest, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{host_name, mac_name, set_times, system_time, write_mac_file, ExportFormat};
+    use chrono::{TimeZone, Utc};
+    use crate::filesys::MacFile;
+    use crate::types::{OSType, PString};
+
+    #[test]
+    fn names() {
+        assert_eq!(host_name("Read Me"), "Read Me");
+        assert_eq!(host_name("a/b"), "a:b");
+        assert_eq!(host_name(".cshrc 100%"), "%2Ecshrc 100%25");
+        assert_eq!(host_name("Icon\r"), "Icon%0D");
+        assert_eq!(host_name("a.b"), "a.b");
+        for name in ["a/b", ".x", "100%", "Icon\r", "%2", "é:"].iter() {
+            assert_eq!(mac_name(&host_name(name)), *name);
+        }
+        assert_eq!(mac_name("50%"), "50%");
+    }
+
+    #[test]
+    fn sidecar() {
+        let dir = std::env::temp_dir().join(format!("marmelade-export-{}", std::process::id()));
+        let mut file = MacFile::new(PString::from("a"), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+        file.data = b"data".to_vec();
+        file.rsrc = b"rsrc".to_vec();
+        let written = write_mac_file(&file, &dir.join("a"), ExportFormat::Sidecar).unwrap();
+        assert_eq!(written, vec![dir.join("a"), dir.join(".rsrc").join("a"), dir.join(".finf").join("a")]);
+        assert_eq!(std::fs::read(dir.join(".rsrc").join("a")).unwrap(), b"rsrc");
+        assert_eq!(&std::fs::read(dir.join(".finf").join("a")).unwrap()[0..8], b"TEXTttxt");
+
+        let written = write_mac_file(&file, &dir.join("b"), ExportFormat::Raw).unwrap();
+        assert_eq!(written, vec![dir.join("b"), dir.join("b.rsrc")]);
+
+        let created = Utc.timestamp_opt(500_000_000, 0).unwrap();
+        let modified = Utc.timestamp_opt(600_000_000, 0).unwrap();
+        for path in [dir.join("b"), dir.join(".rsrc")].iter() {
+            set_times(path, &created, &modified).unwrap();
+            assert_eq!(std::fs::metadata(path).unwrap().modified().unwrap(), system_time(&modified));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,6 +3,8 @@ use crate::serialization::{
     SerialReadStorage
 };
 
+use crate::Result;
+
 use std::rc::Rc;
 use std::borrow::Borrow;
 
@@ -28,18 +30,18 @@ impl BlockAccess {
         }
     }
 
-    fn do_read_blk(&self, offset: u64, len: u64) -> std::io::Result<SerialReadStorage> {
+    fn do_read_blk(&self, offset: u64, len: u64) -> Result<SerialReadStorage> {
         let storage : &Box<dyn SerialAccess> = self.storage.borrow();
         storage.read(offset, len)
     }
 
-    fn do_read_extdescriptor(&self, descr: &ExtDescriptor, offset: u64, len: u64) -> std::io::Result<SerialReadStorage> {
+    fn do_read_extdescriptor(&self, descr: &ExtDescriptor, offset: u64, len: u64) -> Result<SerialReadStorage> {
         self.do_read_blk(
             self.alblk_start + (descr.xdrStABN as u64) * self.alblk_size + offset,
             len)
     }
 
-    pub fn read_extdatarec(&self, rec : &ExtDataRec, offset : u64, len : u64) -> std::io::Result<SerialReadStorage> {
+    pub fn read_extdatarec(&self, rec : &ExtDataRec, offset : u64, len : u64) -> Result<SerialReadStorage> {
         let mut left_offset = offset;
         let mut left_len = len;
         let mut output : SerialReadStorage = SerialReadStorage::from(vec![]); 
@@ -77,8 +79,10 @@ mod tests {
         ExtDataRec,
         ExtDescriptor,
         Rc,
-        SerialReadStorage
+        SerialReadStorage,
+        Result
     };
+    use crate::Error;
 
     #[derive(Debug)]
     struct MockDisk {
@@ -86,12 +90,12 @@ mod tests {
     }
 
     impl SerialAccess for MockDisk {
-        fn size(&self) -> std::io::Result<u64> {
+        fn size(&self) -> Result<u64> {
             Ok(self.size)
         }
-        fn read(&self, pos : u64, len : u64) -> std::io::Result<SerialReadStorage> {
+        fn read(&self, pos : u64, len : u64) -> Result<SerialReadStorage> {
             if pos + len >= self.size {
-                Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))
+                Err(Error::UnexpectedEof { offset: pos, len, size: self.size })
             } else {
                 let output : Vec<u8> = (pos as u8..(pos + len) as u8).collect();
                 Ok(SerialReadStorage::from(output))
@@ -110,7 +114,7 @@ mod tests {
     }
 
     #[test]
-    fn read_ext_single_block() -> std::io::Result<()> {
+    fn read_ext_single_block() -> Result<()> {
         let ba = mock_ba(50,8);
 
         let datarec = ExtDataRec ([
@@ -126,7 +130,7 @@ mod tests {
     }
 
     #[test]
-    fn read_ext_multi_block_offset() -> std::io::Result<()> {
+    fn read_ext_multi_block_offset() -> Result<()> {
         let ba = mock_ba(50,4);
 
         let datarec = ExtDataRec ([
@@ -142,7 +146,7 @@ mod tests {
     }
 
     #[test]
-    fn read_ext_no_continous_block() -> std::io::Result<()> {
+    fn read_ext_no_continous_block() -> Result<()> {
         let ba = mock_ba(50,4);
 
         let datarec = ExtDataRec ([
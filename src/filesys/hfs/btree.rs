@@ -8,17 +8,21 @@ use super::{
     types::{btree::BTHdrRec, btree::NodeDescriptor, common::ExtDataRec},
 };
 
+use crate::{Error, Result};
+
 use std::marker::PhantomData;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 
 #[derive(Debug)]
-struct BTreeNode {
+pub struct BTreeNode {
     nd: NodeDescriptor,
     recs: Vec<SerialReadStorage>,
 }
 
 impl BTreeNode {
-    pub fn new(rdr: &mut SerialReadStorage) -> std::io::Result<BTreeNode> {
-        rdr.seek(0);
+    pub fn new(rdr: &mut SerialReadStorage) -> Result<BTreeNode> {
+        rdr.seek(0)?;
 
         let nd = NodeDescriptor::read(rdr)?;
         let mut recs: Vec<SerialReadStorage> = Vec::with_capacity(nd.ndNRecs as usize);
@@ -26,10 +30,23 @@ impl BTreeNode {
         let size = rdr.size();
 
         for i in 0..nd.ndNRecs {
-            rdr.seek(size - 4 - 2 * (i as u64));
+            // Offset table grows backwards from end of node, one entry more
+            // than number of records, to give the end of the last record
+            let table_pos = size.checked_sub(4 + 2 * (i as u64))
+                .ok_or(Error::UnexpectedEof { offset: 0, len: 4 + 2 * (i as u64), size })?;
+            rdr.seek(table_pos)?;
             let idx_end = rdr.read_u16()?;
             let idx_start = rdr.read_u16()?;
-            recs.push(rdr.sub_reader(idx_start as u64, (idx_end - idx_start) as u64));
+            if idx_end < idx_start {
+                return Err(Error::invalid_value(
+                    table_pos,
+                    "BTreeNode",
+                    "offsets",
+                    format!("record end >= {}", idx_start),
+                    idx_end
+                ));
+            }
+            recs.push(rdr.sub_reader(idx_start as u64, (idx_end - idx_start) as u64)?);
         }
 
         Ok(BTreeNode { nd, recs })
@@ -87,24 +104,30 @@ pub struct BTreeHeaderNode {
 }
 
 impl BTreeHeaderNode {
-    pub fn new(rdr: &mut SerialReadStorage) -> std::io::Result<BTreeHeaderNode> {
-        Ok(BTreeHeaderNode::from(BTreeNode::new(rdr)?))
+    pub fn new(rdr: &mut SerialReadStorage) -> Result<BTreeHeaderNode> {
+        BTreeHeaderNode::try_from(BTreeNode::new(rdr)?)
     }
 }
 
-impl From<BTreeNode> for BTreeHeaderNode {
-    fn from(node: BTreeNode) -> BTreeHeaderNode {
+impl TryFrom<BTreeNode> for BTreeHeaderNode {
+    type Error = Error;
+
+    fn try_from(node: BTreeNode) -> Result<BTreeHeaderNode> {
         let mut node = node;
-        assert_eq!(node.nd.ndType, 1);
-        assert_eq!(node.nd.ndNRecs, 3);
+        if node.nd.ndType != 1 {
+            return Err(Error::invalid_value(8, "NodeDescriptor", "ndType", 1, node.nd.ndType));
+        }
+        if node.nd.ndNRecs != 3 {
+            return Err(Error::invalid_value(10, "NodeDescriptor", "ndNRecs", 3, node.nd.ndNRecs));
+        }
 
-        let header = BTHdrRec::read(&mut node.recs[0]).unwrap();
+        let header = BTHdrRec::read(&mut node.recs[0])?;
         // TODO: Read rest of records
 
-        BTreeHeaderNode {
+        Ok(BTreeHeaderNode {
             nd: node.nd,
             header,
-        }
+        })
     }
 }
 
@@ -123,31 +146,37 @@ where
     K: SerialRead + PartialOrd + std::fmt::Debug,
     V: SerialRead + std::fmt::Debug
 {
-    pub fn new(rdr: &mut SerialReadStorage) -> std::io::Result<BTreeLeafNode<K, V>> {
-        Ok(BTreeLeafNode::from(BTreeNode::new(rdr)?))
+    pub fn new(rdr: &mut SerialReadStorage) -> Result<BTreeLeafNode<K, V>> {
+        BTreeLeafNode::try_from(BTreeNode::new(rdr)?)
     }
 }
 
-impl<K, V> From<BTreeNode> for BTreeLeafNode<K, V>
+impl<K, V> TryFrom<BTreeNode> for BTreeLeafNode<K, V>
 where
     K: SerialRead + PartialOrd + std::fmt::Debug,
     V: SerialRead + std::fmt::Debug
 {
-    fn from(node: BTreeNode) -> BTreeLeafNode<K, V> {
-        assert_eq!(node.nd.ndType, -1i8);
+    type Error = Error;
+
+    fn try_from(node: BTreeNode) -> Result<BTreeLeafNode<K, V>> {
+        if node.nd.ndType != -1i8 {
+            return Err(Error::invalid_value(8, "NodeDescriptor", "ndType", -1, node.nd.ndType));
+        }
 
         let mut recs = Vec::with_capacity(node.recs.len());
 
         for mut rdr in node.recs {
             if let Ok(key) = K::read(&mut rdr) {
-                rdr.align(2);
+                if rdr.align(2).is_err() {
+                    continue;
+                }
                 if let Ok(val) = V::read(&mut rdr) {
                     recs.push((key, val));
                 }
             }
         }
 
-        BTreeLeafNode { nd: node.nd, recs }
+        Ok(BTreeLeafNode { nd: node.nd, recs })
     }
 }
 
@@ -158,7 +187,9 @@ where
 {
     btree: &'iter BTree<K, V>,
     nd: NodeDescriptor,
-    recs: Vec<(K, V)>
+    recs: Vec<(K, V)>,
+    // Nodes already iterated, to not loop forever on corrupt forward links
+    visited: HashSet<u32>
 }
 
 impl<'iter, K, V> std::iter::Iterator for BTreeIter<'iter, K, V>
@@ -169,16 +200,15 @@ where
     type Item = (K, V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(elem) = self.recs.pop() {
-            Some(elem)
-        } else {
-            if self.nd.ndFLink == 0 {
-                None
+        loop {
+            if let Some(elem) = self.recs.pop() {
+                break Some(elem)
+            } else if self.nd.ndFLink == 0 || !self.visited.insert(self.nd.ndFLink) {
+                break None
             } else {
                 let newiter = self.btree.iter_from_block(self.nd.ndFLink);
                 self.nd = newiter.nd;
                 self.recs = newiter.recs;
-                self.recs.pop()
             }
         }
     }
@@ -206,7 +236,7 @@ where
     pub fn new(
         storage: &BlockAccess,
         datarec: &ExtDataRec,
-    ) -> std::io::Result<BTree<K, V>> {
+    ) -> Result<BTree<K, V>> {
         let storage = storage.clone();
         let datarec = datarec.clone();
 
@@ -223,10 +253,12 @@ where
     }
 
     pub fn iter<'iter>(&'iter self) -> BTreeIter<'iter, K, V> {
-        self.iter_from_block(self.header.header.bthFNode)
+        let mut iter = self.iter_from_block(self.header.header.bthFNode);
+        iter.visited.insert(self.header.header.bthFNode);
+        iter
     }
 
-    fn try_iter_from_block<'iter>(&'iter self, blknum: u32) -> std::io::Result<BTreeIter<'iter, K, V>> {
+    fn try_iter_from_block<'iter>(&'iter self, blknum: u32) -> Result<BTreeIter<'iter, K, V>> {
         let mut lnblk = self.storage.read_extdatarec(
             &self.datarec,
             blknum as u64 * 512,
@@ -241,7 +273,8 @@ where
         Ok(BTreeIter::<'iter, K, V> {
             btree: self,
             nd: node.nd,
-            recs: recs
+            recs,
+            visited: HashSet::new()
         })
     }
 
@@ -259,7 +292,8 @@ where
                     ndNRecs:   0,
                     ndResv2:   0
                 },
-                recs: vec![]
+                recs: vec![],
+                visited: HashSet::new()
             }
         }
     }
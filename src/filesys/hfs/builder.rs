@@ -0,0 +1,744 @@
+use std::cmp::Ordering;
+
+use crate::{Error, Result};
+use crate::filesys::MacFile;
+use crate::serialization::{
+    SerialWriteStorage,
+    SerialWrite
+};
+use crate::types::{
+    DateTime,
+    PString
+};
+
+use super::types::{
+    btree::{BTHdrRec, NodeDescriptor},
+    catalog::{CatDataRec, CatKeyRec, CdrDirRec, CdrFilRec, CdrThdRec, DInfo, DXInfo},
+    common::ExtDataRec,
+    mdb::MDB
+};
+
+// Folder ID of the root folder, and the parent ID given to it
+pub const ROOT_ID: u32 = 2;
+const ROOT_PARENT_ID: u32 = 1;
+// IDs below are reserved for the system
+const FIRST_ID: u32 = 16;
+
+const SECTOR: u64 = 512;
+const NODE_SIZE: u64 = 512;
+const NODE_DESCRIPTOR_LEN: u64 = 14;
+// Maximum key length of the catalog, and index keys are always this long
+const CATALOG_KEY_LEN: u8 = 37;
+const EXTENTS_KEY_LEN: u16 = 7;
+// The map record in the header node has one bit per node
+const MAX_NODES: u64 = 256 * 8;
+
+// Standard floppy sizes, tried in order when no size is given
+const FLOPPY_SIZES: [u64; 3] = [400 * 1024, 800 * 1024, 1440 * 1024];
+const MAX_SIZE: u64 = 0x7fff_ffff;
+
+#[derive(Debug)]
+enum Entry {
+    Folder {
+        id: u32,
+        parent: u32,
+        name: PString,
+        created: DateTime,
+        modified: DateTime
+    },
+    File {
+        id: u32,
+        parent: u32,
+        file: MacFile
+    }
+}
+
+impl Entry {
+    fn parent(&self) -> u32 {
+        match self {
+            Entry::Folder { parent, .. } | Entry::File { parent, .. } => *parent
+        }
+    }
+
+    fn name(&self) -> &PString {
+        match self {
+            Entry::Folder { name, .. } => name,
+            Entry::File { file, .. } => &file.name
+        }
+    }
+}
+
+// Builds a new HFS volume in memory. Files and folders are added to an
+// in-memory tree, and laid out when the image is built, with each fork in one
+// contiguous extent so the extents overflow file stays empty.
+#[derive(Debug)]
+pub struct HfsBuilder {
+    name: PString,
+    created: DateTime,
+    entries: Vec<Entry>,
+    next_id: u32,
+    boot_blocks: Vec<u8>,
+    blessed: u32,
+    size: Option<u64>
+}
+
+// Layout of the volume for a given size
+#[derive(Debug)]
+struct Layout {
+    sectors: u64,
+    block_size: u64,
+    blocks: u64,
+    bitmap_sectors: u64,
+    clump_size: u64
+}
+
+impl Layout {
+    fn new(size: u64) -> Layout {
+        let sectors = size / SECTOR;
+        // Boot blocks, MDB and the bitmap at the start, alternate MDB and a
+        // reserved sector at the end. Block size grows with the volume as
+        // there can be at most 65535 allocation blocks.
+        let mut block_size = SECTOR;
+        while (sectors.saturating_sub(5) * SECTOR) / block_size > 0xffff {
+            block_size += SECTOR;
+        }
+        let max_blocks = (sectors.saturating_sub(5) * SECTOR) / block_size;
+        let bitmap_sectors = max_blocks.div_ceil(4096).max(1);
+        let blocks = (sectors.saturating_sub(5 + bitmap_sectors) * SECTOR) / block_size;
+        // Clump size for the B-tree files, about 1/128 of the volume as
+        // the Finder formats them, but no more nodes than the map record in
+        // the header node covers, as no map nodes are written
+        let max_clump = (MAX_NODES * NODE_SIZE / block_size).max(1) * block_size;
+        let clump_size = ((size / 128).div_ceil(block_size).max(1) * block_size).min(max_clump);
+        Layout { sectors, block_size, blocks, bitmap_sectors, clump_size }
+    }
+
+    fn first_block(&self) -> u64 {
+        3 + self.bitmap_sectors
+    }
+
+    fn blocks_for(&self, len: u64) -> u64 {
+        len.div_ceil(self.block_size)
+    }
+}
+
+impl HfsBuilder {
+    pub fn new(name: PString) -> Result<HfsBuilder> {
+        if name.is_empty() || name.len() > 27 || name.as_bytes().contains(&b':') {
+            return Err(Error::invalid_value(0, "MDB", "drVN", "1 to 27 bytes without ':'", name));
+        }
+        Ok(HfsBuilder {
+            name,
+            created: DateTime::now(),
+            entries: vec![],
+            next_id: FIRST_ID,
+            boot_blocks: vec![0; 1024],
+            blessed: 0,
+            size: None
+        })
+    }
+
+    pub fn set_created(&mut self, created: DateTime) {
+        self.created = created;
+    }
+
+    // Size of the image in bytes, rounded down to whole sectors. By default
+    // the smallest floppy size that fits is used, or just enough for the
+    // content if it doesn't fit on a floppy.
+    pub fn set_size(&mut self, size: u64) -> Result<()> {
+        if !(800 * SECTOR..=MAX_SIZE).contains(&size) {
+            return Err(Error::invalid_value(0, "HfsBuilder", "size", "400K to 2G", size));
+        }
+        self.size = Some(size);
+        Ok(())
+    }
+
+    // Boot blocks, at most 1024 bytes, as in the 'boot' 1 resource of the
+    // System file. Without them the volume isn't startable.
+    pub fn set_boot_blocks(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() > 1024 {
+            return Err(Error::invalid_value(0, "HfsBuilder", "boot blocks", "at most 1024 bytes", data.len()));
+        }
+        self.boot_blocks = data.to_vec();
+        self.boot_blocks.resize(1024, 0);
+        Ok(())
+    }
+
+    // Mark the folder holding the System file
+    pub fn bless(&mut self, folder: u32) -> Result<()> {
+        if !self.is_folder(folder) {
+            return Err(Error::NotFound);
+        }
+        self.blessed = folder;
+        Ok(())
+    }
+
+    fn is_folder(&self, id: u32) -> bool {
+        id == ROOT_ID || self.entries.iter().any(|entry| matches!(entry, Entry::Folder { id: folder, .. } if *folder == id))
+    }
+
+    fn check_name(&self, parent: u32, name: &PString) -> Result<()> {
+        if name.is_empty() || name.len() > 31 || name.as_bytes().contains(&b':') {
+            return Err(Error::invalid_value(0, "CatKeyRec", "ckrCName", "1 to 31 bytes without ':'", name));
+        }
+        if !self.is_folder(parent) {
+            return Err(Error::NotFound);
+        }
+        let exists = self.entries.iter()
+            .any(|entry| entry.parent() == parent && compare_names(entry.name(), name) == Ordering::Equal);
+        if exists {
+            return Err(Error::AlreadyExists);
+        }
+        Ok(())
+    }
+
+    // Add a folder, returning its ID for adding content to it
+    pub fn add_folder(&mut self, parent: u32, name: PString, created: DateTime, modified: DateTime) -> Result<u32> {
+        self.check_name(parent, &name)?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push(Entry::Folder { id, parent, name, created, modified });
+        Ok(id)
+    }
+
+    pub fn add_file(&mut self, parent: u32, file: MacFile) -> Result<u32> {
+        self.check_name(parent, &file.name)?;
+        if file.data.len() > u32::MAX as usize || file.rsrc.len() > u32::MAX as usize {
+            return Err(Error::invalid_value(0, "CdrFilRec", "filLgLen", "fork below 4G", file.name));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push(Entry::File { id, parent, file });
+        Ok(id)
+    }
+
+    fn valence(&self, folder: u32) -> usize {
+        self.entries.iter().filter(|entry| entry.parent() == folder).count()
+    }
+
+    fn fork_bytes(&self) -> u64 {
+        self.entries.iter().map(|entry| match entry {
+            Entry::File { file, .. } => file.data.len() as u64 + file.rsrc.len() as u64,
+            _ => 0
+        }).sum()
+    }
+
+    // Allocation blocks used by the forks of all files
+    fn fork_blocks(&self, layout: &Layout) -> u64 {
+        self.entries.iter().map(|entry| match entry {
+            Entry::File { file, .. } => layout.blocks_for(file.data.len() as u64) + layout.blocks_for(file.rsrc.len() as u64),
+            _ => 0
+        }).sum()
+    }
+
+    // Blocks of the extents and catalog files, for a catalog of the given
+    // number of nodes. Some free nodes are left for the Finder to use.
+    fn btree_blocks(&self, layout: &Layout, nodes: u64) -> (u64, u64) {
+        let clump = layout.clump_size / layout.block_size;
+        let extents = clump;
+        let spare = nodes / 4 + 4;
+        let catalog = layout.blocks_for((nodes + spare) * NODE_SIZE);
+        let catalog = catalog.div_ceil(clump) * clump;
+        // Rounding up to the clump must not exceed what the map record
+        // covers, unless the nodes themselves don't fit
+        let max_catalog = (MAX_NODES * NODE_SIZE / layout.block_size).max(layout.blocks_for((nodes + spare) * NODE_SIZE));
+        let catalog = catalog.min(max_catalog);
+        (extents, catalog)
+    }
+
+    fn fits(&self, layout: &Layout, nodes: u64) -> bool {
+        let (extents, catalog) = self.btree_blocks(layout, nodes);
+        layout.blocks <= 0xffff && extents + catalog + self.fork_blocks(layout) <= layout.blocks
+    }
+
+    fn choose_layout(&self, nodes: u64) -> Result<Layout> {
+        if let Some(size) = self.size {
+            let layout = Layout::new(size);
+            if !self.fits(&layout, nodes) {
+                return Err(Error::invalid_value(0, "HfsBuilder", "size", "room for all files", size));
+            }
+            return Ok(layout);
+        }
+        for size in FLOPPY_SIZES.iter() {
+            let layout = Layout::new(*size);
+            if self.fits(&layout, nodes) {
+                return Ok(layout);
+            }
+        }
+        // Grow in steps of 64K from the size of the content
+        let mut size = (self.fork_bytes() + nodes * NODE_SIZE) / (64 * 1024) * (64 * 1024);
+        loop {
+            size += 64 * 1024;
+            if size > MAX_SIZE {
+                return Err(Error::invalid_value(0, "HfsBuilder", "size", "content below 2G", self.fork_bytes()));
+            }
+            let layout = Layout::new(size);
+            if self.fits(&layout, nodes) {
+                return Ok(layout);
+            }
+        }
+    }
+
+    // First block of the data and resource fork of each entry, allocated
+    // one after the other from the given block. Empty forks and folders get
+    // block 0.
+    fn allocate(&self, layout: &Layout, mut next_block: u64) -> Vec<(u64, u64)> {
+        let mut alloc = |len: usize| {
+            let count = layout.blocks_for(len as u64);
+            let start = if count > 0 { next_block } else { 0 };
+            next_block += count;
+            start
+        };
+        self.entries.iter().map(|entry| match entry {
+            Entry::File { file, .. } => {
+                let data = alloc(file.data.len());
+                (data, alloc(file.rsrc.len()))
+            },
+            Entry::Folder { .. } => (0, 0)
+        }).collect()
+    }
+
+    // Catalog records in key order, with a thread record for each file and
+    // folder
+    fn catalog_records(&self, layout: &Layout, starts: &[(u64, u64)]) -> Vec<(CatKeyRec, CatDataRec)> {
+        let dir_rec = |id: u32, valence: usize, created: DateTime, modified: DateTime| CatDataRec::CdrDirRec(CdrDirRec {
+            dirFlags: 0,
+            dirVal: valence as i16,
+            dirDirID: id,
+            dirCrDat: created,
+            dirMdDat: modified,
+            dirBkDat: DateTime::from_mac_u32(0),
+            dirUsrInfo: DInfo::default(),
+            dirFndrInfo: DXInfo::default(),
+            dirResrv: [0; 4]
+        });
+        let thread = |parent: u32, name: &PString| CdrThdRec {
+            thdResrv: [0; 2],
+            thdParID: parent,
+            thdCName: name.clone()
+        };
+
+        let mut recs = vec![
+            (CatKeyRec::new(ROOT_PARENT_ID, self.name.clone()), dir_rec(ROOT_ID, self.valence(ROOT_ID), self.created, self.created)),
+            (CatKeyRec::new(ROOT_ID, PString::from("")), CatDataRec::CdrThdRec(thread(ROOT_PARENT_ID, &self.name)))
+        ];
+        for (entry, (data_start, rsrc_start)) in self.entries.iter().zip(starts.iter()) {
+            match entry {
+                Entry::Folder { id, parent, name, created, modified } => {
+                    recs.push((CatKeyRec::new(*parent, name.clone()), dir_rec(*id, self.valence(*id), *created, *modified)));
+                    recs.push((CatKeyRec::new(*id, PString::from("")), CatDataRec::CdrThdRec(thread(*parent, name))));
+                },
+                Entry::File { id, parent, file } => {
+                    let extent = |start: u64, len: usize| {
+                        let count = layout.blocks_for(len as u64);
+                        (ExtDataRec::single(start as u16, count as u16), (count * layout.block_size) as u32)
+                    };
+                    let (data_ext, data_len) = extent(*data_start, file.data.len());
+                    let (rsrc_ext, rsrc_len) = extent(*rsrc_start, file.rsrc.len());
+                    let record = CdrFilRec {
+                        // Locked, and has a file thread record
+                        filFlags: file.locked as u8 | 0x02,
+                        filTyp: 0,
+                        filUsrWds: file.info.clone(),
+                        filFlNum: *id,
+                        filStBlk: data_ext.0[0].xdrStABN,
+                        filLgLen: file.data.len() as u32,
+                        filPyLen: data_len,
+                        filRStBlk: rsrc_ext.0[0].xdrStABN,
+                        filRLgLen: file.rsrc.len() as u32,
+                        filRPyLen: rsrc_len,
+                        filCrDat: file.created,
+                        filMdDat: file.modified,
+                        filBkDat: DateTime::from_mac_u32(0),
+                        filFndrInfo: file.ext_info.clone(),
+                        filClpSize: 0,
+                        filExtRec: data_ext,
+                        filRExtRec: rsrc_ext,
+                        filResrv: 0
+                    };
+                    recs.push((CatKeyRec::new(*parent, file.name.clone()), CatDataRec::CdrFilRec(record)));
+                    recs.push((CatKeyRec::new(*id, PString::from("")), CatDataRec::CdrFThdRec(thread(*parent, &file.name))));
+                }
+            }
+        }
+        recs.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+        recs
+    }
+
+    pub fn build(&self) -> Result<Vec<u8>> {
+        // The number of catalog nodes doesn't depend on the layout, as
+        // records have fixed size
+        let probe = Layout::new(MAX_SIZE);
+        let probe = self.catalog_records(&probe, &self.allocate(&probe, 0));
+        let nodes = catalog_nodes(&probe)?.len() as u64 + 1;
+        let layout = self.choose_layout(nodes)?;
+        let (extents_blocks, catalog_blocks) = self.btree_blocks(&layout, nodes);
+
+        let starts = self.allocate(&layout, extents_blocks + catalog_blocks);
+        let records = self.catalog_records(&layout, &starts);
+        let leaf_count = records.len() as u32;
+        let tree = catalog_nodes(&records)?;
+        let catalog_nodes_total = catalog_blocks * layout.block_size / NODE_SIZE;
+        if catalog_nodes_total > MAX_NODES {
+            return Err(Error::invalid_value(0, "HfsBuilder", "catalog", format!("at most {} nodes", MAX_NODES), catalog_nodes_total));
+        }
+
+        let used_blocks = extents_blocks + catalog_blocks + self.fork_blocks(&layout);
+        let mut wtr = SerialWriteStorage::new();
+
+        wtr.write_bytes(&self.boot_blocks)?;
+
+        let files = self.entries.iter().filter(|entry| matches!(entry, Entry::File { .. })).count();
+        let folders = self.entries.len() - files;
+        let root_files = self.entries.iter().filter(|entry| matches!(entry, Entry::File { parent, .. } if *parent == ROOT_ID)).count();
+        let root_folders = self.valence(ROOT_ID) - root_files;
+        let mut finder_info = [0i32; 8];
+        finder_info[0] = self.blessed as i32;
+        let mdb = MDB {
+            drSigWord: 0x4244,
+            drCrDate: self.created,
+            drLsMod: self.created,
+            // Cleanly unmounted
+            drAtrb: 0x0100,
+            drNmFls: root_files as i16,
+            drVBMSt: 3,
+            drAllocPtr: used_blocks as i16,
+            drNmAlBlks: layout.blocks as u16,
+            drAlBlkSiz: layout.block_size as i32,
+            drClpSiz: (4 * layout.block_size) as i32,
+            drAlBlSt: layout.first_block() as i16,
+            drNxtCNID: self.next_id as i32,
+            drFreeBks: (layout.blocks - used_blocks) as u16,
+            drVN: self.name.clone(),
+            drVolBkUp: DateTime::from_mac_u32(0),
+            drVSeqNum: 0,
+            drWrCnt: 0,
+            drXTClpSiz: layout.clump_size as i32,
+            drCTClpSiz: layout.clump_size as i32,
+            drNmRtDirs: root_folders as i16,
+            drFilCnt: files as i32,
+            drDirCnt: folders as i32,
+            drFndrInfo: finder_info,
+            drVCSize: 0,
+            drVBMCSize: 0,
+            drCtlCSize: 0,
+            drXTFlSize: (extents_blocks * layout.block_size) as i32,
+            drXTExtRec: ExtDataRec::single(0, extents_blocks as u16),
+            drCTFlSize: (catalog_blocks * layout.block_size) as i32,
+            drCTExtRec: ExtDataRec::single(extents_blocks as u16, catalog_blocks as u16)
+        };
+        wtr.seek(2 * SECTOR)?;
+        mdb.write(&mut wtr)?;
+        wtr.seek((layout.sectors - 2) * SECTOR)?;
+        mdb.write(&mut wtr)?;
+
+        // Volume bitmap, with the used blocks all at the start
+        let mut bitmap = vec![0u8; (layout.bitmap_sectors * SECTOR) as usize];
+        for block in 0..used_blocks as usize {
+            bitmap[block / 8] |= 0x80 >> (block % 8);
+        }
+        wtr.seek(3 * SECTOR)?;
+        wtr.write_bytes(&bitmap)?;
+
+        let block_offset = |block: u64| layout.first_block() * SECTOR + block * layout.block_size;
+
+        let extents_nodes = extents_blocks * layout.block_size / NODE_SIZE;
+        wtr.seek(block_offset(0))?;
+        write_header_node(&mut wtr, &BTHdrRec {
+            bthDepth: 0,
+            bthRoot: 0,
+            bthNRecs: 0,
+            bthFNode: 0,
+            bthLNode: 0,
+            bthNodeSize: NODE_SIZE as u16,
+            bthKeyLen: EXTENTS_KEY_LEN,
+            bthNNodes: extents_nodes as u32,
+            bthFree: extents_nodes as u32 - 1,
+            bthResv: [0; 19]
+        })?;
+
+        let catalog_start = block_offset(extents_blocks);
+        let nodes = tree.len() as u64 + 1;
+        let depth = tree.last().map(|node| node.height as u16).unwrap_or(0);
+        let leaves = tree.iter().filter(|node| node.height == 1).count() as u32;
+        wtr.seek(catalog_start)?;
+        write_header_node(&mut wtr, &BTHdrRec {
+            bthDepth: depth,
+            bthRoot: tree.len() as u32,
+            bthNRecs: leaf_count,
+            bthFNode: 1,
+            bthLNode: leaves,
+            bthNodeSize: NODE_SIZE as u16,
+            bthKeyLen: CATALOG_KEY_LEN as u16,
+            bthNNodes: catalog_nodes_total as u32,
+            bthFree: (catalog_nodes_total - nodes) as u32,
+            bthResv: [0; 19]
+        })?;
+        for (index, node) in tree.iter().enumerate() {
+            wtr.seek(catalog_start + (index as u64 + 1) * NODE_SIZE)?;
+            wtr.write_bytes(&node.data)?;
+        }
+
+        for (entry, (data_start, rsrc_start)) in self.entries.iter().zip(starts.iter()) {
+            if let Entry::File { file, .. } = entry {
+                for (fork, start) in [(&file.data, data_start), (&file.rsrc, rsrc_start)].iter() {
+                    if !fork.is_empty() {
+                        wtr.seek(block_offset(**start))?;
+                        wtr.write_bytes(fork)?;
+                    }
+                }
+            }
+        }
+
+        wtr.seek(layout.sectors * SECTOR)?;
+        Ok(wtr.to_vec())
+    }
+}
+
+// Sort order of characters in names. Names compare case insensitively, with
+// accented letters ordered right after the plain letter. This approximates
+// the ordering used by RelString, which the File Manager uses for catalog
+// keys, for Mac Roman letters.
+fn char_order(c: u8) -> u16 {
+    let (base, accent) = match c {
+        b'a'..=b'z' => (c - 32, 0),
+        0x80 | 0x8a => (b'A', 4), 0x81 | 0x8c => (b'A', 6), 0x87 | 0xe7 => (b'A', 2),
+        0x88 | 0xcb => (b'A', 1), 0x89 | 0xe5 => (b'A', 3), 0x8b | 0xcc => (b'A', 5),
+        0x82 | 0x8d => (b'C', 7),
+        0x83 | 0x8e => (b'E', 2), 0x8f | 0xe9 => (b'E', 1), 0x90 | 0xe6 => (b'E', 3), 0x91 | 0xe8 => (b'E', 4),
+        0x92 | 0xea => (b'I', 2), 0x93 | 0xed => (b'I', 1), 0x94 | 0xeb => (b'I', 3), 0x95 | 0xec => (b'I', 4),
+        0x84 | 0x96 => (b'N', 5),
+        0x97 | 0xee => (b'O', 2), 0x98 | 0xf1 => (b'O', 1), 0x99 | 0xef => (b'O', 3), 0x85 | 0x9a => (b'O', 4),
+        0x9b | 0xcd => (b'O', 5),
+        0x9c | 0xf2 => (b'U', 2), 0x9d | 0xf4 => (b'U', 1), 0x9e | 0xf3 => (b'U', 3), 0x86 | 0x9f => (b'U', 4),
+        0xd8 | 0xd9 => (b'Y', 4),
+        c => (c, 0)
+    };
+    (base as u16) << 3 | accent
+}
+
+pub fn compare_names(a: &PString, b: &PString) -> Ordering {
+    let a = a.as_bytes().iter().map(|c| char_order(*c));
+    let b = b.as_bytes().iter().map(|c| char_order(*c));
+    a.cmp(b)
+}
+
+fn compare_keys(a: &CatKeyRec, b: &CatKeyRec) -> Ordering {
+    a.ckrParID.cmp(&b.ckrParID).then_with(|| compare_names(&a.ckrCName, &b.ckrCName))
+}
+
+// Catalog node, with the first key for the index node above it
+struct Node {
+    height: u8,
+    first_key: CatKeyRec,
+    data: Vec<u8>
+}
+
+// Fill nodes of one level with records in order, as many as fits in each
+fn pack_nodes(records: Vec<(CatKeyRec, Vec<u8>)>, node_type: i8, height: u8) -> Result<Vec<Node>> {
+    let mut nodes: Vec<(CatKeyRec, Vec<Vec<u8>>)> = vec![];
+    let mut used = NODE_SIZE;
+    for (key, rec) in records {
+        // Record, and its entry in the offset table
+        let needed = rec.len() as u64 + 2;
+        // The offset table has one more entry than the number of records
+        if used + needed > NODE_SIZE - NODE_DESCRIPTOR_LEN - 2 {
+            nodes.push((key, vec![]));
+            used = 0;
+        }
+        used += needed;
+        nodes.last_mut().unwrap().1.push(rec);
+    }
+
+    nodes.into_iter().map(|(first_key, recs)| {
+        let mut wtr = SerialWriteStorage::new();
+        NodeDescriptor {
+            ndFLink: 0,
+            ndBLink: 0,
+            ndType: node_type,
+            ndNHeight: height as i8,
+            ndNRecs: recs.len() as u16,
+            ndResv2: 0
+        }.write(&mut wtr)?;
+        let mut offsets = vec![NODE_DESCRIPTOR_LEN as u16];
+        for rec in recs.iter() {
+            wtr.write_bytes(rec)?;
+            offsets.push(wtr.pos() as u16);
+        }
+        for (index, offset) in offsets.iter().enumerate() {
+            wtr.seek(NODE_SIZE - 2 * (index as u64 + 1))?;
+            wtr.write_u16(*offset)?;
+        }
+        Ok(Node { height, first_key, data: wtr.to_vec() })
+    }).collect()
+}
+
+// Catalog nodes after the header node, leaves first and the root last. Nodes
+// at each level are linked to each other.
+fn catalog_nodes(records: &[(CatKeyRec, CatDataRec)]) -> Result<Vec<Node>> {
+    let mut leaf_records = Vec::with_capacity(records.len());
+    for (key, rec) in records {
+        let mut wtr = SerialWriteStorage::new();
+        key.write(&mut wtr)?;
+        wtr.align(2)?;
+        rec.write(&mut wtr)?;
+        leaf_records.push((CatKeyRec::new(key.ckrParID, key.ckrCName.clone()), wtr.to_vec()));
+    }
+
+    let mut tree: Vec<Node> = vec![];
+    let mut level = pack_nodes(leaf_records, -1, 1)?;
+    loop {
+        let first = tree.len() as u32 + 1;
+        let count = level.len() as u32;
+        for (index, node) in level.iter_mut().enumerate() {
+            let index = index as u32;
+            let flink = if index + 1 < count { first + index + 1 } else { 0 };
+            let blink = if index > 0 { first + index - 1 } else { 0 };
+            node.data[0..4].copy_from_slice(&flink.to_be_bytes());
+            node.data[4..8].copy_from_slice(&blink.to_be_bytes());
+        }
+        let height = level[0].height;
+        if count == 1 {
+            tree.extend(level);
+            break;
+        }
+
+        // Index records have keys padded to the maximum length, followed by
+        // the node number of the child
+        let mut index_records = Vec::with_capacity(level.len());
+        for (index, node) in level.iter().enumerate() {
+            let mut wtr = SerialWriteStorage::new();
+            wtr.write_u8(CATALOG_KEY_LEN)?;
+            wtr.write_u8(0)?;
+            wtr.write_u32(node.first_key.ckrParID)?;
+            node.first_key.ckrCName.write(&mut wtr)?;
+            wtr.seek(CATALOG_KEY_LEN as u64 + 1)?;
+            wtr.write_u32(first + index as u32)?;
+            index_records.push((CatKeyRec::new(node.first_key.ckrParID, node.first_key.ckrCName.clone()), wtr.to_vec()));
+        }
+        tree.extend(level);
+        level = pack_nodes(index_records, 0, height + 1)?;
+    }
+    Ok(tree)
+}
+
+// Header node with the header record, an empty user data record and the map
+// record for node allocation
+fn write_header_node(wtr: &mut SerialWriteStorage, header: &BTHdrRec) -> Result<()> {
+    let start = wtr.pos();
+    NodeDescriptor {
+        ndFLink: 0,
+        ndBLink: 0,
+        ndType: 1,
+        ndNHeight: 0,
+        ndNRecs: 3,
+        ndResv2: 0
+    }.write(wtr)?;
+    header.write(wtr)?;
+    wtr.pad(128)?;
+    let used = header.bthNNodes - header.bthFree;
+    let mut map = [0u8; 256];
+    for node in 0..used as usize {
+        map[node / 8] |= 0x80 >> (node % 8);
+    }
+    wtr.write_bytes(&map)?;
+    for (index, offset) in [14u16, 120, 248, 504].iter().enumerate() {
+        wtr.seek(start + NODE_SIZE - 2 * (index as u64 + 1))?;
+        wtr.write_u16(*offset)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::io::Cursor;
+
+    use super::{compare_names, HfsBuilder, Layout, MAX_NODES, MAX_SIZE, NODE_SIZE, ROOT_ID};
+    use crate::Error;
+    use crate::filesys::MacFile;
+    use crate::filesys::hfs::HfsImage;
+    use crate::serialization::SerialAdaptor;
+    use crate::types::{DateTime, OSType, PString};
+
+    #[test]
+    fn order() {
+        let cmp = |a: &str, b: &str| compare_names(&PString::from(a), &PString::from(b));
+        assert_eq!(cmp("read me", "Read Me"), Ordering::Equal);
+        assert_eq!(cmp("a", "B"), Ordering::Less);
+        assert_eq!(cmp("Zed", "ab"), Ordering::Greater);
+        assert_eq!(cmp("é", "E"), Ordering::Greater);
+        assert_eq!(cmp("é", "F"), Ordering::Less);
+        assert_eq!(cmp("É", "é"), Ordering::Equal);
+        assert_eq!(cmp("", "a"), Ordering::Less);
+    }
+
+    #[test]
+    fn build() {
+        let mut builder = HfsBuilder::new(PString::from("Test")).unwrap();
+        let date = DateTime::from_mac_u32(0xa61e6e80);
+        let folder = builder.add_folder(ROOT_ID, PString::from("Folder"), date, date).unwrap();
+        let mut file = MacFile::new(PString::from("Read Me"), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+        file.data = b"Hello".to_vec();
+        file.rsrc = vec![7; 1000];
+        builder.add_file(folder, file.clone()).unwrap();
+        assert!(matches!(builder.add_file(folder, file.clone()), Err(Error::AlreadyExists)));
+        assert!(matches!(builder.add_file(99, file), Err(Error::NotFound)));
+        let long = MacFile::new(PString::from(&[b'a'; 32][..]), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+        assert!(builder.add_file(ROOT_ID, long).is_err());
+        for index in 0..100 {
+            let name = PString::from(format!("File {}", index).as_str());
+            builder.add_file(ROOT_ID, MacFile::new(name, OSType::from(b"TEXT"), OSType::from(b"ttxt"))).unwrap();
+        }
+        builder.bless(folder).unwrap();
+
+        let image = builder.build().unwrap();
+        assert_eq!(image.len(), 400 * 1024);
+        let img = HfsImage::from(SerialAdaptor::new(Cursor::new(image))).unwrap();
+        assert_eq!(img.get_name(), "Test");
+        assert_eq!(img.object_count(), (101, 1));
+        assert_eq!(img.open_root().count(), 101);
+
+        let dir = img.locate("Folder").unwrap().to_dir().unwrap();
+        assert_eq!((dir.get_id(), dir.get_valence()), (folder, 1));
+        let file = img.locate("folder:read me").unwrap().to_file().unwrap();
+        let read = file.read_mac_file().unwrap();
+        assert_eq!(read.info.fdCreator, OSType::from(b"ttxt"));
+        assert_eq!(read.data, b"Hello");
+        assert_eq!(read.rsrc, vec![7; 1000]);
+        assert!(img.locate("File 99").unwrap().is_file());
+    }
+
+    #[test]
+    fn size() {
+        let mut builder = HfsBuilder::new(PString::from("Test")).unwrap();
+        assert!(builder.set_size(1024).is_err());
+        builder.set_size(400 * 1024).unwrap();
+        let mut file = MacFile::new(PString::from("Big"), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+        file.data = vec![0; 500 * 1024];
+        builder.add_file(ROOT_ID, file).unwrap();
+        assert!(builder.build().is_err());
+        builder.set_size(2 * 1024 * 1024).unwrap();
+        assert_eq!(builder.build().unwrap().len(), 2 * 1024 * 1024);
+    }
+
+    #[test]
+    fn large() {
+        let mut builder = HfsBuilder::new(PString::from("Large")).unwrap();
+        builder.set_size(200 * 1024 * 1024).unwrap();
+        let image = builder.build().unwrap();
+        assert_eq!(image.len(), 200 * 1024 * 1024);
+        let img = HfsImage::from(SerialAdaptor::new(Cursor::new(image))).unwrap();
+        assert_eq!(img.get_name(), "Large");
+        assert_eq!(img.open_root().count(), 0);
+
+        // The B-tree files fit the header map record on the largest volumes
+        for size in [129 * 1024 * 1024, MAX_SIZE].iter() {
+            let layout = Layout::new(*size);
+            let builder = HfsBuilder::new(PString::from("Large")).unwrap();
+            let (extents, catalog) = builder.btree_blocks(&layout, 2);
+            assert!(extents * layout.block_size / NODE_SIZE <= MAX_NODES);
+            assert!(catalog * layout.block_size / NODE_SIZE <= MAX_NODES);
+        }
+    }
+}
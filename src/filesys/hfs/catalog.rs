@@ -1,3 +1,5 @@
+use crate::Result;
+
 use super::{
     types::{
         common::{
@@ -5,7 +7,8 @@ use super::{
         },
         catalog::{
             CatKeyRec,
-            CatDataRec
+            CatDataRec,
+            CdrThdRec
         }
     },
     blockaccess::BlockAccess,
@@ -21,13 +24,33 @@ pub struct Catalog {
 }
 
 impl Catalog {
-    pub fn new(storage : &BlockAccess, datarec: &ExtDataRec) -> std::io::Result<Catalog> {
+    pub fn new(storage : &BlockAccess, datarec: &ExtDataRec) -> Result<Catalog> {
         let btree = BTree::new(storage, datarec)?;
         Ok(Catalog{
             btree
         })
     }
 
+    // Thread record of a folder, or of a file with a file ID reference,
+    // giving its parent and name
+    pub fn thread(&self, id: u32) -> Option<CdrThdRec> {
+        self.btree.iter()
+            .filter(|(key, _)| key.ckrParID == id && key.ckrCName.is_empty())
+            .find_map(|(_, data)| match data {
+                CatDataRec::CdrThdRec(thread) | CatDataRec::CdrFThdRec(thread) => Some(thread),
+                _ => None
+            })
+    }
+
+    // File or folder with the given ID, found by searching the whole catalog
+    pub fn object(&self, id: u32) -> Option<(CatKeyRec, CatDataRec)> {
+        self.btree.iter().find(|(_, data)| match data {
+            CatDataRec::CdrFilRec(file) => file.filFlNum == id,
+            CatDataRec::CdrDirRec(dir) => dir.dirDirID == id,
+            _ => false
+        })
+    }
+
     pub fn dir<'iter>(&'iter self, dir: u32) -> CatalogIterator<'iter> {
         CatalogIterator {
             iter: self.btree.iter(),
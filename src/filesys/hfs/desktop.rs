@@ -0,0 +1,244 @@
+use crate::Result;
+use crate::filesys::rsrc::Rsrc;
+use crate::filesys::rsrc::decode::{Bundle, FileReference};
+use crate::serialization::{
+    SerialAdaptor,
+    SerialRead
+};
+use crate::types::{
+    OSType,
+    PString
+};
+
+use super::{HfsFileRef, HfsObjRef};
+
+// The Finder keeps information about the files of a volume in a database at
+// the root of the volume. Up to System 6 it's the resource fork of the
+// invisible "Desktop" file. The "Desktop DB" and "Desktop DF" files of the
+// System 7 Desktop Manager are not read, as their layout isn't documented.
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum IconKind {
+    // 'ICN#'
+    Large,
+    // 'icl4'
+    Large4,
+    // 'icl8'
+    Large8,
+    // 'ics#'
+    Small,
+    // 'ics4'
+    Small4,
+    // 'ics8'
+    Small8
+}
+
+impl IconKind {
+    pub const ALL: [IconKind; 6] = [
+        IconKind::Large,
+        IconKind::Large4,
+        IconKind::Large8,
+        IconKind::Small,
+        IconKind::Small4,
+        IconKind::Small8
+    ];
+
+    pub fn rsrc_type(&self) -> OSType {
+        match self {
+            IconKind::Large => OSType::from(b"ICN#"),
+            IconKind::Large4 => OSType::from(b"icl4"),
+            IconKind::Large8 => OSType::from(b"icl8"),
+            IconKind::Small => OSType::from(b"ics#"),
+            IconKind::Small4 => OSType::from(b"ics4"),
+            IconKind::Small8 => OSType::from(b"ics8")
+        }
+    }
+}
+
+// Application known to the Finder, for opening documents by creator
+#[derive(Debug)]
+pub struct DesktopApp {
+    pub creator: OSType,
+    // Folder holding the application
+    pub parent: u32,
+    pub name: PString
+}
+
+struct DesktopIcon {
+    creator: OSType,
+    file_type: OSType,
+    kind: IconKind,
+    data: Vec<u8>
+}
+
+pub struct Desktop {
+    apps: Vec<DesktopApp>,
+    icons: Vec<DesktopIcon>,
+    // Get Info comments, by the comment ID in the Finder information
+    comments: Vec<(i16, PString)>
+}
+
+impl Desktop {
+    // Read the resource fork of a Desktop file. Application bundles are
+    // copied into it by the Finder, and are used for the icons.
+    pub fn from_desktop_file(file: &HfsFileRef) -> Result<Desktop> {
+        let rsrc = Rsrc::new(SerialAdaptor::new(file.open_rsrc()))?;
+        let mut desktop = Desktop::empty();
+
+        // 'APPL' holds a list of creator, folder ID and name, word aligned
+        let appl = OSType::from(b"APPL");
+        for info in rsrc.resources(&appl) {
+            let mut rdr = info.open()?;
+            while rdr.pos() + 9 <= rdr.size() {
+                let creator = OSType::read(&mut rdr)?;
+                let parent = rdr.read_u32()?;
+                let name = PString::read(&mut rdr)?;
+                if rdr.pos() < rdr.size() {
+                    rdr.align(2)?;
+                }
+                desktop.apps.push(DesktopApp { creator, parent, name });
+            }
+        }
+
+        let fcmt = OSType::from(b"FCMT");
+        for info in rsrc.resources(&fcmt) {
+            desktop.comments.push((info.id(), PString::read(&mut info.open()?)?));
+        }
+
+        let bndl = OSType::from(b"BNDL");
+        let fref = OSType::from(b"FREF");
+        let icn = OSType::from(b"ICN#");
+        for info in rsrc.resources(&bndl) {
+            let bundle = match Bundle::read(&mut info.open()?) {
+                Ok(bundle) => bundle,
+                Err(_) => continue
+            };
+            let refs = bundle.types.iter()
+                .filter(|t| t.rsrc_type == fref)
+                .flat_map(|t| t.mappings.iter());
+            for mapping in refs {
+                let file_ref = match rsrc.open(fref.clone(), mapping.rsrc_id).and_then(|mut rdr| FileReference::read(&mut rdr)) {
+                    Ok(file_ref) => file_ref,
+                    Err(_) => continue
+                };
+                // Color icons have the same ID as the 'ICN#'
+                let id = match bundle.rsrc_id(&icn, file_ref.local_id) {
+                    Some(id) => id,
+                    None => continue
+                };
+                for kind in IconKind::ALL.iter() {
+                    if let Ok(rdr) = rsrc.open(kind.rsrc_type(), id) {
+                        desktop.icons.push(DesktopIcon {
+                            creator: bundle.signature.clone(),
+                            file_type: file_ref.file_type.clone(),
+                            kind: *kind,
+                            data: rdr.to_vec()
+                        });
+                    }
+                }
+            }
+        }
+        Ok(desktop)
+    }
+
+    fn empty() -> Desktop {
+        Desktop {
+            apps: vec![],
+            icons: vec![],
+            comments: vec![]
+        }
+    }
+
+    pub fn applications(&self) -> impl Iterator<Item = &DesktopApp> {
+        self.apps.iter()
+    }
+
+    // Application opening documents with the given creator
+    pub fn application(&self, creator: &OSType) -> Option<&DesktopApp> {
+        self.apps.iter().find(|app| &app.creator == creator)
+    }
+
+    // Icon data for a file type, in the format of the resource type of the
+    // icon kind
+    pub fn icon(&self, creator: &OSType, file_type: &OSType, kind: IconKind) -> Option<&[u8]> {
+        self.icons.iter()
+            .find(|icon| &icon.creator == creator && &icon.file_type == file_type && icon.kind == kind)
+            .map(|icon| &icon.data[..])
+    }
+
+    pub fn comment(&self, obj: &HfsObjRef) -> Option<&PString> {
+        let comment_id = match obj {
+            HfsObjRef::FileRef(file) => file.fr.filFndrInfo.fdComment,
+            HfsObjRef::DirRef(dir) => dir.dr.dirFndrInfo.frComment
+        };
+        self.comments.iter()
+            .find(|(comment_for, _)| comment_id != 0 && *comment_for == comment_id)
+            .map(|(_, comment)| comment)
+    }
+}
+
+impl std::fmt::Debug for Desktop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Desktop")
+            .field("apps", &self.apps)
+            .field("icons", &format!("{} icons", self.icons.len()))
+            .field("comments", &self.comments.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::filesys::MacFile;
+    use crate::filesys::hfs::{HfsBuilder, HfsImage, ROOT_ID};
+    use crate::filesys::rsrc::RsrcFork;
+    use crate::serialization::SerialAdaptor;
+    use crate::types::{DateTime, OSType, PString};
+
+    #[test]
+    fn desktop_file() {
+        let mut builder = HfsBuilder::new(PString::from("Test")).unwrap();
+        let date = DateTime::from_mac_u32(0xa61e6e80);
+        let apps = builder.add_folder(ROOT_ID, PString::from("Apps"), date, date).unwrap();
+
+        let mut appl = b"ttxt".to_vec();
+        appl.extend_from_slice(&apps.to_be_bytes());
+        appl.extend_from_slice(b"\x09TeachText");
+        let mut fork = RsrcFork::new();
+        fork.add(OSType::from(b"APPL"), 0, None, appl).unwrap();
+        fork.add(OSType::from(b"FCMT"), 5, None, b"\x05Hello".to_vec()).unwrap();
+        let mut desktop = MacFile::new(PString::from("Desktop"), OSType::from(b"FNDR"), OSType::from(b"ERIK"));
+        desktop.rsrc = fork.to_bytes().unwrap();
+        builder.add_file(ROOT_ID, desktop).unwrap();
+        let mut doc = MacFile::new(PString::from("Read Me"), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+        doc.ext_info.fdComment = 5;
+        builder.add_file(apps, doc).unwrap();
+
+        let img = HfsImage::from(SerialAdaptor::new(Cursor::new(builder.build().unwrap()))).unwrap();
+        let desktop = img.desktop().unwrap().unwrap();
+        let app = desktop.application(&OSType::from(b"ttxt")).unwrap();
+        assert_eq!((app.parent, &app.name), (apps, &PString::from("TeachText")));
+        assert!(desktop.application(&OSType::from(b"MPNT")).is_none());
+        assert_eq!(img.folder_path(app.parent).as_deref(), Some("Apps"));
+        assert_eq!(img.folder_path(ROOT_ID).as_deref(), Some(""));
+
+        let doc = img.locate("Apps:Read Me").unwrap();
+        assert_eq!(desktop.comment(&doc), Some(&PString::from("Hello")));
+        assert_eq!(desktop.comment(&img.locate("Apps").unwrap()), None);
+    }
+
+    #[test]
+    fn desktop_manager() {
+        let mut builder = HfsBuilder::new(PString::from("Test")).unwrap();
+        for name in ["Desktop DB", "Desktop DF"].iter() {
+            builder.add_file(ROOT_ID, MacFile::new(PString::from(*name), OSType::from(b"BTFL"), OSType::from(b"DMGR"))).unwrap();
+        }
+        let img = HfsImage::from(SerialAdaptor::new(Cursor::new(builder.build().unwrap()))).unwrap();
+        assert!(img.desktop().unwrap().is_none());
+    }
+}
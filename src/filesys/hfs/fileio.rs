@@ -39,7 +39,7 @@ impl Seek for FileIO {
             SeekFrom::End(offset) => offset as i64 + self.size as i64,
         };
 
-        if newpos < 0 || newpos >= self.size as i64 {
+        if newpos < 0 || newpos > self.size as i64 {
             Err(Error::from(ErrorKind::InvalidInput))
         } else {
             self.cur = newpos as u64;
@@ -54,18 +54,19 @@ impl Read for FileIO {
         let buf_len: i64 = buf.len() as i64;
         let to_read = if data_left > buf_len { buf_len } else { data_left };
 
-        let mut reader = self.storage.read_extdatarec(
+        let reader = self.storage.read_extdatarec(
             &self.rec,
             self.cur,
             to_read as u64
         )?;
 
-        for i in 0..to_read as usize {
-            if let Ok(byte) = reader.read_u8() {
-                self.cur += 1;
-                buf[i] = byte;
-            }
+        // Extents may cover less than the logical size on corrupt images
+        let data = reader.to_vec();
+        if data.is_empty() && to_read > 0 {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
         }
-        Ok(to_read as usize)
+        buf[..data.len()].copy_from_slice(&data);
+        self.cur += data.len() as u64;
+        Ok(data.len())
     }
 }
\ No newline at end of file
@@ -3,10 +3,21 @@ mod blockaccess;
 mod btree;
 mod catalog;
 mod fileio;
-
-use std::io;
-
+mod builder;
+mod desktop;
+
+use crate::{Error, Result};
+use crate::types::{
+    Encoding,
+    DateTime,
+    OSType,
+    PString
+};
+use crate::filesys::MacFile;
+use crate::filesys::rsrc::Rsrc;
+use crate::filesys::rsrc::decode::{Alias, AliasKind, TypedRsrc};
 use crate::serialization::{
+    SerialAdaptor,
     SerialAccess,
     SerialReadStorage,
     SerialRead
@@ -22,6 +33,10 @@ use types::{
     }
 };
 use blockaccess::BlockAccess;
+use chrono::{
+    FixedOffset,
+    Utc
+};
 
 use catalog::{
     Catalog,
@@ -29,30 +44,145 @@ use catalog::{
 };
 
 pub use fileio::FileIO;
+pub use builder::{
+    HfsBuilder,
+    ROOT_ID
+};
+pub use desktop::{
+    Desktop,
+    DesktopApp,
+    IconKind
+};
+pub use types::catalog::{
+    FInfo,
+    FXInfo
+};
+
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    pub use super::btree::{
+        BTreeNode,
+        BTreeHeaderNode,
+        BTreeLeafNode
+    };
+    pub use super::types::catalog::{
+        CatKeyRec,
+        CatDataRec
+    };
+}
 
 #[derive(Debug)]
 pub struct HfsImage
 {
     storage: BlockAccess,
     mdb: MDB,
+    encoding: Encoding,
+    timezone: FixedOffset,
+    follow_aliases: bool,
     pub catalog: Catalog
 }
 
+// Finder flag of alias files
+const IS_ALIAS: u16 = 0x8000;
+
+// Aliases may point to other aliases, but not in loops
+const MAX_ALIAS_DEPTH: usize = 8;
+
 impl HfsImage
 {
-    pub fn from(storage: Box<dyn SerialAccess>) -> io::Result<HfsImage> {
+    pub fn from(storage: Box<dyn SerialAccess>) -> Result<HfsImage> {
         // let size = storage.size()?;
 
         // Bootstrap with getting header, to get block size information
         let mut mdb_block : SerialReadStorage = SerialReadStorage::from(storage.read(2*512, 512)?);
         let mdb = MDB::read(&mut mdb_block)?;
+        if mdb.drSigWord != 0x4244 {
+            return Err(Error::invalid_value(2*512, "MDB", "drSigWord", "$4244", format!("${:04x}", mdb.drSigWord)));
+        }
+        if mdb.drAlBlkSiz <= 0 || mdb.drAlBlkSiz % 512 != 0 {
+            return Err(Error::invalid_value(2*512 + 20, "MDB", "drAlBlkSiz", "positive multiple of 512", mdb.drAlBlkSiz));
+        }
+        if mdb.drAlBlSt < 0 {
+            return Err(Error::invalid_value(2*512 + 28, "MDB", "drAlBlSt", "positive block number", mdb.drAlBlSt));
+        }
 
         // Set up block access
         let storage = BlockAccess::new(storage, mdb.drAlBlSt as u64, mdb.drAlBlkSiz as u64);
 
         let catalog = Catalog::new(&storage, &mdb.drCTExtRec)?;
 
-        Ok(HfsImage {storage, mdb, catalog})
+        Ok(HfsImage {
+            storage,
+            mdb,
+            encoding: Encoding::default(),
+            timezone: FixedOffset::east_opt(0).unwrap(),
+            follow_aliases: false,
+            catalog
+        })
+    }
+
+    // Names on the volume are stored in the encoding of the system that
+    // created them, which isn't recorded in the volume. Default is Mac Roman.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    // Timestamps are stored in local time of the system that wrote them,
+    // which isn't recorded either. Default is UTC.
+    pub fn set_timezone(&mut self, timezone: FixedOffset) {
+        self.timezone = timezone;
+    }
+
+    pub fn timezone(&self) -> FixedOffset {
+        self.timezone
+    }
+
+    // Makes locate return the target of alias files, also for aliases of
+    // folders along the path, as the Finder does. Default is off.
+    pub fn set_follow_aliases(&mut self, follow: bool) {
+        self.follow_aliases = follow;
+    }
+
+    pub fn follow_aliases(&self) -> bool {
+        self.follow_aliases
+    }
+
+    fn to_utc(&self, time: &DateTime) -> chrono::DateTime<Utc> {
+        time.to_utc(self.timezone)
+    }
+
+    pub fn get_name(&self) -> String {
+        self.mdb.drVN.decode(self.encoding)
+    }
+
+    pub fn get_created(&self) -> chrono::DateTime<Utc> {
+        self.to_utc(&self.mdb.drCrDate)
+    }
+
+    pub fn get_modified(&self) -> chrono::DateTime<Utc> {
+        self.to_utc(&self.mdb.drLsMod)
+    }
+
+    pub fn get_backup(&self) -> chrono::DateTime<Utc> {
+        self.to_utc(&self.mdb.drVolBkUp)
+    }
+
+    pub fn block_size(&self) -> u32 {
+        self.mdb.drAlBlkSiz as u32
+    }
+
+    // Number of allocation blocks, and how many of them are unused
+    pub fn block_count(&self) -> (u16, u16) {
+        (self.mdb.drNmAlBlks, self.mdb.drFreeBks)
+    }
+
+    // Number of files and folders on the volume, excluding the root folder
+    pub fn object_count(&self) -> (u32, u32) {
+        (self.mdb.drFilCnt as u32, self.mdb.drDirCnt as u32)
     }
 
     pub fn open_root<'img>(&'img self) -> HfsDirIter<'img> {
@@ -69,24 +199,152 @@ impl HfsImage
         }
     }
 
+    // The System file is located in the blessed folder, which is recorded in
+    // the Finder information of the volume
+    pub fn system_file<'img>(&'img self) -> Option<HfsFileRef<'img>> {
+        let blessed = self.mdb.drFndrInfo[0] as u32;
+        if blessed == 0 {
+            return None;
+        }
+        self.open_dir(blessed)
+            .filter_map(|obj| obj.to_file())
+            .find(|file| names_equal(&file.get_name(), "System"))
+    }
+
+    // Path of a folder, in the form used by locate, found by following the
+    // thread records up to the root folder, which has an empty path
+    pub fn folder_path(&self, id: u32) -> Option<String> {
+        let mut names = vec![];
+        let mut id = id;
+        while id != ROOT_ID {
+            let thread = self.catalog.thread(id)?;
+            // A damaged catalog could have threads forming a loop
+            if names.len() > self.mdb.drDirCnt as usize {
+                return None;
+            }
+            names.push(thread.thdCName.decode(self.encoding));
+            id = thread.thdParID;
+        }
+        names.reverse();
+        Some(names.join(":"))
+    }
+
+    // Finder database of the volume, from the Desktop file. Volumes only
+    // used with System 7 have no Desktop file.
+    pub fn desktop(&self) -> Result<Option<Desktop>> {
+        let desktop = self.open_root()
+            .filter_map(|obj| obj.to_file())
+            .find(|file| names_equal(&file.get_name(), "Desktop"));
+        match desktop {
+            Some(file) => Desktop::from_desktop_file(&file).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    // Paths are relative to the root folder, with an optional leading ':'
     pub fn locate<'img>(&'img self, path: &str) -> Option<HfsObjRef<'img>> {
+        let path = path.strip_prefix(':').unwrap_or(path);
         let mut path: Vec<&str> = path.split(':').collect();
 
         let plast = path.pop()?;
 
         let mut iter = self.open_root();
         for part in path {
-            let obj = iter.find(|objr| objr.get_name() == part)?;
-            if let HfsObjRef::DirRef(dir) = obj {
+            let obj = iter.find(|objr| names_equal(&objr.get_name(), part))?;
+            if let HfsObjRef::DirRef(dir) = self.located(obj)? {
                 iter = dir.open();
             } else {
                 return None;
             }
         }
-        iter.find(|objr| objr.get_name() == plast)
+        self.located(iter.find(|objr| names_equal(&objr.get_name(), plast))?)
+    }
+
+    fn located<'img>(&'img self, obj: HfsObjRef<'img>) -> Option<HfsObjRef<'img>> {
+        if self.follow_aliases {
+            self.follow(obj)
+        } else {
+            Some(obj)
+        }
+    }
+
+    // Target of an alias file, following aliases to aliases. Other files and
+    // folders are returned as they are.
+    pub fn follow<'img>(&'img self, obj: HfsObjRef<'img>) -> Option<HfsObjRef<'img>> {
+        let mut obj = obj;
+        for _ in 0..MAX_ALIAS_DEPTH {
+            let alias = match &obj {
+                HfsObjRef::FileRef(file) if file.is_alias() => file.read_alias().ok()?,
+                _ => return Some(obj)
+            };
+            obj = self.resolve_alias(&alias)?;
+        }
+        None
+    }
+
+    // Child of a folder by name
+    fn child<'img>(&'img self, parent: u32, name: &PString) -> Option<HfsObjRef<'img>> {
+        let name = name.decode(self.encoding);
+        self.open_dir(parent).find(|obj| names_equal(&obj.get_name(), &name))
+    }
+
+    // File or folder by ID. Folders, and files with a file ID reference, have
+    // thread records giving the parent and name. Other files are searched for
+    // in the whole catalog.
+    pub fn find_id<'img>(&'img self, id: u32) -> Option<HfsObjRef<'img>> {
+        let by_thread = self.catalog.thread(id)
+            .and_then(|thread| self.child(thread.thdParID, &thread.thdCName))
+            .filter(|obj| obj.get_id() == id);
+        if by_thread.is_some() {
+            return by_thread;
+        }
+        match self.catalog.object(id)? {
+            (key, CatDataRec::CdrFilRec(fr)) => Some(HfsObjRef::FileRef(HfsFileRef { img: self, key, fr })),
+            (key, CatDataRec::CdrDirRec(dr)) => Some(HfsObjRef::DirRef(HfsDirRef { img: self, key, dr })),
+            _ => None
+        }
+    }
+
+    // Find the target of an alias on this volume, as the Alias Manager does:
+    // by the ID of the target, then by parent folder and name, and last by the
+    // path from the root. Aliases to other volumes aren't resolved.
+    pub fn resolve_alias<'img>(&'img self, alias: &Alias) -> Option<HfsObjRef<'img>> {
+        let same_volume = names_equal(&alias.volume_name.decode(self.encoding), &self.get_name())
+            || alias.volume_created == self.mdb.drCrDate;
+        if !same_volume {
+            return None;
+        }
+        let matches_kind = |obj: &HfsObjRef| match alias.kind {
+            AliasKind::File => obj.is_file(),
+            AliasKind::Folder => obj.is_dir(),
+            AliasKind::Unknown(_) => true
+        };
+        if let Some(obj) = self.find_id(alias.target_id).filter(matches_kind) {
+            return Some(obj);
+        }
+        if let Some(obj) = self.child(alias.parent_id, &alias.name).filter(matches_kind) {
+            return Some(obj);
+        }
+        // Not followed further, as locate may follow aliases itself
+        let path = alias.volume_path(self.encoding)?;
+        let mut path: Vec<&str> = path.split(':').collect();
+        let last = path.pop()?;
+        let mut iter = self.open_root();
+        for part in path {
+            match iter.find(|obj| names_equal(&obj.get_name(), part))? {
+                HfsObjRef::DirRef(dir) => iter = dir.open(),
+                HfsObjRef::FileRef(_) => return None
+            }
+        }
+        iter.find(|obj| names_equal(&obj.get_name(), last)).filter(matches_kind)
     }
 }
 
+// HFS names are case insensitive, but case preserving
+fn names_equal(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
 
 pub struct HfsDirIter<'img> {
     img: &'img HfsImage,
@@ -133,13 +391,49 @@ impl<'img> std::iter::Iterator for HfsDirIter<'img> {
 
 impl<'img> HfsFileRef<'img> {
     pub fn get_name(&self) -> String {
-        String::from(&self.key.ckrCName)
+        self.key.ckrCName.decode(self.img.encoding)
+    }
+
+    pub fn get_raw_name(&self) -> &PString {
+        &self.key.ckrCName
+    }
+
+    pub fn get_id(&self) -> u32 {
+        self.fr.filFlNum
     }
 
     pub fn get_size(&self) -> (u32, u32) {
         (self.fr.filLgLen, self.fr.filRLgLen)
     }
 
+    pub fn get_type(&self) -> &OSType {
+        &self.fr.filUsrWds.fdType
+    }
+
+    pub fn get_creator(&self) -> &OSType {
+        &self.fr.filUsrWds.fdCreator
+    }
+
+    pub fn get_finder_info(&self) -> &FInfo {
+        &self.fr.filUsrWds
+    }
+
+    pub fn get_ext_finder_info(&self) -> &FXInfo {
+        &self.fr.filFndrInfo
+    }
+
+    pub fn get_created(&self) -> chrono::DateTime<Utc> {
+        self.img.to_utc(&self.fr.filCrDat)
+    }
+
+    pub fn get_modified(&self) -> chrono::DateTime<Utc> {
+        self.img.to_utc(&self.fr.filMdDat)
+    }
+
+    pub fn get_backup(&self) -> chrono::DateTime<Utc> {
+        self.img.to_utc(&self.fr.filBkDat)
+    }
+
     pub fn open(&self) -> FileIO {
         FileIO::open(
             self.img.storage.clone(),
@@ -155,11 +449,63 @@ impl<'img> HfsFileRef<'img> {
             self.fr.filRExtRec.clone()
         )
     }
+
+    // Alias files are marked by a Finder flag
+    pub fn is_alias(&self) -> bool {
+        self.fr.filUsrWds.fdFlags & IS_ALIAS != 0
+    }
+
+    // Alias record of an alias file, which the Finder stores with ID 0. Alias
+    // files made by other tools may use another ID, so the first record is
+    // used when there is none with ID 0.
+    pub fn read_alias(&self) -> Result<Alias> {
+        let rsrc = Rsrc::new(SerialAdaptor::new(self.open_rsrc()))?;
+        let info = rsrc.get(&Alias::RSRC_TYPE, 0)
+            .or_else(|| rsrc.resources(&Alias::RSRC_TYPE).next())
+            .ok_or(Error::NotFound)?;
+        Alias::read(&mut info.open()?)
+    }
+
+    // Read both forks and the Finder information, for export in an archive
+    // format. Dates are kept in the local time of the volume.
+    pub fn read_mac_file(&self) -> Result<MacFile> {
+        let mut data = Vec::with_capacity(self.fr.filLgLen as usize);
+        let mut rsrc = Vec::with_capacity(self.fr.filRLgLen as usize);
+        std::io::Read::read_to_end(&mut self.open(), &mut data)?;
+        std::io::Read::read_to_end(&mut self.open_rsrc(), &mut rsrc)?;
+        Ok(MacFile {
+            name: self.key.ckrCName.clone(),
+            info: self.fr.filUsrWds.clone(),
+            ext_info: self.fr.filFndrInfo.clone(),
+            created: self.fr.filCrDat,
+            modified: self.fr.filMdDat,
+            locked: self.fr.filFlags & 0x01 != 0,
+            data,
+            rsrc
+        })
+    }
 }
 
 impl<'img> HfsDirRef<'img> {
     pub fn get_name(&self) -> String {
-        String::from(&self.key.ckrCName)
+        self.key.ckrCName.decode(self.img.encoding)
+    }
+
+    pub fn get_created(&self) -> chrono::DateTime<Utc> {
+        self.img.to_utc(&self.dr.dirCrDat)
+    }
+
+    pub fn get_modified(&self) -> chrono::DateTime<Utc> {
+        self.img.to_utc(&self.dr.dirMdDat)
+    }
+
+    pub fn get_id(&self) -> u32 {
+        self.dr.dirDirID
+    }
+
+    // Number of files and folders directly in the folder
+    pub fn get_valence(&self) -> u16 {
+        self.dr.dirVal as u16
     }
 
     pub fn open(&self) -> HfsDirIter<'img> {
@@ -175,6 +521,35 @@ impl<'img> HfsObjRef<'img> {
         }
     }
 
+    pub fn get_id(&self) -> u32 {
+        match self {
+            HfsObjRef::FileRef(fr) => fr.get_id(),
+            HfsObjRef::DirRef(dr) => dr.get_id()
+        }
+    }
+
+    // ID of the folder holding the file or folder
+    pub fn get_parent_id(&self) -> u32 {
+        match self {
+            HfsObjRef::FileRef(fr) => fr.key.ckrParID,
+            HfsObjRef::DirRef(dr) => dr.key.ckrParID
+        }
+    }
+
+    pub fn get_created(&self) -> chrono::DateTime<Utc> {
+        match self {
+            HfsObjRef::FileRef(fr) => fr.get_created(),
+            HfsObjRef::DirRef(dr) => dr.get_created()
+        }
+    }
+
+    pub fn get_modified(&self) -> chrono::DateTime<Utc> {
+        match self {
+            HfsObjRef::FileRef(fr) => fr.get_modified(),
+            HfsObjRef::DirRef(dr) => dr.get_modified()
+        }
+    }
+
     pub fn is_dir(&self) -> bool {
         match self {
             HfsObjRef::FileRef(_) => false,
@@ -1,4 +1,5 @@
-use crate::serialization::{SerialReadStorage, SerialRead};
+use crate::serialization::{SerialReadStorage, SerialRead, SerialWriteStorage, SerialWrite};
+use crate::Result;
 
 #[derive(Debug)]
 #[derive(SerialRead)]
@@ -28,3 +29,32 @@ pub struct BTHdrRec {
    pub bthFree:       u32, //LongInt;    {number of free nodes}
    pub bthResv:       [u32; 19] //ARRAY[1..76] OF SignedByte;   {reserved}
 }
+
+impl SerialWrite for NodeDescriptor {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        self.ndFLink.write(wtr)?;
+        self.ndBLink.write(wtr)?;
+        self.ndType.write(wtr)?;
+        self.ndNHeight.write(wtr)?;
+        self.ndNRecs.write(wtr)?;
+        self.ndResv2.write(wtr)
+    }
+}
+
+impl SerialWrite for BTHdrRec {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        self.bthDepth.write(wtr)?;
+        self.bthRoot.write(wtr)?;
+        self.bthNRecs.write(wtr)?;
+        self.bthFNode.write(wtr)?;
+        self.bthLNode.write(wtr)?;
+        self.bthNodeSize.write(wtr)?;
+        self.bthKeyLen.write(wtr)?;
+        self.bthNNodes.write(wtr)?;
+        self.bthFree.write(wtr)?;
+        for resv in self.bthResv.iter() {
+            resv.write(wtr)?;
+        }
+        Ok(())
+    }
+}
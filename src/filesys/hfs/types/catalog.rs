@@ -1,9 +1,12 @@
-use crate::serialization::{SerialReadStorage, SerialRead};
+use crate::serialization::{SerialReadStorage, SerialRead, SerialWriteStorage, SerialWrite};
+use crate::{Error, Result};
 
 use crate::types::{
     PString,
     DateTime,
-    OSType
+    OSType,
+    Point,
+    Rect
 };
 
 use super::common::{
@@ -11,65 +14,97 @@ use super::common::{
 };
 
 #[derive(SerialRead)]
+#[derive(Clone)]
 #[derive(Debug)]
 #[allow(non_snake_case)] // This struct comes from old Mac structs
-pub struct Point {
-   v: i16, // INTEGER:     {vertical coordinate}
-   h: i16  // INTEGER;     {horizontal  coordinate}
+pub struct FInfo {
+    pub fdType:     OSType, // OSType;     {file type}
+    pub fdCreator:  OSType, // OSType;     {file creator}
+    pub fdFlags:    u16, // Integer;    {Finder flags}
+    pub fdLocation: Point, // Point;      {file's location in window}
+    pub fdFldr:     u16, // Integer;    {directory that contains file}
 }
 
-#[derive(SerialRead)]
-#[derive(Debug)]
-#[allow(non_snake_case)] // This struct comes from old Mac structs
-pub struct Rect {
-   topLeft: Point,
-   botRight: Point,
+impl SerialWrite for FInfo {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        self.fdType.write(wtr)?;
+        self.fdCreator.write(wtr)?;
+        self.fdFlags.write(wtr)?;
+        self.fdLocation.write(wtr)?;
+        self.fdFldr.write(wtr)
+    }
 }
 
 #[derive(SerialRead)]
+#[derive(Clone)]
+#[derive(Default)]
 #[derive(Debug)]
 #[allow(non_snake_case)] // This struct comes from old Mac structs
-pub struct FInfo {
-    fdType:     OSType, // OSType;     {file type}
-    fdCreator:  OSType, // OSType;     {file creator}
-    fdFlags:    u16, // Integer;    {Finder flags}
-    fdLocation: Point, // Point;      {file's location in window}
-    fdFldr:     u16, // Integer;    {directory that contains file}
+pub struct FXInfo {
+    pub fdIconID:      i16, // Integer;    {icon ID}
+    pub fdUnused:      [i16; 3], // ARRAY[1..3] OF Integer; {unused but reserved 6 bytes}
+    pub fdScript:      i8, // SignedByte; {script flag and code}
+    pub fdXFlags:      i8, // SignedByte; {reserved}
+    pub fdComment:     i16, // Integer;    {comment ID}
+    pub fdPutAway:     u32, // LongInt;    {home directory ID}
 }
 
-#[derive(SerialRead)]
-#[derive(Debug)]
-#[allow(non_snake_case)] // This struct comes from old Mac structs
-pub struct FXInfo {
-    fdIconID:      i16, // Integer;    {icon ID}
-    fdUnused:      [i16; 3], // ARRAY[1..3] OF Integer; {unused but reserved 6 bytes}
-    fdScript:      i8, // SignedByte; {script flag and code}
-    fdXFlags:      i8, // SignedByte; {reserved}
-    fdComment:     i16, // Integer;    {comment ID}
-    fdPutAway:     u32, // LongInt;    {home directory ID}
+impl SerialWrite for FXInfo {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        self.fdIconID.write(wtr)?;
+        for unused in &self.fdUnused {
+            unused.write(wtr)?;
+        }
+        self.fdScript.write(wtr)?;
+        self.fdXFlags.write(wtr)?;
+        self.fdComment.write(wtr)?;
+        self.fdPutAway.write(wtr)
+    }
 }
 
 // TODO: Reverse engineered
 #[derive(SerialRead)]
+#[derive(Default)]
 #[derive(Debug)]
 #[allow(non_snake_case)] // This struct comes from old Mac structs
 pub struct DInfo {
-    frRect:     Rect,  // Rect;    {folder's window rectangle}
-    frFlags:    u16,   // Integer; {flags}
-    frLocation: Point, // Point;   {folder's location in window}
-    frView:     u16,   // Integer; {folder's view}
+    pub frRect:     Rect,  // Rect;    {folder's window rectangle}
+    pub frFlags:    u16,   // Integer; {flags}
+    pub frLocation: Point, // Point;   {folder's location in window}
+    pub frView:     u16,   // Integer; {folder's view}
+}
+
+impl SerialWrite for DInfo {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        self.frRect.write(wtr)?;
+        self.frFlags.write(wtr)?;
+        self.frLocation.write(wtr)?;
+        self.frView.write(wtr)
+    }
 }
 
 #[derive(SerialRead)]
+#[derive(Default)]
 #[derive(Debug)]
 #[allow(non_snake_case)] // This struct comes from old Mac structs
 pub struct DXInfo {
-    frScroll:      Point, // Point;      {scroll position}
-    frOpenChain:   u32, // LongInt;    {directory ID chain of open folders}
-    frScript:      i8, // SignedByte; {script flag and code}
-    frXFlags:      u8, // SignedByte; {reserved}
-    frComment:     i16, // Integer;    {comment ID}
-    frPutAway:     u32, // LongInt;    {home directory ID}
+    pub frScroll:      Point, // Point;      {scroll position}
+    pub frOpenChain:   u32, // LongInt;    {directory ID chain of open folders}
+    pub frScript:      i8, // SignedByte; {script flag and code}
+    pub frXFlags:      u8, // SignedByte; {reserved}
+    pub frComment:     i16, // Integer;    {comment ID}
+    pub frPutAway:     u32, // LongInt;    {home directory ID}
+}
+
+impl SerialWrite for DXInfo {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        self.frScroll.write(wtr)?;
+        self.frOpenChain.write(wtr)?;
+        self.frScript.write(wtr)?;
+        self.frXFlags.write(wtr)?;
+        self.frComment.write(wtr)?;
+        self.frPutAway.write(wtr)
+    }
 }
 
 #[derive(PartialOrd)]
@@ -84,6 +119,26 @@ pub struct CatKeyRec {
     pub ckrCName : PString,
 }
 
+impl CatKeyRec {
+    pub fn new(parent: u32, name: PString) -> CatKeyRec {
+        CatKeyRec {
+            ckrKeyLen: 6 + name.len() as u8,
+            ckrResrv1: 0,
+            ckrParID: parent,
+            ckrCName: name
+        }
+    }
+}
+
+impl SerialWrite for CatKeyRec {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        self.ckrKeyLen.write(wtr)?;
+        self.ckrResrv1.write(wtr)?;
+        self.ckrParID.write(wtr)?;
+        self.ckrCName.write(wtr)
+    }
+}
+
 #[derive(SerialRead)]
 #[derive(Debug)]
 #[allow(non_snake_case)] // This struct comes from old Mac structs
@@ -99,6 +154,23 @@ pub struct CdrDirRec {
    pub dirResrv:      [u32; 4] // ARRAY[1..4] OF LongInt {reserved}
 }
 
+impl SerialWrite for CdrDirRec {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        self.dirFlags.write(wtr)?;
+        self.dirVal.write(wtr)?;
+        self.dirDirID.write(wtr)?;
+        self.dirCrDat.write(wtr)?;
+        self.dirMdDat.write(wtr)?;
+        self.dirBkDat.write(wtr)?;
+        self.dirUsrInfo.write(wtr)?;
+        self.dirFndrInfo.write(wtr)?;
+        for resv in self.dirResrv.iter() {
+            resv.write(wtr)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(SerialRead)]
 #[derive(Debug)]
 #[allow(non_snake_case)] // This struct comes from old Mac structs
@@ -123,6 +195,29 @@ pub struct CdrFilRec {
    pub filResrv:      u32, // LongInt     {reserved}
 }
 
+impl SerialWrite for CdrFilRec {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        self.filFlags.write(wtr)?;
+        self.filTyp.write(wtr)?;
+        self.filUsrWds.write(wtr)?;
+        self.filFlNum.write(wtr)?;
+        self.filStBlk.write(wtr)?;
+        self.filLgLen.write(wtr)?;
+        self.filPyLen.write(wtr)?;
+        self.filRStBlk.write(wtr)?;
+        self.filRLgLen.write(wtr)?;
+        self.filRPyLen.write(wtr)?;
+        self.filCrDat.write(wtr)?;
+        self.filMdDat.write(wtr)?;
+        self.filBkDat.write(wtr)?;
+        self.filFndrInfo.write(wtr)?;
+        self.filClpSize.write(wtr)?;
+        self.filExtRec.write(wtr)?;
+        self.filRExtRec.write(wtr)?;
+        self.filResrv.write(wtr)
+    }
+}
+
 #[derive(SerialRead)]
 #[derive(Debug)]
 #[allow(non_snake_case)] // This struct comes from old Mac structs
@@ -132,6 +227,23 @@ pub struct CdrThdRec {
    pub thdCName:      PString,  // Str31;     {name of this directory}
 }
 
+impl SerialWrite for CdrThdRec {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        for resv in self.thdResrv.iter() {
+            resv.write(wtr)?;
+        }
+        self.thdParID.write(wtr)?;
+        // Str31, always taking 32 bytes
+        let start = wtr.pos();
+        if self.thdCName.len() > 31 {
+            return Err(Error::invalid_value(start, "CdrThdRec", "thdCName", "at most 31 bytes", self.thdCName.len()));
+        }
+        self.thdCName.write(wtr)?;
+        wtr.seek(start + 32)?;
+        Ok(())
+    }
+}
+
 // CdrFThdRec and CdrThdRec is the same, also according to an explicit comment
 // in Inside Macintosh. Reuse CdrThdRec for simplicity, but keep CdrFThdRec for
 // reference
@@ -163,14 +275,15 @@ struct CatDataRecHeader {
 }
 
 impl SerialRead for CatDataRec {
-    fn read(rdr : &mut SerialReadStorage) -> std::io::Result<CatDataRec> {
+    fn read(rdr : &mut SerialReadStorage) -> Result<CatDataRec> {
+        let offset = rdr.pos();
         let header = CatDataRecHeader::read(rdr)?;
         Ok(match header.cdrType {
             1 => CatDataRec::CdrDirRec(CdrDirRec::read(rdr)?),
             2 => CatDataRec::CdrFilRec(CdrFilRec::read(rdr)?),
             3 => CatDataRec::CdrThdRec(CdrThdRec::read(rdr)?),
             4 => CatDataRec::CdrFThdRec(CdrThdRec::read(rdr)?),
-            _ => return Err(std::io::Error::from(std::io::ErrorKind::InvalidData))
+            t => return Err(Error::invalid_value(offset, "CatDataRecHeader", "cdrType", "1 to 4", t))
         })
     }
 }
@@ -184,4 +297,23 @@ impl CatDataRec {
          CatDataRec::CdrFThdRec(_) => false
       }
    }
-}
\ No newline at end of file
+}
+
+impl SerialWrite for CatDataRec {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        let cdr_type: i8 = match self {
+            CatDataRec::CdrDirRec(_) => 1,
+            CatDataRec::CdrFilRec(_) => 2,
+            CatDataRec::CdrThdRec(_) => 3,
+            CatDataRec::CdrFThdRec(_) => 4
+        };
+        cdr_type.write(wtr)?;
+        wtr.write_i8(0)?;
+        match self {
+            CatDataRec::CdrDirRec(rec) => rec.write(wtr),
+            CatDataRec::CdrFilRec(rec) => rec.write(wtr),
+            CatDataRec::CdrThdRec(rec) => rec.write(wtr),
+            CatDataRec::CdrFThdRec(rec) => rec.write(wtr)
+        }
+    }
+}
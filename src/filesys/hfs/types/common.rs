@@ -1,18 +1,40 @@
-use crate::serialization::{SerialReadStorage, SerialRead};
+use crate::serialization::{SerialReadStorage, SerialRead, SerialWriteStorage, SerialWrite};
+use crate::Result;
 
 #[derive(Debug)]
 #[derive(Clone)]
+#[derive(Default)]
 #[derive(SerialRead)]
 #[allow(non_snake_case)] // This struct comes from old Mac structs
 pub struct ExtDescriptor {
     pub xdrStABN: u16,    // first allocation block
-    pub xdrNumABlks: i16, // number of allocation blocks
+    pub xdrNumABlks: u16, // number of allocation blocks
 }
 
 #[derive(Debug)]
 #[derive(Clone)]
+#[derive(Default)]
 #[derive(SerialRead)]
 #[allow(non_snake_case)] // This struct comes from old Mac structs
 pub struct ExtDataRec(
     pub [ExtDescriptor; 3]
 );
+
+impl ExtDataRec {
+    // Record with a single extent, and the remaining two unused
+    pub fn single(start: u16, count: u16) -> ExtDataRec {
+        let mut rec = ExtDataRec::default();
+        rec.0[0] = ExtDescriptor { xdrStABN: start, xdrNumABlks: count };
+        rec
+    }
+}
+
+impl SerialWrite for ExtDataRec {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        for ext in self.0.iter() {
+            wtr.write_u16(ext.xdrStABN)?;
+            wtr.write_u16(ext.xdrNumABlks)?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use crate::serialization::{SerialReadStorage, SerialRead};
+use crate::serialization::{SerialReadStorage, SerialRead, SerialWriteStorage, SerialWrite};
+use crate::{Error, Result};
 
 use crate::types::{
     PString,
@@ -45,4 +46,47 @@ pub struct MDB {
     pub drXTExtRec: ExtDataRec, //ExtDataRec, // extent record for extents overflow file
     pub drCTFlSize: i32,        //LongInt,    // size of catalog file
     pub drCTExtRec: ExtDataRec, //ExtDataRec, // extent record for catalog file
-}
\ No newline at end of file
+}
+
+impl SerialWrite for MDB {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        self.drSigWord.write(wtr)?;
+        self.drCrDate.write(wtr)?;
+        self.drLsMod.write(wtr)?;
+        self.drAtrb.write(wtr)?;
+        self.drNmFls.write(wtr)?;
+        self.drVBMSt.write(wtr)?;
+        self.drAllocPtr.write(wtr)?;
+        self.drNmAlBlks.write(wtr)?;
+        self.drAlBlkSiz.write(wtr)?;
+        self.drClpSiz.write(wtr)?;
+        self.drAlBlSt.write(wtr)?;
+        self.drNxtCNID.write(wtr)?;
+        self.drFreeBks.write(wtr)?;
+        // String[27], always taking 28 bytes
+        let start = wtr.pos();
+        if self.drVN.len() > 27 {
+            return Err(Error::invalid_value(start, "MDB", "drVN", "at most 27 bytes", self.drVN.len()));
+        }
+        self.drVN.write(wtr)?;
+        wtr.seek(start + 28)?;
+        self.drVolBkUp.write(wtr)?;
+        self.drVSeqNum.write(wtr)?;
+        self.drWrCnt.write(wtr)?;
+        self.drXTClpSiz.write(wtr)?;
+        self.drCTClpSiz.write(wtr)?;
+        self.drNmRtDirs.write(wtr)?;
+        self.drFilCnt.write(wtr)?;
+        self.drDirCnt.write(wtr)?;
+        for info in self.drFndrInfo.iter() {
+            info.write(wtr)?;
+        }
+        self.drVCSize.write(wtr)?;
+        self.drVBMCSize.write(wtr)?;
+        self.drCtlCSize.write(wtr)?;
+        self.drXTFlSize.write(wtr)?;
+        self.drXTExtRec.write(wtr)?;
+        self.drCTFlSize.write(wtr)?;
+        self.drCTExtRec.write(wtr)
+    }
+}
@@ -0,0 +1,264 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use chrono::{FixedOffset, Utc};
+
+use crate::{Error, Result};
+use crate::serialization::{
+    SerialAdaptor,
+    SerialReadStorage,
+    SerialRead
+};
+use crate::types::{
+    DateTime,
+    Encoding,
+    OSType,
+    PString
+};
+
+use super::{appledouble, macbinary, MacFile};
+use super::export::mac_name;
+use super::hfs::{FInfo, FXInfo, HfsBuilder, ROOT_ID};
+use super::rsrc::Rsrc;
+
+// Reading host files back into Mac files, the inverse of export. All formats
+// written by export are recognized, so a tree exported in any format can be
+// imported again.
+
+#[derive(Debug)]
+#[derive(Default)]
+pub struct ImportSummary {
+    pub files: usize,
+    pub folders: usize,
+    // Host files and folders which couldn't be imported. The import
+    // continues with the remaining files.
+    pub failed: Vec<(PathBuf, Error)>,
+    // Folder blessed as the System Folder, if one was found
+    pub system_folder: Option<PathBuf>
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn sidecar_path(path: &Path, folder: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default();
+    path.with_file_name(folder).join(name)
+}
+
+fn host_modified(path: &Path) -> Result<DateTime> {
+    let modified: chrono::DateTime<Utc> = fs::metadata(path)?.modified()?.into();
+    Ok(DateTime::from_utc(&modified, FixedOffset::east_opt(0).unwrap()))
+}
+
+// Type and creator for files without Finder information, by extension
+fn guess_type(name: &str) -> (OSType, OSType) {
+    let extension = name.rsplit('.').next().unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "txt" | "text" | "md" => (OSType::from(b"TEXT"), OSType::from(b"ttxt")),
+        "pict" | "pct" => (OSType::from(b"PICT"), OSType::from(b"ttxt")),
+        _ => (OSType::from(b"????"), OSType::from(b"????"))
+    }
+}
+
+// Host files which are part of another file, and aren't imported by
+// themselves
+fn is_companion(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    if name.starts_with('.') {
+        // AppleDouble headers, sidecar folders, and host hidden files
+        return true;
+    }
+    match name.strip_suffix(".rsrc") {
+        Some(data_name) => path.with_file_name(data_name).is_file(),
+        None => false
+    }
+}
+
+fn encode_name(name: &str, encoding: Encoding) -> Result<PString> {
+    PString::encode(&mac_name(name), encoding)
+}
+
+// Read a host file with its resource fork and Finder information, from any of
+// the formats written by export. Files in MacBinary and AppleSingle format are
+// recognized by content, the others by the files next to them. Files without
+// Finder information get a type from the extension, and the host
+// modification date, as UTC.
+pub fn read_host_file(path: &Path, encoding: Encoding) -> Result<MacFile> {
+    let host = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let content = fs::read(path)?;
+
+    let header = path.with_file_name(appledouble::header_name(&host));
+    if header.is_file() {
+        // The data file names the file, as a renamed file keeps its header
+        let mut file = appledouble::decode(&fs::read(&header)?)?;
+        file.name = encode_name(&host, encoding)?;
+        file.data = content;
+        return Ok(file);
+    }
+
+    if content.len() >= 4 && content[0..4] == [0, 5, 0x16, 0] {
+        return appledouble::decode(&content);
+    }
+    if host.ends_with(".bin") {
+        if let Ok(file) = macbinary::decode(&content) {
+            return Ok(file);
+        }
+    }
+
+    let (file_type, creator) = guess_type(&host);
+    let mut file = MacFile::new(encode_name(&host, encoding)?, file_type, creator);
+    file.modified = host_modified(path)?;
+    file.created = file.modified;
+    file.data = content;
+
+    let finder_info = sidecar_path(path, ".finf");
+    if finder_info.is_file() {
+        let mut rdr = SerialReadStorage::from(fs::read(&finder_info)?);
+        file.info = FInfo::read(&mut rdr)?;
+        file.ext_info = FXInfo::read(&mut rdr).unwrap_or_default();
+    }
+    for rsrc in [sidecar_path(path, ".rsrc"), with_suffix(path, ".rsrc")].iter() {
+        if rsrc.is_file() {
+            file.rsrc = fs::read(rsrc)?;
+            break;
+        }
+    }
+    Ok(file)
+}
+
+// Folder blessed when it holds both a System file and a Finder, with the boot
+// blocks from the System file
+struct SystemFolder {
+    id: u32,
+    path: PathBuf,
+    boot_blocks: Vec<u8>
+}
+
+fn boot_blocks(system: &MacFile) -> Result<Vec<u8>> {
+    let rsrc = Rsrc::new(SerialAdaptor::new(Cursor::new(system.rsrc.clone())))?;
+    Ok(rsrc.open(OSType::from(b"boot"), 1)?.to_vec())
+}
+
+fn import_walk(builder: &mut HfsBuilder, parent: u32, src: &Path, encoding: Encoding, summary: &mut ImportSummary, system: &mut Option<SystemFolder>) -> Result<()> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(src)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    paths.sort();
+
+    let mut system_boot = None;
+    let mut has_finder = false;
+    for path in paths.into_iter().filter(|path| !is_companion(path)) {
+        if path.is_dir() {
+            let host = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let modified = host_modified(&path)?;
+            let added = encode_name(&host, encoding)
+                .and_then(|name| builder.add_folder(parent, name, modified, modified));
+            match added {
+                Ok(id) => {
+                    summary.folders += 1;
+                    import_walk(builder, id, &path, encoding, summary, system)?;
+                },
+                Err(err) => summary.failed.push((path, err))
+            }
+            continue;
+        }
+
+        let added = read_host_file(&path, encoding).and_then(|file| {
+            let file_type = file.info.fdType.clone();
+            let boot = if file_type == OSType::from(b"zsys") && file.info.fdCreator == OSType::from(b"MACS") {
+                boot_blocks(&file).ok()
+            } else {
+                None
+            };
+            builder.add_file(parent, file)?;
+            Ok((file_type, boot))
+        });
+        match added {
+            Ok((file_type, boot)) => {
+                summary.files += 1;
+                has_finder |= file_type == OSType::from(b"FNDR");
+                system_boot = system_boot.or(boot);
+            },
+            Err(err) => summary.failed.push((path, err))
+        }
+    }
+
+    if let (Some(boot_blocks), true, None) = (system_boot, has_finder, system.as_ref()) {
+        *system = Some(SystemFolder { id: parent, path: src.to_path_buf(), boot_blocks });
+    }
+    Ok(())
+}
+
+// Import the contents of a host directory recursively into a folder of the
+// volume being built. Errors reading the directories stops the import, while
+// files that can't be read or added are listed in the summary.
+pub fn import_dir(builder: &mut HfsBuilder, parent: u32, src: &Path, encoding: Encoding) -> Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    let mut system = None;
+    import_walk(builder, parent, src, encoding, &mut summary, &mut system)?;
+    Ok(summary)
+}
+
+// Import a host directory as the root of the volume. If a System Folder is
+// found, it is blessed and the boot blocks set, to make the volume startable.
+pub fn import_volume(builder: &mut HfsBuilder, src: &Path, encoding: Encoding) -> Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    let mut system = None;
+    import_walk(builder, ROOT_ID, src, encoding, &mut summary, &mut system)?;
+    if let Some(system) = system {
+        builder.bless(system.id)?;
+        builder.set_boot_blocks(&system.boot_blocks)?;
+        summary.system_folder = Some(system.path);
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+
+    use super::import_volume;
+    use crate::filesys::MacFile;
+    use crate::filesys::export::{write_mac_file, ExportFormat};
+    use crate::filesys::hfs::{HfsBuilder, HfsImage};
+    use crate::serialization::SerialAdaptor;
+    use crate::types::{Encoding, OSType, PString};
+
+    #[test]
+    fn formats() {
+        let dir = std::env::temp_dir().join(format!("marmelade-import-{}", std::process::id()));
+        let mut file = MacFile::new(PString::from("a"), OSType::from(b"APPL"), OSType::from(b"ttxt"));
+        file.data = b"data".to_vec();
+        file.rsrc = b"rsrc".to_vec();
+        write_mac_file(&file, &dir.join("double"), ExportFormat::AppleDouble).unwrap();
+        write_mac_file(&file, &dir.join("sub").join("side"), ExportFormat::Sidecar).unwrap();
+        write_mac_file(&file, &dir.join("raw"), ExportFormat::Raw).unwrap();
+        file.name = PString::from("a/b");
+        write_mac_file(&file, &dir.join("a:b.bin"), ExportFormat::MacBinary).unwrap();
+        fs::write(dir.join("notes.txt"), b"text").unwrap();
+        fs::write(dir.join(".DS_Store"), b"").unwrap();
+
+        let mut builder = HfsBuilder::new(PString::from("Test")).unwrap();
+        let summary = import_volume(&mut builder, &dir, Encoding::MacRoman).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!((summary.files, summary.folders, summary.failed.len()), (5, 1, 0));
+        assert_eq!(summary.system_folder, None);
+
+        let img = HfsImage::from(SerialAdaptor::new(Cursor::new(builder.build().unwrap()))).unwrap();
+        for path in ["double", "sub:side", "a/b"].iter() {
+            let read = img.locate(path).unwrap().to_file().unwrap().read_mac_file().unwrap();
+            assert_eq!(read.info.fdType, OSType::from(b"APPL"), "{}", path);
+            assert_eq!((read.data.as_slice(), read.rsrc.as_slice()), (&b"data"[..], &b"rsrc"[..]));
+        }
+        let raw = img.locate("raw").unwrap().to_file().unwrap();
+        assert_eq!((raw.get_type(), raw.get_size()), (&OSType::from(b"????"), (4, 4)));
+        let notes = img.locate("notes.txt").unwrap().to_file().unwrap();
+        assert_eq!(notes.get_type(), &OSType::from(b"TEXT"));
+        assert!(img.locate(".DS_Store").is_none());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{Error, Result};
+use crate::serialization::{
+    SerialReadStorage,
+    SerialRead,
+    SerialWriteStorage,
+    SerialWrite
+};
+use crate::types::{
+    DateTime,
+    OSType,
+    PString,
+    Point
+};
+
+use super::MacFile;
+use super::hfs::{
+    FInfo,
+    FXInfo
+};
+
+// MacBinary II, a 128 byte header followed by the data and resource forks,
+// each padded to a multiple of 128 bytes. MacBinary I files, without the
+// CRC, are accepted when decoding.
+
+const HEADER_LEN: u64 = 128;
+const VERSION: u8 = 129;
+
+// CRC-16/XMODEM, as used for the MacBinary II header
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+pub fn encode(file: &MacFile) -> Result<Vec<u8>> {
+    let name = file.name.as_bytes();
+    if name.is_empty() || name.len() > 63 {
+        return Err(Error::invalid_value(1, "MacBinary", "name", "1 to 63 bytes", name.len()));
+    }
+    let mut wtr = SerialWriteStorage::new();
+    wtr.write_u8(0)?;
+    file.name.write(&mut wtr)?;
+    wtr.seek(65)?;
+    file.info.fdType.write(&mut wtr)?;
+    file.info.fdCreator.write(&mut wtr)?;
+    wtr.write_u8((file.info.fdFlags >> 8) as u8)?;
+    wtr.write_u8(0)?;
+    file.info.fdLocation.write(&mut wtr)?;
+    wtr.write_u16(file.info.fdFldr)?;
+    wtr.write_u8(file.locked as u8)?;
+    wtr.write_u8(0)?;
+    wtr.write_u32(file.data.len() as u32)?;
+    wtr.write_u32(file.rsrc.len() as u32)?;
+    file.created.write(&mut wtr)?;
+    file.modified.write(&mut wtr)?;
+    // No Get Info comment
+    wtr.write_u16(0)?;
+    wtr.write_u8(file.info.fdFlags as u8)?;
+    wtr.seek(122)?;
+    wtr.write_u8(VERSION)?;
+    wtr.write_u8(VERSION)?;
+    let mut out = wtr.to_vec();
+    let crc = crc16(&out[0..124]);
+    out.extend_from_slice(&crc.to_be_bytes());
+    out.extend_from_slice(&[0, 0]);
+
+    for fork in [&file.data, &file.rsrc].iter() {
+        out.extend_from_slice(fork);
+        let padding = (HEADER_LEN as usize - out.len() % HEADER_LEN as usize) % HEADER_LEN as usize;
+        out.resize(out.len() + padding, 0);
+    }
+    Ok(out)
+}
+
+pub fn decode(data: &[u8]) -> Result<MacFile> {
+    if (data.len() as u64) < HEADER_LEN {
+        return Err(Error::invalid_value(0, "MacBinary", "header", "128 bytes", format!("{} bytes", data.len())));
+    }
+    for offset in [0u64, 74, 82].iter() {
+        if data[*offset as usize] != 0 {
+            return Err(Error::invalid_value(*offset, "MacBinary", "zero", 0, data[*offset as usize]));
+        }
+    }
+    let name_len = data[1];
+    if name_len == 0 || name_len > 63 {
+        return Err(Error::invalid_value(1, "MacBinary", "name", "1 to 63 bytes", name_len));
+    }
+    // MacBinary I has no version, and the remaining header is zero
+    let crc = u16::from_be_bytes([data[124], data[125]]);
+    let version = data[122];
+    if version != 0 && crc != crc16(&data[0..124]) {
+        return Err(Error::invalid_value(124, "MacBinary", "crc", format!("${:04x}", crc16(&data[0..124])), format!("${:04x}", crc)));
+    }
+
+    let mut rdr = SerialReadStorage::from(data[0..HEADER_LEN as usize].to_vec());
+    rdr.seek(1)?;
+    let name = PString::read(&mut rdr)?;
+    rdr.seek(65)?;
+    let file_type = OSType::read(&mut rdr)?;
+    let creator = OSType::read(&mut rdr)?;
+    let flags_high = rdr.read_u8()?;
+    rdr.seek(75)?;
+    let location = Point::read(&mut rdr)?;
+    let folder = rdr.read_u16()?;
+    let locked = rdr.read_u8()? & 0x01 != 0;
+    rdr.seek(83)?;
+    let data_len = rdr.read_u32()? as u64;
+    let rsrc_len = rdr.read_u32()? as u64;
+    let created = DateTime::read(&mut rdr)?;
+    let modified = DateTime::read(&mut rdr)?;
+    rdr.seek(101)?;
+    let flags_low = if version != 0 { rdr.read_u8()? } else { 0 };
+    rdr.seek(120)?;
+    let secondary_len = rdr.read_u16()? as u64;
+
+    let round = |len: u64| len.div_ceil(HEADER_LEN) * HEADER_LEN;
+    let data_start = HEADER_LEN + round(secondary_len);
+    let rsrc_start = data_start + round(data_len);
+    let end = rsrc_start + rsrc_len;
+    if end > data.len() as u64 {
+        return Err(Error::UnexpectedEof { offset: data_start, len: end - data_start, size: data.len() as u64 });
+    }
+
+    Ok(MacFile {
+        name,
+        info: FInfo {
+            fdType: file_type,
+            fdCreator: creator,
+            fdFlags: (flags_high as u16) << 8 | flags_low as u16,
+            fdLocation: location,
+            fdFldr: folder
+        },
+        ext_info: FXInfo::default(),
+        created,
+        modified,
+        locked,
+        data: data[data_start as usize..(data_start + data_len) as usize].to_vec(),
+        rsrc: data[rsrc_start as usize..end as usize].to_vec()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc16, decode, encode};
+    use crate::filesys::MacFile;
+    use crate::types::{DateTime, OSType, PString};
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut file = MacFile::new(PString::from("Read Me"), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+        file.info.fdFlags = 0x0120;
+        file.created = DateTime::from_mac_u32(0xa61e6e80);
+        file.modified = DateTime::from_mac_u32(0xa61e6e81);
+        file.data = b"Hello".to_vec();
+        file.rsrc = vec![1; 200];
+
+        let bin = encode(&file).unwrap();
+        assert_eq!(bin.len(), 128 + 128 + 256);
+        assert_eq!(&bin[65..73], b"TEXTttxt");
+        assert_eq!((bin[73], bin[101], bin[122]), (0x01, 0x20, 129));
+
+        let decoded = decode(&bin).unwrap();
+        assert_eq!(decoded.name, file.name);
+        assert_eq!(decoded.info.fdFlags, 0x0120);
+        assert_eq!(decoded.info.fdCreator, file.info.fdCreator);
+        assert_eq!((decoded.created, decoded.modified), (file.created, file.modified));
+        assert_eq!(decoded.data, file.data);
+        assert_eq!(decoded.rsrc, file.rsrc);
+    }
+
+    #[test]
+    fn invalid() {
+        let file = MacFile::new(PString::from("a"), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+        let mut bin = encode(&file).unwrap();
+        assert!(decode(&bin[0..100]).is_err());
+        bin[10] = 1;
+        assert!(decode(&bin).is_err());
+        // Without version and CRC, as MacBinary I
+        bin[122] = 0;
+        assert!(decode(&bin).is_ok());
+        bin[86] = 1;
+        assert!(decode(&bin).is_err());
+        let long = MacFile::new(PString::from(&[b'a'; 64][..]), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+        assert!(encode(&long).is_err());
+    }
+}
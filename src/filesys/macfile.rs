@@ -0,0 +1,62 @@
+use crate::types::{
+    DateTime,
+    OSType,
+    PString,
+    Point
+};
+
+use super::hfs::{
+    FInfo,
+    FXInfo
+};
+
+// A file with both forks and its Finder information, as stored in archive
+// formats such as MacBinary and AppleDouble
+#[derive(Clone)]
+pub struct MacFile {
+    pub name: PString,
+    pub info: FInfo,
+    pub ext_info: FXInfo,
+    pub created: DateTime,
+    pub modified: DateTime,
+    pub locked: bool,
+    pub data: Vec<u8>,
+    pub rsrc: Vec<u8>
+}
+
+impl MacFile {
+    pub fn new(name: PString, file_type: OSType, creator: OSType) -> MacFile {
+        let now = DateTime::now();
+        MacFile {
+            name,
+            info: FInfo {
+                fdType: file_type,
+                fdCreator: creator,
+                fdFlags: 0,
+                fdLocation: Point::default(),
+                fdFldr: 0
+            },
+            ext_info: FXInfo::default(),
+            created: now,
+            modified: now,
+            locked: false,
+            data: vec![],
+            rsrc: vec![]
+        }
+    }
+}
+
+impl std::fmt::Debug for MacFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MacFile")
+            .field("name", &self.name)
+            .field("info", &self.info)
+            .field("ext_info", &self.ext_info)
+            .field("created", &self.created)
+            .field("modified", &self.modified)
+            .field("locked", &self.locked)
+            .field("data", &format!("{} bytes", self.data.len()))
+            .field("rsrc", &format!("{} bytes", self.rsrc.len()))
+            .finish()
+    }
+}
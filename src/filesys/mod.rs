@@ -1,2 +1,10 @@
 pub mod hfs;
-pub mod rsrc;
\ No newline at end of file
+pub mod rsrc;
+pub mod macbinary;
+pub mod appledouble;
+pub mod export;
+pub mod import;
+
+mod macfile;
+
+pub use macfile::MacFile;
@@ -0,0 +1,215 @@
+use crate::serialization::{SerialAdaptor, SerialReadStorage};
+use crate::filesys::hfs::HfsImage;
+use crate::{Error, Result};
+use crate::types::OSType;
+
+use super::{
+    Rsrc,
+    RsrcInfo,
+    decode::TypedRsrc
+};
+
+// Reference number of the System file. UseResFile also accepts 0 for it.
+pub const SYSTEM_REFNUM: i16 = 2;
+
+// Reference numbers of other files are allocated from here
+const FIRST_REFNUM: i16 = 3;
+
+// Open resource files, searched the same way as the Resource Manager does.
+//
+// Files are kept in the order they were opened, with the most recently opened
+// on top of the chain and the System file at the bottom. Lookups starts at the
+// current resource file and continues down the chain, so a resource in an
+// application overrides one with the same type and ID in the System file.
+#[derive(Debug)]
+pub struct ResourceChain {
+    // Top of the chain first
+    files: Vec<(i16, Rsrc)>,
+    current: Option<i16>
+}
+
+impl ResourceChain {
+    pub fn new() -> ResourceChain {
+        ResourceChain {
+            files: vec![],
+            current: None
+        }
+    }
+
+    // OpenResFile, the file is put on top of the chain and made current.
+    // Reference numbers of closed files are reused, like the file control
+    // blocks they refer to on the Mac.
+    pub fn open(&mut self, rsrc: Rsrc) -> Result<i16> {
+        let refnum = (FIRST_REFNUM..=i16::MAX)
+            .find(|refnum| self.index(*refnum).is_none())
+            .ok_or_else(|| Error::invalid_value(0, "ResourceChain", "files", "a free reference number", self.files.len()))?;
+        self.files.insert(0, (refnum, rsrc));
+        self.current = Some(refnum);
+        Ok(refnum)
+    }
+
+    // The System file is always at the bottom of the chain. It is only made
+    // current if no other file is open.
+    pub fn open_system(&mut self, rsrc: Rsrc) -> Result<i16> {
+        if self.file(SYSTEM_REFNUM).is_some() {
+            return Err(Error::AlreadyExists);
+        }
+        self.files.push((SYSTEM_REFNUM, rsrc));
+        if self.current.is_none() {
+            self.current = Some(SYSTEM_REFNUM);
+        }
+        Ok(SYSTEM_REFNUM)
+    }
+
+    // Load the resource fork of the System file in the blessed folder
+    pub fn open_system_from(&mut self, img: &HfsImage) -> Result<i16> {
+        let file = img.system_file().ok_or(Error::NotFound)?;
+        let rsrc = Rsrc::new(SerialAdaptor::new(file.open_rsrc()))?;
+        self.open_system(rsrc)
+    }
+
+    // CloseResFile, if the current file is closed, the next file down the
+    // chain becomes current
+    pub fn close(&mut self, refnum: i16) -> Result<Rsrc> {
+        let idx = self.index(refnum).ok_or(Error::NotFound)?;
+        let (_, rsrc) = self.files.remove(idx);
+        if self.current == Some(refnum) {
+            self.current = self.files.get(idx).map(|(refnum, _)| *refnum);
+        }
+        Ok(rsrc)
+    }
+
+    // CurResFile
+    pub fn current(&self) -> Option<i16> {
+        self.current
+    }
+
+    // UseResFile
+    pub fn use_file(&mut self, refnum: i16) -> Result<()> {
+        let refnum = if refnum == 0 { SYSTEM_REFNUM } else { refnum };
+        self.index(refnum).ok_or(Error::NotFound)?;
+        self.current = Some(refnum);
+        Ok(())
+    }
+
+    pub fn file(&self, refnum: i16) -> Option<&Rsrc> {
+        self.index(refnum).map(|idx| &self.files[idx].1)
+    }
+
+    fn index(&self, refnum: i16) -> Option<usize> {
+        self.files.iter().position(|(cur, _)| *cur == refnum)
+    }
+
+    // Files searched by GetResource, starting from the current file
+    fn search_path(&self) -> impl Iterator<Item = &(i16, Rsrc)> {
+        let start = self.current
+            .and_then(|refnum| self.index(refnum))
+            .unwrap_or(self.files.len());
+        self.files[start..].iter()
+    }
+
+    // GetResource
+    pub fn get<'rsrc>(&'rsrc self, rsrc_type: &'rsrc OSType, id: i16) -> Option<RsrcInfo<'rsrc>> {
+        self.search_path().filter_map(|(_, rsrc)| rsrc.get(rsrc_type, id)).next()
+    }
+
+    // Get1Resource, only searches the current file
+    pub fn get1<'rsrc>(&'rsrc self, rsrc_type: &'rsrc OSType, id: i16) -> Option<RsrcInfo<'rsrc>> {
+        self.search_path().next()?.1.get(rsrc_type, id)
+    }
+
+    // GetNamedResource
+    pub fn get_named<'rsrc>(&'rsrc self, rsrc_type: &'rsrc OSType, name: &str) -> Option<RsrcInfo<'rsrc>> {
+        self.search_path().filter_map(|(_, rsrc)| rsrc.get_named(rsrc_type, name)).next()
+    }
+
+    // Get1NamedResource
+    pub fn get1_named<'rsrc>(&'rsrc self, rsrc_type: &'rsrc OSType, name: &str) -> Option<RsrcInfo<'rsrc>> {
+        self.search_path().next()?.1.get_named(rsrc_type, name)
+    }
+
+    // HomeResFile, the file a resource would be loaded from
+    pub fn home_file(&self, rsrc_type: &OSType, id: i16) -> Option<i16> {
+        self.search_path()
+            .find(|(_, rsrc)| rsrc.get(rsrc_type, id).is_some())
+            .map(|(refnum, _)| *refnum)
+    }
+
+    // CountResources, resources hidden by another file with the same ID are
+    // counted once
+    pub fn count(&self, rsrc_type: &OSType) -> usize {
+        let mut ids: Vec<i16> = self.search_path()
+            .flat_map(|(_, rsrc)| rsrc.resources(rsrc_type).map(|info| info.id()))
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids.len()
+    }
+
+    pub fn open_rsrc(&self, rsrc_type: &OSType, id: i16) -> Result<SerialReadStorage> {
+        self.get(rsrc_type, id).ok_or(Error::NotFound)?.open()
+    }
+
+    // GetResource, decoded as the type given by T
+    pub fn load<T: TypedRsrc>(&self, id: i16) -> Result<T> {
+        T::read(&mut self.open_rsrc(&T::RSRC_TYPE, id)?)
+    }
+}
+
+impl Default for ResourceChain {
+    fn default() -> ResourceChain {
+        ResourceChain::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResourceChain, SYSTEM_REFNUM};
+    use crate::filesys::rsrc::{Rsrc, RsrcFork};
+    use crate::serialization::SerialAdaptor;
+    use crate::types::{OSType, PString};
+    use std::io::Cursor;
+
+    fn rsrc(entries: &[(&[u8; 4], i16, &str, u8)]) -> Rsrc {
+        let mut fork = RsrcFork::new();
+        for (t, id, name, data) in entries {
+            fork.add(OSType::from(*t), *id, Some(PString::from(*name)), vec![*data]).unwrap();
+        }
+        Rsrc::new(SerialAdaptor::new(Cursor::new(fork.to_bytes().unwrap()))).unwrap()
+    }
+
+    #[test]
+    fn search_order() {
+        let font = OSType::from(b"FONT");
+        let curs = OSType::from(b"CURS");
+
+        let mut chain = ResourceChain::new();
+        assert_eq!(chain.open_system(rsrc(&[(b"FONT", 12, "Geneva", 1), (b"CURS", 1, "IBeam", 2)])).unwrap(), SYSTEM_REFNUM);
+        assert!(chain.open_system(rsrc(&[])).is_err());
+        let app = chain.open(rsrc(&[(b"FONT", 12, "Geneva", 3), (b"FONT", 20, "Times", 4)])).unwrap();
+
+        assert_eq!(chain.current(), Some(app));
+        assert_eq!(chain.open_rsrc(&font, 12).unwrap().to_vec(), vec![3]);
+        assert_eq!(chain.open_rsrc(&curs, 1).unwrap().to_vec(), vec![2]);
+        assert!(chain.get1(&curs, 1).is_none());
+        assert_eq!(chain.get_named(&curs, "ibeam").unwrap().id(), 1);
+        assert_eq!(chain.home_file(&curs, 1), Some(SYSTEM_REFNUM));
+        assert_eq!(chain.count(&font), 2);
+
+        chain.use_file(0).unwrap();
+        assert_eq!(chain.current(), Some(SYSTEM_REFNUM));
+        assert_eq!(chain.open_rsrc(&font, 12).unwrap().to_vec(), vec![1]);
+        assert!(chain.get(&font, 20).is_none());
+        assert_eq!(chain.count(&font), 1);
+
+        chain.use_file(app).unwrap();
+        chain.close(app).unwrap();
+        assert_eq!(chain.current(), Some(SYSTEM_REFNUM));
+        assert!(chain.use_file(app).is_err());
+
+        let doc = chain.open(rsrc(&[])).unwrap();
+        assert_eq!(chain.open(rsrc(&[])).unwrap(), doc + 1);
+        chain.close(doc).unwrap();
+        assert_eq!(chain.open(rsrc(&[])).unwrap(), doc);
+    }
+}
@@ -0,0 +1,456 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::{Error, Result};
+
+// Decompression of compressed resources, as introduced in System 7.1.
+//
+// A compressed resource has the compressed attribute set, and the data starts
+// with a header telling which 'dcmp' resource to use for decompression. Only
+// the standard decompressors 0, 1 and 2, shipped in the System file, are
+// supported. Format as documented by the rsrcfork project.
+
+const SIGNATURE: u32 = 0xa89f6572;
+const HEADER_LEN: u16 = 0x12;
+const VERSION_8: u16 = 0x0801;
+const VERSION_9: u16 = 0x0901;
+
+#[derive(SerialRead)]
+struct CompressedHeader {
+    signature: u32,
+    header_len: u16,
+    version: u16,
+    decompressed_len: u32
+}
+
+// Common 68k instruction words, for codes $4B to $FD
+const DCMP0_TABLE: [u16; 179] = [
+    0x0000, 0x4eba, 0x0008, 0x4e75, 0x000c, 0x4ead, 0x2053, 0x2f0b,
+    0x6100, 0x0010, 0x7000, 0x2f00, 0x486e, 0x2050, 0x206e, 0x2f2e,
+    0xfffc, 0x48e7, 0x3f3c, 0x0004, 0xfff8, 0x2f0c, 0x2006, 0x4eed,
+    0x4e56, 0x2068, 0x4e5e, 0x0001, 0x588f, 0x4fef, 0x0002, 0x0018,
+    0x6000, 0xffff, 0x508f, 0x4e90, 0x0006, 0x266e, 0x0014, 0xfff4,
+    0x4cee, 0x000a, 0x000e, 0x41ee, 0x4cdf, 0x48c0, 0xfff0, 0x2d40,
+    0x0012, 0x302e, 0x7001, 0x2f28, 0x2054, 0x6700, 0x0020, 0x001c,
+    0x205f, 0x1800, 0x266f, 0x4878, 0x0016, 0x41fa, 0x303c, 0x2840,
+    0x7200, 0x286e, 0x200c, 0x6600, 0x206b, 0x2f07, 0x558f, 0x0028,
+    0xfffe, 0xffec, 0x22d8, 0x200b, 0x000f, 0x598f, 0x2f3c, 0xff00,
+    0x0118, 0x81e1, 0x4a00, 0x4eb0, 0xffe8, 0x48c7, 0x0003, 0x0022,
+    0x0007, 0x001a, 0x6706, 0x6708, 0x4ef9, 0x0024, 0x2078, 0x0800,
+    0x6604, 0x002a, 0x4ed0, 0x3028, 0x265f, 0x6704, 0x0030, 0x43ee,
+    0x3f00, 0x201f, 0x001e, 0xfff6, 0x202e, 0x42a7, 0x2007, 0xfffa,
+    0x6002, 0x3d40, 0x0c40, 0x6606, 0x0026, 0x2d48, 0x2f01, 0x70ff,
+    0x6004, 0x1880, 0x4a40, 0x0040, 0x002c, 0x2f08, 0x0011, 0xffe4,
+    0x2140, 0x2640, 0xfff2, 0x426e, 0x4eb9, 0x3d7c, 0x0038, 0x000d,
+    0x6006, 0x422e, 0x203c, 0x670c, 0x2d68, 0x6608, 0x4a2e, 0x4aae,
+    0x002e, 0x4840, 0x225f, 0x2200, 0x670a, 0x3007, 0x4267, 0x0032,
+    0x2028, 0x0009, 0x487a, 0x0200, 0x2f2b, 0x0005, 0x226e, 0x6602,
+    0xe580, 0x670e, 0x660a, 0x0050, 0x3e00, 0x660c, 0x2e00, 0xffee,
+    0x206d, 0x2040, 0xffe0, 0x5340, 0x6008, 0x0480, 0x0068, 0x0b7c,
+    0x4400, 0x41e8, 0x4841
+];
+
+// Common byte pairs, for codes $D5 to $FD
+const DCMP1_TABLE: [u16; 41] = [
+    0x0000, 0x0001, 0x0002, 0x0003, 0x2e01, 0x3e01, 0x0101, 0x1e01,
+    0xffff, 0x0e01, 0x3100, 0x1112, 0x0107, 0x3332, 0x1239, 0xed10,
+    0x0127, 0x2322, 0x0137, 0x0706, 0x0117, 0x0123, 0x00ff, 0x002f,
+    0x070e, 0xfd3c, 0x0135, 0x0115, 0x0102, 0x0007, 0x003e, 0x05d5,
+    0x0201, 0x0607, 0x0708, 0x3001, 0x0133, 0x0010, 0x1716, 0x373e,
+    0x3637
+];
+
+// Used when the resource doesn't include a custom table
+const DCMP2_DEFAULT_TABLE: [u16; 256] = [
+    0x0000, 0x0008, 0x4eba, 0x206e, 0x4e75, 0x000c, 0x0004, 0x7000,
+    0x0010, 0x0002, 0x486e, 0xfffc, 0x6000, 0x0001, 0x48e7, 0x2f2e,
+    0x4e56, 0x0006, 0x4e5e, 0x2f00, 0x6100, 0xfff8, 0x2f0b, 0xffff,
+    0x0014, 0x000a, 0x0018, 0x205f, 0x000e, 0x2050, 0x3f3c, 0xfff4,
+    0x4cee, 0x302e, 0x6700, 0x4cdf, 0x266e, 0x0012, 0x001c, 0x4267,
+    0xfff0, 0x303c, 0x2f0c, 0x0003, 0x4ed0, 0x0020, 0x7001, 0x0016,
+    0x2d40, 0x48c0, 0x2078, 0x7200, 0x588f, 0x6600, 0x4fef, 0x42a7,
+    0x6706, 0xfffa, 0x558f, 0x286e, 0x3f00, 0xfffe, 0x2f3c, 0x6704,
+    0x598f, 0x206b, 0x0024, 0x201f, 0x41fa, 0x81e1, 0x6604, 0x6708,
+    0x001a, 0x4eb9, 0x508f, 0x202e, 0x0007, 0x4eb0, 0xfff2, 0x3d40,
+    0x001e, 0x2068, 0x6606, 0xfff6, 0x4ef9, 0x0800, 0x0c40, 0x3d7c,
+    0xffec, 0x0005, 0x203c, 0xffe8, 0xdefc, 0x4a2e, 0x0030, 0x0028,
+    0x2f08, 0x200b, 0x6002, 0x426e, 0x2d48, 0x2053, 0x2040, 0x1800,
+    0x6004, 0x41ee, 0x2f28, 0x2f01, 0x670a, 0x4840, 0x2007, 0x6608,
+    0x0118, 0x2f07, 0x3028, 0x3f2e, 0x302b, 0x226e, 0x2f2b, 0x002c,
+    0x670c, 0x225f, 0x6006, 0x00ff, 0x3007, 0xffee, 0x5340, 0x0040,
+    0xffe4, 0x4a40, 0x660a, 0x000f, 0x4ead, 0x70ff, 0x22d8, 0x486b,
+    0x0022, 0x204b, 0x670e, 0x4aae, 0x4e90, 0xffe0, 0xffc0, 0x002a,
+    0x2740, 0x6702, 0x51c8, 0x02b6, 0x487a, 0x2278, 0xb06e, 0xffe6,
+    0x0009, 0x322e, 0x3e00, 0x4841, 0xffea, 0x43ee, 0x4e71, 0x7400,
+    0x2f2c, 0x206c, 0x003c, 0x0026, 0x0050, 0x1880, 0x301f, 0x2200,
+    0x660c, 0xffda, 0x0038, 0x6602, 0x302c, 0x200c, 0x2d6e, 0x4240,
+    0xffe2, 0xa9f0, 0xff00, 0x377c, 0xe580, 0xffdc, 0x4868, 0x594f,
+    0x0034, 0x3e1f, 0x6008, 0x2f06, 0xffde, 0x600a, 0x7002, 0x0032,
+    0xffcc, 0x0080, 0x2251, 0x101f, 0x317c, 0xa029, 0xffd8, 0x5240,
+    0x0100, 0x6710, 0xa023, 0xffce, 0xffd4, 0x2006, 0x4878, 0x002e,
+    0x504f, 0x43fa, 0x6712, 0x7600, 0x41e8, 0x4a6e, 0x20d9, 0x005a,
+    0x7fff, 0x51ca, 0x005c, 0x2e00, 0x0240, 0x48c7, 0x6714, 0x0c80,
+    0x2e9f, 0xffd6, 0x8000, 0x1000, 0x4842, 0x4a6b, 0xffd2, 0x0048,
+    0x4a47, 0x4ed1, 0x206f, 0x0041, 0x600c, 0x2a78, 0x422e, 0x3200,
+    0x6574, 0x6716, 0x0044, 0x486d, 0x2008, 0x486c, 0x0b7c, 0x2640,
+    0x0400, 0x0068, 0x206d, 0x000d, 0x2a40, 0x000b, 0x003e, 0x0220
+];
+
+// Output buffer that refuses to grow past the size given in the header, so
+// corrupt repeat counts can't exhaust memory
+struct Output {
+    data: Vec<u8>,
+    limit: usize
+}
+
+impl Output {
+    fn push(&mut self, bytes: &[u8]) -> Result<()> {
+        if self.data.len() + bytes.len() > self.limit {
+            return Err(Error::invalid_value(0, "CompressedHeader", "decompressed_len", "at least the decompressed size", self.limit));
+        }
+        self.data.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn push_word(&mut self, word: u16) -> Result<()> {
+        self.push(&word.to_be_bytes())
+    }
+}
+
+fn invalid_code(rdr: &SerialReadStorage, field: &'static str, actual: impl std::fmt::Display) -> Error {
+    Error::invalid_value(rdr.pos(), "CompressedData", field, "known code", actual)
+}
+
+// Variable length integer, used in the extended codes
+fn read_var_int(rdr: &mut SerialReadStorage) -> Result<i32> {
+    let head = rdr.read_u8()?;
+    if head == 0xff {
+        rdr.read_i32()
+    } else if head >= 0x80 {
+        let high = head.wrapping_sub(0xc0);
+        let low = rdr.read_u8()?;
+        Ok((((high as u16) << 8) | low as u16) as i16 as i32)
+    } else {
+        Ok(head as i32)
+    }
+}
+
+fn read_count(rdr: &mut SerialReadStorage) -> Result<usize> {
+    let count = read_var_int(rdr)?;
+    if count < 0 {
+        return Err(invalid_code(rdr, "count", count));
+    }
+    Ok(count as usize)
+}
+
+fn lookup<'a>(rdr: &SerialReadStorage, literals: &'a [Vec<u8>], idx: usize) -> Result<&'a [u8]> {
+    literals.get(idx)
+        .map(|lit| lit.as_slice())
+        .ok_or_else(|| invalid_code(rdr, "backreference", idx))
+}
+
+fn extended_repeat(rdr: &mut SerialReadStorage, out: &mut Output, width: usize) -> Result<()> {
+    let value = read_var_int(rdr)?;
+    let count = read_count(rdr)? + 1;
+    let bytes = value.to_be_bytes();
+    for _ in 0..count {
+        out.push(&bytes[4 - width..])?;
+    }
+    Ok(())
+}
+
+fn dcmp0(rdr: &mut SerialReadStorage, out: &mut Output) -> Result<()> {
+    let mut literals: Vec<Vec<u8>> = vec![];
+    loop {
+        let code = rdr.read_u8()?;
+        match code {
+            0x00..=0x1f => {
+                let count = match code & 0x0f {
+                    0 => rdr.read_u8()? as usize,
+                    n => n as usize
+                };
+                let literal = rdr.read_bytes(count as u64 * 2)?;
+                out.push(&literal)?;
+                if code >= 0x10 {
+                    literals.push(literal);
+                }
+            },
+            0x20 | 0x21 => {
+                let idx = 0x28 + (((code as usize - 0x20) << 8) | rdr.read_u8()? as usize);
+                out.push(lookup(rdr, &literals, idx)?)?;
+            },
+            0x22 => {
+                let idx = 0x28 + rdr.read_u16()? as usize;
+                out.push(lookup(rdr, &literals, idx)?)?;
+            },
+            0x23..=0x4a => {
+                out.push(lookup(rdr, &literals, code as usize - 0x23)?)?;
+            },
+            0x4b..=0xfd => {
+                out.push_word(DCMP0_TABLE[code as usize - 0x4b])?;
+            },
+            0xfe => {
+                let kind = rdr.read_u8()?;
+                match kind {
+                    // Segment loader jump table entries. The address of the
+                    // first entry is encoded separately before this code.
+                    0x00 => {
+                        let segment = read_var_int(rdr)? as u16;
+                        let count = read_count(rdr)?;
+                        if count == 0 {
+                            return Err(invalid_code(rdr, "count", count));
+                        }
+                        let tail = [0x3f, 0x3c, (segment >> 8) as u8, segment as u8, 0xa9, 0xf0];
+                        out.push(&tail)?;
+                        for _ in 1..count {
+                            out.push_word(read_var_int(rdr)? as u16)?;
+                            out.push(&tail)?;
+                        }
+                    },
+                    0x02 => extended_repeat(rdr, out, 1)?,
+                    0x03 => extended_repeat(rdr, out, 2)?,
+                    // Words encoded as 8 bit differences to the previous
+                    0x04 => {
+                        let mut value = read_var_int(rdr)? as u16;
+                        out.push_word(value)?;
+                        for _ in 0..read_count(rdr)? {
+                            value = value.wrapping_add(rdr.read_i8()? as u16);
+                            out.push_word(value)?;
+                        }
+                    },
+                    // Long words encoded as differences to the previous
+                    0x06 => {
+                        let mut value = read_var_int(rdr)?;
+                        out.push(&value.to_be_bytes())?;
+                        for _ in 0..read_count(rdr)? {
+                            value = value.wrapping_add(read_var_int(rdr)?);
+                            out.push(&value.to_be_bytes())?;
+                        }
+                    },
+                    kind => return Err(invalid_code(rdr, "extended", kind))
+                }
+            },
+            _ => return Ok(())
+        }
+    }
+}
+
+fn dcmp1(rdr: &mut SerialReadStorage, out: &mut Output) -> Result<()> {
+    let mut literals: Vec<Vec<u8>> = vec![];
+    loop {
+        let code = rdr.read_u8()?;
+        match code {
+            0x00..=0x1f | 0xd0 | 0xd1 => {
+                let (count, store) = if code < 0x20 {
+                    ((code & 0x0f) as usize + 1, code >= 0x10)
+                } else {
+                    (rdr.read_u8()? as usize, code == 0xd1)
+                };
+                let literal = rdr.read_bytes(count as u64)?;
+                out.push(&literal)?;
+                if store {
+                    literals.push(literal);
+                }
+            },
+            0x20..=0xcf => {
+                out.push(lookup(rdr, &literals, code as usize - 0x20)?)?;
+            },
+            0xd2 | 0xd3 => {
+                let idx = 0xb0 + (((code as usize - 0xd2) << 8) | rdr.read_u8()? as usize);
+                out.push(lookup(rdr, &literals, idx)?)?;
+            },
+            0xd4 => {
+                let idx = 0xb0 + rdr.read_u16()? as usize;
+                out.push(lookup(rdr, &literals, idx)?)?;
+            },
+            0xd5..=0xfd => {
+                out.push_word(DCMP1_TABLE[code as usize - 0xd5])?;
+            },
+            0xfe => {
+                let kind = rdr.read_u8()?;
+                match kind {
+                    0x02 => extended_repeat(rdr, out, 1)?,
+                    kind => return Err(invalid_code(rdr, "extended", kind))
+                }
+            },
+            _ => return Ok(())
+        }
+    }
+}
+
+// dcmp 2 has no end marker, the data ends at the end of the resource. If the
+// decompressed size is odd, the last byte is stored as is.
+fn dcmp2(rdr: &mut SerialReadStorage, out: &mut Output, params: [u8; 4]) -> Result<()> {
+    let table_len = params[2] as usize + 1;
+    let custom_table = params[3] & 0x01 != 0;
+    let tagged = params[3] & 0x02 != 0;
+
+    let table: Vec<u16> = if custom_table {
+        let mut table = Vec::with_capacity(table_len);
+        for _ in 0..table_len {
+            table.push(rdr.read_u16()?);
+        }
+        table
+    } else {
+        DCMP2_DEFAULT_TABLE.to_vec()
+    };
+    let odd = !out.limit.is_multiple_of(2);
+    let remaining = |rdr: &SerialReadStorage| rdr.size() - rdr.pos();
+    let entry = |rdr: &mut SerialReadStorage| -> Result<u16> {
+        let idx = rdr.read_u8()? as usize;
+        table.get(idx).cloned().ok_or_else(|| invalid_code(rdr, "table", idx))
+    };
+
+    while remaining(rdr) > 0 {
+        if odd && remaining(rdr) == 1 {
+            out.push(&[rdr.read_u8()?])?;
+            break;
+        }
+        if !tagged {
+            out.push_word(entry(rdr)?)?;
+            continue;
+        }
+        let tag = rdr.read_u8()?;
+        for bit in (0..8).rev() {
+            if remaining(rdr) == 0 {
+                break;
+            }
+            if tag & (1 << bit) != 0 {
+                out.push_word(entry(rdr)?)?;
+            } else if odd && remaining(rdr) == 1 {
+                out.push(&[rdr.read_u8()?])?;
+            } else {
+                out.push_word(rdr.read_u16()?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.len() >= 18 && data[0..4] == SIGNATURE.to_be_bytes()
+}
+
+pub fn decompress(data: Vec<u8>) -> Result<Vec<u8>> {
+    let mut rdr = SerialReadStorage::from(data);
+    let header = CompressedHeader::read(&mut rdr)?;
+    if header.signature != SIGNATURE {
+        return Err(Error::invalid_value(0, "CompressedHeader", "signature", format!("${:08x}", SIGNATURE), format!("${:08x}", header.signature)));
+    }
+    if header.header_len != HEADER_LEN {
+        return Err(Error::invalid_value(4, "CompressedHeader", "header_len", HEADER_LEN, header.header_len));
+    }
+
+    let dcmp_id = match header.version {
+        VERSION_8 => {
+            rdr.pad(2)?; // Working buffer and expansion buffer sizes
+            let id = rdr.read_i16()?;
+            rdr.pad(2)?;
+            id
+        },
+        VERSION_9 => rdr.read_i16()?,
+        version => return Err(Error::invalid_value(6, "CompressedHeader", "version", "$0801 or $0901", format!("${:04x}", version)))
+    };
+    let mut params = [0u8; 4];
+    if header.version == VERSION_9 {
+        for p in params.iter_mut() {
+            *p = rdr.read_u8()?;
+        }
+    }
+
+    let mut out = Output {
+        data: Vec::with_capacity((header.decompressed_len as usize).min(rdr.size() as usize * 8)),
+        limit: header.decompressed_len as usize
+    };
+    match dcmp_id {
+        0 => dcmp0(&mut rdr, &mut out)?,
+        1 => dcmp1(&mut rdr, &mut out)?,
+        2 => dcmp2(&mut rdr, &mut out, params)?,
+        id => return Err(Error::invalid_value(12, "CompressedHeader", "dcmp_id", "0, 1 or 2", id))
+    }
+    if out.data.len() != out.limit {
+        return Err(Error::invalid_value(8, "CompressedHeader", "decompressed_len", out.limit, out.data.len()));
+    }
+    Ok(out.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, is_compressed};
+
+    fn header(version: u16, len: u32, dcmp_id: i16, params: [u8; 4]) -> Vec<u8> {
+        let mut data = vec![0xa8, 0x9f, 0x65, 0x72, 0x00, 0x12];
+        data.extend_from_slice(&version.to_be_bytes());
+        data.extend_from_slice(&len.to_be_bytes());
+        if version == 0x0801 {
+            data.extend_from_slice(&[0, 0]);
+            data.extend_from_slice(&dcmp_id.to_be_bytes());
+            data.extend_from_slice(&[0, 0]);
+        } else {
+            data.extend_from_slice(&dcmp_id.to_be_bytes());
+            data.extend_from_slice(&params);
+        }
+        data
+    }
+
+    #[test]
+    fn dcmp0() {
+        let mut data = header(0x0801, 34, 0, [0; 4]);
+        data.extend_from_slice(&[
+            0x12, b'A', b'B', b'C', b'D', // Stored literal
+            0x23,                         // First stored literal
+            0x4b, 0x4e,                   // Table
+            0xfe, 0x03, 0xd2, 0x34, 0x01, // Repeat $1234 two times
+            0xfe, 0x00, 0x01, 0x02, 0x10, // Jump table, segment 1, two entries
+            0xfe, 0x04, 0x05, 0x01, 0xff, // Word deltas
+            0xff
+        ]);
+        assert!(is_compressed(&data));
+        assert_eq!(decompress(data).unwrap(), vec![
+            b'A', b'B', b'C', b'D', b'A', b'B', b'C', b'D',
+            0x00, 0x00, 0x4e, 0x75,
+            0x12, 0x34, 0x12, 0x34,
+            0x3f, 0x3c, 0x00, 0x01, 0xa9, 0xf0,
+            0x00, 0x10, 0x3f, 0x3c, 0x00, 0x01, 0xa9, 0xf0,
+            0x00, 0x05, 0x00, 0x04
+        ]);
+    }
+
+    #[test]
+    fn dcmp1() {
+        let mut data = header(0x0801, 13, 1, [0; 4]);
+        data.extend_from_slice(&[
+            0x12, b'x', b'y', b'z',     // Stored literal
+            0x20,                       // First stored literal
+            0xd5,                       // Table
+            0xd0, 0x02, b'h', b'i',     // Long literal
+            0xfe, 0x02, 0x41, 0x02,     // Repeat 'A' three times
+            0xff
+        ]);
+        assert_eq!(decompress(data).unwrap(), b"xyzxyz\x00\x00hiAAA".to_vec());
+    }
+
+    #[test]
+    fn dcmp2() {
+        let mut data = header(0x0901, 5, 2, [0, 0xff, 0xff, 0x00]);
+        data.extend_from_slice(&[0x02, 0x04, 0x99]);
+        assert_eq!(decompress(data).unwrap(), vec![0x4e, 0xba, 0x4e, 0x75, 0x99]);
+
+        let mut data = header(0x0901, 6, 2, [0, 0, 1, 0x03]);
+        data.extend_from_slice(&[0xaa, 0xbb, 0xcc, 0xdd]); // Custom table
+        data.extend_from_slice(&[0xa0, 0x01, 0xef, 0x01, 0x00]);
+        assert_eq!(decompress(data).unwrap(), vec![0xcc, 0xdd, 0xef, 0x01, 0xaa, 0xbb]);
+    }
+
+    #[test]
+    fn corrupt() {
+        let mut data = header(0x0801, 4, 0, [0; 4]);
+        data.extend_from_slice(&[0xfe, 0x02, 0x41, 0xff, 0x7f, 0xff, 0xff, 0xff]);
+        assert!(decompress(data).is_err());
+
+        let mut data = header(0x0801, 4, 0, [0; 4]);
+        data.extend_from_slice(&[0x24, 0xff]);
+        assert!(decompress(data).is_err());
+
+        let data = header(0x0801, 4, 3, [0; 4]);
+        assert!(decompress(data).is_err());
+    }
+}
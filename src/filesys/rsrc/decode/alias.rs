@@ -0,0 +1,275 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::{Error, Result};
+use crate::types::{
+    DateTime,
+    Encoding,
+    OSType,
+    PString
+};
+
+use super::{skip_alignment, TypedRsrc};
+
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub enum AliasKind {
+    File,
+    Folder,
+    Unknown(i16)
+}
+
+impl From<i16> for AliasKind {
+    fn from(kind: i16) -> AliasKind {
+        match kind {
+            0 => AliasKind::File,
+            1 => AliasKind::Folder,
+            kind => AliasKind::Unknown(kind)
+        }
+    }
+}
+
+// Tags of the variable length data following the fixed part of the record
+const TAG_PARENT_NAME: i16 = 0;
+const TAG_FOLDER_IDS: i16 = 1;
+const TAG_PATH: i16 = 2;
+const TAG_END: i16 = -1;
+
+// 'alis', an Alias Manager record locating a file or folder, as stored in
+// the resource fork of Finder alias files. The target is found by its ID,
+// and by name and path when the IDs have changed, for example after copying
+// the volume. Only version 2 records, as written by System 7, are read.
+#[derive(Debug)]
+pub struct Alias {
+    // Application defined type of the record
+    pub user_type: OSType,
+    pub kind: AliasKind,
+    pub volume_name: PString,
+    pub volume_created: DateTime,
+    // 'BD' for HFS volumes
+    pub volume_signature: u16,
+    pub drive_type: i16,
+    pub parent_id: u32,
+    pub name: PString,
+    // File ID, or the folder ID for folders
+    pub target_id: u32,
+    pub created: DateTime,
+    pub file_type: OSType,
+    pub creator: OSType,
+    // Number of levels up from the alias file and down to the target, for the
+    // path from the alias file to the target. -1 if on different volumes.
+    pub levels_from: i16,
+    pub levels_to: i16,
+    pub volume_attributes: u32,
+    pub volume_fs_id: i16,
+    pub parent_name: Option<PString>,
+    // Folders from the parent of the target up towards the root
+    pub folder_ids: Vec<u32>,
+    // Full path, starting with the volume name and separated by ':'
+    pub path: Option<Vec<u8>>,
+    // Other tagged data, such as AppleShare server and zone names
+    pub extra: Vec<(i16, Vec<u8>)>
+}
+
+// String stored in a fixed size field, with a length byte
+fn read_fixed_string(rdr: &mut SerialReadStorage, field: &'static str, size: u64) -> Result<PString> {
+    let start = rdr.pos();
+    let name = PString::read(rdr)?;
+    if name.len() as u64 >= size {
+        return Err(Error::invalid_value(start, "Alias", field, format!("at most {} bytes", size - 1), name.len()));
+    }
+    rdr.seek(start + size)?;
+    Ok(name)
+}
+
+impl Alias {
+    pub fn path_string(&self, encoding: Encoding) -> Option<String> {
+        self.path.as_ref().map(|path| encoding.decode(path))
+    }
+
+    // Path of the target relative to the root of its volume, in the form used
+    // by HfsImage::locate
+    pub fn volume_path(&self, encoding: Encoding) -> Option<String> {
+        let path = self.path_string(encoding)?;
+        let (_, relative) = path.split_once(':')?;
+        Some(relative.trim_end_matches(':').to_string())
+    }
+}
+
+impl SerialRead for Alias {
+    fn read(rdr: &mut SerialReadStorage) -> Result<Alias> {
+        let user_type = OSType::read(rdr)?;
+        let size = rdr.read_u16()?;
+        let version = rdr.read_i16()?;
+        if version != 2 {
+            return Err(Error::invalid_value(rdr.pos() - 2, "Alias", "version", 2, version));
+        }
+        let kind = AliasKind::from(rdr.read_i16()?);
+        let volume_name = read_fixed_string(rdr, "volume_name", 28)?;
+        let volume_created = DateTime::read(rdr)?;
+        let volume_signature = rdr.read_u16()?;
+        let drive_type = rdr.read_i16()?;
+        let parent_id = rdr.read_u32()?;
+        let name = read_fixed_string(rdr, "name", 64)?;
+        let target_id = rdr.read_u32()?;
+        let created = DateTime::read(rdr)?;
+        let file_type = OSType::read(rdr)?;
+        let creator = OSType::read(rdr)?;
+        let levels_from = rdr.read_i16()?;
+        let levels_to = rdr.read_i16()?;
+        let volume_attributes = rdr.read_u32()?;
+        let volume_fs_id = rdr.read_i16()?;
+        rdr.pad(10)?;
+
+        let mut alias = Alias {
+            user_type,
+            kind,
+            volume_name,
+            volume_created,
+            volume_signature,
+            drive_type,
+            parent_id,
+            name,
+            target_id,
+            created,
+            file_type,
+            creator,
+            levels_from,
+            levels_to,
+            volume_attributes,
+            volume_fs_id,
+            parent_name: None,
+            folder_ids: vec![],
+            path: None,
+            extra: vec![]
+        };
+
+        // The record size covers the tagged data, which may be missing
+        let end = (size as u64).min(rdr.size());
+        while rdr.pos() + 4 <= end {
+            let tag = rdr.read_i16()?;
+            if tag == TAG_END {
+                break;
+            }
+            let len = rdr.read_u16()?;
+            let data = rdr.read_bytes(len as u64)?;
+            match tag {
+                TAG_PARENT_NAME => alias.parent_name = Some(PString::from(&data[..])),
+                TAG_FOLDER_IDS => alias.folder_ids = data.chunks_exact(4)
+                    .map(|id| u32::from_be_bytes([id[0], id[1], id[2], id[3]]))
+                    .collect(),
+                TAG_PATH => alias.path = Some(data),
+                tag => alias.extra.push((tag, data))
+            }
+            skip_alignment(rdr)?;
+        }
+        Ok(alias)
+    }
+}
+
+impl TypedRsrc for Alias {
+    const RSRC_TYPE: OSType = OSType(*b"alis");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{Alias, AliasKind, TypedRsrc};
+    use crate::filesys::MacFile;
+    use crate::filesys::hfs::{HfsBuilder, HfsImage, ROOT_ID};
+    use crate::filesys::rsrc::RsrcFork;
+    use crate::serialization::{SerialAdaptor, SerialRead, SerialReadStorage};
+    use crate::types::{DateTime, Encoding, OSType, PString};
+
+    fn fixed(s: &[u8], size: usize) -> Vec<u8> {
+        let mut data = vec![s.len() as u8];
+        data.extend_from_slice(s);
+        data.resize(size, 0);
+        data
+    }
+
+    fn alias_record(folder: bool, parent: u32, name: &[u8], target: u32, path: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; 4];
+        data.extend_from_slice(&[0, 0, 0, 2, 0, folder as u8]);
+        data.extend(fixed(b"Test", 28));
+        data.extend_from_slice(&[0, 0, 0, 0, b'B', b'D', 0, 0]);
+        data.extend_from_slice(&parent.to_be_bytes());
+        data.extend(fixed(name, 64));
+        data.extend_from_slice(&target.to_be_bytes());
+        data.extend_from_slice(b"\0\0\0\0TEXTttxt\xff\xff\xff\xff");
+        data.resize(150, 0);
+        data.extend_from_slice(&[0, 0, 0, 3, b'A', b'p', b'p', 0]);
+        data.extend_from_slice(&[0, 2, 0, path.len() as u8]);
+        data.extend_from_slice(path);
+        if !path.len().is_multiple_of(2) {
+            data.push(0);
+        }
+        data.extend_from_slice(&[0xff, 0xff, 0, 0]);
+        let size = data.len() as u16;
+        data[4..6].copy_from_slice(&size.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn decode() {
+        let data = alias_record(false, 16, b"Read Me", 20, b"Test:Apps:Read Me");
+        let alias = Alias::read(&mut SerialReadStorage::from(data)).unwrap();
+        assert_eq!(alias.kind, AliasKind::File);
+        assert_eq!((alias.parent_id, alias.target_id), (16, 20));
+        assert_eq!((&alias.volume_name, &alias.name), (&PString::from("Test"), &PString::from("Read Me")));
+        assert_eq!(alias.parent_name, Some(PString::from("App")));
+        assert_eq!(alias.volume_path(Encoding::MacRoman).as_deref(), Some("Apps:Read Me"));
+
+        let mut old = alias_record(false, 16, b"Read Me", 20, b"");
+        old[7] = 1;
+        assert!(Alias::read(&mut SerialReadStorage::from(old)).is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        let mut builder = HfsBuilder::new(PString::from("Test")).unwrap();
+        let date = DateTime::from_mac_u32(0xa61e6e80);
+        let apps = builder.add_folder(ROOT_ID, PString::from("Apps"), date, date).unwrap();
+        let doc = builder.add_file(apps, MacFile::new(PString::from("Read Me"), OSType::from(b"TEXT"), OSType::from(b"ttxt"))).unwrap();
+
+        let aliases = [
+            ("By ID", alias_record(false, 99, b"Moved", doc, b"")),
+            ("By name", alias_record(false, apps, b"Read Me", 99, b"")),
+            ("By path", alias_record(false, 99, b"Moved", 99, b"Test:Apps:Read Me")),
+            ("Folder", alias_record(true, ROOT_ID, b"Apps", apps, b"")),
+            ("Missing", alias_record(false, 99, b"Moved", 99, b"Test:Read Me"))
+        ];
+        for (name, record) in aliases.iter() {
+            let mut fork = RsrcFork::new();
+            fork.add(Alias::RSRC_TYPE, 0, None, record.clone()).unwrap();
+            let mut file = MacFile::new(PString::from(*name), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+            file.info.fdFlags = 0x8000;
+            file.rsrc = fork.to_bytes().unwrap();
+            builder.add_file(ROOT_ID, file).unwrap();
+        }
+        // Other records come before the one with ID 0 in the map
+        let mut fork = RsrcFork::new();
+        fork.add(Alias::RSRC_TYPE, -128, None, aliases[4].1.clone()).unwrap();
+        fork.add(Alias::RSRC_TYPE, 0, None, aliases[0].1.clone()).unwrap();
+        let mut file = MacFile::new(PString::from("Two records"), OSType::from(b"TEXT"), OSType::from(b"ttxt"));
+        file.info.fdFlags = 0x8000;
+        file.rsrc = fork.to_bytes().unwrap();
+        builder.add_file(ROOT_ID, file).unwrap();
+
+        let mut img = HfsImage::from(SerialAdaptor::new(Cursor::new(builder.build().unwrap()))).unwrap();
+        for name in ["By ID", "By name", "By path", "Two records"].iter() {
+            let alias = img.locate(name).unwrap().to_file().unwrap();
+            assert!(alias.is_alias());
+            let target = img.resolve_alias(&alias.read_alias().unwrap()).unwrap();
+            assert_eq!(target.get_id(), doc, "{}", name);
+        }
+        assert!(img.locate("Missing").map(|obj| img.follow(obj).is_none()).unwrap());
+        assert_eq!(img.locate("Folder:Read Me").map(|obj| obj.get_id()), None);
+
+        img.set_follow_aliases(true);
+        assert_eq!(img.locate("Folder:Read Me").map(|obj| obj.get_id()), Some(doc));
+        assert_eq!(img.locate("By ID").map(|obj| obj.get_id()), Some(doc));
+        assert!(img.locate("Missing").is_none());
+    }
+}
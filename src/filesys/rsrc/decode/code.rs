@@ -0,0 +1,424 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::tools::disasm;
+use crate::types::{
+    OSType,
+    PString
+};
+use crate::{Error, Result};
+
+use super::TypedRsrc;
+use super::super::Rsrc;
+
+// Size of a jump table entry, and offset of the jump instruction in a loaded
+// entry, as called by JSR offset(A5)
+const ENTRY_LEN: u32 = 8;
+const ENTRY_JUMP: u32 = 2;
+
+const PUSH_WORD: u16 = 0x3f3c; // MOVE.W #segment, -(A7)
+const LOAD_SEG: u16 = 0xa9f0;
+
+// Entry of the jump table, in the unloaded state it has on disk
+#[derive(SerialRead)]
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct JumpTableEntry {
+    pub offset: u16,
+    pub push: u16,
+    pub segment: i16,
+    pub trap: u16
+}
+
+impl JumpTableEntry {
+    // Segment with the routine, if the entry has the standard LoadSeg form
+    pub fn segment(&self) -> Option<i16> {
+        if self.push == PUSH_WORD && self.trap == LOAD_SEG {
+            Some(self.segment)
+        } else {
+            None
+        }
+    }
+}
+
+// Header of 'CODE' 0, with the size of the A5 world and the jump table
+#[derive(SerialRead)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Clone)]
+pub struct JumpTableHeader {
+    pub above_a5: u32,
+    pub below_a5: u32,
+    pub length: u32,
+    pub offset_a5: u32
+}
+
+// Header of 'CODE' 1 and up, with the entries of the jump table referring
+// to the segment. The first entry is a byte offset into the jump table.
+#[derive(SerialRead)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Clone)]
+pub struct SegmentHeader {
+    pub first_entry: u16,
+    pub entry_count: u16
+}
+
+// 'CODE' 0, the jump table and the size of the A5 world, Inside Macintosh II-61
+#[derive(Debug)]
+pub struct JumpTable {
+    pub header: JumpTableHeader,
+    pub entries: Vec<JumpTableEntry>
+}
+
+impl JumpTable {
+    // A5 relative offset used to call an entry. Instructions only address
+    // 16 bit offsets, so larger values wrap like they do on the 68000.
+    pub fn a5_offset(&self, index: usize) -> i32 {
+        self.header.offset_a5
+            .wrapping_add(ENTRY_JUMP)
+            .wrapping_add((index as u32).wrapping_mul(ENTRY_LEN)) as i32
+    }
+
+    // Entry called through an A5 relative offset
+    pub fn index_of(&self, a5_offset: i32) -> Option<usize> {
+        let rel = a5_offset as i64 - (self.header.offset_a5 as i64 + ENTRY_JUMP as i64);
+        if rel < 0 || rel % ENTRY_LEN as i64 != 0 {
+            return None;
+        }
+        let index = usize::try_from(rel / ENTRY_LEN as i64).ok()?;
+        if index < self.entries.len() {
+            Some(index)
+        } else {
+            None
+        }
+    }
+}
+
+impl SerialRead for JumpTable {
+    fn read(rdr: &mut SerialReadStorage) -> Result<JumpTable> {
+        let header = JumpTableHeader::read(rdr)?;
+        let count = header.length / ENTRY_LEN;
+        let mut entries = Vec::with_capacity(count.min(0x2000) as usize);
+        for _ in 0..count {
+            let offset = rdr.pos();
+            entries.push(JumpTableEntry::read(rdr).map_err(|err| err.in_field("JumpTable", "entries", offset))?);
+        }
+        Ok(JumpTable {
+            header,
+            entries
+        })
+    }
+}
+
+impl TypedRsrc for JumpTable {
+    const RSRC_TYPE: OSType = OSType(*b"CODE");
+}
+
+// 'CODE' 1 and up, a segment of code using the near model header. The
+// header tells which part of the jump table refers to the segment.
+pub struct CodeSegment {
+    pub header: SegmentHeader,
+    pub code: Vec<u8>
+}
+
+impl CodeSegment {
+    // Index of the first jump table entry for the segment
+    pub fn first_index(&self) -> usize {
+        (self.header.first_entry as u32 / ENTRY_LEN) as usize
+    }
+}
+
+impl SerialRead for CodeSegment {
+    fn read(rdr: &mut SerialReadStorage) -> Result<CodeSegment> {
+        let header = SegmentHeader::read(rdr)?;
+        if header.first_entry == 0xffff {
+            return Err(Error::invalid_value(0, "CodeSegment", "first_entry", "near model segment", "far model header"));
+        }
+        let len = rdr.size() - rdr.pos();
+        Ok(CodeSegment {
+            header,
+            code: rdr.read_bytes(len)?
+        })
+    }
+}
+
+impl TypedRsrc for CodeSegment {
+    const RSRC_TYPE: OSType = OSType(*b"CODE");
+}
+
+impl std::fmt::Debug for CodeSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CodeSegment")
+            .field("header", &self.header)
+            .field("code", &format!("{} bytes", self.code.len()))
+            .finish()
+    }
+}
+
+// Line of a disassembled segment, either an instruction or a MacsBug symbol
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct DisasmLine {
+    pub offset: u32,
+    pub bytes: Vec<u8>,
+    pub text: String,
+    pub label: Option<String>,
+    pub comment: Option<String>
+}
+
+impl std::fmt::Display for DisasmLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(label) = &self.label {
+            writeln!(f, "{}:", label)?;
+        }
+        let words: Vec<String> = self.bytes.chunks(2)
+            .map(|w| w.iter().map(|b| format!("{:02X}", b)).collect())
+            .collect();
+        let line = format!("    {:06X}  {:<24} {}", self.offset, words.join(" "), self.text);
+        match &self.comment {
+            Some(comment) => write!(f, "{:<72} ; {}", line, comment),
+            None => write!(f, "{}", line.trim_end())
+        }
+    }
+}
+
+struct Segment {
+    id: i16,
+    name: Option<PString>,
+    segment: CodeSegment,
+    lines: Vec<DisasmLine>
+}
+
+// All code of an application. Segments are disassembled by a linear sweep,
+// and procedures named by their MacsBug symbols when present.
+pub struct CodeAnalysis {
+    jump_table: JumpTable,
+    segments: Vec<Segment>,
+    names: HashMap<(i16, u32), String>
+}
+
+impl CodeAnalysis {
+    pub fn new(rsrc: &Rsrc) -> Result<CodeAnalysis> {
+        let jump_table = rsrc.load::<JumpTable>(0)?;
+        let mut segments = Vec::new();
+        let mut names = HashMap::new();
+        for info in rsrc.resources(&CodeSegment::RSRC_TYPE) {
+            if info.id() == 0 {
+                continue;
+            }
+            let segment = CodeSegment::read(&mut info.open()?)?;
+            let lines = sweep(&segment.code, info.id(), &mut names);
+            segments.push(Segment {
+                id: info.id(),
+                name: info.name().cloned(),
+                segment,
+                lines
+            });
+        }
+        Ok(CodeAnalysis {
+            jump_table,
+            segments,
+            names
+        })
+    }
+
+    pub fn jump_table(&self) -> &JumpTable {
+        &self.jump_table
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = (i16, Option<&PString>, &CodeSegment)> {
+        self.segments.iter().map(|s| (s.id, s.name.as_ref(), &s.segment))
+    }
+
+    // MacsBug name of the procedure at an offset in a segment
+    pub fn procedure_name(&self, segment: i16, offset: u32) -> Option<&str> {
+        self.names.get(&(segment, offset)).map(|s| s.as_str())
+    }
+
+    fn name_or_address(&self, segment: i16, offset: u32) -> String {
+        match self.procedure_name(segment, offset) {
+            Some(name) => name.to_string(),
+            None => format!("CODE_{}_{:04X}", segment, offset)
+        }
+    }
+
+    // Symbolic name of the routine a jump table entry refers to
+    pub fn entry_name(&self, index: usize) -> Option<String> {
+        let entry = self.jump_table.entries.get(index)?;
+        Some(self.name_or_address(entry.segment()?, entry.offset as u32))
+    }
+
+    pub fn disassemble(&self, id: i16) -> Result<Vec<DisasmLine>> {
+        let segment = self.segments.iter().find(|s| s.id == id).ok_or(Error::NotFound)?;
+        let code = &segment.segment.code;
+        // Entry points and local call targets get labels
+        let mut labels: HashSet<u32> = self.jump_table.entries.iter()
+            .filter(|entry| entry.segment() == Some(id))
+            .map(|entry| entry.offset as u32)
+            .collect();
+        for line in segment.lines.iter() {
+            if let Some(Reference::Local(target)) = reference(code, line.offset) {
+                labels.insert(target);
+            }
+        }
+
+        let mut lines = segment.lines.clone();
+        for line in lines.iter_mut() {
+            let offset = line.offset;
+            if line.label.is_none() && labels.contains(&offset) {
+                line.label = Some(self.name_or_address(id, offset));
+            }
+            line.comment = match reference(code, offset) {
+                Some(Reference::A5(a5_offset)) => self.jump_table.index_of(a5_offset)
+                    .and_then(|index| self.entry_name(index)),
+                Some(Reference::Local(target)) => Some(self.name_or_address(id, target)),
+                None => None
+            };
+        }
+        Ok(lines)
+    }
+}
+
+enum Reference {
+    A5(i32),
+    Local(u32)
+}
+
+fn word_at(code: &[u8], offset: u32) -> Option<i16> {
+    disasm::read_word(code, offset).map(|w| w as i16)
+}
+
+// Targets of calls and address computations which can be named
+fn reference(code: &[u8], offset: u32) -> Option<Reference> {
+    let op = disasm::read_word(code, offset)?;
+    let local = |disp: i32| {
+        let target = offset as i64 + 2 + disp as i64;
+        if target >= 0 && (target as usize) < code.len() {
+            Some(Reference::Local(target as u32))
+        } else {
+            None
+        }
+    };
+    match op {
+        // JSR, JMP and PEA d16(A5)
+        0x4ead | 0x4eed | 0x486d => Some(Reference::A5(word_at(code, offset + 2)? as i32)),
+        // JSR, JMP and PEA d16(PC)
+        0x4eba | 0x4efa | 0x487a => local(word_at(code, offset + 2)? as i32),
+        // LEA d16(PC), An
+        op if op & 0xf1ff == 0x41fa => local(word_at(code, offset + 2)? as i32),
+        // BSR with 16 bit or 8 bit displacement
+        0x6100 => local(word_at(code, offset + 2)? as i32),
+        op if op & 0xff00 == 0x6100 && op & 0xff != 0xff => local(op as u8 as i8 as i32),
+        _ => None
+    }
+}
+
+// Disassemble a segment and collect the names of its procedures. A symbol
+// names the procedure which started after the previous symbol.
+fn sweep(code: &[u8], id: i16, names: &mut HashMap<(i16, u32), String>) -> Vec<DisasmLine> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    let mut procedure_start = 0;
+    let mut start_line = 0;
+    while (offset as usize) < code.len() {
+        let inst = disasm::decode(code, offset);
+        let end = disasm::is_procedure_end(code, offset);
+        lines.push(DisasmLine {
+            offset,
+            bytes: code[offset as usize..(offset + inst.len) as usize].to_vec(),
+            text: inst.text,
+            label: None,
+            comment: None
+        });
+        offset += inst.len;
+
+        if end {
+            if let Some((name, len)) = disasm::macsbug_symbol(code, offset) {
+                lines[start_line].label = Some(name.clone());
+                names.insert((id, procedure_start), name.clone());
+                lines.push(DisasmLine {
+                    offset,
+                    bytes: Vec::new(),
+                    text: format!("{:10} '{}'", "dc.b", name),
+                    label: None,
+                    comment: None
+                });
+                offset += len;
+                procedure_start = offset;
+                start_line = lines.len();
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodeAnalysis, JumpTable};
+    use crate::filesys::rsrc::{Rsrc, RsrcFork};
+    use crate::serialization::{SerialAdaptor, SerialRead, SerialReadStorage};
+    use crate::types::OSType;
+    use std::io::Cursor;
+
+    fn jump_table() -> Vec<u8> {
+        let mut data = vec![
+            0, 0, 0, 0x30, 0, 0, 0x10, 0, 0, 0, 0, 0x10, 0, 0, 0, 0x20
+        ];
+        data.extend_from_slice(&[0x00, 0x00, 0x3f, 0x3c, 0x00, 0x01, 0xa9, 0xf0]);
+        data.extend_from_slice(&[0x00, 0x10, 0x3f, 0x3c, 0x00, 0x01, 0xa9, 0xf0]);
+        data
+    }
+
+    #[test]
+    fn jump_table_entries() {
+        let data = jump_table();
+        let table = JumpTable::read(&mut SerialReadStorage::from(data)).unwrap();
+        assert_eq!(table.entries.len(), 2);
+        assert_eq!(table.entries[1].segment(), Some(1));
+        assert_eq!(table.entries[1].offset, 0x10);
+        assert_eq!(table.a5_offset(1), 0x2a);
+        assert_eq!(table.index_of(0x2a), Some(1));
+        assert_eq!(table.index_of(0x2b), None);
+        assert_eq!(table.index_of(0x32), None);
+        assert_eq!(table.index_of(i32::MIN), None);
+
+        let mut table = table;
+        table.header.offset_a5 = u32::MAX;
+        assert_eq!(table.a5_offset(1), 9);
+        assert_eq!(table.index_of(i32::MAX), None);
+    }
+
+    #[test]
+    fn analysis() {
+        let code = vec![
+            0x00, 0x00, 0x00, 0x02, // First entry, count
+            0x4e, 0xad, 0x00, 0x2a, // JSR $2a(A5)
+            0xa9, 0xf4,             // _ExitToShell
+            0x4e, 0x75,             // RTS
+            0x84, b'M', b'a', b'i', b'n', 0x00, 0x00, 0x00,
+            0x61, 0xee,             // BSR to 0
+            0x4e, 0x75              // RTS
+        ];
+        let mut fork = RsrcFork::new();
+        fork.add(OSType::from(b"CODE"), 0, None, jump_table()).unwrap();
+        fork.add(OSType::from(b"CODE"), 1, None, code).unwrap();
+        let rsrc = Rsrc::new(SerialAdaptor::new(Cursor::new(fork.to_bytes().unwrap()))).unwrap();
+
+        let analysis = CodeAnalysis::new(&rsrc).unwrap();
+        assert_eq!(analysis.procedure_name(1, 0), Some("Main"));
+        assert_eq!(analysis.entry_name(0), Some(String::from("Main")));
+        assert_eq!(analysis.entry_name(1), Some(String::from("CODE_1_0010")));
+
+        let lines = analysis.disassemble(1).unwrap();
+        let find = |offset| lines.iter().find(|l| l.offset == offset).unwrap();
+        assert_eq!(find(0).label, Some(String::from("Main")));
+        assert_eq!(find(0).comment, Some(String::from("CODE_1_0010")));
+        assert_eq!(find(4).text, "_ExitToShell");
+        assert_eq!(find(8).text, "dc.b       'Main'");
+        assert_eq!(find(0x10).label, Some(String::from("CODE_1_0010")));
+        assert_eq!(find(0x10).comment, Some(String::from("Main")));
+        assert!(analysis.disassemble(2).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use crate::types::PString;
+
+use super::{
+    Decoded,
+    DialogItemKind,
+    Field,
+    Value,
+    BitmapFont
+};
+
+// Decoded resources as a tree of labeled fields, the same form as resources
+// decoded using a 'TMPL', for listing them as text or JSON. Bitmaps such as
+// icons are summarized, as they are shown as images.
+
+fn field(label: &str, value: Value) -> Field {
+    Field { label: PString::from(label), value }
+}
+
+fn int(value: impl Into<i64>) -> Value {
+    Value::Integer(value.into())
+}
+
+fn hex(value: impl Into<u64>, size: usize) -> Value {
+    Value::Hex { value: value.into(), size }
+}
+
+fn text(s: &PString) -> Value {
+    Value::Text(s.as_bytes().to_vec())
+}
+
+fn string(s: &str) -> Value {
+    Value::Text(s.as_bytes().to_vec())
+}
+
+fn list<T>(items: &[T], fields: impl Fn(&T) -> Vec<Field>) -> Value {
+    Value::List(items.iter().map(fields).collect())
+}
+
+fn size(width: usize, height: usize) -> Vec<Field> {
+    vec![
+        field("Width", int(width as i64)),
+        field("Height", int(height as i64))
+    ]
+}
+
+fn font_fields(font: &BitmapFont) -> Vec<Field> {
+    let rec = &font.record;
+    vec![
+        field("Font type", hex(rec.fontType, 2)),
+        field("First char", int(rec.firstChar)),
+        field("Last char", int(rec.lastChar)),
+        field("Max width", int(rec.widMax)),
+        field("Max kern", int(rec.kernMax)),
+        field("Rect width", int(rec.fRectWidth)),
+        field("Rect height", int(rec.fRectHeight)),
+        field("Ascent", int(rec.ascent)),
+        field("Descent", int(rec.descent)),
+        field("Leading", int(rec.leading)),
+        field("Depth", int(font.depth() as i64))
+    ]
+}
+
+fn item_fields(kind: &DialogItemKind) -> Vec<Field> {
+    let (name, value) = match kind {
+        DialogItemKind::User => ("User item", None),
+        DialogItemKind::Button(title) => ("Button", Some(text(title))),
+        DialogItemKind::CheckBox(title) => ("Check box", Some(text(title))),
+        DialogItemKind::RadioButton(title) => ("Radio button", Some(text(title))),
+        DialogItemKind::Control(id) => ("Control", Some(int(*id))),
+        DialogItemKind::StaticText(title) => ("Static text", Some(text(title))),
+        DialogItemKind::EditText(title) => ("Edit text", Some(text(title))),
+        DialogItemKind::Icon(id) => ("Icon", Some(int(*id))),
+        DialogItemKind::Picture(id) => ("Picture", Some(int(*id))),
+        DialogItemKind::Other { item_type, data } => {
+            return vec![
+                field("Type", int(*item_type)),
+                field("Data", Value::Bytes(data.clone()))
+            ];
+        }
+    };
+    let mut fields = vec![field("Type", string(name))];
+    if let Some(value) = value {
+        let label = match kind {
+            DialogItemKind::Control(_) | DialogItemKind::Icon(_) | DialogItemKind::Picture(_) => "Resource ID",
+            _ => "Text"
+        };
+        fields.push(field(label, value));
+    }
+    fields
+}
+
+impl Decoded {
+    pub fn fields(&self) -> Vec<Field> {
+        match self {
+            Decoded::Str(s) => vec![field("String", text(&s.0))],
+            Decoded::StrList(list_) => vec![
+                field("Strings", list(&list_.strings, |s| vec![field("String", text(s))]))
+            ],
+            Decoded::Text(t) => vec![field("Text", Value::Text(t.0.clone()))],
+            Decoded::Version(vers) => vec![
+                field("Version", string(&vers.number())),
+                field("Stage", string(&format!("{:?}", vers.stage))),
+                field("Non-release", int(vers.non_release)),
+                field("Region", int(vers.region)),
+                field("Short version", text(&vers.short_version)),
+                field("Long version", text(&vers.long_version))
+            ],
+            Decoded::Bundle(bndl) => vec![
+                field("Signature", Value::Type(bndl.signature.clone())),
+                field("Signature ID", int(bndl.signature_id)),
+                field("Types", list(&bndl.types, |t| vec![
+                    field("Type", Value::Type(t.rsrc_type.clone())),
+                    field("IDs", list(&t.mappings, |m| vec![
+                        field("Local ID", int(m.local_id)),
+                        field("Resource ID", int(m.rsrc_id))
+                    ]))
+                ]))
+            ],
+            Decoded::FileReference(fref) => {
+                let mut fields = vec![
+                    field("File type", Value::Type(fref.file_type.clone())),
+                    field("Local ID", int(fref.local_id))
+                ];
+                if let Some(name) = &fref.file_name {
+                    fields.push(field("File name", text(name)));
+                }
+                fields
+            },
+            Decoded::SizeInfo(size) => vec![
+                field("Flags", hex(size.flags, 2)),
+                field("Preferred size", int(size.preferred_size)),
+                field("Minimum size", int(size.minimum_size))
+            ],
+            Decoded::Alias(alias) => {
+                let mut fields = vec![
+                    field("User type", Value::Type(alias.user_type.clone())),
+                    field("Kind", string(&format!("{:?}", alias.kind))),
+                    field("Volume name", text(&alias.volume_name)),
+                    field("Volume created", Value::Date(alias.volume_created)),
+                    field("Parent ID", int(alias.parent_id)),
+                    field("Name", text(&alias.name)),
+                    field("Target ID", int(alias.target_id)),
+                    field("Created", Value::Date(alias.created)),
+                    field("File type", Value::Type(alias.file_type.clone())),
+                    field("Creator", Value::Type(alias.creator.clone()))
+                ];
+                if let Some(path) = &alias.path {
+                    fields.push(field("Path", Value::Text(path.clone())));
+                }
+                fields
+            },
+            Decoded::Menu(menu) => vec![
+                field("Menu ID", int(menu.menu_id)),
+                field("MDEF ID", int(menu.proc_id)),
+                field("Enable flags", hex(menu.enable_flags, 4)),
+                field("Title", text(&menu.title)),
+                field("Items", Value::List(menu.items.iter().enumerate().map(|(index, item)| vec![
+                    field("Text", text(&item.text)),
+                    field("Enabled", Value::Boolean(menu.is_enabled(index + 1))),
+                    field("Icon", int(item.icon)),
+                    field("Key equivalent", Value::Char(item.key_equiv)),
+                    field("Mark", Value::Char(item.mark)),
+                    field("Style", hex(item.style, 1))
+                ]).collect()))
+            ],
+            Decoded::WindowTemplate(wind) => vec![
+                field("Bounds", Value::Rect(wind.bounds)),
+                field("WDEF ID", int(wind.proc_id)),
+                field("Visible", Value::Boolean(wind.visible)),
+                field("Close box", Value::Boolean(wind.go_away)),
+                field("Ref con", hex(wind.ref_con, 4)),
+                field("Title", text(&wind.title))
+            ],
+            Decoded::DialogTemplate(dlog) => vec![
+                field("Bounds", Value::Rect(dlog.bounds)),
+                field("WDEF ID", int(dlog.proc_id)),
+                field("Visible", Value::Boolean(dlog.visible)),
+                field("Close box", Value::Boolean(dlog.go_away)),
+                field("Ref con", hex(dlog.ref_con, 4)),
+                field("DITL ID", int(dlog.items_id)),
+                field("Title", text(&dlog.title))
+            ],
+            Decoded::AlertTemplate(alrt) => vec![
+                field("Bounds", Value::Rect(alrt.bounds)),
+                field("DITL ID", int(alrt.items_id)),
+                field("Stages", hex(alrt.stages, 2))
+            ],
+            Decoded::DialogItemList(ditl) => vec![
+                field("Items", list(&ditl.items, |item| {
+                    let mut fields = vec![
+                        field("Bounds", Value::Rect(item.bounds)),
+                        field("Enabled", Value::Boolean(item.enabled))
+                    ];
+                    fields.extend(item_fields(&item.kind));
+                    fields
+                }))
+            ],
+            Decoded::ControlTemplate(cntl) => vec![
+                field("Bounds", Value::Rect(cntl.bounds)),
+                field("Value", int(cntl.value)),
+                field("Visible", Value::Boolean(cntl.visible)),
+                field("Max", int(cntl.max)),
+                field("Min", int(cntl.min)),
+                field("CDEF ID", int(cntl.proc_id)),
+                field("Ref con", hex(cntl.ref_con, 4)),
+                field("Title", text(&cntl.title))
+            ],
+            Decoded::Icon(_) | Decoded::IconList(_) | Decoded::LargeIcon4(_) | Decoded::LargeIcon8(_) => size(32, 32),
+            Decoded::SmallIconList(_) | Decoded::SmallIcon4(_) | Decoded::SmallIcon8(_) => size(16, 16),
+            Decoded::ColorIcon(cicn) => {
+                let bounds = cicn.pix_map.bounds;
+                let mut fields = size(bounds.width().max(0) as usize, bounds.height().max(0) as usize);
+                fields.push(field("Pixel size", int(cicn.pix_map.pixelSize)));
+                fields.push(field("Colors", int(cicn.color_table.ctTable.len() as i64)));
+                fields
+            },
+            Decoded::PixPattern(ppat) => {
+                let mut fields = vec![
+                    field("Type", int(ppat.pat_type)),
+                    field("Pattern", Value::Bytes(ppat.pattern.0.to_vec()))
+                ];
+                if let Some((pix_map, _)) = &ppat.pix_map {
+                    fields.extend(size(pix_map.bounds.width().max(0) as usize, pix_map.bounds.height().max(0) as usize));
+                    fields.push(field("Pixel size", int(pix_map.pixelSize)));
+                }
+                fields
+            },
+            Decoded::Picture(pict) => vec![
+                field("Version", int(pict.version())),
+                field("Frame", Value::Rect(pict.frame))
+            ],
+            Decoded::Sound(snd) => {
+                let mut fields = vec![
+                    field("Format", int(snd.format)),
+                    field("Synthesizers", list(&snd.synths, |synth| vec![
+                        field("Synth ID", int(synth.synth_id)),
+                        field("Init option", hex(synth.init_option as u32, 4))
+                    ])),
+                    field("Commands", list(&snd.commands, |cmd| vec![
+                        field("Command", int(cmd.command())),
+                        field("Param 1", int(cmd.param1)),
+                        field("Param 2", int(cmd.param2))
+                    ]))
+                ];
+                if let Some(header) = &snd.header {
+                    fields.extend(vec![
+                        field("Channels", int(header.channels)),
+                        field("Sample rate", string(&format!("{:.2}", header.sample_rate_hz()))),
+                        field("Sample size", int(header.sample_size)),
+                        field("Sample format", Value::Type(header.format.clone())),
+                        field("Frames", int(header.frames)),
+                        field("Loop start", int(header.loop_start)),
+                        field("Loop end", int(header.loop_end)),
+                        field("Base note", int(header.base_note))
+                    ]);
+                }
+                fields
+            },
+            Decoded::Font(font) => font_fields(&font.0),
+            Decoded::NewFont(font) => font_fields(&font.0),
+            Decoded::FontFamily(fond) => {
+                let mut fields = vec![
+                    field("Flags", hex(fond.flags, 2)),
+                    field("Family ID", int(fond.family_id)),
+                    field("First char", int(fond.first_char)),
+                    field("Last char", int(fond.last_char)),
+                    field("Version", int(fond.version)),
+                    field("Fonts", list(&fond.associations, |assoc| vec![
+                        field("Size", int(assoc.size)),
+                        field("Style", hex(assoc.style as u16, 2)),
+                        field("Font ID", int(assoc.font_id))
+                    ])),
+                    field("Kerning tables", list(&fond.kerning, |table| vec![
+                        field("Style", hex(table.style as u16, 2)),
+                        field("Pairs", int(table.pairs.len() as i64))
+                    ]))
+                ];
+                if let Some(mapping) = &fond.style_mapping {
+                    fields.push(field("Style names", list(&mapping.names, |name| vec![field("Name", text(name))])));
+                }
+                fields
+            }
+        }
+    }
+}
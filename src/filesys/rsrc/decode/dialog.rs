@@ -0,0 +1,220 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::Result;
+use crate::types::{
+    OSType,
+    PString,
+    Rect
+};
+
+use super::{
+    TypedRsrc,
+    read_boolean,
+    read_optional_u16,
+    skip_alignment
+};
+
+// Window positioning is only present from System 7, and is preceded by
+// padding to word alignment
+fn read_positioning(rdr: &mut SerialReadStorage) -> Result<Option<u16>> {
+    skip_alignment(rdr)?;
+    read_optional_u16(rdr)
+}
+
+// 'WIND'
+#[derive(Debug)]
+pub struct WindowTemplate {
+    pub bounds: Rect,
+    pub proc_id: i16,  // Window definition ID
+    pub visible: bool,
+    pub go_away: bool,
+    pub ref_con: u32,
+    pub title: PString,
+    pub positioning: Option<u16>
+}
+
+impl SerialRead for WindowTemplate {
+    fn read(rdr: &mut SerialReadStorage) -> Result<WindowTemplate> {
+        Ok(WindowTemplate {
+            bounds: Rect::read(rdr)?,
+            proc_id: rdr.read_i16()?,
+            visible: read_boolean(rdr)?,
+            go_away: read_boolean(rdr)?,
+            ref_con: rdr.read_u32()?,
+            title: PString::read(rdr)?,
+            positioning: read_positioning(rdr)?
+        })
+    }
+}
+
+impl TypedRsrc for WindowTemplate {
+    const RSRC_TYPE: OSType = OSType(*b"WIND");
+}
+
+// 'DLOG'
+#[derive(Debug)]
+pub struct DialogTemplate {
+    pub bounds: Rect,
+    pub proc_id: i16,
+    pub visible: bool,
+    pub go_away: bool,
+    pub ref_con: u32,
+    pub items_id: i16,  // Resource ID of the 'DITL'
+    pub title: PString,
+    pub positioning: Option<u16>
+}
+
+impl SerialRead for DialogTemplate {
+    fn read(rdr: &mut SerialReadStorage) -> Result<DialogTemplate> {
+        Ok(DialogTemplate {
+            bounds: Rect::read(rdr)?,
+            proc_id: rdr.read_i16()?,
+            visible: read_boolean(rdr)?,
+            go_away: read_boolean(rdr)?,
+            ref_con: rdr.read_u32()?,
+            items_id: rdr.read_i16()?,
+            title: PString::read(rdr)?,
+            positioning: read_positioning(rdr)?
+        })
+    }
+}
+
+impl TypedRsrc for DialogTemplate {
+    const RSRC_TYPE: OSType = OSType(*b"DLOG");
+}
+
+// 'ALRT'
+#[derive(Debug)]
+pub struct AlertTemplate {
+    pub bounds: Rect,
+    pub items_id: i16,
+    pub stages: u16,
+    pub positioning: Option<u16>
+}
+
+impl AlertTemplate {
+    // Stages are numbered 1 to 4, each stored in a nibble starting from the
+    // low nibble: bold outlined item (1 or 2), visible, and number of beeps
+    pub fn stage(&self, stage: usize) -> Option<u8> {
+        if !(1..=4).contains(&stage) {
+            return None;
+        }
+        Some(((self.stages >> ((stage - 1) * 4)) & 0x0f) as u8)
+    }
+}
+
+impl SerialRead for AlertTemplate {
+    fn read(rdr: &mut SerialReadStorage) -> Result<AlertTemplate> {
+        Ok(AlertTemplate {
+            bounds: Rect::read(rdr)?,
+            items_id: rdr.read_i16()?,
+            stages: rdr.read_u16()?,
+            positioning: read_optional_u16(rdr)?
+        })
+    }
+}
+
+impl TypedRsrc for AlertTemplate {
+    const RSRC_TYPE: OSType = OSType(*b"ALRT");
+}
+
+#[derive(Debug)]
+pub enum DialogItemKind {
+    User,
+    Button(PString),
+    CheckBox(PString),
+    RadioButton(PString),
+    Control(i16),  // Resource ID of the 'CNTL'
+    StaticText(PString),
+    EditText(PString),
+    Icon(i16),
+    Picture(i16),
+    Other { item_type: u8, data: Vec<u8> }
+}
+
+#[derive(Debug)]
+pub struct DialogItem {
+    pub bounds: Rect,
+    pub enabled: bool,
+    pub kind: DialogItemKind
+}
+
+impl SerialRead for DialogItem {
+    fn read(rdr: &mut SerialReadStorage) -> Result<DialogItem> {
+        rdr.pad(4)?; // Placeholder for handle or procedure
+        let bounds = Rect::read(rdr)?;
+        let item_type = rdr.read_u8()?;
+        let len = rdr.read_u8()?;
+        let data_offset = rdr.pos();
+
+        let text = |rdr: &mut SerialReadStorage| -> Result<PString> {
+            Ok(PString::from(rdr.read_bytes(len as u64)?.as_slice()))
+        };
+        let rsrc_id = |rdr: &mut SerialReadStorage| rdr.read_i16();
+
+        // The high bit disables the item
+        let kind = match item_type & 0x7f {
+            0 => DialogItemKind::User,
+            4 => DialogItemKind::Button(text(rdr)?),
+            5 => DialogItemKind::CheckBox(text(rdr)?),
+            6 => DialogItemKind::RadioButton(text(rdr)?),
+            7 => DialogItemKind::Control(rsrc_id(rdr)?),
+            8 => DialogItemKind::StaticText(text(rdr)?),
+            16 => DialogItemKind::EditText(text(rdr)?),
+            32 => DialogItemKind::Icon(rsrc_id(rdr)?),
+            64 => DialogItemKind::Picture(rsrc_id(rdr)?),
+            _ => DialogItemKind::Other { item_type: item_type & 0x7f, data: rdr.read_bytes(len as u64)? }
+        };
+        rdr.seek(data_offset + len as u64)?;
+        skip_alignment(rdr)?;
+
+        Ok(DialogItem {
+            bounds,
+            enabled: item_type & 0x80 == 0,
+            kind
+        })
+    }
+}
+
+// 'DITL'
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct DialogItemList {
+    #[count_prefix_minus_one(i16)]
+    pub items: Vec<DialogItem>
+}
+
+impl TypedRsrc for DialogItemList {
+    const RSRC_TYPE: OSType = OSType(*b"DITL");
+}
+
+// 'CNTL'
+#[derive(Debug)]
+pub struct ControlTemplate {
+    pub bounds: Rect,
+    pub value: i16,
+    pub visible: bool,
+    pub max: i16,
+    pub min: i16,
+    pub proc_id: i16,  // Control definition ID
+    pub ref_con: u32,
+    pub title: PString
+}
+
+impl SerialRead for ControlTemplate {
+    fn read(rdr: &mut SerialReadStorage) -> Result<ControlTemplate> {
+        Ok(ControlTemplate {
+            bounds: Rect::read(rdr)?,
+            value: rdr.read_i16()?,
+            visible: read_boolean(rdr)?,
+            max: rdr.read_i16()?,
+            min: rdr.read_i16()?,
+            proc_id: rdr.read_i16()?,
+            ref_con: rdr.read_u32()?,
+            title: PString::read(rdr)?
+        })
+    }
+}
+
+impl TypedRsrc for ControlTemplate {
+    const RSRC_TYPE: OSType = OSType(*b"CNTL");
+}
@@ -0,0 +1,195 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::Result;
+use crate::types::{
+    OSType,
+    PString
+};
+
+use super::TypedRsrc;
+
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub enum VersionStage {
+    Development,
+    Alpha,
+    Beta,
+    Release,
+    Unknown(u8)
+}
+
+impl From<u8> for VersionStage {
+    fn from(stage: u8) -> VersionStage {
+        match stage {
+            0x20 => VersionStage::Development,
+            0x40 => VersionStage::Alpha,
+            0x60 => VersionStage::Beta,
+            0x80 => VersionStage::Release,
+            stage => VersionStage::Unknown(stage)
+        }
+    }
+}
+
+// 'vers', version numbers are stored in BCD
+#[derive(Debug)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+    pub bug_fix: u8,
+    pub stage: VersionStage,
+    pub non_release: u8,
+    pub region: i16,
+    pub short_version: PString,
+    pub long_version: PString
+}
+
+fn from_bcd(val: u8) -> u8 {
+    (val >> 4) * 10 + (val & 0x0f)
+}
+
+impl Version {
+    // Formatted the way the Finder shows it, such as 7.0.1b3
+    pub fn number(&self) -> String {
+        let mut s = format!("{}.{}", self.major, self.minor);
+        if self.bug_fix != 0 {
+            s.push_str(&format!(".{}", self.bug_fix));
+        }
+        let stage = match self.stage {
+            VersionStage::Development => Some("d"),
+            VersionStage::Alpha => Some("a"),
+            VersionStage::Beta => Some("b"),
+            VersionStage::Release | VersionStage::Unknown(_) => None
+        };
+        if let Some(stage) = stage {
+            s.push_str(&format!("{}{}", stage, self.non_release));
+        }
+        s
+    }
+}
+
+impl SerialRead for Version {
+    fn read(rdr: &mut SerialReadStorage) -> Result<Version> {
+        let major = from_bcd(rdr.read_u8()?);
+        let minor_and_bug_fix = rdr.read_u8()?;
+        let stage = VersionStage::from(rdr.read_u8()?);
+        let non_release = from_bcd(rdr.read_u8()?);
+        Ok(Version {
+            major,
+            minor: minor_and_bug_fix >> 4,
+            bug_fix: minor_and_bug_fix & 0x0f,
+            stage,
+            non_release,
+            region: rdr.read_i16()?,
+            short_version: PString::read(rdr)?,
+            long_version: PString::read(rdr)?
+        })
+    }
+}
+
+impl TypedRsrc for Version {
+    const RSRC_TYPE: OSType = OSType(*b"vers");
+}
+
+// Local ID in the bundle, mapped to the actual resource ID
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct BundleMapping {
+    pub local_id: i16,
+    pub rsrc_id: i16
+}
+
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct BundleType {
+    pub rsrc_type: OSType,
+    #[count_prefix_minus_one(i16)]
+    pub mappings: Vec<BundleMapping>
+}
+
+// 'BNDL', tells the Finder which icons and file references belongs to an
+// application
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct Bundle {
+    pub signature: OSType,
+    pub signature_id: i16,
+    #[count_prefix_minus_one(i16)]
+    pub types: Vec<BundleType>
+}
+
+impl Bundle {
+    // Resource ID of a local ID, as used by 'FREF' icon references
+    pub fn rsrc_id(&self, rsrc_type: &OSType, local_id: i16) -> Option<i16> {
+        self.types.iter()
+            .filter(|t| &t.rsrc_type == rsrc_type)
+            .flat_map(|t| t.mappings.iter())
+            .find(|m| m.local_id == local_id)
+            .map(|m| m.rsrc_id)
+    }
+}
+
+impl TypedRsrc for Bundle {
+    const RSRC_TYPE: OSType = OSType(*b"BNDL");
+}
+
+// 'FREF', a file type handled by the application and its icon
+#[derive(Debug)]
+pub struct FileReference {
+    pub file_type: OSType,
+    pub local_id: i16,
+    // Not present in all resources
+    pub file_name: Option<PString>
+}
+
+impl SerialRead for FileReference {
+    fn read(rdr: &mut SerialReadStorage) -> Result<FileReference> {
+        let file_type = OSType::read(rdr)?;
+        let local_id = rdr.read_i16()?;
+        let file_name = if rdr.pos() < rdr.size() {
+            Some(PString::read(rdr)?)
+        } else {
+            None
+        };
+        Ok(FileReference {
+            file_type,
+            local_id,
+            file_name
+        })
+    }
+}
+
+impl TypedRsrc for FileReference {
+    const RSRC_TYPE: OSType = OSType(*b"FREF");
+}
+
+// 'SIZE', MultiFinder partition sizes and capabilities of the application
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct SizeInfo {
+    pub flags: u16,
+    pub preferred_size: u32,
+    pub minimum_size: u32
+}
+
+impl SizeInfo {
+    pub const ACCEPT_SUSPEND_RESUME_EVENTS: u16 = 0x4000;
+    pub const CAN_BACKGROUND: u16 = 0x1000;
+    pub const DOES_ACTIVATE_ON_FG_SWITCH: u16 = 0x0800;
+    pub const ONLY_BACKGROUND: u16 = 0x0400;
+    pub const GET_FRONT_CLICKS: u16 = 0x0200;
+    pub const ACCEPT_APP_DIED_EVENTS: u16 = 0x0100;
+    pub const IS_32_BIT_COMPATIBLE: u16 = 0x0080;
+    pub const IS_HIGH_LEVEL_EVENT_AWARE: u16 = 0x0040;
+    pub const LOCAL_AND_REMOTE_HL_EVENTS: u16 = 0x0020;
+    pub const IS_STATIONERY_AWARE: u16 = 0x0010;
+    pub const USE_TEXT_EDIT_SERVICES: u16 = 0x0008;
+
+    pub fn contains(&self, flags: u16) -> bool {
+        self.flags & flags == flags
+    }
+}
+
+impl TypedRsrc for SizeInfo {
+    const RSRC_TYPE: OSType = OSType(*b"SIZE");
+}
@@ -0,0 +1,533 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::{Error, Result};
+use crate::tools::BitImage;
+use crate::types::{
+    OSType,
+    PString
+};
+
+use super::TypedRsrc;
+
+// Font type flags
+pub const FONT_HAS_HEIGHT_TABLE: u16 = 0x0001;
+pub const FONT_HAS_WIDTH_TABLE: u16 = 0x0002;
+pub const FONT_FIXED_WIDTH: u16 = 0x2000;
+
+// Marks a character missing from the font in the offset/width table
+const MISSING_CHAR: u16 = 0xffff;
+
+#[derive(SerialRead)]
+#[derive(Clone)]
+#[derive(Debug)]
+#[allow(non_snake_case)] // This struct comes from old Mac structs
+pub struct FontRec {
+    pub fontType: u16,    // INTEGER;     {font type}
+    pub firstChar: i16,   // INTEGER;     {ASCII code of first character}
+    pub lastChar: i16,    // INTEGER;     {ASCII code of last character}
+    pub widMax: i16,      // INTEGER;     {maximum character width}
+    pub kernMax: i16,     // INTEGER;     {negative of maximum character kern}
+    pub nDescent: i16,    // INTEGER;     {negative of descent, or high word of owTLoc}
+    pub fRectWidth: i16,  // INTEGER;     {width of font rectangle}
+    pub fRectHeight: i16, // INTEGER;     {height of font rectangle}
+    pub owTLoc: u16,      // INTEGER;     {offset to offset/width table}
+    pub ascent: i16,      // INTEGER;     {ascent}
+    pub descent: i16,     // INTEGER;     {descent}
+    pub leading: i16,     // INTEGER;     {leading}
+    pub rowWords: i16     // INTEGER;     {row width of bit image / 2}
+}
+
+// Bitmapped font strike, as stored in 'FONT' and 'NFNT' resources. All
+// characters are stored side by side in a single bit image, with the
+// location table giving the first column of each character.
+pub struct BitmapFont {
+    pub record: FontRec,
+    bit_image: Vec<u8>,
+    locations: Vec<u16>,
+    offset_widths: Vec<u16>,
+    pub widths: Option<Vec<u16>>,  // 8.8 fixed point
+    pub heights: Option<Vec<u16>>  // first row in high byte, rows in low byte
+}
+
+// Character to draw, after falling back to the missing character glyph
+struct Glyph {
+    offset: i32,
+    advance: i32,
+    first_col: usize,
+    last_col: usize
+}
+
+impl BitmapFont {
+    // Bits per pixel in the bit image, 1 except for color fonts
+    pub fn depth(&self) -> usize {
+        1 << ((self.record.fontType >> 2) & 3)
+    }
+
+    fn glyph(&self, ch: u8) -> Option<Glyph> {
+        let first = self.record.firstChar as usize;
+        let last = self.record.lastChar as usize;
+        let missing = last - first + 1;
+        let mut idx = if (first..=last).contains(&(ch as usize)) { ch as usize - first } else { missing };
+        if self.offset_widths[idx] == MISSING_CHAR {
+            idx = missing;
+        }
+        let offset_width = self.offset_widths[idx];
+        if offset_width == MISSING_CHAR {
+            return None;
+        }
+        let first_col = self.locations[idx] as usize;
+        let last_col = (self.locations[idx + 1] as usize).max(first_col);
+        Some(Glyph {
+            offset: self.record.kernMax as i32 + (offset_width >> 8) as i32,
+            advance: (offset_width & 0xff) as i32,
+            first_col,
+            last_col
+        })
+    }
+
+    fn pixel(&self, col: usize, row: usize) -> bool {
+        let depth = self.depth();
+        let bit = col * depth;
+        let byte = row * self.record.rowWords as usize * 2 + bit / 8;
+        let mask = (0xffu16 << (8 - depth)) as u8 >> (bit % 8);
+        self.bit_image.get(byte).is_some_and(|b| b & mask != 0)
+    }
+
+    // Advance width of a character, as used by CharWidth
+    pub fn char_width(&self, ch: u8) -> i32 {
+        self.glyph(ch).map_or(0, |glyph| glyph.advance)
+    }
+
+    pub fn string_width(&self, text: &[u8]) -> i32 {
+        text.iter().map(|ch| self.char_width(*ch)).sum()
+    }
+
+    // Draws Mac Roman text with the baseline at v, starting at h. Returns
+    // the position after the last character, like the pen after DrawString.
+    pub fn draw(&self, image: &mut BitImage, h: i32, v: i32, text: &[u8]) -> i32 {
+        let top = v - self.record.ascent as i32;
+        let mut pen = h;
+        for ch in text {
+            if let Some(glyph) = self.glyph(*ch) {
+                let left = pen + glyph.offset;
+                for row in 0..self.record.fRectHeight.max(0) as usize {
+                    for col in glyph.first_col..glyph.last_col {
+                        let x = left + (col - glyph.first_col) as i32;
+                        let y = top + row as i32;
+                        if x >= 0 && y >= 0 && self.pixel(col, row) {
+                            image.set(x as usize, y as usize, true);
+                        }
+                    }
+                }
+                pen += glyph.advance;
+            }
+        }
+        pen
+    }
+
+    // Text drawn into an image just large enough to hold it, with the
+    // baseline at the ascent
+    pub fn draw_string(&self, text: &[u8]) -> BitImage {
+        let mut pen = 0;
+        let (mut left, mut right) = (0, 0);
+        for ch in text {
+            if let Some(glyph) = self.glyph(*ch) {
+                left = left.min(pen + glyph.offset);
+                right = right.max(pen + glyph.offset + (glyph.last_col - glyph.first_col) as i32);
+                pen += glyph.advance;
+                right = right.max(pen);
+            }
+        }
+        let height = self.record.ascent as i32 + self.record.descent as i32;
+        let mut image = BitImage::new((right - left) as usize, height.max(0) as usize);
+        self.draw(&mut image, -left, self.record.ascent as i32, text);
+        image
+    }
+}
+
+fn read_table(rdr: &mut SerialReadStorage, count: usize) -> Result<Vec<u16>> {
+    let mut table = Vec::with_capacity(count);
+    for _ in 0..count {
+        table.push(rdr.read_u16()?);
+    }
+    Ok(table)
+}
+
+impl SerialRead for BitmapFont {
+    fn read(rdr: &mut SerialReadStorage) -> Result<BitmapFont> {
+        let start = rdr.pos();
+        let record = FontRec::read(rdr)?;
+        if record.firstChar < 0 || record.lastChar > 255 || record.lastChar < record.firstChar {
+            let range = format!("{}..{}", record.firstChar, record.lastChar);
+            return Err(Error::invalid_value(start + 4, "FontRec", "lastChar", "character range within 0..255", range));
+        }
+        if record.rowWords < 0 || record.fRectHeight < 0 {
+            return Err(Error::invalid_value(start + 24, "FontRec", "rowWords", "positive size", record.rowWords.to_string()));
+        }
+        let count = (record.lastChar - record.firstChar + 3) as usize;
+        let bit_image = rdr.read_bytes(record.rowWords as u64 * 2 * record.fRectHeight as u64)?;
+        let locations = read_table(rdr, count)?;
+
+        // Large fonts stores the high word of owTLoc in nDescent
+        let ow_loc = ((record.nDescent.max(0) as u64) << 16) | record.owTLoc as u64;
+        rdr.seek(start + 16 + ow_loc * 2)?;
+        let offset_widths = read_table(rdr, count)?;
+        let widths = match record.fontType & FONT_HAS_WIDTH_TABLE {
+            0 => None,
+            _ => Some(read_table(rdr, count)?)
+        };
+        let heights = match record.fontType & FONT_HAS_HEIGHT_TABLE {
+            0 => None,
+            _ => Some(read_table(rdr, count)?)
+        };
+        Ok(BitmapFont {
+            record,
+            bit_image,
+            locations,
+            offset_widths,
+            widths,
+            heights
+        })
+    }
+}
+
+impl std::fmt::Debug for BitmapFont {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BitmapFont")
+            .field("record", &self.record)
+            .field("bit_image", &format_args!("{} bytes", self.bit_image.len()))
+            .field("widths", &self.widths.is_some())
+            .field("heights", &self.heights.is_some())
+            .finish()
+    }
+}
+
+// 'FONT', the original font resource. The ID is the family number times 128
+// plus the point size.
+#[derive(Debug)]
+pub struct Font(pub BitmapFont);
+
+impl SerialRead for Font {
+    fn read(rdr: &mut SerialReadStorage) -> Result<Font> {
+        Ok(Font(BitmapFont::read(rdr)?))
+    }
+}
+
+impl TypedRsrc for Font {
+    const RSRC_TYPE: OSType = OSType(*b"FONT");
+}
+
+impl std::ops::Deref for Font {
+    type Target = BitmapFont;
+
+    fn deref(&self) -> &BitmapFont {
+        &self.0
+    }
+}
+
+// 'NFNT', same layout as 'FONT', referenced by a font family
+#[derive(Debug)]
+pub struct NewFont(pub BitmapFont);
+
+impl SerialRead for NewFont {
+    fn read(rdr: &mut SerialReadStorage) -> Result<NewFont> {
+        Ok(NewFont(BitmapFont::read(rdr)?))
+    }
+}
+
+impl TypedRsrc for NewFont {
+    const RSRC_TYPE: OSType = OSType(*b"NFNT");
+}
+
+impl std::ops::Deref for NewFont {
+    type Target = BitmapFont;
+
+    fn deref(&self) -> &BitmapFont {
+        &self.0
+    }
+}
+
+// Font of a given size and style in a family. Size 0 is an outline font.
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct FontAssociation {
+    pub size: i16,
+    pub style: i16,
+    pub font_id: i16
+}
+
+// Widths in 4.12 fixed point, for each character of the family and the
+// missing character
+#[derive(Debug)]
+pub struct StyleWidths {
+    pub style: i16,
+    pub widths: Vec<i16>
+}
+
+// Kerning distance in 4.12 fixed point, relative to the point size
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct KerningPair {
+    pub first: u8,
+    pub second: u8,
+    pub distance: i16
+}
+
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct KerningTable {
+    pub style: i16,
+    #[count_prefix(i16)]
+    pub pairs: Vec<KerningPair>
+}
+
+// Names of the styled fonts of the family, used by printer drivers to find
+// the matching font. Each style indexes a string listing the suffixes to
+// add to the base name, which is the first string.
+#[derive(Debug)]
+pub struct StyleMapping {
+    pub font_class: i16,
+    pub indexes: Vec<u8>,
+    pub names: Vec<PString>
+}
+
+impl StyleMapping {
+    pub fn name(&self, style: u8) -> Option<PString> {
+        let base = self.names.first()?;
+        let idx = *self.indexes.get(style as usize)? as usize;
+        let mut name = Vec::from(base.as_bytes());
+        if idx > 1 {
+            for suffix in self.names.get(idx - 1)?.as_bytes() {
+                name.extend_from_slice(self.names.get((*suffix as usize).checked_sub(1)?)?.as_bytes());
+            }
+        }
+        Some(PString::from(&name[..]))
+    }
+}
+
+impl SerialRead for StyleMapping {
+    fn read(rdr: &mut SerialReadStorage) -> Result<StyleMapping> {
+        let font_class = rdr.read_i16()?;
+        rdr.pad(8)?; // offset to glyph encoding table, reserved
+        let indexes = rdr.read_bytes(48)?;
+        let count = rdr.read_i16()?;
+        let mut names = vec![];
+        for _ in 0..count {
+            names.push(PString::read(rdr)?);
+        }
+        Ok(StyleMapping {
+            font_class,
+            indexes,
+            names
+        })
+    }
+}
+
+// 'FOND', a font family with the fonts available in each size and style,
+// and the metrics shared by the fonts
+#[derive(Debug)]
+pub struct FontFamily {
+    pub flags: u16,
+    pub family_id: i16,
+    pub first_char: i16,
+    pub last_char: i16,
+    pub ascent: i16,      // 4.12 fixed point
+    pub descent: i16,     // 4.12 fixed point
+    pub leading: i16,     // 4.12 fixed point
+    pub wid_max: i16,     // 4.12 fixed point
+    pub properties: Vec<i16>,
+    pub version: i16,
+    pub associations: Vec<FontAssociation>,
+    pub widths: Vec<StyleWidths>,
+    pub kerning: Vec<KerningTable>,
+    pub style_mapping: Option<StyleMapping>
+}
+
+impl FontFamily {
+    // Resource ID of the font of the given size and style
+    pub fn font_id(&self, size: i16, style: i16) -> Option<i16> {
+        self.associations.iter()
+            .find(|assoc| assoc.size == size && assoc.style == style)
+            .map(|assoc| assoc.font_id)
+    }
+
+    // Kerning of a character pair, in 4.12 fixed point
+    pub fn kerning(&self, style: i16, first: u8, second: u8) -> i16 {
+        self.kerning.iter()
+            .filter(|table| table.style == style)
+            .flat_map(|table| table.pairs.iter())
+            .find(|pair| pair.first == first && pair.second == second)
+            .map_or(0, |pair| pair.distance)
+    }
+}
+
+// Tables are located by offsets from the start of the resource, where 0
+// means the table is missing
+fn seek_table(rdr: &mut SerialReadStorage, start: u64, offset: i32) -> Result<bool> {
+    if offset <= 0 {
+        return Ok(false);
+    }
+    rdr.seek(start + offset as u64)?;
+    Ok(true)
+}
+
+impl SerialRead for FontFamily {
+    fn read(rdr: &mut SerialReadStorage) -> Result<FontFamily> {
+        let start = rdr.pos();
+        let flags = rdr.read_u16()?;
+        let family_id = rdr.read_i16()?;
+        let first_char = rdr.read_i16()?;
+        let last_char = rdr.read_i16()?;
+        let ascent = rdr.read_i16()?;
+        let descent = rdr.read_i16()?;
+        let leading = rdr.read_i16()?;
+        let wid_max = rdr.read_i16()?;
+        let width_offset = rdr.read_i32()?;
+        let kerning_offset = rdr.read_i32()?;
+        let style_offset = rdr.read_i32()?;
+        let mut properties = vec![];
+        for _ in 0..9 {
+            properties.push(rdr.read_i16()?);
+        }
+        rdr.pad(4)?; // ffIntl, reserved
+        let version = rdr.read_i16()?;
+        let count = rdr.read_i16()? as i32 + 1;
+        let mut associations = vec![];
+        for _ in 0..count {
+            associations.push(FontAssociation::read(rdr)?);
+        }
+
+        let mut widths = vec![];
+        if seek_table(rdr, start, width_offset)? {
+            let count = rdr.read_i16()? as i32 + 1;
+            let chars = (last_char as i32 - first_char as i32 + 3).max(0);
+            for _ in 0..count {
+                let style = rdr.read_i16()?;
+                let mut style_widths = vec![];
+                for _ in 0..chars {
+                    style_widths.push(rdr.read_i16()?);
+                }
+                widths.push(StyleWidths { style, widths: style_widths });
+            }
+        }
+
+        let mut kerning = vec![];
+        if seek_table(rdr, start, kerning_offset)? {
+            let count = rdr.read_i16()? as i32 + 1;
+            for _ in 0..count {
+                kerning.push(KerningTable::read(rdr)?);
+            }
+        }
+
+        let style_mapping = match seek_table(rdr, start, style_offset)? {
+            true => Some(StyleMapping::read(rdr)?),
+            false => None
+        };
+
+        Ok(FontFamily {
+            flags,
+            family_id,
+            first_char,
+            last_char,
+            ascent,
+            descent,
+            leading,
+            wid_max,
+            properties,
+            version,
+            associations,
+            widths,
+            kerning,
+            style_mapping
+        })
+    }
+}
+
+impl TypedRsrc for FontFamily {
+    const RSRC_TYPE: OSType = OSType(*b"FOND");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two characters 'A' and 'B' of 3 and 2 pixels in a 3 row strike, and a
+    // 1 pixel missing character
+    fn font() -> Vec<u8> {
+        let mut data = vec![
+            0x90, 0x00, 0x00, 0x41, 0x00, 0x42, 0x00, 0x04, 0xff, 0xff, 0xff, 0xfe,
+            0x00, 0x04, 0x00, 0x03, 0x00, 0x0c, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x01
+        ];
+        data.extend_from_slice(&[0b0101_1100, 0x00]);
+        data.extend_from_slice(&[0b1111_0100, 0x00]);
+        data.extend_from_slice(&[0b1011_1100, 0x00]);
+        data.extend_from_slice(&[0, 0, 0, 3, 0, 5, 0, 6]);
+        data.extend_from_slice(&[0x01, 0x04, 0x01, 0x03, 0x01, 0x02, 0xff, 0xff]);
+        data
+    }
+
+    #[test]
+    fn strike() {
+        let font = Font::read(&mut SerialReadStorage::from(font())).unwrap();
+        assert_eq!(font.depth(), 1);
+        assert_eq!(font.char_width(b'A'), 4);
+        assert_eq!(font.char_width(b'Z'), 2);
+        assert_eq!(font.string_width(b"AB"), 7);
+
+        let image = font.draw_string(b"AB");
+        assert_eq!((image.width(), image.height()), (7, 3));
+        let rows: Vec<String> = (0..3).map(|y| {
+            (0..7).map(|x| if image.get(x, y) { '#' } else { '.' }).collect()
+        }).collect();
+        assert_eq!(rows, vec![".#..##.", "###.#..", "#.#.##."]);
+
+        // Missing characters use the last glyph
+        let mut image = BitImage::new(4, 3);
+        assert_eq!(font.draw(&mut image, 0, 2, b"?"), 2);
+        assert!(image.get(0, 0) && image.get(0, 2));
+    }
+
+    #[test]
+    fn invalid_strike() {
+        let mut data = font();
+        data[5] = 0x43;
+        data[3] = 0x44;
+        assert!(Font::read(&mut SerialReadStorage::from(data)).is_err());
+    }
+
+    #[test]
+    fn family() {
+        let mut data = vec![
+            0x00, 0x00, 0x00, 0x15, 0x00, 0x20, 0x00, 0xd8, 0x0c, 0x00, 0x03, 0x00,
+            0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x42,
+            0x00, 0x00, 0x00, 0x4c
+        ];
+        data.extend_from_slice(&[0; 22]);
+        data.extend_from_slice(&[0x00, 0x01]);
+        data.extend_from_slice(&[0x00, 0x01, 0x00, 0x0c, 0x00, 0x00, 0x0a, 0x8c, 0x00, 0x0c, 0x00, 0x01, 0x0a, 0x8d]);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x41, 0x56, 0xfe, 0x00]);
+        data.extend_from_slice(&[0x00, 0x01]);
+        data.extend_from_slice(&[0; 8]);
+        let mut indexes = [0u8; 48];
+        indexes[0] = 1;
+        indexes[1] = 2;
+        data.extend_from_slice(&indexes);
+        data.extend_from_slice(&[0x00, 0x03, 0x06]);
+        data.extend_from_slice(b"Geneva");
+        data.extend_from_slice(&[0x01, 0x03, 0x04]);
+        data.extend_from_slice(b"Bold");
+
+        let family = FontFamily::read(&mut SerialReadStorage::from(data)).unwrap();
+        assert_eq!(family.family_id, 21);
+        assert_eq!(family.font_id(12, 1), Some(2701));
+        assert_eq!(family.font_id(9, 0), None);
+        assert_eq!(family.kerning(0, b'A', b'V'), -512);
+        assert_eq!(family.kerning(1, b'A', b'V'), 0);
+        assert!(family.widths.is_empty());
+        let mapping = family.style_mapping.as_ref().unwrap();
+        assert_eq!(mapping.font_class, 1);
+        assert_eq!(mapping.name(0).unwrap().as_bytes(), b"Geneva");
+        assert_eq!(mapping.name(1).unwrap().as_bytes(), b"GenevaBold");
+        assert_eq!(mapping.name(2).unwrap().as_bytes(), b"Geneva");
+    }
+}
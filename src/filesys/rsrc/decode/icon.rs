@@ -0,0 +1,589 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::{Error, Result};
+use crate::tools::Image;
+use crate::types::{
+    BitMap,
+    ColorTable,
+    OSType,
+    Pattern,
+    PixMap,
+    Rect,
+    RGBColor
+};
+
+use super::TypedRsrc;
+use super::super::Rsrc;
+
+const LARGE: usize = 32;
+const SMALL: usize = 16;
+
+// Standard 16 color palette, used by 'icl4' and 'ics4'
+const CLUT_4: [RGBColor; 16] = [
+    RGBColor { red: 0xffff, green: 0xffff, blue: 0xffff }, // White
+    RGBColor { red: 0xfc00, green: 0xf37d, blue: 0x052f }, // Yellow
+    RGBColor { red: 0xffff, green: 0x648a, blue: 0x028c }, // Orange
+    RGBColor { red: 0xdd6b, green: 0x08c2, blue: 0x06a2 }, // Red
+    RGBColor { red: 0xf2d7, green: 0x0856, blue: 0x84ec }, // Magenta
+    RGBColor { red: 0x46e3, green: 0x0000, blue: 0xa53e }, // Purple
+    RGBColor { red: 0x0000, green: 0x0000, blue: 0xd400 }, // Blue
+    RGBColor { red: 0x0241, green: 0xab54, blue: 0xeaff }, // Cyan
+    RGBColor { red: 0x1f21, green: 0xb793, blue: 0x1431 }, // Green
+    RGBColor { red: 0x0000, green: 0x64af, blue: 0x11b0 }, // Dark green
+    RGBColor { red: 0x5600, green: 0x2c9d, blue: 0x0524 }, // Brown
+    RGBColor { red: 0x90d7, green: 0x7160, blue: 0x3a34 }, // Tan
+    RGBColor { red: 0xc000, green: 0xc000, blue: 0xc000 }, // Light gray
+    RGBColor { red: 0x8000, green: 0x8000, blue: 0x8000 }, // Medium gray
+    RGBColor { red: 0x4000, green: 0x4000, blue: 0x4000 }, // Dark gray
+    RGBColor { red: 0x0000, green: 0x0000, blue: 0x0000 }, // Black
+];
+
+// Levels of the red, green, blue and gray ramps in the 256 color palette
+const CLUT_8_RAMP: [u8; 10] = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+pub fn system_color_4(index: u8) -> RGBColor {
+    CLUT_4[(index & 0x0f) as usize]
+}
+
+// Standard 256 color palette, used by 'icl8' and 'ics8'. The first 215
+// entries is a 6x6x6 color cube from white, without black, followed by ramps
+// of red, green, blue and gray, and black last.
+pub fn system_color_8(index: u8) -> RGBColor {
+    let index = index as usize;
+    if index < 215 {
+        let level = |n: usize| (5 - n) as u8 * 0x33;
+        RGBColor::from_rgb8(level(index / 36), level(index / 6 % 6), level(index % 6))
+    } else if index < 255 {
+        let level = CLUT_8_RAMP[(index - 215) % 10];
+        match (index - 215) / 10 {
+            0 => RGBColor::from_rgb8(level, 0, 0),
+            1 => RGBColor::from_rgb8(0, level, 0),
+            2 => RGBColor::from_rgb8(0, 0, level),
+            _ => RGBColor::from_rgb8(level, level, level)
+        }
+    } else {
+        RGBColor::from_rgb8(0, 0, 0)
+    }
+}
+
+// Pixel value in packed pixel data, with the leftmost pixel in the most
+// significant bits. Pixels outside the data are 0.
+fn pixel(data: &[u8], row_bytes: usize, depth: usize, x: usize, y: usize) -> u8 {
+    let bit = x * depth;
+    let byte = data.get(y * row_bytes + bit / 8).cloned().unwrap_or(0);
+    let shift = 8 - depth - bit % 8;
+    (byte >> shift) & ((1u16 << depth) - 1) as u8
+}
+
+fn mono_image(data: &[u8], size: usize, mask: Option<&[u8]>) -> Image {
+    indexed_image(data, size, 1, |idx| if idx == 0 { CLUT_4[0] } else { CLUT_4[15] }, mask)
+}
+
+fn indexed_image(data: &[u8], size: usize, depth: usize, palette: impl Fn(u8) -> RGBColor, mask: Option<&[u8]>) -> Image {
+    let row_bytes = size * depth / 8;
+    let mut image = Image::new(size, size);
+    for y in 0..size {
+        for x in 0..size {
+            image.set_color(x, y, palette(pixel(data, row_bytes, depth, x, y)));
+        }
+    }
+    if let Some(mask) = mask {
+        image.apply_mask(|x, y| pixel(mask, size / 8, 1, x, y) != 0);
+    }
+    image
+}
+
+fn fmt_icon(f: &mut std::fmt::Formatter<'_>, name: &str, data: &[u8]) -> std::fmt::Result {
+    f.debug_struct(name)
+        .field("data", &format_args!("{} bytes", data.len()))
+        .finish()
+}
+
+// 'ICON', 32x32 black and white icon without mask
+pub struct Icon {
+    pub data: Vec<u8>
+}
+
+impl Icon {
+    pub fn to_image(&self) -> Image {
+        mono_image(&self.data, LARGE, None)
+    }
+}
+
+impl SerialRead for Icon {
+    fn read(rdr: &mut SerialReadStorage) -> Result<Icon> {
+        Ok(Icon { data: rdr.read_bytes((LARGE * LARGE / 8) as u64)? })
+    }
+}
+
+impl TypedRsrc for Icon {
+    const RSRC_TYPE: OSType = OSType(*b"ICON");
+}
+
+impl std::fmt::Debug for Icon {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_icon(f, "Icon", &self.data)
+    }
+}
+
+// 'ICN#', 32x32 black and white icon followed by its mask. The mask also
+// applies to the color icons with the same ID.
+pub struct IconList {
+    pub icon: Vec<u8>,
+    pub mask: Vec<u8>
+}
+
+impl IconList {
+    pub fn to_image(&self) -> Image {
+        mono_image(&self.icon, LARGE, Some(&self.mask))
+    }
+}
+
+impl SerialRead for IconList {
+    fn read(rdr: &mut SerialReadStorage) -> Result<IconList> {
+        Ok(IconList {
+            icon: rdr.read_bytes((LARGE * LARGE / 8) as u64)?,
+            mask: rdr.read_bytes((LARGE * LARGE / 8) as u64)?
+        })
+    }
+}
+
+impl TypedRsrc for IconList {
+    const RSRC_TYPE: OSType = OSType(*b"ICN#");
+}
+
+impl std::fmt::Debug for IconList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_icon(f, "IconList", &self.icon)
+    }
+}
+
+// 'ics#', 16x16 version of 'ICN#'
+pub struct SmallIconList {
+    pub icon: Vec<u8>,
+    pub mask: Vec<u8>
+}
+
+impl SmallIconList {
+    pub fn to_image(&self) -> Image {
+        mono_image(&self.icon, SMALL, Some(&self.mask))
+    }
+}
+
+impl SerialRead for SmallIconList {
+    fn read(rdr: &mut SerialReadStorage) -> Result<SmallIconList> {
+        Ok(SmallIconList {
+            icon: rdr.read_bytes((SMALL * SMALL / 8) as u64)?,
+            mask: rdr.read_bytes((SMALL * SMALL / 8) as u64)?
+        })
+    }
+}
+
+impl TypedRsrc for SmallIconList {
+    const RSRC_TYPE: OSType = OSType(*b"ics#");
+}
+
+impl std::fmt::Debug for SmallIconList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_icon(f, "SmallIconList", &self.icon)
+    }
+}
+
+// 'icl4', 32x32 icon in the 16 color palette
+pub struct LargeIcon4 {
+    pub data: Vec<u8>
+}
+
+impl LargeIcon4 {
+    pub fn to_image(&self, mask: Option<&IconList>) -> Image {
+        indexed_image(&self.data, LARGE, 4, system_color_4, mask.map(|m| m.mask.as_slice()))
+    }
+}
+
+impl SerialRead for LargeIcon4 {
+    fn read(rdr: &mut SerialReadStorage) -> Result<LargeIcon4> {
+        Ok(LargeIcon4 { data: rdr.read_bytes((LARGE * LARGE / 2) as u64)? })
+    }
+}
+
+impl TypedRsrc for LargeIcon4 {
+    const RSRC_TYPE: OSType = OSType(*b"icl4");
+}
+
+impl std::fmt::Debug for LargeIcon4 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_icon(f, "LargeIcon4", &self.data)
+    }
+}
+
+// 'ics4', 16x16 icon in the 16 color palette
+pub struct SmallIcon4 {
+    pub data: Vec<u8>
+}
+
+impl SmallIcon4 {
+    pub fn to_image(&self, mask: Option<&SmallIconList>) -> Image {
+        indexed_image(&self.data, SMALL, 4, system_color_4, mask.map(|m| m.mask.as_slice()))
+    }
+}
+
+impl SerialRead for SmallIcon4 {
+    fn read(rdr: &mut SerialReadStorage) -> Result<SmallIcon4> {
+        Ok(SmallIcon4 { data: rdr.read_bytes((SMALL * SMALL / 2) as u64)? })
+    }
+}
+
+impl TypedRsrc for SmallIcon4 {
+    const RSRC_TYPE: OSType = OSType(*b"ics4");
+}
+
+impl std::fmt::Debug for SmallIcon4 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_icon(f, "SmallIcon4", &self.data)
+    }
+}
+
+// 'icl8', 32x32 icon in the 256 color palette
+pub struct LargeIcon8 {
+    pub data: Vec<u8>
+}
+
+impl LargeIcon8 {
+    pub fn to_image(&self, mask: Option<&IconList>) -> Image {
+        indexed_image(&self.data, LARGE, 8, system_color_8, mask.map(|m| m.mask.as_slice()))
+    }
+}
+
+impl SerialRead for LargeIcon8 {
+    fn read(rdr: &mut SerialReadStorage) -> Result<LargeIcon8> {
+        Ok(LargeIcon8 { data: rdr.read_bytes((LARGE * LARGE) as u64)? })
+    }
+}
+
+impl TypedRsrc for LargeIcon8 {
+    const RSRC_TYPE: OSType = OSType(*b"icl8");
+}
+
+impl std::fmt::Debug for LargeIcon8 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_icon(f, "LargeIcon8", &self.data)
+    }
+}
+
+// 'ics8', 16x16 icon in the 256 color palette
+pub struct SmallIcon8 {
+    pub data: Vec<u8>
+}
+
+impl SmallIcon8 {
+    pub fn to_image(&self, mask: Option<&SmallIconList>) -> Image {
+        indexed_image(&self.data, SMALL, 8, system_color_8, mask.map(|m| m.mask.as_slice()))
+    }
+}
+
+impl SerialRead for SmallIcon8 {
+    fn read(rdr: &mut SerialReadStorage) -> Result<SmallIcon8> {
+        Ok(SmallIcon8 { data: rdr.read_bytes((SMALL * SMALL) as u64)? })
+    }
+}
+
+impl TypedRsrc for SmallIcon8 {
+    const RSRC_TYPE: OSType = OSType(*b"ics8");
+}
+
+impl std::fmt::Debug for SmallIcon8 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_icon(f, "SmallIcon8", &self.data)
+    }
+}
+
+// Dimensions of a bitmap, negative sizes are treated as empty
+fn bounds_size(row_bytes: usize, width: i32, height: i32) -> (usize, usize, u64) {
+    let width = width.max(0) as usize;
+    let height = height.max(0) as usize;
+    (width, height, (row_bytes * height) as u64)
+}
+
+// Rows must cover the width of the bounds, which also limits the size of the
+// images made from them
+fn check_row_bytes(offset: u64, structure: &'static str, row_bytes: usize, bounds: &Rect, depth: usize) -> Result<()> {
+    if row_bytes as u64 * 8 < bounds.width().max(0) as u64 * depth as u64 {
+        return Err(Error::invalid_value(offset, structure, "rowBytes", "row covering the width", row_bytes));
+    }
+    Ok(())
+}
+
+fn check_depth(offset: u64, structure: &'static str, pix_map: &PixMap) -> Result<usize> {
+    match pix_map.pixelSize {
+        1 | 2 | 4 | 8 => Ok(pix_map.pixelSize as usize),
+        size => Err(Error::invalid_value(offset, structure, "pixelSize", "1, 2, 4 or 8", size))
+    }
+}
+
+// Indexed pixel image described by a PixMap, colors not in the table are
+// drawn black
+fn pix_map_image(pix_map: &PixMap, color_table: &ColorTable, pixels: &[u8]) -> Image {
+    let (width, height, _) = bounds_size(pix_map.row_bytes(), pix_map.bounds.width(), pix_map.bounds.height());
+    let depth = pix_map.pixelSize as usize;
+    let mut image = Image::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let index = pixel(pixels, pix_map.row_bytes(), depth, x, y);
+            image.set_color(x, y, color_table.lookup(index).unwrap_or_default());
+        }
+    }
+    image
+}
+
+// 'cicn', color icon of any size with its own color table, mask and black
+// and white version
+pub struct ColorIcon {
+    pub pix_map: PixMap,
+    pub mask_map: BitMap,
+    pub bit_map: BitMap,
+    pub mask: Vec<u8>,
+    pub bits: Vec<u8>,
+    pub color_table: ColorTable,
+    pub pixels: Vec<u8>
+}
+
+impl ColorIcon {
+    pub fn to_image(&self) -> Image {
+        let mut image = pix_map_image(&self.pix_map, &self.color_table, &self.pixels);
+        let row_bytes = self.mask_map.rowBytes as usize;
+        image.apply_mask(|x, y| pixel(&self.mask, row_bytes, 1, x, y) != 0);
+        image
+    }
+
+    // The black and white version, used on 1 bit screens
+    pub fn bits_image(&self) -> Image {
+        let row_bytes = self.bit_map.rowBytes as usize;
+        let (width, height, _) = bounds_size(row_bytes, self.bit_map.bounds.width(), self.bit_map.bounds.height());
+        let mut image = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let set = pixel(&self.bits, row_bytes, 1, x, y) != 0;
+                image.set_color(x, y, if set { CLUT_4[15] } else { CLUT_4[0] });
+            }
+        }
+        let mask_row_bytes = self.mask_map.rowBytes as usize;
+        image.apply_mask(|x, y| pixel(&self.mask, mask_row_bytes, 1, x, y) != 0);
+        image
+    }
+}
+
+impl SerialRead for ColorIcon {
+    fn read(rdr: &mut SerialReadStorage) -> Result<ColorIcon> {
+        let offset = rdr.pos();
+        let pix_map = PixMap::read(rdr)?;
+        let depth = check_depth(offset, "ColorIcon", &pix_map)?;
+        check_row_bytes(offset + 4, "ColorIcon", pix_map.row_bytes(), &pix_map.bounds, depth)?;
+        let mask_offset = rdr.pos();
+        let mask_map = BitMap::read(rdr)?;
+        check_row_bytes(mask_offset + 4, "ColorIcon", mask_map.rowBytes as usize, &mask_map.bounds, 1)?;
+        let bits_offset = rdr.pos();
+        let bit_map = BitMap::read(rdr)?;
+        check_row_bytes(bits_offset + 4, "ColorIcon", bit_map.rowBytes as usize, &bit_map.bounds, 1)?;
+        rdr.pad(4)?; // Placeholder for the handle to the pixel data
+
+        let (_, _, mask_len) = bounds_size(mask_map.rowBytes as usize, mask_map.bounds.width(), mask_map.bounds.height());
+        let mask = rdr.read_bytes(mask_len)?;
+        let (_, _, bits_len) = bounds_size(bit_map.rowBytes as usize, bit_map.bounds.width(), bit_map.bounds.height());
+        let bits = rdr.read_bytes(bits_len)?;
+        let color_table = ColorTable::read(rdr)?;
+        let (_, _, pixels_len) = bounds_size(pix_map.row_bytes(), pix_map.bounds.width(), pix_map.bounds.height());
+        let pixels = rdr.read_bytes(pixels_len)?;
+
+        Ok(ColorIcon {
+            pix_map,
+            mask_map,
+            bit_map,
+            mask,
+            bits,
+            color_table,
+            pixels
+        })
+    }
+}
+
+impl TypedRsrc for ColorIcon {
+    const RSRC_TYPE: OSType = OSType(*b"cicn");
+}
+
+impl std::fmt::Debug for ColorIcon {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ColorIcon")
+            .field("bounds", &self.pix_map.bounds)
+            .field("pixelSize", &self.pix_map.pixelSize)
+            .field("colors", &self.color_table.ctTable.len())
+            .finish()
+    }
+}
+
+// 'ppat', color pattern. Old style patterns only have the black and white
+// pattern, the others also a pixel image.
+#[derive(Debug)]
+pub struct PixPattern {
+    pub pat_type: i16,
+    pub pattern: Pattern,
+    pub pix_map: Option<(PixMap, ColorTable)>,
+    pub pixels: Vec<u8>
+}
+
+impl PixPattern {
+    pub fn to_image(&self) -> Image {
+        if let Some((pix_map, color_table)) = &self.pix_map {
+            return pix_map_image(pix_map, color_table, &self.pixels);
+        }
+        let mut image = Image::new(8, 8);
+        for y in 0..8 {
+            for x in 0..8 {
+                let set = self.pattern.is_set(x as i32, y as i32);
+                image.set_color(x, y, if set { CLUT_4[15] } else { CLUT_4[0] });
+            }
+        }
+        image
+    }
+}
+
+impl SerialRead for PixPattern {
+    // Handles in the resource are offsets from the start of the resource
+    fn read(rdr: &mut SerialReadStorage) -> Result<PixPattern> {
+        let pat_type = rdr.read_i16()?;
+        let pat_map = rdr.read_u32()?;
+        let pat_data = rdr.read_u32()?;
+        rdr.pad(10)?; // patXData, patXValid and patXMap
+        let pattern = Pattern::read(rdr)?;
+
+        if pat_type == 0 {
+            return Ok(PixPattern {
+                pat_type,
+                pattern,
+                pix_map: None,
+                pixels: vec![]
+            });
+        }
+
+        rdr.seek(pat_map as u64)?;
+        let pix_map = PixMap::read(rdr)?;
+        let depth = check_depth(pat_map as u64, "PixPattern", &pix_map)?;
+        check_row_bytes(pat_map as u64 + 4, "PixPattern", pix_map.row_bytes(), &pix_map.bounds, depth)?;
+        rdr.seek(pix_map.pmTable as u64)?;
+        let color_table = ColorTable::read(rdr)?;
+        let (_, _, pixels_len) = bounds_size(pix_map.row_bytes(), pix_map.bounds.width(), pix_map.bounds.height());
+        rdr.seek(pat_data as u64)?;
+        let pixels = rdr.read_bytes(pixels_len)?;
+
+        Ok(PixPattern {
+            pat_type,
+            pattern,
+            pix_map: Some((pix_map, color_table)),
+            pixels
+        })
+    }
+}
+
+impl TypedRsrc for PixPattern {
+    const RSRC_TYPE: OSType = OSType(*b"ppat");
+}
+
+// Renders any of the icon types, with the mask from the black and white icon
+// list of the same ID applied to the color icons
+pub fn icon_image(rsrc: &Rsrc, rsrc_type: &OSType, id: i16) -> Result<Option<Image>> {
+    let large_mask = || rsrc.load::<IconList>(id).ok();
+    let small_mask = || rsrc.load::<SmallIconList>(id).ok();
+    let image = match &rsrc_type.0 {
+        b"ICON" => rsrc.load::<Icon>(id)?.to_image(),
+        b"ICN#" => rsrc.load::<IconList>(id)?.to_image(),
+        b"ics#" => rsrc.load::<SmallIconList>(id)?.to_image(),
+        b"icl4" => rsrc.load::<LargeIcon4>(id)?.to_image(large_mask().as_ref()),
+        b"ics4" => rsrc.load::<SmallIcon4>(id)?.to_image(small_mask().as_ref()),
+        b"icl8" => rsrc.load::<LargeIcon8>(id)?.to_image(large_mask().as_ref()),
+        b"ics8" => rsrc.load::<SmallIcon8>(id)?.to_image(small_mask().as_ref()),
+        b"cicn" => rsrc.load::<ColorIcon>(id)?.to_image(),
+        b"ppat" => rsrc.load::<PixPattern>(id)?.to_image(),
+        _ => return Ok(None)
+    };
+    Ok(Some(image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palettes() {
+        assert_eq!(system_color_4(0).to_rgb8(), [0xff, 0xff, 0xff]);
+        assert_eq!(system_color_4(15).to_rgb8(), [0x00, 0x00, 0x00]);
+        assert_eq!(system_color_8(0).to_rgb8(), [0xff, 0xff, 0xff]);
+        assert_eq!(system_color_8(5).to_rgb8(), [0xff, 0xff, 0x00]);
+        assert_eq!(system_color_8(214).to_rgb8(), [0x00, 0x00, 0x33]);
+        assert_eq!(system_color_8(215).to_rgb8(), [0xee, 0x00, 0x00]);
+        assert_eq!(system_color_8(254).to_rgb8(), [0x11, 0x11, 0x11]);
+        assert_eq!(system_color_8(255).to_rgb8(), [0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn icon_list() {
+        let mut data = vec![0u8; 256];
+        data[0] = 0x80;           // Black top left pixel
+        data[128] = 0xc0;         // Masking in the two first pixels
+        let icon = IconList::read(&mut SerialReadStorage::from(data)).unwrap();
+        let image = icon.to_image();
+        assert_eq!(image.get(0, 0), Some([0, 0, 0, 0xff]));
+        assert_eq!(image.get(1, 0), Some([0xff, 0xff, 0xff, 0xff]));
+        assert_eq!(image.get(2, 0), Some([0, 0, 0, 0]));
+
+        let color = LargeIcon4 { data: vec![0x37; 512] };
+        let image = color.to_image(Some(&icon));
+        assert_eq!(image.get(0, 0), Some([0xdd, 0x08, 0x06, 0xff]));
+        assert_eq!(image.get(1, 0), Some([0x02, 0xab, 0xea, 0xff]));
+        assert_eq!(image.get(0, 1), Some([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn color_icon() {
+        let mut data = vec![];
+        // PixMap, 2x2 pixels at 2 bits
+        data.extend_from_slice(&[0, 0, 0, 0, 0x80, 2, 0, 0, 0, 0, 0, 2, 0, 2]);
+        data.extend_from_slice(&[0; 16]);
+        data.extend_from_slice(&[0, 0, 0, 2, 0, 1, 0, 2]);
+        data.extend_from_slice(&[0; 12]);
+        // Mask and bitmap
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 2]);
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 2]);
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&[0x80, 0, 0xc0, 0]);
+        data.extend_from_slice(&[0xc0, 0, 0x00, 0]);
+        // Color table with two entries
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        data.extend_from_slice(&[0, 0, 0xff, 0xff, 0, 0, 0, 0]);
+        data.extend_from_slice(&[0, 3, 0, 0, 0xff, 0xff, 0, 0]);
+        // Pixels
+        data.extend_from_slice(&[0x30, 0, 0xc0, 0]);
+
+        let icon = ColorIcon::read(&mut SerialReadStorage::from(data.clone())).unwrap();
+        let image = icon.to_image();
+        assert_eq!(image.width(), 2);
+        assert_eq!(image.get(0, 0), Some([0xff, 0, 0, 0xff]));
+        assert_eq!(image.get(1, 0), Some([0, 0, 0, 0]));
+        assert_eq!(image.get(0, 1), Some([0, 0xff, 0, 0xff]));
+        assert_eq!(image.get(1, 1), Some([0xff, 0, 0, 0xff]));
+        assert_eq!(icon.bits_image().get(1, 0), Some([0, 0, 0, 0]));
+
+        // Black and white bitmap without rows for its bounds
+        data[64 + 4..64 + 14].copy_from_slice(&[0, 0, 0, 0, 0, 0, 0x7f, 0xff, 0x7f, 0xff]);
+        assert!(ColorIcon::read(&mut SerialReadStorage::from(data)).is_err());
+    }
+
+    #[test]
+    fn pix_pattern_row_bytes() {
+        let mut data = vec![0, 1, 0, 0, 0, 28, 0, 0, 0, 78];
+        data.extend_from_slice(&[0; 18]);
+        // PixMap, 32767x32767 pixels at 1 bit with rowBytes 0
+        data.extend_from_slice(&[0, 0, 0, 0, 0x80, 0, 0, 0, 0, 0, 0x7f, 0xff, 0x7f, 0xff]);
+        data.extend_from_slice(&[0; 16]);
+        data.extend_from_slice(&[0, 0, 0, 1, 0, 1, 0, 1]);
+        data.extend_from_slice(&[0; 12]);
+        assert!(PixPattern::read(&mut SerialReadStorage::from(data)).is_err());
+    }
+}
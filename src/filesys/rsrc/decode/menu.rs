@@ -0,0 +1,81 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::Result;
+use crate::types::{
+    OSType,
+    PString
+};
+
+use super::TypedRsrc;
+
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct MenuItem {
+    pub text: PString,
+    pub icon: u8,     // Resource ID of the icon - 256, or 0 for none
+    pub key_equiv: u8,
+    pub mark: u8,
+    pub style: u8
+}
+
+impl MenuItem {
+    pub fn is_separator(&self) -> bool {
+        self.text.as_bytes().first() == Some(&b'-')
+    }
+}
+
+// 'MENU', the menu record as stored in the resource
+#[derive(Debug)]
+pub struct Menu {
+    pub menu_id: i16,
+    pub width: i16,
+    pub height: i16,
+    pub proc_id: i16,  // Resource ID of the 'MDEF'
+    pub enable_flags: u32,
+    pub title: PString,
+    pub items: Vec<MenuItem>
+}
+
+impl Menu {
+    // Bit 0 enables the menu itself, bit 1 to 31 the items
+    pub fn is_enabled(&self, item: usize) -> bool {
+        // Items past 31 are always enabled
+        item > 31 || self.enable_flags & (1 << item) != 0
+    }
+}
+
+impl SerialRead for Menu {
+    fn read(rdr: &mut SerialReadStorage) -> Result<Menu> {
+        let menu_id = rdr.read_i16()?;
+        let width = rdr.read_i16()?;
+        let height = rdr.read_i16()?;
+        let proc_id = rdr.read_i16()?;
+        rdr.pad(2)?; // Filler
+        let enable_flags = rdr.read_u32()?;
+        let title = PString::read(rdr)?;
+
+        // The item list is terminated by an empty string
+        let mut items = vec![];
+        while rdr.pos() < rdr.size() {
+            let offset = rdr.pos();
+            if rdr.read_u8()? == 0 {
+                break;
+            }
+            rdr.seek(offset)?;
+            items.push(MenuItem::read(rdr)?);
+        }
+
+        Ok(Menu {
+            menu_id,
+            width,
+            height,
+            proc_id,
+            enable_flags,
+            title,
+            items
+        })
+    }
+}
+
+impl TypedRsrc for Menu {
+    const RSRC_TYPE: OSType = OSType(*b"MENU");
+}
@@ -0,0 +1,306 @@
+mod strings;
+mod finder;
+mod menu;
+mod dialog;
+mod icon;
+mod pict;
+mod sound;
+mod font;
+mod template;
+mod code;
+mod alias;
+mod describe;
+
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::Result;
+use crate::types::OSType;
+
+pub use strings::{
+    Str,
+    StrList,
+    Text
+};
+
+pub use finder::{
+    Version,
+    VersionStage,
+    Bundle,
+    BundleType,
+    BundleMapping,
+    FileReference,
+    SizeInfo
+};
+
+pub use menu::{
+    Menu,
+    MenuItem
+};
+
+pub use dialog::{
+    WindowTemplate,
+    DialogTemplate,
+    AlertTemplate,
+    DialogItemList,
+    DialogItem,
+    DialogItemKind,
+    ControlTemplate
+};
+
+pub use icon::{
+    Icon,
+    IconList,
+    SmallIconList,
+    LargeIcon4,
+    SmallIcon4,
+    LargeIcon8,
+    SmallIcon8,
+    ColorIcon,
+    PixPattern,
+    icon_image,
+    system_color_4,
+    system_color_8
+};
+
+pub use pict::Picture;
+pub use sound::{
+    Sound,
+    SoundCommand,
+    SoundHeader,
+    SynthReference,
+    NULL_CMD,
+    SOUND_CMD,
+    BUFFER_CMD,
+    SAMPLED_SYNTH
+};
+pub use font::{
+    BitmapFont,
+    FontRec,
+    Font,
+    NewFont,
+    FontAssociation,
+    StyleWidths,
+    KerningPair,
+    KerningTable,
+    StyleMapping,
+    FontFamily,
+    FONT_HAS_HEIGHT_TABLE,
+    FONT_HAS_WIDTH_TABLE,
+    FONT_FIXED_WIDTH
+};
+pub use template::{
+    Template,
+    TemplateItem,
+    Field,
+    Value,
+    format_fields,
+    fields_json
+};
+
+pub use alias::{
+    Alias,
+    AliasKind
+};
+
+pub use code::{
+    JumpTableHeader,
+    SegmentHeader,
+    JumpTable,
+    JumpTableEntry,
+    CodeSegment,
+    CodeAnalysis,
+    DisasmLine
+};
+
+// Resource with a known layout, decoded from the resource data
+pub trait TypedRsrc: SerialRead {
+    const RSRC_TYPE: OSType;
+}
+
+// Any of the decodable resources, for listing resources of unknown type
+#[derive(Debug)]
+pub enum Decoded {
+    Str(Str),
+    StrList(StrList),
+    Text(Text),
+    Version(Version),
+    Bundle(Bundle),
+    FileReference(FileReference),
+    SizeInfo(SizeInfo),
+    Alias(Alias),
+    Menu(Menu),
+    WindowTemplate(WindowTemplate),
+    DialogTemplate(DialogTemplate),
+    AlertTemplate(AlertTemplate),
+    DialogItemList(DialogItemList),
+    ControlTemplate(ControlTemplate),
+    Icon(Icon),
+    IconList(IconList),
+    SmallIconList(SmallIconList),
+    LargeIcon4(LargeIcon4),
+    SmallIcon4(SmallIcon4),
+    LargeIcon8(LargeIcon8),
+    SmallIcon8(SmallIcon8),
+    ColorIcon(ColorIcon),
+    PixPattern(PixPattern),
+    Picture(Picture),
+    Sound(Sound),
+    Font(Font),
+    NewFont(NewFont),
+    FontFamily(FontFamily)
+}
+
+type Decoder = fn(&mut SerialReadStorage) -> Result<Decoded>;
+
+fn decode_as<T: TypedRsrc>(rdr: &mut SerialReadStorage, wrap: fn(T) -> Decoded) -> Result<Decoded> {
+    Ok(wrap(T::read(rdr)?))
+}
+
+// Decodes the resource data by type. Types without a decoder returns None.
+pub fn decode(rsrc_type: &OSType, rdr: &mut SerialReadStorage) -> Result<Option<Decoded>> {
+    let decoders: [(OSType, Decoder); 28] = [
+        (Str::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::Str)),
+        (StrList::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::StrList)),
+        (Text::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::Text)),
+        (Version::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::Version)),
+        (Bundle::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::Bundle)),
+        (FileReference::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::FileReference)),
+        (SizeInfo::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::SizeInfo)),
+        (Alias::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::Alias)),
+        (Menu::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::Menu)),
+        (WindowTemplate::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::WindowTemplate)),
+        (DialogTemplate::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::DialogTemplate)),
+        (AlertTemplate::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::AlertTemplate)),
+        (DialogItemList::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::DialogItemList)),
+        (ControlTemplate::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::ControlTemplate)),
+        (Icon::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::Icon)),
+        (IconList::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::IconList)),
+        (SmallIconList::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::SmallIconList)),
+        (LargeIcon4::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::LargeIcon4)),
+        (SmallIcon4::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::SmallIcon4)),
+        (LargeIcon8::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::LargeIcon8)),
+        (SmallIcon8::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::SmallIcon8)),
+        (ColorIcon::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::ColorIcon)),
+        (PixPattern::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::PixPattern)),
+        (Picture::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::Picture)),
+        (Sound::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::Sound)),
+        (Font::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::Font)),
+        (NewFont::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::NewFont)),
+        (FontFamily::RSRC_TYPE, |rdr| decode_as(rdr, Decoded::FontFamily)),
+    ];
+    match decoders.iter().find(|(t, _)| t == rsrc_type) {
+        Some((_, decoder)) => Ok(Some(decoder(rdr)?)),
+        None => Ok(None)
+    }
+}
+
+// Boolean fields in resource templates occupy a word, with the value in the
+// high byte
+fn read_boolean(rdr: &mut SerialReadStorage) -> Result<bool> {
+    Ok(rdr.read_u16()? != 0)
+}
+
+// Trailing field added in later system versions, missing in older resources
+fn read_optional_u16(rdr: &mut SerialReadStorage) -> Result<Option<u16>> {
+    if rdr.size() - rdr.pos() >= 2 {
+        Ok(Some(rdr.read_u16()?))
+    } else {
+        Ok(None)
+    }
+}
+
+// Word alignment padding, which may be left out at the end of the resource
+fn skip_alignment(rdr: &mut SerialReadStorage) -> Result<()> {
+    if !rdr.pos().is_multiple_of(2) && rdr.pos() < rdr.size() {
+        rdr.pad(1)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Rect;
+
+    fn rdr(data: &[u8]) -> SerialReadStorage {
+        SerialReadStorage::from(Vec::from(data))
+    }
+
+    #[test]
+    fn strings() {
+        let list = StrList::read(&mut rdr(b"\x00\x02\x03One\x03Two")).unwrap();
+        assert_eq!(list.get_ind(2).unwrap().to_string(), "Two");
+        assert!(list.get_ind(0).is_none());
+        assert!(list.get_ind(3).is_none());
+
+        let text = Text::read(&mut rdr(b"a\rb")).unwrap();
+        assert_eq!(text.decode(crate::types::Encoding::MacRoman), "a\nb");
+    }
+
+    #[test]
+    fn version() {
+        let vers = Version::read(&mut rdr(b"\x07\x01\x60\x12\x00\x00\x037.0\x0cSystem 7.0.1")).unwrap();
+        assert_eq!(vers.major, 7);
+        assert_eq!(vers.stage, VersionStage::Beta);
+        assert_eq!(vers.number(), "7.0.1b12");
+        assert_eq!(vers.long_version.to_string(), "System 7.0.1");
+    }
+
+    #[test]
+    fn dialog_items() {
+        let mut data = vec![0, 1];
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 10, 0, 20, 0, 30, 0, 80, 4, 2, b'O', b'K']);
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 40, 0, 20, 0, 72, 0, 52, 0xa0, 2, 0, 128]);
+        let ditl = DialogItemList::read(&mut rdr(&data)).unwrap();
+        assert_eq!(ditl.items.len(), 2);
+        assert_eq!(ditl.items[0].bounds, Rect::new(20, 10, 80, 30));
+        assert!(ditl.items[0].enabled);
+        match &ditl.items[0].kind {
+            DialogItemKind::Button(title) => assert_eq!(title.to_string(), "OK"),
+            kind => panic!("Unexpected item {:?}", kind)
+        }
+        assert!(!ditl.items[1].enabled);
+        match &ditl.items[1].kind {
+            DialogItemKind::Icon(128) => (),
+            kind => panic!("Unexpected item {:?}", kind)
+        }
+    }
+
+    #[test]
+    fn bundle() {
+        let mut data = b"ttxt\x00\x00\x00\x01ICN#\x00\x00\x00\x00\x00\x80".to_vec();
+        data.extend_from_slice(b"FREF\xff\xff");
+        let bndl = Bundle::read(&mut rdr(&data)).unwrap();
+        assert_eq!(bndl.types.len(), 2);
+        assert_eq!(bndl.rsrc_id(&OSType::from(b"ICN#"), 0), Some(128));
+        assert!(bndl.types[1].mappings.is_empty());
+    }
+
+    #[test]
+    fn registry() {
+        let mut data = vec![0, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xfb, 4, b'F', b'i', b'l', b'e'];
+        data.extend_from_slice(b"\x04Open\x00\x4f\x00\x00\x01-\x00\x00\x00\x00\x00");
+        match decode(&OSType::from(b"MENU"), &mut rdr(&data)).unwrap() {
+            Some(Decoded::Menu(menu)) => {
+                assert_eq!(menu.title.to_string(), "File");
+                assert_eq!(menu.items.len(), 2);
+                assert!(menu.is_enabled(1));
+                assert!(!menu.is_enabled(2));
+                assert!(menu.items[1].is_separator());
+            },
+            decoded => panic!("Unexpected resource {:?}", decoded)
+        }
+        assert!(decode(&OSType::from(b"CODE"), &mut rdr(&data)).unwrap().is_none());
+    }
+
+    #[test]
+    fn fields() {
+        let vers = Version::read(&mut rdr(b"\x07\x01\x60\x12\x00\x00\x037.0\x0cSystem 7.0.1")).unwrap();
+        let fields = Decoded::Version(vers).fields();
+        assert!(format_fields(&fields).starts_with("Version: \"7.0.1b12\"\nStage: \"Beta\"\n"));
+        assert!(fields_json(&fields).to_string().ends_with(r#""Long version":"System 7.0.1"}"#));
+
+        let ditl = DialogItemList::read(&mut rdr(b"\x00\x00\x00\x00\x00\x00\x00\x0a\x00\x14\x00\x1e\x00\x50\x04\x02OK")).unwrap();
+        assert_eq!(fields_json(&Decoded::DialogItemList(ditl).fields()).to_string(),
+            r#"{"Items":[{"Bounds":{"top":10,"left":20,"bottom":30,"right":80},"Enabled":true,"Type":"Button","Text":"OK"}]}"#);
+    }
+}
@@ -0,0 +1,813 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::{Error, Result};
+use crate::tools::{
+    Image,
+    packbits,
+    canvas::{
+        Canvas,
+        Ink,
+        PAT_COPY,
+        PAT_XOR,
+        SRC_COPY
+    }
+};
+use crate::types::{
+    ColorTable,
+    OSType,
+    Pattern,
+    PixMap,
+    Point,
+    Polygon,
+    Rect,
+    Region,
+    RGBColor
+};
+
+use super::TypedRsrc;
+
+// PICT files starts with a header reserved for the application
+const FILE_HEADER_LEN: usize = 512;
+
+// Larger pictures are most likely corrupt, and would use a lot of memory
+const MAX_PIXELS: i64 = 0x1000000;
+
+const OP_END_PIC: u16 = 0x00ff;
+
+// 'PICT', a recorded sequence of QuickDraw operations. Version 1 pictures
+// use byte opcodes, version 2 word opcodes aligned to words.
+pub struct Picture {
+    pub frame: Rect,
+    data: Vec<u8>
+}
+
+impl Picture {
+    // PICT file, as stored in the data fork of a file of type 'PICT'
+    pub fn from_file(data: &[u8]) -> Result<Picture> {
+        if data.len() < FILE_HEADER_LEN {
+            return Err(Error::UnexpectedEof { offset: 0, len: FILE_HEADER_LEN as u64, size: data.len() as u64 });
+        }
+        Picture::read(&mut SerialReadStorage::from(Vec::from(&data[FILE_HEADER_LEN..])))
+    }
+
+    // The opcodes, starting at the version opcode
+    fn opcodes(&self) -> SerialReadStorage {
+        let mut rdr = SerialReadStorage::from(self.data.clone());
+        rdr.seek(10).ok();
+        rdr
+    }
+
+    pub fn version(&self) -> u8 {
+        match self.data.get(10..14) {
+            Some([0x00, 0x11, 0x02, 0xff]) => 2,
+            _ => 1
+        }
+    }
+
+    // Plays back the picture onto a white background the size of the frame.
+    // Text is not drawn.
+    pub fn render(&self) -> Result<Image> {
+        let area = self.frame.width() as i64 * self.frame.height() as i64;
+        if self.frame.is_empty() || area > MAX_PIXELS {
+            return Err(Error::invalid_value(2, "Picture", "picFrame", "non-empty frame", format!("{:?}", self.frame)));
+        }
+        let mut player = Player::new(self.frame, self.version());
+        let mut rdr = self.opcodes();
+        player.play(&mut rdr)?;
+        Ok(player.canvas.into_image())
+    }
+}
+
+impl SerialRead for Picture {
+    fn read(rdr: &mut SerialReadStorage) -> Result<Picture> {
+        let start = rdr.pos();
+        rdr.pad(2)?; // picSize, only the low 16 bits of the size
+        let frame = Rect::read(rdr)?;
+        rdr.seek(start)?;
+        let len = rdr.size() - start;
+        Ok(Picture {
+            frame,
+            data: rdr.read_bytes(len)?
+        })
+    }
+}
+
+impl TypedRsrc for Picture {
+    const RSRC_TYPE: OSType = OSType(*b"PICT");
+}
+
+impl std::fmt::Debug for Picture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Picture")
+            .field("version", &self.version())
+            .field("frame", &self.frame)
+            .field("data", &format_args!("{} bytes", self.data.len()))
+            .finish()
+    }
+}
+
+// Classic QuickDraw colors, as used by the FgColor and BkColor opcodes
+fn old_color(color: u32, default: RGBColor) -> RGBColor {
+    match color {
+        33 => RGBColor::from_rgb8(0x00, 0x00, 0x00),  // blackColor
+        30 => RGBColor::from_rgb8(0xff, 0xff, 0xff),  // whiteColor
+        205 => RGBColor::from_rgb8(0xff, 0x00, 0x00), // redColor
+        341 => RGBColor::from_rgb8(0x00, 0xff, 0x00), // greenColor
+        409 => RGBColor::from_rgb8(0x00, 0x00, 0xff), // blueColor
+        273 => RGBColor::from_rgb8(0x00, 0xff, 0xff), // cyanColor
+        137 => RGBColor::from_rgb8(0xff, 0x00, 0xff), // magentaColor
+        69 => RGBColor::from_rgb8(0xff, 0xff, 0x00),  // yellowColor
+        _ => default
+    }
+}
+
+// Hilite and arithmetic modes are drawn as copy
+fn simple_mode(mode: u16, copy: u16) -> u16 {
+    if mode >= 32 { copy } else { mode & 0x0f }
+}
+
+// Degrees clockwise from 12 o'clock, scaled to the rectangle so 45 degrees
+// is always towards the top right corner
+fn angle_in_rect(rect: &Rect, h: i32, v: i32) -> f64 {
+    let cx = (rect.left as f64 + rect.right as f64) / 2.0;
+    let cy = (rect.top as f64 + rect.bottom as f64) / 2.0;
+    let dx = (h as f64 + 0.5 - cx) / (rect.width() as f64 / 2.0);
+    let dy = (v as f64 + 0.5 - cy) / (rect.height() as f64 / 2.0);
+    let angle = dx.atan2(-dy).to_degrees();
+    if angle < 0.0 { angle + 360.0 } else { angle }
+}
+
+fn in_oval(rect: &Rect, h: i32, v: i32) -> bool {
+    let rx = rect.width() as f64 / 2.0;
+    let ry = rect.height() as f64 / 2.0;
+    let dx = (h as f64 + 0.5 - (rect.left as f64 + rx)) / rx;
+    let dy = (v as f64 + 0.5 - (rect.top as f64 + ry)) / ry;
+    rect.contains(Point::new(h as i16, v as i16)) && dx * dx + dy * dy <= 1.0
+}
+
+fn in_rrect(rect: &Rect, oval: Point, h: i32, v: i32) -> bool {
+    if !rect.contains(Point::new(h as i16, v as i16)) {
+        return false;
+    }
+    let ow = (oval.h as i32).min(rect.width()).max(0);
+    let oh = (oval.v as i32).min(rect.height()).max(0);
+    let (left, top) = (rect.left as i32, rect.top as i32);
+    let (right, bottom) = (rect.right as i32, rect.bottom as i32);
+    // Outside the corners, the rounded rect is the same as the rect
+    let ch = if h < left + ow / 2 { left } else if h >= right - ow / 2 { right - ow } else { return true };
+    let cv = if v < top + oh / 2 { top } else if v >= bottom - oh / 2 { bottom - oh } else { return true };
+    let corner = Rect::new(ch as i16, cv as i16, (ch + ow) as i16, (cv + oh) as i16);
+    in_oval(&corner, h, v)
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+enum Verb {
+    Frame,
+    Paint,
+    Erase,
+    Invert,
+    Fill
+}
+
+enum Shape {
+    Rect(Rect),
+    RRect(Rect, Point),
+    Oval(Rect),
+    Arc(Rect, i16, i16),
+    Poly(Polygon),
+    Rgn(Region)
+}
+
+impl Shape {
+    fn bounds(&self) -> Rect {
+        match self {
+            Shape::Rect(r) | Shape::RRect(r, _) | Shape::Oval(r) | Shape::Arc(r, _, _) => *r,
+            Shape::Poly(poly) => poly.polyBBox,
+            Shape::Rgn(rgn) => rgn.bounds
+        }
+    }
+
+    fn contains(&self, h: i32, v: i32) -> bool {
+        match self {
+            Shape::Rect(r) => r.contains(Point::new(h as i16, v as i16)),
+            Shape::RRect(r, oval) => in_rrect(r, *oval, h, v),
+            Shape::Oval(r) => in_oval(r, h, v),
+            Shape::Arc(r, start, arc) => {
+                if !in_oval(r, h, v) {
+                    return false;
+                }
+                let (start, arc) = if *arc < 0 { (*start as f64 + *arc as f64, -(*arc as f64)) } else { (*start as f64, *arc as f64) };
+                let rel = ((angle_in_rect(r, h, v) - start) % 360.0 + 360.0) % 360.0;
+                arc >= 360.0 || rel <= arc
+            },
+            Shape::Poly(poly) => poly.contains(h, v),
+            Shape::Rgn(rgn) => rgn.contains(h, v)
+        }
+    }
+
+    // Pixels of the outline drawn by the pen, inside the shape
+    fn on_frame(&self, pen: Point, h: i32, v: i32) -> bool {
+        let (ph, pv) = (pen.h as i32, pen.v as i32);
+        match self {
+            Shape::Rect(r) => self.contains(h, v) && !Shape::Rect(r.inset(pen.h, pen.v)).contains(h, v),
+            Shape::Oval(r) => self.contains(h, v) && !Shape::Oval(r.inset(pen.h, pen.v)).contains(h, v),
+            Shape::RRect(r, oval) => {
+                let inner = Point::new(oval.h.saturating_sub(pen.h.saturating_mul(2)), oval.v.saturating_sub(pen.v.saturating_mul(2)));
+                self.contains(h, v) && !in_rrect(&r.inset(pen.h, pen.v), inner, h, v)
+            },
+            Shape::Arc(r, start, arc) => {
+                Shape::Arc(*r, *start, *arc).contains(h, v) && !in_oval(&r.inset(pen.h, pen.v), h, v)
+            },
+            Shape::Poly(_) | Shape::Rgn(_) => {
+                self.contains(h, v) && !(
+                    self.contains(h - ph, v) && self.contains(h + ph, v) &&
+                    self.contains(h, v - pv) && self.contains(h, v + pv)
+                )
+            }
+        }
+    }
+}
+
+// Pixel data of a bits opcode, unpacked to one row per vector
+struct Pixels {
+    bounds: Rect,
+    depth: usize,
+    pack_type: i16,
+    cmp_count: usize,
+    row_bytes: usize,
+    table: Option<ColorTable>,
+    rows: Vec<Vec<u8>>
+}
+
+enum Sample {
+    Bit(bool),
+    Color(RGBColor)
+}
+
+impl Pixels {
+    fn read_rows(&mut self, rdr: &mut SerialReadStorage, packed: bool) -> Result<()> {
+        let height = self.bounds.height().max(0) as usize;
+        let width = self.bounds.width().max(0) as usize;
+        // Rows shorter than 8 bytes are never packed, and pack type 0 is the
+        // default for the depth
+        self.pack_type = match (self.pack_type, self.depth) {
+            _ if !packed || self.row_bytes < 8 => 1,
+            (0, 16) => 3,
+            (0, 32) => 4,
+            (pack_type, _) => pack_type
+        };
+        for _ in 0..height {
+            let row = if self.pack_type == 1 {
+                rdr.read_bytes(self.row_bytes as u64)?
+            } else if self.pack_type == 2 {
+                // 32 bit pixels without the unused byte, not run length encoded
+                rdr.read_bytes(width as u64 * 3)?
+            } else {
+                let len = if self.row_bytes > 250 { rdr.read_u16()? } else { rdr.read_u8()? as u16 };
+                let unit = if self.pack_type == 3 { 2 } else { 1 };
+                packbits::unpack(&rdr.read_bytes(len as u64)?, unit)?
+            };
+            self.rows.push(row);
+        }
+        Ok(())
+    }
+
+    // Pixel at a position relative to the bounds. Missing data is black.
+    fn sample(&self, x: usize, y: usize) -> Sample {
+        let row = match self.rows.get(y) {
+            Some(row) => row.as_slice(),
+            None => &[]
+        };
+        let byte = |idx: usize| row.get(idx).cloned().unwrap_or(0);
+        match self.depth {
+            1 | 2 | 4 | 8 => {
+                let bit = x * self.depth;
+                let shift = 8 - self.depth - bit % 8;
+                let index = (byte(bit / 8) >> shift) & ((1u16 << self.depth) - 1) as u8;
+                match &self.table {
+                    Some(table) => Sample::Color(table.lookup(index).unwrap_or_default()),
+                    None => Sample::Bit(index != 0)
+                }
+            },
+            16 => {
+                let px = (byte(x * 2) as u16) << 8 | byte(x * 2 + 1) as u16;
+                let expand = |c: u16| { let c = (c & 0x1f) as u8; (c << 3) | (c >> 2) };
+                Sample::Color(RGBColor::from_rgb8(expand(px >> 10), expand(px >> 5), expand(px)))
+            },
+            _ => {
+                let width = self.bounds.width().max(0) as usize;
+                let [r, g, b] = match self.pack_type {
+                    // Component planes, with alpha first if present
+                    4 => {
+                        let skip = self.cmp_count.saturating_sub(3);
+                        [byte((skip) * width + x), byte((skip + 1) * width + x), byte((skip + 2) * width + x)]
+                    },
+                    2 => [byte(x * 3), byte(x * 3 + 1), byte(x * 3 + 2)],
+                    _ => [byte(x * 4 + 1), byte(x * 4 + 2), byte(x * 4 + 3)]
+                };
+                Sample::Color(RGBColor::from_rgb8(r, g, b))
+            }
+        }
+    }
+}
+
+struct Player {
+    canvas: Canvas,
+    version: u8,
+    origin: Point,
+    pen_loc: Point,
+    pen_size: Point,
+    pen_mode: u16,
+    pen_pat: Pattern,
+    fill_pat: Pattern,
+    bk_pat: Pattern,
+    fore: RGBColor,
+    back: RGBColor,
+    ov_size: Point,
+    last_rect: Rect,
+    last_poly: Option<Polygon>,
+    last_rgn: Option<Region>
+}
+
+impl Player {
+    fn new(frame: Rect, version: u8) -> Player {
+        Player {
+            canvas: Canvas::new(frame),
+            version,
+            origin: Point::new(0, 0),
+            pen_loc: Point::new(0, 0),
+            pen_size: Point::new(1, 1),
+            pen_mode: PAT_COPY,
+            pen_pat: Pattern::BLACK,
+            fill_pat: Pattern::BLACK,
+            bk_pat: Pattern::WHITE,
+            fore: RGBColor::new(0, 0, 0),
+            back: RGBColor::new(0xffff, 0xffff, 0xffff),
+            ov_size: Point::new(0, 0),
+            last_rect: Rect::default(),
+            last_poly: None,
+            last_rgn: None
+        }
+    }
+
+    fn ink(&self, pattern: Pattern, mode: u16) -> Ink {
+        Ink {
+            pattern,
+            fore: self.fore,
+            back: self.back,
+            mode: simple_mode(mode, PAT_COPY)
+        }
+    }
+
+    // Coordinates are recorded relative to the origin at the time
+    fn point(&self, rdr: &mut SerialReadStorage) -> Result<Point> {
+        Ok(Point::read(rdr)? - self.origin)
+    }
+
+    fn rect(&self, rdr: &mut SerialReadStorage) -> Result<Rect> {
+        Ok(Rect::read(rdr)?.offset(-self.origin.h, -self.origin.v))
+    }
+
+    fn opcode(&self, rdr: &mut SerialReadStorage) -> Result<u16> {
+        if self.version == 2 {
+            rdr.align(2)?;
+            rdr.read_u16()
+        } else {
+            rdr.read_u8().map(|op| op as u16)
+        }
+    }
+
+    fn play(&mut self, rdr: &mut SerialReadStorage) -> Result<()> {
+        loop {
+            let offset = rdr.pos();
+            let opcode = self.opcode(rdr)?;
+            if opcode == OP_END_PIC {
+                return Ok(());
+            }
+            self.op(rdr, opcode).map_err(|err| err.in_field("Picture", "opcode", offset))?;
+        }
+    }
+
+    fn op(&mut self, rdr: &mut SerialReadStorage, opcode: u16) -> Result<()> {
+        match opcode {
+            0x0000 => (), // NOP
+            0x0001 => {
+                let clip = Region::read(rdr)?;
+                let bounds = clip.bounds.offset(-self.origin.h, -self.origin.v);
+                // Only rectangular clip regions are offset
+                let clip = if clip.is_rect() { Region::from_rect(bounds) } else { clip };
+                self.canvas.set_clip(Some(clip));
+            },
+            0x0002 => self.bk_pat = Pattern::read(rdr)?,
+            0x0003 | 0x0005 | 0x000d | 0x0015 | 0x0016 => { rdr.pad(2)?; }, // Text settings
+            0x0004 => { rdr.pad(1)?; }, // TxFace
+            0x0006 => { rdr.pad(4)?; }, // SpExtra
+            0x0007 => {
+                // A pen larger than the picture draws nothing more within it,
+                // so the size is capped to keep drawing bounded
+                let size = Point::read(rdr)?;
+                let frame = self.canvas.bounds();
+                let cap = |len: i32| len.clamp(0, i16::MAX as i32) as i16;
+                self.pen_size = Point::new(size.h.clamp(0, cap(frame.width())), size.v.clamp(0, cap(frame.height())));
+            },
+            0x0008 => self.pen_mode = rdr.read_u16()?,
+            0x0009 => self.pen_pat = Pattern::read(rdr)?,
+            0x000a => self.fill_pat = Pattern::read(rdr)?,
+            0x000b => self.ov_size = Point::read(rdr)?,
+            0x000c => {
+                let dh = rdr.read_i16()?;
+                let dv = rdr.read_i16()?;
+                self.origin = self.origin + Point::new(dh, dv);
+            },
+            0x000e => self.fore = old_color(rdr.read_u32()?, RGBColor::new(0, 0, 0)),
+            0x000f => self.back = old_color(rdr.read_u32()?, RGBColor::new(0xffff, 0xffff, 0xffff)),
+            0x0010 => { rdr.pad(8)?; }, // TxRatio
+            0x0011 => { rdr.pad(if self.version == 2 { 2 } else { 1 })?; },
+            0x0012..=0x0014 => self.pix_pat(rdr, opcode)?,
+            0x001a => self.fore = RGBColor::read(rdr)?,
+            0x001b => self.back = RGBColor::read(rdr)?,
+            0x001c | 0x001e => (), // HiliteMode, DefHilite
+            0x001d | 0x001f => { rdr.pad(6)?; }, // HiliteColor, OpColor
+            0x0020 => {
+                let from = self.point(rdr)?;
+                let to = self.point(rdr)?;
+                self.line_to(from, to);
+            },
+            0x0021 => {
+                let to = self.point(rdr)?;
+                self.line_to(self.pen_loc, to);
+            },
+            0x0022 => {
+                let from = self.point(rdr)?;
+                let dh = rdr.read_i8()? as i16;
+                let dv = rdr.read_i8()? as i16;
+                self.line_to(from, from + Point::new(dh, dv));
+            },
+            0x0023 => {
+                let dh = rdr.read_i8()? as i16;
+                let dv = rdr.read_i8()? as i16;
+                self.line_to(self.pen_loc, self.pen_loc + Point::new(dh, dv));
+            },
+            0x0028 => { rdr.pad(4)?; self.skip_text(rdr)?; }, // LongText
+            0x0029 | 0x002a => { rdr.pad(1)?; self.skip_text(rdr)?; }, // DHText, DVText
+            0x002b => { rdr.pad(2)?; self.skip_text(rdr)?; }, // DHDVText
+            0x0030..=0x0037 => {
+                self.last_rect = self.rect(rdr)?;
+                self.draw(opcode, Shape::Rect(self.last_rect));
+            },
+            0x0038..=0x003f => self.draw(opcode, Shape::Rect(self.last_rect)),
+            0x0040..=0x0047 => {
+                self.last_rect = self.rect(rdr)?;
+                self.draw(opcode, Shape::RRect(self.last_rect, self.ov_size));
+            },
+            0x0048..=0x004f => self.draw(opcode, Shape::RRect(self.last_rect, self.ov_size)),
+            0x0050..=0x0057 => {
+                self.last_rect = self.rect(rdr)?;
+                self.draw(opcode, Shape::Oval(self.last_rect));
+            },
+            0x0058..=0x005f => self.draw(opcode, Shape::Oval(self.last_rect)),
+            0x0060..=0x0067 => {
+                self.last_rect = self.rect(rdr)?;
+                let start = rdr.read_i16()?;
+                let arc = rdr.read_i16()?;
+                self.draw(opcode, Shape::Arc(self.last_rect, start, arc));
+            },
+            0x0068..=0x006f => {
+                let start = rdr.read_i16()?;
+                let arc = rdr.read_i16()?;
+                self.draw(opcode, Shape::Arc(self.last_rect, start, arc));
+            },
+            0x0070..=0x0077 => {
+                let mut poly = Polygon::read(rdr)?;
+                for pt in poly.polyPoints.iter_mut() {
+                    *pt = *pt - self.origin;
+                }
+                poly.polyBBox = poly.polyBBox.offset(-self.origin.h, -self.origin.v);
+                self.last_poly = Some(poly.clone());
+                self.draw(opcode, Shape::Poly(poly));
+            },
+            0x0078..=0x007f => if let Some(poly) = self.last_poly.clone() {
+                self.draw(opcode, Shape::Poly(poly));
+            },
+            0x0080..=0x0087 => {
+                let rgn = Region::read(rdr)?;
+                self.last_rgn = Some(rgn.clone());
+                self.draw(opcode, Shape::Rgn(rgn));
+            },
+            0x0088..=0x008f => if let Some(rgn) = self.last_rgn.clone() {
+                self.draw(opcode, Shape::Rgn(rgn));
+            },
+            0x0090 | 0x0091 | 0x0098 | 0x0099 | 0x009a | 0x009b => self.bits(rdr, opcode)?,
+            0x00a0 => { rdr.pad(2)?; }, // ShortComment
+            0x00a1 => {
+                rdr.pad(2)?;
+                let len = rdr.read_u16()?;
+                rdr.pad(len as i64)?;
+            },
+            0x0c00 => { rdr.pad(24)?; }, // HeaderOp
+            _ => self.skip_reserved(rdr, opcode)?
+        }
+        Ok(())
+    }
+
+    // Data lengths of reserved opcodes, as specified for version 2
+    fn skip_reserved(&self, rdr: &mut SerialReadStorage, opcode: u16) -> Result<()> {
+        let len = match opcode {
+            0x0017..=0x0019 | 0x003d..=0x003f | 0x004d..=0x004f |
+            0x005d..=0x005f | 0x007d..=0x007f | 0x008d..=0x008f |
+            0x00b0..=0x00cf | 0x8000..=0x80ff => 0,
+            0x0024..=0x0027 | 0x002c..=0x002f | 0x0092..=0x0097 |
+            0x009c..=0x009f | 0x00a2..=0x00af => rdr.read_u16()? as u64,
+            0x00d0..=0x00fe | 0x8100..=0xffff => rdr.read_u32()? as u64,
+            0x0100..=0x7fff => (opcode as u64 >> 8) * 2,
+            _ => return Err(Error::invalid_value(rdr.pos(), "Picture", "opcode", "known opcode", format!("${:04x}", opcode)))
+        };
+        rdr.pad(len as i64)?;
+        Ok(())
+    }
+
+    fn skip_text(&self, rdr: &mut SerialReadStorage) -> Result<()> {
+        let len = rdr.read_u8()?;
+        rdr.pad(len as i64)?;
+        Ok(())
+    }
+
+    fn line_to(&mut self, from: Point, to: Point) {
+        let ink = self.ink(self.pen_pat, self.pen_mode);
+        self.canvas.line(from, to, self.pen_size, &ink);
+        self.pen_loc = to;
+    }
+
+    fn draw(&mut self, opcode: u16, shape: Shape) {
+        let verb = match opcode & 0x07 {
+            0 => Verb::Frame,
+            1 => Verb::Paint,
+            2 => Verb::Erase,
+            3 => Verb::Invert,
+            4 => Verb::Fill,
+            _ => return // Reserved
+        };
+        let ink = match verb {
+            Verb::Frame | Verb::Paint => self.ink(self.pen_pat, self.pen_mode),
+            Verb::Erase => self.ink(self.bk_pat, PAT_COPY),
+            Verb::Invert => self.ink(Pattern::BLACK, PAT_XOR),
+            Verb::Fill => self.ink(self.fill_pat, PAT_COPY)
+        };
+        let pen = self.pen_size;
+        let bounds = shape.bounds();
+        if let (Verb::Frame, Shape::Poly(poly)) = (verb, &shape) {
+            for pts in poly.polyPoints.windows(2) {
+                self.canvas.line(pts[0], pts[1], pen, &ink);
+            }
+        } else if let Verb::Frame = verb {
+            self.canvas.fill(bounds, &ink, |h, v| shape.on_frame(pen, h, v));
+        } else {
+            self.canvas.fill(bounds, &ink, |h, v| shape.contains(h, v));
+        }
+    }
+
+    // Pixel patterns, only the black and white fallback pattern is used
+    fn pix_pat(&mut self, rdr: &mut SerialReadStorage, opcode: u16) -> Result<()> {
+        let pat_type = rdr.read_u16()?;
+        let pattern = Pattern::read(rdr)?;
+        if pat_type == 1 {
+            let mut pixels = self.read_pix_map(rdr, false)?;
+            pixels.read_rows(rdr, true)?;
+        } else if pat_type == 2 {
+            rdr.pad(6)?; // RGB color
+        }
+        match opcode {
+            0x0012 => self.bk_pat = pattern,
+            0x0013 => self.pen_pat = pattern,
+            _ => self.fill_pat = pattern
+        }
+        Ok(())
+    }
+
+    // PixMap without the base address, followed by the color table unless
+    // it is direct pixels
+    fn read_pix_map(&self, rdr: &mut SerialReadStorage, direct: bool) -> Result<Pixels> {
+        let mut data = vec![0u8; 4];
+        data.extend(rdr.read_bytes(46)?);
+        let pix_map = PixMap::read(&mut SerialReadStorage::from(data))?;
+        let depth = match pix_map.pixelSize {
+            1 | 2 | 4 | 8 | 16 | 32 => pix_map.pixelSize as usize,
+            size => return Err(Error::invalid_value(rdr.pos(), "PixMap", "pixelSize", "1, 2, 4, 8, 16 or 32", size))
+        };
+        let table = if direct || depth > 8 {
+            None
+        } else {
+            Some(ColorTable::read(rdr)?)
+        };
+        Ok(Pixels {
+            bounds: pix_map.bounds,
+            depth,
+            pack_type: pix_map.packType,
+            cmp_count: pix_map.cmpCount.max(0) as usize,
+            row_bytes: pix_map.row_bytes(),
+            table,
+            rows: vec![]
+        })
+    }
+
+    fn bits(&mut self, rdr: &mut SerialReadStorage, opcode: u16) -> Result<()> {
+        let direct = opcode == 0x009a || opcode == 0x009b;
+        let packed = opcode >= 0x0098;
+        let with_rgn = opcode & 1 != 0;
+        if direct {
+            rdr.pad(4)?; // Base address
+        }
+
+        let offset = rdr.pos();
+        let row_bytes = rdr.read_u16()?;
+        let mut pixels = if direct || row_bytes & 0x8000 != 0 {
+            rdr.seek(offset)?;
+            self.read_pix_map(rdr, direct)?
+        } else {
+            Pixels {
+                bounds: Rect::read(rdr)?,
+                depth: 1,
+                pack_type: 0,
+                cmp_count: 1,
+                row_bytes: row_bytes as usize,
+                table: None,
+                rows: vec![]
+            }
+        };
+        let src = Rect::read(rdr)?;
+        let dst = self.rect(rdr)?;
+        let mode = simple_mode(rdr.read_u16()?, SRC_COPY);
+        let mask = if with_rgn { Some(Region::read(rdr)?) } else { None };
+        pixels.read_rows(rdr, packed)?;
+
+        if src.is_empty() || dst.is_empty() {
+            return Ok(());
+        }
+        let area = match dst.intersection(&self.canvas.bounds()) {
+            Some(area) => area,
+            None => return Ok(())
+        };
+        let ink = self.ink(Pattern::BLACK, mode);
+        for v in area.top as i32..area.bottom as i32 {
+            for h in area.left as i32..area.right as i32 {
+                if let Some(mask) = &mask {
+                    if !mask.contains(h, v) {
+                        continue;
+                    }
+                }
+                // Nearest pixel when scaling, which overflows i32 for rects
+                // spanning the whole coordinate range
+                let sh = src.left as i64 + (h - dst.left as i32) as i64 * src.width() as i64 / dst.width() as i64;
+                let sv = src.top as i64 + (v - dst.top as i32) as i64 * src.height() as i64 / dst.height() as i64;
+                let x = sh - pixels.bounds.left as i64;
+                let y = sv - pixels.bounds.top as i64;
+                if x < 0 || y < 0 || x >= pixels.bounds.width() as i64 || y >= pixels.bounds.height() as i64 {
+                    continue;
+                }
+                match pixels.sample(x as usize, y as usize) {
+                    Sample::Bit(set) => self.canvas.plot(h, v, set, &ink),
+                    Sample::Color(color) => self.canvas.plot_color(h, v, color, &ink)
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Picture;
+    use crate::serialization::{SerialRead, SerialReadStorage};
+    use crate::tools::packbits;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 0xff];
+    const WHITE: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+    fn picture(version: u8, ops: &[u8]) -> Picture {
+        let mut data = vec![0, 0, 0, 0, 0, 0, 0, 16, 0, 16];
+        if version == 2 {
+            data.extend_from_slice(&[0x00, 0x11, 0x02, 0xff, 0x0c, 0x00]);
+            data.extend_from_slice(&[0; 24]);
+        } else {
+            data.extend_from_slice(&[0x11, 0x01]);
+        }
+        data.extend_from_slice(ops);
+        Picture::read(&mut SerialReadStorage::from(data)).unwrap()
+    }
+
+    #[test]
+    fn shapes_v1() {
+        let pict = picture(1, &[
+            0x50, 0, 0, 0, 8, 0, 8, 0, 16, // frameOval (0,8)-(8,16)
+            0x31, 0, 2, 0, 2, 0, 4, 0, 4,  // paintRect (2,2)-(4,4)
+            0x22, 0, 15, 0, 0, 15, 0,      // shortLine (0,15) 15 right
+            0x3b,                          // invertSameRect
+            0xff
+        ]);
+        assert_eq!(pict.version(), 1);
+        let image = pict.render().unwrap();
+        assert_eq!(image.width(), 16);
+        assert_eq!(image.get(2, 2), Some(WHITE));
+        assert_eq!(image.get(1, 1), Some(WHITE));
+        assert_eq!(image.get(12, 0), Some(BLACK));
+        assert_eq!(image.get(12, 4), Some(WHITE));
+        assert_eq!(image.get(8, 4), Some(BLACK));
+        assert_eq!(image.get(15, 15), Some(BLACK));
+        assert_eq!(image.get(15, 14), Some(WHITE));
+    }
+
+    #[test]
+    fn large_pen() {
+        let pict = picture(1, &[
+            0x07, 0x7f, 0xff, 0x7f, 0xff,  // pnSize 32767x32767
+            0x20, 0, 0, 0, 0, 0, 8, 0, 8,  // line (0,0) to (8,8)
+            0x40, 0, 0, 0, 0, 0, 16, 0, 16, // frameRRect
+            0xff
+        ]);
+        let image = pict.render().unwrap();
+        assert_eq!(image.get(0, 0), Some(BLACK));
+        assert_eq!(image.get(15, 15), Some(BLACK));
+    }
+
+    #[test]
+    fn bits_full_range() {
+        let pict = picture(1, &[
+            0x90, 0, 2, 0, 0, 0, 0, 0, 1, 0, 16,       // BitsRect, 16x1 pixels
+            0, 0, 0x80, 0, 0, 1, 0x7f, 0xff,           // srcRect
+            0, 0, 0x80, 0, 0, 16, 0x7f, 0xff,          // dstRect
+            0, 0,                                      // srcCopy
+            0xaa, 0xaa,
+            0xff
+        ]);
+        let image = pict.render().unwrap();
+        assert_eq!(image.get(0, 0), Some(BLACK));
+        assert_eq!(image.get(1, 0), Some(WHITE));
+        assert_eq!(image.get(14, 15), Some(BLACK));
+        assert_eq!(image.get(15, 15), Some(WHITE));
+    }
+
+    #[test]
+    fn bits_v2() {
+        let mut ops = vec![];
+        // PackBitsRect, 16x2 pixels at 8 bits with a two color table
+        ops.extend_from_slice(&[0x00, 0x98, 0x80, 0x10, 0, 0, 0, 0, 0, 2, 0, 16]);
+        ops.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0x48, 0, 0, 0, 0x48, 0, 0]);
+        ops.extend_from_slice(&[0, 0, 0, 8, 0, 1, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        ops.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        ops.extend_from_slice(&[0, 0, 0xff, 0xff, 0, 0, 0, 0]);
+        ops.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0xff, 0xff]);
+        ops.extend_from_slice(&[0, 0, 0, 0, 0, 2, 0, 16]);  // srcRect
+        ops.extend_from_slice(&[0, 4, 0, 0, 0, 8, 0, 16]);  // dstRect, scaled vertically
+        ops.extend_from_slice(&[0, 0]);                     // srcCopy
+        for row in [[0u8; 16], [1u8; 16]].iter() {
+            let packed = packbits::pack(&row[..]);
+            ops.push(packed.len() as u8);
+            ops.extend_from_slice(&packed);
+        }
+        if ops.len() % 2 != 0 {
+            ops.push(0);
+        }
+        // DirectBitsRect, 2x1 pixels at 32 bits, packed per component
+        ops.extend_from_slice(&[0x00, 0x9a, 0, 0, 0, 0xff, 0x80, 0x08, 0, 0, 0, 0, 0, 1, 0, 2]);
+        ops.extend_from_slice(&[0, 0, 0, 4, 0, 0, 0, 0, 0, 0x48, 0, 0, 0, 0x48, 0, 0]);
+        ops.extend_from_slice(&[0, 16, 0, 32, 0, 3, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        ops.extend_from_slice(&[0, 0, 0, 0, 0, 1, 0, 2]);
+        ops.extend_from_slice(&[0, 12, 0, 0, 0, 13, 0, 2]);
+        ops.extend_from_slice(&[0, 0]);
+        ops.extend_from_slice(&[7, 5, 0x10, 0x20, 0x30, 0x40, 0x50, 0x60]);
+        ops.extend_from_slice(&[0x00, 0xff]);
+
+        let pict = picture(2, &ops);
+        assert_eq!(pict.version(), 2);
+        let image = pict.render().unwrap();
+        assert_eq!(image.get(0, 3), Some(WHITE));
+        assert_eq!(image.get(0, 4), Some([0xff, 0, 0, 0xff]));
+        assert_eq!(image.get(15, 5), Some([0xff, 0, 0, 0xff]));
+        assert_eq!(image.get(15, 6), Some([0, 0, 0xff, 0xff]));
+        assert_eq!(image.get(0, 12), Some([0x10, 0x30, 0x50, 0xff]));
+        assert_eq!(image.get(1, 12), Some([0x20, 0x40, 0x60, 0xff]));
+        assert_eq!(image.get(2, 12), Some(WHITE));
+    }
+
+    #[test]
+    fn bits_default_packing() {
+        // DirectBitsRect, 4x1 pixels at 16 bits with pack type 0, which is
+        // run length encoded by words
+        let mut ops = vec![];
+        ops.extend_from_slice(&[0x00, 0x9a, 0, 0, 0, 0xff, 0x80, 0x08, 0, 0, 0, 0, 0, 1, 0, 4]);
+        ops.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0x48, 0, 0, 0, 0x48, 0, 0]);
+        ops.extend_from_slice(&[0, 16, 0, 16, 0, 3, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        ops.extend_from_slice(&[0, 0, 0, 0, 0, 1, 0, 4]);
+        ops.extend_from_slice(&[0, 0, 0, 0, 0, 1, 0, 4]);
+        ops.extend_from_slice(&[0, 0]);
+        ops.extend_from_slice(&[6, 0xff, 0x7c, 0x00, 0xff, 0x00, 0x1f]);
+        ops.extend_from_slice(&[0, 0x00, 0xff]);
+
+        let image = picture(2, &ops).render().unwrap();
+        assert_eq!(image.get(0, 0), Some([0xff, 0, 0, 0xff]));
+        assert_eq!(image.get(1, 0), Some([0xff, 0, 0, 0xff]));
+        assert_eq!(image.get(2, 0), Some([0, 0, 0xff, 0xff]));
+        assert_eq!(image.get(3, 0), Some([0, 0, 0xff, 0xff]));
+        assert_eq!(image.get(4, 0), Some(WHITE));
+    }
+}
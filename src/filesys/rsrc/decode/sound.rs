@@ -0,0 +1,358 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::{Error, Result};
+use crate::tools::{
+    Pcm,
+    mace
+};
+use crate::types::OSType;
+
+use super::TypedRsrc;
+
+// Sound commands
+pub const NULL_CMD: u16 = 0;
+pub const SOUND_CMD: u16 = 80;
+pub const BUFFER_CMD: u16 = 81;
+
+// Set in the command when param2 is an offset in the resource
+const DATA_OFFSET_FLAG: u16 = 0x8000;
+
+// Sound header encodings
+const STANDARD_HEADER: u8 = 0x00;
+const EXTENDED_HEADER: u8 = 0xff;
+const COMPRESSED_HEADER: u8 = 0xfe;
+
+// Compression IDs of compressed headers without a format
+const THREE_TO_ONE: i16 = 3;
+const SIX_TO_ONE: i16 = 4;
+
+// Far more than the Sound Manager plays, larger counts are corrupt
+const MAX_CHANNELS: u32 = 32;
+
+// Synthesizer used by format 1 sounds
+pub const SAMPLED_SYNTH: i16 = 5;
+
+#[derive(SerialRead)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct SoundCommand {
+    pub cmd: u16,
+    pub param1: i16,
+    pub param2: i32
+}
+
+impl SoundCommand {
+    // Command without the data offset flag
+    pub fn command(&self) -> u16 {
+        self.cmd & !DATA_OFFSET_FLAG
+    }
+
+    pub fn has_data_offset(&self) -> bool {
+        self.cmd & DATA_OFFSET_FLAG != 0
+    }
+}
+
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct SynthReference {
+    pub synth_id: i16,
+    pub init_option: i32
+}
+
+// Sampled sound, from a standard, extended or compressed sound header. The
+// format is 'raw ' for offset binary samples, 'twos' for two's complement,
+// or the compression format such as 'MAC3'.
+pub struct SoundHeader {
+    pub channels: u16,
+    pub sample_rate: u32,   // UnsignedFixed
+    pub loop_start: u32,
+    pub loop_end: u32,
+    pub encoding: u8,
+    pub base_note: u8,
+    pub frames: u32,
+    pub sample_size: u16,
+    pub format: OSType,
+    data: Vec<u8>
+}
+
+fn uncompressed_format(sample_size: u16) -> OSType {
+    if sample_size <= 8 {
+        OSType(*b"raw ")
+    } else {
+        OSType(*b"twos")
+    }
+}
+
+impl SoundHeader {
+    pub fn sample_rate_hz(&self) -> f64 {
+        self.sample_rate as f64 / 65536.0
+    }
+
+    // Sample data as stored in the resource
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    // Bytes of sample data for all channels in a frame, which is a packet
+    // for compressed formats
+    fn frame_len(&self) -> Option<u64> {
+        let channels = self.channels as u64;
+        match &self.format.0 {
+            b"raw " | b"twos" => Some(channels * (self.sample_size as u64).div_ceil(8)),
+            b"MAC3" => Some(channels * 2),
+            b"MAC6" => Some(channels),
+            _ => None
+        }
+    }
+
+    pub fn to_pcm(&self) -> Result<Pcm> {
+        let wide = self.sample_size > 8;
+        let samples = match &self.format.0 {
+            b"raw " if wide => self.data.chunks_exact(2).map(|s| (u16::from_be_bytes([s[0], s[1]]) ^ 0x8000) as i16).collect(),
+            b"raw " => self.data.iter().map(|s| ((*s ^ 0x80) as i8 as i16) << 8).collect(),
+            b"twos" if wide => self.data.chunks_exact(2).map(|s| i16::from_be_bytes([s[0], s[1]])).collect(),
+            b"twos" => self.data.iter().map(|s| (*s as i8 as i16) << 8).collect(),
+            b"MAC3" => mace::decode_mace3(&self.data, self.channels as usize),
+            b"MAC6" => mace::decode_mace6(&self.data, self.channels as usize),
+            _ => return Err(Error::invalid_value(0, "SoundHeader", "format", "raw, twos, MAC3 or MAC6", format!("{}", self.format)))
+        };
+        let sample_size = if wide { 16 } else { 8 };
+        Ok(Pcm::new(self.channels, self.sample_rate_hz().round() as u32, sample_size, samples))
+    }
+}
+
+impl SerialRead for SoundHeader {
+    fn read(rdr: &mut SerialReadStorage) -> Result<SoundHeader> {
+        let start = rdr.pos();
+        rdr.pad(4)?; // samplePtr, the samples follow the header in resources
+        let length_or_channels = rdr.read_u32()?;
+        let sample_rate = rdr.read_u32()?;
+        let loop_start = rdr.read_u32()?;
+        let loop_end = rdr.read_u32()?;
+        let encoding = rdr.read_u8()?;
+        let base_note = rdr.read_u8()?;
+        if encoding != STANDARD_HEADER && length_or_channels > MAX_CHANNELS {
+            return Err(Error::invalid_value(start + 4, "SoundHeader", "numChannels", format!("at most {} channels", MAX_CHANNELS), length_or_channels));
+        }
+        let mut header = SoundHeader {
+            channels: 1,
+            sample_rate,
+            loop_start,
+            loop_end,
+            encoding,
+            base_note,
+            frames: length_or_channels,
+            sample_size: 8,
+            format: uncompressed_format(8),
+            data: vec![]
+        };
+        match encoding {
+            STANDARD_HEADER => (),
+            EXTENDED_HEADER => {
+                header.channels = length_or_channels as u16;
+                header.frames = rdr.read_u32()?;
+                rdr.pad(22)?; // AIFFSampleRate, markerChunk, instrumentChunks, AESRecording
+                header.sample_size = rdr.read_u16()?;
+                header.format = uncompressed_format(header.sample_size);
+                rdr.pad(14)?;
+            },
+            COMPRESSED_HEADER => {
+                header.channels = length_or_channels as u16;
+                header.frames = rdr.read_u32()?;
+                rdr.pad(14)?; // AIFFSampleRate, markerChunk
+                let format = OSType::read(rdr)?;
+                rdr.pad(12)?; // futureUse2, stateVars, leftOverSamples
+                let compression_id = rdr.read_i16()?;
+                rdr.pad(4)?; // packetSize, snthID
+                header.sample_size = rdr.read_u16()?;
+                header.format = match (format.0, compression_id) {
+                    ([0, 0, 0, 0], THREE_TO_ONE) => OSType(*b"MAC3"),
+                    ([0, 0, 0, 0], SIX_TO_ONE) => OSType(*b"MAC6"),
+                    ([0, 0, 0, 0], _) => uncompressed_format(header.sample_size),
+                    _ => format
+                };
+            },
+            encoding => return Err(Error::invalid_value(start + 20, "SoundHeader", "encode", "0x00, 0xfe or 0xff", format!("{:#04x}", encoding)))
+        }
+        if header.channels == 0 {
+            return Err(Error::invalid_value(start + 4, "SoundHeader", "numChannels", "at least one channel", "0"));
+        }
+        // Some resources claim more frames than are stored, only the data
+        // present is used
+        let remaining = rdr.size() - rdr.pos();
+        let len = match header.frame_len() {
+            Some(frame_len) => (header.frames as u64 * frame_len).min(remaining),
+            None => remaining
+        };
+        header.data = rdr.read_bytes(len)?;
+        Ok(header)
+    }
+}
+
+impl std::fmt::Debug for SoundHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SoundHeader")
+            .field("channels", &self.channels)
+            .field("sample_rate", &self.sample_rate_hz())
+            .field("loop_start", &self.loop_start)
+            .field("loop_end", &self.loop_end)
+            .field("encoding", &self.encoding)
+            .field("base_note", &self.base_note)
+            .field("frames", &self.frames)
+            .field("sample_size", &self.sample_size)
+            .field("format", &self.format)
+            .field("data", &format_args!("{} bytes", self.data.len()))
+            .finish()
+    }
+}
+
+// 'snd ', a sequence of sound commands. Format 1 resources list the
+// synthesizers to use, format 2 resources are always sampled sound. The
+// sampled sound is the header referenced by the first sound or buffer
+// command.
+#[derive(Debug)]
+pub struct Sound {
+    pub format: i16,
+    pub synths: Vec<SynthReference>,
+    pub ref_count: i16,
+    pub commands: Vec<SoundCommand>,
+    pub header: Option<SoundHeader>
+}
+
+impl Sound {
+    pub fn to_pcm(&self) -> Result<Pcm> {
+        match &self.header {
+            Some(header) => header.to_pcm(),
+            None => Err(Error::NotFound)
+        }
+    }
+}
+
+impl SerialRead for Sound {
+    fn read(rdr: &mut SerialReadStorage) -> Result<Sound> {
+        let start = rdr.pos();
+        let format = rdr.read_i16()?;
+        let (synths, ref_count) = match format {
+            1 => {
+                let count = rdr.read_i16()?;
+                let mut synths = vec![];
+                for _ in 0..count {
+                    synths.push(SynthReference::read(rdr)?);
+                }
+                (synths, 0)
+            },
+            2 => (vec![], rdr.read_i16()?),
+            format => return Err(Error::invalid_value(start, "Sound", "format", "1 or 2", format.to_string()))
+        };
+        let count = rdr.read_i16()?;
+        let mut commands = vec![];
+        for _ in 0..count {
+            commands.push(SoundCommand::read(rdr)?);
+        }
+        let sampled = commands.iter().find(|cmd| {
+            cmd.has_data_offset() && (cmd.command() == SOUND_CMD || cmd.command() == BUFFER_CMD)
+        });
+        let header = match sampled {
+            Some(cmd) => {
+                let offset = start + cmd.param2 as u32 as u64;
+                rdr.seek(offset)?;
+                Some(SoundHeader::read(rdr).map_err(|err| err.in_field("Sound", "header", offset))?)
+            },
+            None => None
+        };
+        Ok(Sound {
+            format,
+            synths,
+            ref_count,
+            commands,
+            header
+        })
+    }
+}
+
+impl TypedRsrc for Sound {
+    const RSRC_TYPE: OSType = OSType(*b"snd ");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sound(encoding: u8, fields: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut snd = vec![
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x05, 0x00, 0x00, 0x00, 0x80,
+            0x00, 0x01, 0x80, 0x51, 0x00, 0x00, 0x00, 0x00, 0x00, 0x14
+        ];
+        snd.extend_from_slice(&[0, 0, 0, 0]);
+        snd.extend_from_slice(&fields[0..4]);
+        snd.extend_from_slice(&[0x56, 0xee, 0x8b, 0xa3, 0, 0, 0, 0, 0, 0, 0, 0, encoding, 0x3c]);
+        snd.extend_from_slice(&fields[4..]);
+        snd.extend_from_slice(data);
+        snd
+    }
+
+    #[test]
+    fn standard() {
+        let data = sound(0x00, &[0, 0, 0, 4], &[0x80, 0x00, 0xff, 0x7f, 0x99]);
+        let snd = Sound::read(&mut SerialReadStorage::from(data)).unwrap();
+        assert_eq!(snd.format, 1);
+        assert_eq!(snd.synths[0].synth_id, SAMPLED_SYNTH);
+        assert_eq!(snd.commands[0].command(), BUFFER_CMD);
+        let header = snd.header.as_ref().unwrap();
+        assert_eq!(header.base_note, 60);
+        assert_eq!(header.data(), &[0x80, 0x00, 0xff, 0x7f]);
+        let pcm = snd.to_pcm().unwrap();
+        assert_eq!(pcm.sample_rate, 22255);
+        assert_eq!(pcm.sample_size, 8);
+        assert_eq!(pcm.samples, vec![0, -0x8000, 0x7f00, -0x100]);
+    }
+
+    #[test]
+    fn extended() {
+        let mut fields = vec![0, 0, 0, 2, 0, 0, 0, 2];
+        fields.extend_from_slice(&[0; 22]);
+        fields.extend_from_slice(&[0, 16]);
+        fields.extend_from_slice(&[0; 14]);
+        let data = sound(0xff, &fields, &[0x00, 0x01, 0xff, 0xfe, 0x80, 0x00, 0x7f, 0xff]);
+        let pcm = Sound::read(&mut SerialReadStorage::from(data)).unwrap().to_pcm().unwrap();
+        assert_eq!(pcm.channels, 2);
+        assert_eq!(pcm.frames(), 2);
+        assert_eq!(pcm.samples, vec![1, -2, -0x8000, 0x7fff]);
+    }
+
+    #[test]
+    fn compressed() {
+        let mut fields = vec![0, 0, 0, 1, 0, 0, 0, 3];
+        fields.extend_from_slice(&[0; 14]);
+        fields.extend_from_slice(&[0; 16]);
+        fields.extend_from_slice(&[0, 3, 0, 0, 0, 0, 0, 8]);
+        let data = sound(0xfe, &fields, &[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde]);
+        let snd = Sound::read(&mut SerialReadStorage::from(data)).unwrap();
+        let header = snd.header.as_ref().unwrap();
+        assert_eq!(header.format, OSType(*b"MAC3"));
+        assert_eq!(header.data().len(), 6);
+        assert_eq!(snd.to_pcm().unwrap().samples, mace::decode_mace3(&[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc], 1));
+
+        // Formats other than MACE are read, but not decoded
+        let mut fields = vec![0, 0, 0, 1, 0, 0, 0, 1];
+        fields.extend_from_slice(&[0; 14]);
+        fields.extend_from_slice(b"ima4");
+        fields.extend_from_slice(&[0; 12]);
+        fields.extend_from_slice(&[0xff, 0xfe, 0, 0, 0, 0, 0, 16]);
+        let data = sound(0xfe, &fields, &[0; 34]);
+        let snd = Sound::read(&mut SerialReadStorage::from(data)).unwrap();
+        assert_eq!(snd.header.as_ref().unwrap().data().len(), 34);
+        assert!(snd.to_pcm().is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Sound::read(&mut SerialReadStorage::from(vec![0, 3, 0, 0])).is_err());
+        let data = sound(0x42, &[0, 0, 0, 4], &[0; 4]);
+        assert!(Sound::read(&mut SerialReadStorage::from(data)).is_err());
+        let mut fields = vec![0, 0, 0x80, 0, 0, 0, 0, 2];
+        fields.extend_from_slice(&[0; 40]);
+        let data = sound(0xff, &fields, &[0; 4]);
+        assert!(Sound::read(&mut SerialReadStorage::from(data)).is_err());
+    }
+}
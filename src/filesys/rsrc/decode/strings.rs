@@ -0,0 +1,58 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::Result;
+use crate::types::{
+    Encoding,
+    OSType,
+    PString
+};
+
+use super::TypedRsrc;
+
+// 'STR ', a single Pascal string
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct Str (pub PString);
+
+impl TypedRsrc for Str {
+    const RSRC_TYPE: OSType = OSType(*b"STR ");
+}
+
+// 'STR#', a list of strings, as read by GetIndString
+#[derive(SerialRead)]
+#[derive(Debug)]
+pub struct StrList {
+    #[count_prefix(u16)]
+    pub strings: Vec<PString>
+}
+
+impl StrList {
+    // GetIndString indexes from 1
+    pub fn get_ind(&self, index: usize) -> Option<&PString> {
+        self.strings.get(index.checked_sub(1)?)
+    }
+}
+
+impl TypedRsrc for StrList {
+    const RSRC_TYPE: OSType = OSType(*b"STR#");
+}
+
+// 'TEXT', unformatted text as used by TextEdit, with CR line endings
+#[derive(Debug)]
+pub struct Text (pub Vec<u8>);
+
+impl Text {
+    pub fn decode(&self, encoding: Encoding) -> String {
+        encoding.decode(&self.0).replace('\r', "\n")
+    }
+}
+
+impl SerialRead for Text {
+    fn read(rdr: &mut SerialReadStorage) -> Result<Text> {
+        let len = rdr.size() - rdr.pos();
+        Ok(Text(rdr.read_bytes(len)?))
+    }
+}
+
+impl TypedRsrc for Text {
+    const RSRC_TYPE: OSType = OSType(*b"TEXT");
+}
@@ -0,0 +1,570 @@
+use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::{Error, Result};
+use crate::tools::Json;
+use crate::types::{
+    DateTime,
+    Encoding,
+    Fixed,
+    OSType,
+    Point,
+    PString,
+    Rect,
+    RGBColor
+};
+
+use super::super::Rsrc;
+use super::TypedRsrc;
+
+// Field of a 'TMPL', the label shown by ResEdit and the field type
+#[derive(SerialRead)]
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct TemplateItem {
+    pub label: PString,
+    pub kind: OSType
+}
+
+// Decoded value of a template field. Text is kept as the Mac Roman bytes.
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Debug)]
+pub enum Value {
+    Integer(i64),
+    Hex { value: u64, size: usize },
+    Boolean(bool),
+    Char(u8),
+    Type(OSType),
+    Fixed(Fixed),
+    Date(DateTime),
+    Text(Vec<u8>),
+    Bytes(Vec<u8>),
+    Point(Point),
+    Rect(Rect),
+    Color(RGBColor),
+    List(Vec<Vec<Field>>)
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(val) => write!(f, "{}", val),
+            Value::Hex { value, size } => write!(f, "${:01$X}", value, size * 2),
+            Value::Boolean(val) => write!(f, "{}", val),
+            Value::Char(val) => write!(f, "{:?}", PString::from(&[*val][..])),
+            Value::Type(val) => write!(f, "{}", val),
+            Value::Fixed(val) => write!(f, "{}", val),
+            Value::Date(val) => write!(f, "{}", val),
+            Value::Text(val) => write!(f, "{:?}", PString::from(&val[..])),
+            Value::Bytes(val) => {
+                write!(f, "$")?;
+                for byte in val {
+                    write!(f, "{:02X}", byte)?;
+                }
+                Ok(())
+            },
+            Value::Point(val) => write!(f, "({}, {})", val.v, val.h),
+            Value::Rect(val) => write!(f, "({}, {}, {}, {})", val.top, val.left, val.bottom, val.right),
+            Value::Color(val) => write!(f, "({}, {}, {})", val.red, val.green, val.blue),
+            Value::List(val) => write!(f, "{} entries", val.len())
+        }
+    }
+}
+
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Field {
+    pub label: PString,
+    pub value: Value
+}
+
+// Field tree as indented text, one field per line
+pub fn format_fields(fields: &[Field]) -> String {
+    let mut out = String::new();
+    write_fields(&mut out, fields, 0);
+    out
+}
+
+fn write_fields(out: &mut String, fields: &[Field], indent: usize) {
+    for field in fields {
+        out.push_str(&format!("{:indent$}{}: {}\n", "", field.label, field.value, indent = indent));
+        if let Value::List(entries) = &field.value {
+            for (idx, entry) in entries.iter().enumerate() {
+                out.push_str(&format!("{:indent$}{}) *****\n", "", idx + 1, indent = indent + 2));
+                write_fields(out, entry, indent + 4);
+            }
+        }
+    }
+}
+
+fn value_json(value: &Value) -> Json {
+    match value {
+        Value::Integer(val) => Json::Number(*val),
+        Value::Boolean(val) => Json::Bool(*val),
+        Value::Char(val) => Json::String(Encoding::MacRoman.decode(&[*val])),
+        Value::Text(val) => Json::String(Encoding::MacRoman.decode(val)),
+        Value::Point(val) => Json::object().with("v", val.v).with("h", val.h),
+        Value::Rect(val) => Json::object()
+            .with("top", val.top)
+            .with("left", val.left)
+            .with("bottom", val.bottom)
+            .with("right", val.right),
+        Value::Color(val) => Json::object()
+            .with("red", val.red)
+            .with("green", val.green)
+            .with("blue", val.blue),
+        Value::List(entries) => Json::Array(entries.iter().map(|entry| fields_json(entry)).collect()),
+        value => Json::String(value.to_string())
+    }
+}
+
+// Field tree as a JSON object keyed by the labels
+pub fn fields_json(fields: &[Field]) -> Json {
+    Json::Object(fields.iter()
+        .map(|field| (field.label.to_string(), value_json(&field.value)))
+        .collect())
+}
+
+// 'TMPL', a ResEdit template describing the layout of a resource type. The
+// template for a type is named after the type.
+#[derive(Debug)]
+pub struct Template {
+    pub items: Vec<TemplateItem>
+}
+
+impl SerialRead for Template {
+    fn read(rdr: &mut SerialReadStorage) -> Result<Template> {
+        let mut items = vec![];
+        while rdr.pos() < rdr.size() {
+            items.push(TemplateItem::read(rdr)?);
+        }
+        Ok(Template { items })
+    }
+}
+
+impl TypedRsrc for Template {
+    const RSRC_TYPE: OSType = OSType(*b"TMPL");
+}
+
+impl Template {
+    // Template for a resource type in a resource fork, such as the
+    // application itself or ResEdit
+    pub fn find(rsrc: &Rsrc, rsrc_type: &OSType) -> Result<Option<Template>> {
+        let info = rsrc.resources(&Template::RSRC_TYPE)
+            .find(|info| info.name().is_some_and(|name| name.as_bytes() == rsrc_type.0));
+        match info {
+            Some(info) => Ok(Some(Template::read(&mut info.open()?)?)),
+            None => Ok(None)
+        }
+    }
+
+    pub fn decode(&self, rdr: &mut SerialReadStorage) -> Result<Vec<Field>> {
+        let mut decoder = Decoder {
+            rdr,
+            bits: 0,
+            bits_left: 0
+        };
+        decoder.items(&self.items)
+    }
+}
+
+fn unknown_kind(kind: &OSType) -> Error {
+    Error::invalid_value(0, "TemplateItem", "kind", "known field type", format!("{}", kind))
+}
+
+// Size given by three hex digits in the type, such as 'H00A'
+fn hex_size(kind: &OSType) -> Option<u64> {
+    let digits = std::str::from_utf8(&kind.0[1..]).ok()?;
+    u64::from_str_radix(digits, 16).ok()
+}
+
+// Matching end of a list or keyed section starting at start
+fn block_end(items: &[TemplateItem], start: usize, open: &[&[u8; 4]], close: &[u8; 4]) -> Result<usize> {
+    let mut depth = 0;
+    for (idx, item) in items.iter().enumerate().skip(start) {
+        if open.contains(&&item.kind.0) {
+            depth += 1;
+        } else if &item.kind.0 == close {
+            depth -= 1;
+            if depth == 0 {
+                return Ok(idx);
+            }
+        }
+    }
+    Err(Error::invalid_value(0, "Template", "items", format!("{}", OSType(*close)), "end of template"))
+}
+
+// Value of a 'CASE', the part of the label after '='. Numbers are decimal
+// or hex prefixed by '$', types may be quoted.
+fn case_matches(case: &PString, value: &Value) -> bool {
+    let label = case.as_bytes();
+    let text = match label.iter().rposition(|c| *c == b'=') {
+        Some(pos) => &label[pos + 1..],
+        None => label
+    };
+    let number = std::str::from_utf8(text).ok().and_then(|text| {
+        let text = text.trim();
+        if let Some(hex) = text.strip_prefix('$') {
+            i64::from_str_radix(hex, 16).ok()
+        } else if let Some(hex) = text.strip_prefix("0x") {
+            i64::from_str_radix(hex, 16).ok()
+        } else {
+            text.parse::<i64>().ok()
+        }
+    });
+    let unquoted = text.iter().cloned().filter(|c| *c != b'\'').collect::<Vec<u8>>();
+    match value {
+        Value::Integer(val) => number == Some(*val),
+        Value::Hex { value, .. } => number == Some(*value as i64),
+        Value::Char(val) => unquoted == [*val],
+        Value::Type(val) => unquoted == val.0,
+        _ => false
+    }
+}
+
+struct Decoder<'a> {
+    rdr: &'a mut SerialReadStorage,
+    bits: u32,
+    bits_left: u32
+}
+
+impl<'a> Decoder<'a> {
+    fn at_end(&self) -> bool {
+        self.rdr.pos() >= self.rdr.size()
+    }
+
+    fn items(&mut self, items: &[TemplateItem]) -> Result<Vec<Field>> {
+        let mut fields = vec![];
+        let mut count = None;
+        let mut idx = 0;
+        while idx < items.len() {
+            let item = &items[idx];
+            match &item.kind.0 {
+                b"LSTB" | b"LSTC" | b"LSTZ" => {
+                    let end = block_end(items, idx, &[b"LSTB", b"LSTC", b"LSTZ"], b"LSTE")?;
+                    let body = &items[idx + 1..end];
+                    let mut entries = vec![];
+                    match &item.kind.0 {
+                        b"LSTC" => {
+                            let count = count.take().ok_or_else(|| {
+                                Error::invalid_value(self.rdr.pos(), "Template", "LSTC", "preceding count", "none")
+                            })?;
+                            for _ in 0..count {
+                                let pos = self.rdr.pos();
+                                entries.push(self.items(body)?);
+                                // Entries without data would repeat forever
+                                if self.rdr.pos() == pos {
+                                    break;
+                                }
+                            }
+                        },
+                        b"LSTZ" => {
+                            while self.rdr.read_u8()? != 0 {
+                                let pos = self.rdr.pad(-1)?.pos();
+                                entries.push(self.items(body)?);
+                                if self.rdr.pos() == pos {
+                                    break;
+                                }
+                            }
+                        },
+                        _ => {
+                            while !self.at_end() {
+                                let pos = self.rdr.pos();
+                                entries.push(self.items(body)?);
+                                if self.rdr.pos() == pos {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    fields.push(Field { label: item.label.clone(), value: Value::List(entries) });
+                    idx = end;
+                },
+                b"LSTE" | b"KEYE" => {
+                    return Err(Error::invalid_value(self.rdr.pos(), "Template", "items", "start of section", format!("{}", item.kind)));
+                },
+                [b'K', _, _, _] if &item.kind.0 != b"KEYB" => {
+                    idx = self.keyed(items, idx, &mut fields)?;
+                },
+                b"OCNT" | b"ZCNT" | b"BCNT" | b"LCNT" | b"LZCT" | b"FCNT" => {
+                    let val = match &item.kind.0 {
+                        b"OCNT" => self.rdr.read_u16()? as i64,
+                        b"ZCNT" => self.rdr.read_i16()? as i64 + 1,
+                        b"BCNT" => self.rdr.read_u8()? as i64,
+                        b"LCNT" => self.rdr.read_u32()? as i64,
+                        b"LZCT" => self.rdr.read_i32()? as i64 + 1,
+                        // Fixed count given in the label
+                        _ => {
+                            let digits: String = item.label.to_string().chars().take_while(|c| c.is_ascii_digit()).collect();
+                            digits.parse().unwrap_or(0)
+                        }
+                    };
+                    count = Some(val.max(0));
+                    fields.push(Field { label: item.label.clone(), value: Value::Integer(val) });
+                },
+                _ => {
+                    if let Some(value) = self.field(&item.kind)? {
+                        fields.push(Field { label: item.label.clone(), value });
+                    }
+                }
+            }
+            idx += 1;
+        }
+        Ok(fields)
+    }
+
+    // Key field followed by a 'CASE' for each value, and a 'KEYB'..'KEYE'
+    // section for each case. Only the section of the matching case is
+    // decoded. Returns the index of the last item of the sections.
+    fn keyed(&mut self, items: &[TemplateItem], idx: usize, fields: &mut Vec<Field>) -> Result<usize> {
+        let item = &items[idx];
+        let kind = match &item.kind.0 {
+            b"KBYT" => b"DBYT",
+            b"KWRD" | b"KRID" => b"DWRD",
+            b"KLNG" => b"DLNG",
+            b"KUBT" => b"UBYT",
+            b"KUWD" => b"UWRD",
+            b"KULG" => b"ULNG",
+            b"KHBT" => b"HBYT",
+            b"KHWD" => b"HWRD",
+            b"KHLG" => b"HLNG",
+            b"KCHR" => b"CHAR",
+            b"KTYP" => b"TNAM",
+            _ => return Err(unknown_kind(&item.kind))
+        };
+        let key = self.field(&OSType(*kind))?.ok_or_else(|| unknown_kind(&item.kind))?;
+        fields.push(Field { label: item.label.clone(), value: key.clone() });
+
+        let mut pos = idx + 1;
+        let mut cases = vec![];
+        while pos < items.len() && &items[pos].kind.0 == b"CASE" {
+            cases.push(&items[pos].label);
+            pos += 1;
+        }
+        let selected = cases.iter().position(|case| case_matches(case, &key));
+        let mut last = pos - 1;
+        let mut section = 0;
+        while pos < items.len() && &items[pos].kind.0 == b"KEYB" {
+            let end = block_end(items, pos, &[b"KEYB"], b"KEYE")?;
+            if selected == Some(section) {
+                fields.extend(self.items(&items[pos + 1..end])?);
+            }
+            section += 1;
+            last = end;
+            pos = end + 1;
+        }
+        if selected.is_none() && section > 0 {
+            return Err(Error::invalid_value(self.rdr.pos(), "Template", "CASE", "matching case", format!("{}", key)));
+        }
+        Ok(last)
+    }
+
+    fn bit(&mut self, width: u32) -> Result<bool> {
+        if self.bits_left == 0 {
+            self.bits = if width == 8 { self.rdr.read_u8()? as u32 } else { self.rdr.read_u16()? as u32 };
+            self.bits_left = width;
+        }
+        self.bits_left -= 1;
+        Ok((self.bits >> self.bits_left) & 1 != 0)
+    }
+
+    fn c_string(&mut self) -> Result<Vec<u8>> {
+        let mut text = vec![];
+        loop {
+            match self.rdr.read_u8()? {
+                0 => return Ok(text),
+                c => text.push(c)
+            }
+        }
+    }
+
+    // Value of a single field. Alignment and fill fields have no value.
+    fn field(&mut self, kind: &OSType) -> Result<Option<Value>> {
+        let kind_bytes = &kind.0;
+        match kind_bytes {
+            b"BBIT" => return Ok(Some(Value::Boolean(self.bit(8)?))),
+            b"WBIT" => return Ok(Some(Value::Boolean(self.bit(16)?))),
+            b"CSTR" | b"ECST" | b"OCST" => {
+                self.bits_left = 0;
+                let text = self.c_string()?;
+                // Padded so the text and terminator has an even or odd length
+                if (text.len() % 2 == 0) == (kind_bytes == b"ECST") && kind_bytes != b"CSTR" {
+                    self.rdr.pad(1)?;
+                }
+                return Ok(Some(Value::Text(text)));
+            },
+            _ => self.bits_left = 0
+        }
+        let rdr = &mut *self.rdr;
+        let value = match kind_bytes {
+            b"DBYT" => Value::Integer(rdr.read_i8()? as i64),
+            b"DWRD" | b"RSID" => Value::Integer(rdr.read_i16()? as i64),
+            b"DLNG" => Value::Integer(rdr.read_i32()? as i64),
+            b"UBYT" => Value::Integer(rdr.read_u8()? as i64),
+            b"UWRD" => Value::Integer(rdr.read_u16()? as i64),
+            b"ULNG" => Value::Integer(rdr.read_u32()? as i64),
+            b"HBYT" => Value::Hex { value: rdr.read_u8()? as u64, size: 1 },
+            b"HWRD" => Value::Hex { value: rdr.read_u16()? as u64, size: 2 },
+            b"HLNG" => Value::Hex { value: rdr.read_u32()? as u64, size: 4 },
+            b"CHAR" => Value::Char(rdr.read_u8()?),
+            b"TNAM" => Value::Type(OSType::read(rdr)?),
+            b"BOOL" | b"WFLG" => Value::Boolean(rdr.read_u16()? != 0),
+            b"BFLG" => Value::Boolean(rdr.read_u8()? != 0),
+            b"LFLG" => Value::Boolean(rdr.read_u32()? != 0),
+            b"PSTR" | b"BSTR" => Value::Text(Vec::from(PString::read(rdr)?.as_bytes())),
+            b"ESTR" | b"OSTR" => {
+                let text = PString::read(rdr)?;
+                // Padded so the length byte and text has an even or odd length
+                if (text.len() % 2 == 0) == (kind_bytes == b"ESTR") {
+                    rdr.pad(1)?;
+                }
+                Value::Text(Vec::from(text.as_bytes()))
+            },
+            b"WSTR" => {
+                let len = rdr.read_u16()?;
+                Value::Text(rdr.read_bytes(len as u64)?)
+            },
+            b"LSTR" => {
+                let len = rdr.read_u32()?;
+                Value::Text(rdr.read_bytes(len as u64)?)
+            },
+            b"HEXD" => {
+                let len = rdr.size() - rdr.pos();
+                Value::Bytes(rdr.read_bytes(len)?)
+            },
+            b"RECT" => Value::Rect(Rect::read(rdr)?),
+            b"PNT " => Value::Point(Point::read(rdr)?),
+            b"COLR" => Value::Color(RGBColor::read(rdr)?),
+            b"FIXD" => Value::Fixed(Fixed::read(rdr)?),
+            b"DATE" => Value::Date(DateTime::read(rdr)?),
+            b"AWRD" => { rdr.align(2)?; return Ok(None); },
+            b"ALNG" => { rdr.align(4)?; return Ok(None); },
+            b"AL08" => { rdr.align(8)?; return Ok(None); },
+            b"AL16" => { rdr.align(16)?; return Ok(None); },
+            b"FBYT" => { rdr.pad(1)?; return Ok(None); },
+            b"FWRD" => { rdr.pad(2)?; return Ok(None); },
+            b"FLNG" => { rdr.pad(4)?; return Ok(None); },
+            // Labels for values and section dividers
+            b"CASE" | b"DVDR" => return Ok(None),
+            [b'H', ..] if hex_size(kind).is_some() => {
+                let len = hex_size(kind).unwrap_or(0);
+                Value::Bytes(rdr.read_bytes(len)?)
+            },
+            [b'F', ..] if hex_size(kind).is_some() => {
+                rdr.pad(hex_size(kind).unwrap_or(0) as i64)?;
+                return Ok(None);
+            },
+            // Pascal or C string in a field of fixed size
+            [b'P', ..] | [b'C', ..] if hex_size(kind).is_some() => {
+                let data = rdr.read_bytes(hex_size(kind).unwrap_or(0))?;
+                let text = if kind_bytes[0] == b'P' {
+                    let len = data.first().map_or(0, |len| *len as usize);
+                    data.iter().skip(1).take(len).cloned().collect()
+                } else {
+                    data.iter().take_while(|c| **c != 0).cloned().collect()
+                };
+                Value::Text(text)
+            },
+            _ => return Err(unknown_kind(kind))
+        };
+        Ok(Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(items: &[(&str, &[u8; 4])]) -> Template {
+        Template {
+            items: items.iter().map(|(label, kind)| TemplateItem {
+                label: PString::from(*label),
+                kind: OSType(**kind)
+            }).collect()
+        }
+    }
+
+    fn decode(tmpl: &Template, data: &[u8]) -> Result<Vec<Field>> {
+        tmpl.decode(&mut SerialReadStorage::from(Vec::from(data)))
+    }
+
+    #[test]
+    fn read_template() {
+        let data = vec![0x02, b'I', b'D', b'D', b'W', b'R', b'D', 0x04, b'N', b'a', b'm', b'e', b'P', b'S', b'T', b'R'];
+        let tmpl = Template::read(&mut SerialReadStorage::from(data)).unwrap();
+        assert_eq!(tmpl.items.len(), 2);
+        assert_eq!(tmpl.items[1].kind, OSType(*b"PSTR"));
+    }
+
+    #[test]
+    fn scalars() {
+        let tmpl = template(&[
+            ("Word", b"DWRD"), ("Hex", b"HWRD"), ("Flags", b"BBIT"), ("", b"BBIT"),
+            ("Name", b"ESTR"), ("Type", b"TNAM"), ("", b"AWRD"), ("Fixed", b"P004"), ("Data", b"HEXD")
+        ]);
+        let fields = decode(&tmpl, &[
+            0xff, 0xfe, 0x12, 0xab, 0x80, 0x02, b'H', b'i', 0x00, b'T', b'E', b'X', b'T',
+            0x00, 0x02, b'a', b'b', 0x00, 0x01, 0x02
+        ]).unwrap();
+        let values: Vec<&Value> = fields.iter().map(|f| &f.value).collect();
+        assert_eq!(values, vec![
+            &Value::Integer(-2),
+            &Value::Hex { value: 0x12ab, size: 2 },
+            &Value::Boolean(true),
+            &Value::Boolean(false),
+            &Value::Text(b"Hi".to_vec()),
+            &Value::Type(OSType(*b"TEXT")),
+            &Value::Text(b"ab".to_vec()),
+            &Value::Bytes(vec![0x01, 0x02])
+        ]);
+        assert_eq!(format!("{}", fields[1].value), "$12AB");
+        assert!(decode(&template(&[("What", b"XXXX")]), &[0]).is_err());
+    }
+
+    #[test]
+    fn lists() {
+        let tmpl = template(&[
+            ("Count", b"OCNT"), ("Items", b"LSTC"),
+            ("Value", b"DBYT"), ("Sub", b"ZCNT"), ("Subs", b"LSTC"), ("Char", b"CHAR"), ("", b"LSTE"),
+            ("", b"LSTE"),
+            ("Rest", b"LSTB"), ("Byte", b"UBYT"), ("", b"LSTE")
+        ]);
+        let fields = decode(&tmpl, &[0x00, 0x02, 0x05, 0x00, 0x00, b'a', 0x06, 0xff, 0xff, 0xfe, 0xfd]).unwrap();
+        assert_eq!(fields.len(), 3);
+        match &fields[1].value {
+            Value::List(entries) => {
+                assert_eq!(entries.len(), 2);
+                assert_eq!(entries[0][2].value, Value::List(vec![vec![Field { label: PString::from("Char"), value: Value::Char(b'a') }]]));
+                assert_eq!(entries[1][2].value, Value::List(vec![]));
+            },
+            other => panic!("not a list: {:?}", other)
+        }
+        assert_eq!(fields[2].value, Value::List(vec![
+            vec![Field { label: PString::from("Byte"), value: Value::Integer(0xfe) }],
+            vec![Field { label: PString::from("Byte"), value: Value::Integer(0xfd) }]
+        ]));
+        assert!(format_fields(&fields).contains("    Value: 6\n"));
+
+        let zero = template(&[("Items", b"LSTZ"), ("Byte", b"DBYT"), ("", b"LSTE"), ("End", b"DBYT")]);
+        let fields = decode(&zero, &[0x01, 0x02, 0x00, 0x03]).unwrap();
+        assert_eq!(fields[1].value, Value::Integer(3));
+
+        assert!(decode(&template(&[("Items", b"LSTC"), ("", b"LSTE")]), &[]).is_err());
+        assert!(decode(&template(&[("Items", b"LSTB")]), &[]).is_err());
+    }
+
+    #[test]
+    fn keyed() {
+        let tmpl = template(&[
+            ("Kind", b"KWRD"), ("Text=1", b"CASE"), ("Number=$2", b"CASE"),
+            ("", b"KEYB"), ("Text", b"PSTR"), ("", b"KEYE"),
+            ("", b"KEYB"), ("Number", b"DLNG"), ("", b"KEYE"),
+            ("After", b"DBYT")
+        ]);
+        let fields = decode(&tmpl, &[0x00, 0x02, 0x00, 0x00, 0x00, 0x07, 0x09]).unwrap();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[1].value, Value::Integer(7));
+        assert_eq!(fields[2].value, Value::Integer(9));
+        assert!(decode(&tmpl, &[0x00, 0x03, 0x00]).is_err());
+    }
+}
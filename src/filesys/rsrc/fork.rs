@@ -0,0 +1,304 @@
+use crate::serialization::{SerialWrite, SerialWriteStorage};
+use crate::{Error, Result};
+use crate::types::{
+    OSType,
+    PString
+};
+
+use super::{
+    Rsrc,
+    RsrcAttributes
+};
+
+// Offset of the data area, after the header and the areas reserved for
+// system and application use
+const DATA_OFFSET: u64 = 256;
+
+// Header copy, next map handle, file reference number, attributes, type list
+// offset and name list offset
+const MAP_HEADER_LEN: u64 = 28;
+
+const TYPE_REF_LEN: u64 = 8;
+const RSRC_REF_LEN: u64 = 12;
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct RsrcEntry {
+    pub id : i16,
+    pub name : Option<PString>,
+    pub attributes : RsrcAttributes,
+    pub data : Vec<u8>
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+struct RsrcTypeEntries {
+    rsrc_type: OSType,
+    rsrc: Vec<RsrcEntry>
+}
+
+// Editable resource fork, with all resource data kept in memory. Types and
+// resources keep their order from the original fork, new ones are added last.
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Default)]
+pub struct RsrcFork {
+    pub attributes: u16,
+    types: Vec<RsrcTypeEntries>
+}
+
+impl RsrcFork {
+    pub fn new() -> RsrcFork {
+        RsrcFork::default()
+    }
+
+    pub fn from_rsrc(rsrc: &Rsrc) -> Result<RsrcFork> {
+        let mut types = Vec::with_capacity(rsrc.map.types.len());
+        for t in rsrc.map.types.iter() {
+            let mut entries = Vec::with_capacity(t.rsrc.len());
+            for obj in t.rsrc.iter() {
+                entries.push(RsrcEntry {
+                    id: obj.id,
+                    name: obj.name.clone(),
+                    attributes: obj.attributes,
+                    data: rsrc.read_data(obj)?.to_vec()
+                });
+            }
+            types.push(RsrcTypeEntries {
+                rsrc_type: t.rsrc_type.clone(),
+                rsrc: entries
+            });
+        }
+        Ok(RsrcFork {
+            attributes: rsrc.map.attributes,
+            types
+        })
+    }
+
+    pub fn types(&self) -> impl Iterator<Item = &OSType> {
+        self.types.iter().map(|t| &t.rsrc_type)
+    }
+
+    pub fn resources<'a>(&'a self, rsrc_type: &'a OSType) -> impl Iterator<Item = &'a RsrcEntry> + 'a {
+        self.types.iter()
+            .filter(move |t| &t.rsrc_type == rsrc_type)
+            .flat_map(|t| t.rsrc.iter())
+    }
+
+    pub fn get(&self, rsrc_type: &OSType, id: i16) -> Option<&RsrcEntry> {
+        self.types.iter()
+            .filter(|t| &t.rsrc_type == rsrc_type)
+            .flat_map(|t| t.rsrc.iter())
+            .find(|r| r.id == id)
+    }
+
+    pub fn get_mut(&mut self, rsrc_type: &OSType, id: i16) -> Option<&mut RsrcEntry> {
+        self.types.iter_mut()
+            .filter(|t| &t.rsrc_type == rsrc_type)
+            .flat_map(|t| t.rsrc.iter_mut())
+            .find(|r| r.id == id)
+    }
+
+    fn get_existing(&mut self, rsrc_type: &OSType, id: i16) -> Result<&mut RsrcEntry> {
+        self.get_mut(rsrc_type, id).ok_or(Error::NotFound)
+    }
+
+    pub fn add(&mut self, rsrc_type: OSType, id: i16, name: Option<PString>, data: Vec<u8>) -> Result<()> {
+        if self.get(&rsrc_type, id).is_some() {
+            return Err(Error::AlreadyExists);
+        }
+        let entry = RsrcEntry { id, name, attributes: RsrcAttributes::default(), data };
+        if let Some(t) = self.types.iter_mut().find(|t| t.rsrc_type == rsrc_type) {
+            t.rsrc.push(entry);
+        } else {
+            self.types.push(RsrcTypeEntries {
+                rsrc_type,
+                rsrc: vec![entry]
+            });
+        }
+        Ok(())
+    }
+
+    pub fn replace(&mut self, rsrc_type: &OSType, id: i16, data: Vec<u8>) -> Result<Vec<u8>> {
+        let entry = self.get_existing(rsrc_type, id)?;
+        Ok(std::mem::replace(&mut entry.data, data))
+    }
+
+    pub fn remove(&mut self, rsrc_type: &OSType, id: i16) -> Result<RsrcEntry> {
+        let tidx = self.types.iter()
+            .position(|t| &t.rsrc_type == rsrc_type && t.rsrc.iter().any(|r| r.id == id))
+            .ok_or(Error::NotFound)?;
+        let t = &mut self.types[tidx];
+        let ridx = t.rsrc.iter().position(|r| r.id == id).ok_or(Error::NotFound)?;
+        let entry = t.rsrc.remove(ridx);
+        if t.rsrc.is_empty() {
+            self.types.remove(tidx);
+        }
+        Ok(entry)
+    }
+
+    pub fn rename(&mut self, rsrc_type: &OSType, id: i16, name: Option<PString>) -> Result<()> {
+        self.get_existing(rsrc_type, id)?.name = name;
+        Ok(())
+    }
+
+    pub fn set_id(&mut self, rsrc_type: &OSType, id: i16, new_id: i16) -> Result<()> {
+        if id != new_id && self.get(rsrc_type, new_id).is_some() {
+            return Err(Error::AlreadyExists);
+        }
+        self.get_existing(rsrc_type, id)?.id = new_id;
+        Ok(())
+    }
+
+    pub fn set_attributes(&mut self, rsrc_type: &OSType, id: i16, attributes: RsrcAttributes) -> Result<()> {
+        self.get_existing(rsrc_type, id)?.attributes = attributes;
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut wtr = SerialWriteStorage::new();
+        self.write(&mut wtr)?;
+        Ok(wtr.to_vec())
+    }
+}
+
+fn check_limit(value: u64, limit: u64, field: &'static str) -> Result<()> {
+    if value > limit {
+        return Err(Error::invalid_value(0, "RsrcFork", field, format!("at most {}", limit), value));
+    }
+    Ok(())
+}
+
+// Layout according to Inside Macintosh: More Macintosh Toolbox, 1-121
+impl SerialWrite for RsrcFork {
+    fn write(&self, wtr : &mut SerialWriteStorage) -> Result<()> {
+        let base = wtr.pos();
+        let rsrc_count = self.types.iter().map(|t| t.rsrc.len() as u64).sum::<u64>();
+
+        check_limit(self.types.len() as u64, 0x8000, "types")?;
+        for t in self.types.iter() {
+            check_limit(t.rsrc.len() as u64, 0x10000, "rsrc")?;
+        }
+        let name_list_offset = MAP_HEADER_LEN + 2 + TYPE_REF_LEN * self.types.len() as u64 + RSRC_REF_LEN * rsrc_count;
+        check_limit(name_list_offset, 0xffff, "name_list_offset")?;
+
+        // Data area
+        wtr.seek(base + DATA_OFFSET)?;
+        let mut data_offsets = Vec::with_capacity(rsrc_count as usize);
+        for r in self.types.iter().flat_map(|t| t.rsrc.iter()) {
+            let offset = wtr.pos() - base - DATA_OFFSET;
+            check_limit(offset, 0xffffff, "data_offset")?;
+            check_limit(r.data.len() as u64, 0xffffffff, "data_len")?;
+            data_offsets.push(offset as u32);
+            wtr.write_u32(r.data.len() as u32)?;
+            wtr.write_bytes(&r.data)?;
+        }
+        let map_offset = wtr.pos() - base;
+        let data_len = map_offset - DATA_OFFSET;
+
+        // Map, with header copy filled in later
+        wtr.pad(22)?;
+        self.attributes.write(wtr)?;
+        (MAP_HEADER_LEN as u16).write(wtr)?;
+        (name_list_offset as u16).write(wtr)?;
+
+        // Type list, reference list offsets are relative to the type list
+        ((self.types.len() as i32 - 1) as i16).write(wtr)?;
+        let mut ref_offset = 2 + TYPE_REF_LEN * self.types.len() as u64;
+        for t in self.types.iter() {
+            t.rsrc_type.write(wtr)?;
+            ((t.rsrc.len() - 1) as u16).write(wtr)?;
+            (ref_offset as u16).write(wtr)?;
+            ref_offset += RSRC_REF_LEN * t.rsrc.len() as u64;
+        }
+
+        // Reference lists
+        let mut names = SerialWriteStorage::new();
+        for (r, data_offset) in self.types.iter().flat_map(|t| t.rsrc.iter()).zip(data_offsets) {
+            r.id.write(wtr)?;
+            if let Some(name) = &r.name {
+                check_limit(names.pos(), 0x7fff, "name_offset")?;
+                (names.pos() as i16).write(wtr)?;
+                name.write(&mut names)?;
+            } else {
+                (-1i16).write(wtr)?;
+            }
+            r.attributes.0.write(wtr)?;
+            wtr.write_u24(data_offset)?;
+            0u32.write(wtr)?; // Handle, reserved
+        }
+
+        // Name list
+        wtr.write_bytes(&names.to_vec())?;
+        let end = wtr.pos();
+        let map_len = end - base - map_offset;
+        check_limit(end - base, 0x7fffffff, "size")?;
+
+        // Header, and the copy in the map
+        for pos in [base, base + map_offset].iter() {
+            wtr.seek(*pos)?;
+            (DATA_OFFSET as i32).write(wtr)?;
+            (map_offset as i32).write(wtr)?;
+            (data_len as u32).write(wtr)?;
+            (map_len as u32).write(wtr)?;
+        }
+        wtr.seek(end)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RsrcFork;
+    use crate::filesys::rsrc::{Rsrc, RsrcAttributes};
+    use crate::serialization::SerialAdaptor;
+    use crate::types::{OSType, PString};
+    use crate::Error;
+    use std::io::Cursor;
+
+    fn reopen(fork: &RsrcFork) -> Rsrc {
+        let data = fork.to_bytes().unwrap();
+        Rsrc::new(SerialAdaptor::new(Cursor::new(data))).unwrap()
+    }
+
+    #[test]
+    fn empty_fork() {
+        let rsrc = reopen(&RsrcFork::new());
+        assert!(RsrcFork::from_rsrc(&rsrc).unwrap().types().next().is_none());
+    }
+
+    #[test]
+    fn edit_roundtrip() {
+        let strs = OSType::from(b"STR ");
+        let vers = OSType::from(b"vers");
+        let mut fork = RsrcFork::new();
+        fork.add(strs.clone(), 128, Some(PString::from("hello")), vec![1, 2, 3]).unwrap();
+        fork.add(vers.clone(), 1, None, vec![4]).unwrap();
+        fork.add(strs.clone(), 129, None, vec![5, 6]).unwrap();
+        assert!(matches!(fork.add(strs.clone(), 129, None, vec![]), Err(Error::AlreadyExists)));
+
+        let rsrc = reopen(&fork);
+        assert_eq!(rsrc.open(strs.clone(), 128).unwrap().to_vec(), vec![1, 2, 3]);
+        assert_eq!(rsrc.open(strs.clone(), 129).unwrap().to_vec(), vec![5, 6]);
+        assert_eq!(rsrc.open(vers.clone(), 1).unwrap().to_vec(), vec![4]);
+        assert_eq!(rsrc.name(strs.clone(), 128).unwrap(), Some(PString::from("hello")));
+
+        let mut fork = RsrcFork::from_rsrc(&rsrc).unwrap();
+        fork.replace(&strs, 128, vec![7]).unwrap();
+        fork.rename(&strs, 129, Some(PString::from("world"))).unwrap();
+        fork.set_id(&strs, 128, 130).unwrap();
+        fork.set_attributes(&strs, 130, RsrcAttributes(RsrcAttributes::PURGEABLE)).unwrap();
+        assert!(fork.set_id(&strs, 130, 129).is_err());
+        fork.remove(&vers, 1).unwrap();
+        assert!(fork.remove(&vers, 1).is_err());
+
+        let rsrc = reopen(&fork);
+        assert!(rsrc.open(strs.clone(), 128).is_err());
+        assert!(rsrc.open(vers.clone(), 1).is_err());
+        assert_eq!(rsrc.open(strs.clone(), 130).unwrap().to_vec(), vec![7]);
+        assert_eq!(rsrc.name(strs.clone(), 129).unwrap(), Some(PString::from("world")));
+        let fork = RsrcFork::from_rsrc(&rsrc).unwrap();
+        assert_eq!(fork.types().collect::<Vec<_>>(), vec![&strs]);
+        assert!(fork.get(&strs, 130).unwrap().attributes.purgeable());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::serialization::{SerialRead, SerialReadStorage};
+use crate::Result;
 use crate::types::{
     PString,
     OSType
@@ -6,27 +7,27 @@ use crate::types::{
 
 use super::types::{
     RsrcMapHeader,
-    RsrcTypeRef,
-    RsrcRef
+    RsrcRef,
+    RsrcAttributes
 };
 
 #[derive(Debug)]
 pub struct RsrcObj {
     pub id : i16,
     pub name : Option<PString>,
-    pub attributes : u8,
+    pub attributes : RsrcAttributes,
     pub data_offset : u64
 }
 
 impl RsrcObj {
-    fn read(rdr: &mut SerialReadStorage, maphdr: &RsrcMapHeader) -> std::io::Result<RsrcObj> {
+    fn read(rdr: &mut SerialReadStorage, maphdr: &RsrcMapHeader) -> Result<RsrcObj> {
         let refobj = RsrcRef::read(rdr)?;
 
         let name = if refobj.name_offset >= 0 {
-            rdr.length_start(0); // So we can jump back
-            rdr.seek(maphdr.name_list_offset as u64 + refobj.name_offset as u64);
+            rdr.length_start(0)?; // So we can jump back
+            rdr.seek(maphdr.name_list_offset as u64 + refobj.name_offset as u64)?;
             let name = Some(PString::read(rdr)?);
-            rdr.length_end();
+            rdr.length_end()?;
             name
         } else {
             None
@@ -35,7 +36,7 @@ impl RsrcObj {
         Ok(RsrcObj{
             id: refobj.id,
             name: name,
-            attributes: refobj.attributes,
+            attributes: RsrcAttributes(refobj.attributes),
             data_offset: refobj.data_offset as u64
         })
     }
@@ -54,26 +55,18 @@ pub struct RsrcMap {
 }
 
 impl SerialRead for RsrcMap {
-    fn read(rdr: &mut SerialReadStorage) -> std::io::Result<RsrcMap> {
+    fn read(rdr: &mut SerialReadStorage) -> Result<RsrcMap> {
         let maphdr = RsrcMapHeader::read(rdr)?;
 
-        rdr.seek(maphdr.type_list_offset as u64);
-        let count = u16::read(rdr)? + 1;
-
-        let mut type_refs = Vec::with_capacity(count as usize);
-        for _ in 0..count {
-            type_refs.push(RsrcTypeRef::read(rdr)?);
-        }
-
-        let mut types = Vec::with_capacity(count as usize);
-        for t in type_refs.into_iter() {
-            rdr.seek(maphdr.type_list_offset as u64 + t.type_offset as u64);
+        let mut types = Vec::with_capacity(maphdr.type_refs.len());
+        for t in maphdr.type_refs.iter() {
+            rdr.seek(maphdr.type_list_offset as u64 + t.type_offset as u64)?;
             let mut rsrc = Vec::with_capacity(t.count as usize+1);
-            for _ in 0..t.count+1 {
+            for _ in 0..t.count as usize+1 {
                 rsrc.push(RsrcObj::read(rdr, &maphdr)?);
             }
             types.push(RsrcType{
-                rsrc_type: t.rsrc_type,
+                rsrc_type: t.rsrc_type.clone(),
                 rsrc: rsrc
             });
         }
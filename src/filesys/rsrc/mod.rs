@@ -1,17 +1,55 @@
 mod types;
 mod map;
+mod fork;
+mod chain;
+mod dcmp;
+mod rez;
+mod validate;
+
+pub mod decode;
 
 use crate::serialization::{SerialAccess, SerialRead, SerialReadStorage};
+use crate::{Error, Result};
 
 use types::{
     RsrcHeader
 };
+
+use decode::TypedRsrc;
+
+pub use types::RsrcAttributes;
 use crate::types::{
     OSType,
     PString
 };
 
-use map::RsrcMap;
+use map::{
+    RsrcMap,
+    RsrcObj
+};
+
+pub use fork::{
+    RsrcFork,
+    RsrcEntry
+};
+
+pub use rez::{
+    derez,
+    rez
+};
+
+pub use validate::{
+    validate,
+    salvage,
+    ValidationReport,
+    Problem,
+    Severity
+};
+
+pub use chain::{
+    ResourceChain,
+    SYSTEM_REFNUM
+};
 
 #[derive(Debug)]
 pub struct Rsrc {
@@ -21,7 +59,7 @@ pub struct Rsrc {
 }
 
 impl Rsrc {
-    pub fn new(storage: Box<dyn SerialAccess>) -> std::io::Result<Rsrc> {
+    pub fn new(storage: Box<dyn SerialAccess>) -> Result<Rsrc> {
         let mut rdr = storage.read(0, 16)?;
         let header = RsrcHeader::read(&mut rdr)?;
 
@@ -35,25 +73,222 @@ impl Rsrc {
         })
     }
 
-    pub fn name(&self, rsrc_type: OSType, id: i16) -> std::io::Result<Option<PString>> {
+    // Resource map attributes, mapReadOnly, mapCompact and mapChanged
+    pub fn attributes(&self) -> u16 {
+        self.map.attributes
+    }
+
+    pub fn types(&self) -> impl Iterator<Item = &OSType> {
+        self.map.types.iter().map(|t| &t.rsrc_type)
+    }
+
+    // Count1Types
+    pub fn count_types(&self) -> usize {
+        self.map.types.len()
+    }
+
+    pub fn resources<'rsrc>(&'rsrc self, rsrc_type: &'rsrc OSType) -> impl Iterator<Item = RsrcInfo<'rsrc>> + 'rsrc {
+        self.map.types.iter()
+            .filter(move |t| &t.rsrc_type == rsrc_type)
+            .flat_map(move |t| t.rsrc.iter().map(move |obj| RsrcInfo {
+                rsrc: self,
+                rsrc_type: &t.rsrc_type,
+                obj
+            }))
+    }
+
+    // Count1Resources
+    pub fn count(&self, rsrc_type: &OSType) -> usize {
+        self.resources(rsrc_type).count()
+    }
+
+    pub fn get<'rsrc>(&'rsrc self, rsrc_type: &'rsrc OSType, id: i16) -> Option<RsrcInfo<'rsrc>> {
+        self.resources(rsrc_type).find(|info| info.id() == id)
+    }
+
+    // Get1NamedResource, names are compared case insensitive
+    pub fn get_named<'rsrc>(&'rsrc self, rsrc_type: &'rsrc OSType, name: &str) -> Option<RsrcInfo<'rsrc>> {
+        let name = name.to_lowercase();
+        self.resources(rsrc_type).find(|info| {
+            info.name().is_some_and(|n| n.to_string().to_lowercase() == name)
+        })
+    }
+
+    pub fn name(&self, rsrc_type: OSType, id: i16) -> Result<Option<PString>> {
         let rsrcref = self
             .map.open(rsrc_type, id)
-            .ok_or(std::io::Error::from(
-                    std::io::ErrorKind::NotFound
-                ))?;
+            .ok_or(Error::NotFound)?;
         Ok(rsrcref.name.clone())
     }
 
-    pub fn open(&self, rsrc_type: OSType, id: i16) -> std::io::Result<SerialReadStorage> {
+    // Compressed resources are expanded transparently
+    pub fn open(&self, rsrc_type: OSType, id: i16) -> Result<SerialReadStorage> {
         let rsrcref = self
             .map.open(rsrc_type, id)
-            .ok_or(std::io::Error::from(
-                    std::io::ErrorKind::NotFound
-                ))?;
+            .ok_or(Error::NotFound)?;
+        Ok(self.read_expanded(rsrcref)?.0)
+    }
+
+    // Resource decoded as the type given by T
+    pub fn load<T: TypedRsrc>(&self, id: i16) -> Result<T> {
+        T::read(&mut self.open(T::RSRC_TYPE, id)?)
+    }
+
+    // The length prefix must keep the data inside the data area
+    fn read_data(&self, rsrcref: &RsrcObj) -> Result<SerialReadStorage> {
+        let pos = self.data_pos(rsrcref.data_offset)?;
+        let size = self.read_size(rsrcref)?;
+        let avail = (self.header.data_len as u64).saturating_sub(rsrcref.data_offset + 4);
+        if size as u64 > avail {
+            return Err(Error::invalid_value(pos, "RsrcData", "len", format!("at most {}", avail), size));
+        }
+        self.storage.read(pos + 4, size as u64)
+    }
+
+    // The attribute alone isn't trusted, some resources have it set without
+    // being compressed
+    fn read_expanded(&self, rsrcref: &RsrcObj) -> Result<(SerialReadStorage, bool)> {
+        let rdr = self.read_data(rsrcref)?;
+        if !rsrcref.attributes.compressed() {
+            return Ok((rdr, false));
+        }
+        let data = rdr.to_vec();
+        if dcmp::is_compressed(&data) {
+            Ok((SerialReadStorage::from(dcmp::decompress(data)?), true))
+        } else {
+            Ok((SerialReadStorage::from(data), false))
+        }
+    }
+
+    fn read_size(&self, rsrcref: &RsrcObj) -> Result<u32> {
+        let pos = self.data_pos(rsrcref.data_offset)?;
+        let mut size_rdr = self.storage.read(pos, 4)?;
+        u32::read(&mut size_rdr)
+    }
+
+    fn data_pos(&self, data_offset: u64) -> Result<u64> {
+        if self.header.data_offset < 0 {
+            return Err(Error::invalid_value(0, "RsrcHeader", "data_offset", "positive offset", self.header.data_offset));
+        }
+        Ok(self.header.data_offset as u64 + data_offset)
+    }
+}
+
+// Reference to a resource in a fork, for inspecting it without reading data
+pub struct RsrcInfo<'rsrc> {
+    rsrc: &'rsrc Rsrc,
+    rsrc_type: &'rsrc OSType,
+    obj: &'rsrc RsrcObj
+}
+
+impl<'rsrc> RsrcInfo<'rsrc> {
+    pub fn rsrc_type(&self) -> &'rsrc OSType {
+        self.rsrc_type
+    }
+
+    pub fn id(&self) -> i16 {
+        self.obj.id
+    }
 
-        let mut size_rdr = self.storage.read(self.header.data_offset as u64 + rsrcref.data_offset, 4)?;
-        let size = u32::read(&mut size_rdr)?;
+    pub fn name(&self) -> Option<&'rsrc PString> {
+        self.obj.name.as_ref()
+    }
 
-        self.storage.read(self.header.data_offset as u64 + rsrcref.data_offset+4, size as u64)
+    pub fn attributes(&self) -> RsrcAttributes {
+        self.obj.attributes
     }
-}
\ No newline at end of file
+
+    // Size of the resource data, as stored in the data area
+    pub fn size(&self) -> Result<u32> {
+        self.rsrc.read_size(self.obj)
+    }
+
+    // Resource data, expanded if compressed
+    pub fn open(&self) -> Result<SerialReadStorage> {
+        Ok(self.open_expanded()?.0)
+    }
+
+    // Resource data, and whether it was compressed
+    pub fn open_expanded(&self) -> Result<(SerialReadStorage, bool)> {
+        self.rsrc.read_expanded(self.obj)
+    }
+
+    // Resource data decoded by type, if there is a decoder for the type
+    pub fn decode(&self) -> Result<Option<decode::Decoded>> {
+        decode::decode(self.rsrc_type, &mut self.open()?)
+    }
+
+    // Resource data as stored in the fork, without decompression
+    pub fn open_raw(&self) -> Result<SerialReadStorage> {
+        self.rsrc.read_data(self.obj)
+    }
+}
+
+impl<'rsrc> std::fmt::Debug for RsrcInfo<'rsrc> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RsrcInfo")
+            .field("rsrc_type", self.rsrc_type)
+            .field("id", &self.obj.id)
+            .field("name", &self.obj.name)
+            .field("attributes", &self.obj.attributes)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rsrc, RsrcFork, RsrcAttributes};
+    use crate::serialization::SerialAdaptor;
+    use crate::types::{OSType, PString};
+    use std::io::Cursor;
+
+    #[test]
+    fn enumerate() {
+        let strs = OSType::from(b"STR ");
+        let icon = OSType::from(b"ICN#");
+        let mut fork = RsrcFork::new();
+        fork.add(strs.clone(), 128, Some(PString::from("About")), vec![0; 10]).unwrap();
+        fork.add(icon.clone(), 128, None, vec![0; 256]).unwrap();
+        fork.add(strs.clone(), 129, Some(PString::from("Quit")), vec![0; 3]).unwrap();
+        fork.set_attributes(&strs, 129, RsrcAttributes(RsrcAttributes::PURGEABLE | RsrcAttributes::LOCKED)).unwrap();
+        let rsrc = Rsrc::new(SerialAdaptor::new(Cursor::new(fork.to_bytes().unwrap()))).unwrap();
+
+        assert_eq!(rsrc.types().collect::<Vec<_>>(), vec![&strs, &icon]);
+        assert_eq!(rsrc.count_types(), 2);
+        assert_eq!(rsrc.count(&strs), 2);
+        assert_eq!(rsrc.count(&OSType::from(b"CODE")), 0);
+        assert_eq!(rsrc.resources(&strs).map(|r| r.id()).collect::<Vec<_>>(), vec![128, 129]);
+        assert_eq!(rsrc.get(&icon, 128).unwrap().size().unwrap(), 256);
+
+        let quit = rsrc.get_named(&strs, "quit").unwrap();
+        assert_eq!(quit.id(), 129);
+        assert_eq!(quit.size().unwrap(), 3);
+        assert!(quit.attributes().purgeable());
+        assert!(quit.attributes().locked());
+        assert!(!quit.attributes().preload());
+        assert!(rsrc.get_named(&icon, "quit").is_none());
+    }
+
+    #[test]
+    fn compressed() {
+        let code = OSType::from(b"CODE");
+        let mut data = vec![0xa8, 0x9f, 0x65, 0x72, 0x00, 0x12, 0x09, 0x01, 0, 0, 0, 4, 0, 2, 0, 0, 0xff, 0];
+        data.extend_from_slice(&[0x02, 0x04]);
+        let mut fork = RsrcFork::new();
+        fork.add(code.clone(), 1, None, data.clone()).unwrap();
+        fork.add(code.clone(), 2, None, data.clone()).unwrap();
+        fork.set_attributes(&code, 1, RsrcAttributes(RsrcAttributes::COMPRESSED)).unwrap();
+        let rsrc = Rsrc::new(SerialAdaptor::new(Cursor::new(fork.to_bytes().unwrap()))).unwrap();
+
+        let (rdr, compressed) = rsrc.get(&code, 1).unwrap().open_expanded().unwrap();
+        assert!(compressed);
+        assert_eq!(rdr.to_vec(), vec![0x4e, 0xba, 0x4e, 0x75]);
+        assert_eq!(rsrc.open(code.clone(), 1).unwrap().to_vec(), vec![0x4e, 0xba, 0x4e, 0x75]);
+        assert_eq!(rsrc.get(&code, 1).unwrap().open_raw().unwrap().to_vec(), data);
+
+        // Without the attribute the data is returned as is
+        let (rdr, compressed) = rsrc.get(&code, 2).unwrap().open_expanded().unwrap();
+        assert!(!compressed);
+        assert_eq!(rdr.to_vec(), data);
+    }
+}
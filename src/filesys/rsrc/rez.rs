@@ -0,0 +1,681 @@
+use crate::{Error, Result};
+use crate::types::{
+    Encoding,
+    OSType,
+    PString
+};
+
+use super::{
+    RsrcAttributes,
+    RsrcFork
+};
+
+// Text representation of a resource fork in the syntax of MPW Rez/DeRez.
+//
+// Resources of a few well known types are written as 'resource' statements
+// with their fields, everything else as 'data' statements with hex strings.
+// Like DeRez, the attributes of the resource map itself are not kept.
+
+// Bytes of data per line of a hex block
+const HEX_LINE_LEN: usize = 16;
+
+// Release stages of 'vers', as named in SysTypes.r
+const VERS_STAGES: [(&str, u8); 5] = [
+    ("development", 0x20),
+    ("alpha", 0x40),
+    ("beta", 0x60),
+    ("release", 0x80),
+    ("final", 0x80)
+];
+
+// Attribute keywords, setting or clearing the flag
+const ATTRIBUTES: [(&str, u8, bool); 10] = [
+    ("sysheap", RsrcAttributes::SYS_HEAP, true),
+    ("appheap", RsrcAttributes::SYS_HEAP, false),
+    ("purgeable", RsrcAttributes::PURGEABLE, true),
+    ("nonpurgeable", RsrcAttributes::PURGEABLE, false),
+    ("locked", RsrcAttributes::LOCKED, true),
+    ("unlocked", RsrcAttributes::LOCKED, false),
+    ("protected", RsrcAttributes::PROTECTED, true),
+    ("unprotected", RsrcAttributes::PROTECTED, false),
+    ("preload", RsrcAttributes::PRELOAD, true),
+    ("nonpreload", RsrcAttributes::PRELOAD, false)
+];
+
+const STR: OSType = OSType(*b"STR ");
+const STR_LIST: OSType = OSType(*b"STR#");
+const TEXT: OSType = OSType(*b"TEXT");
+const VERS: OSType = OSType(*b"vers");
+
+// Decompile all resources of a fork
+pub fn derez(fork: &RsrcFork) -> String {
+    let mut out = String::new();
+    for rsrc_type in fork.types() {
+        for entry in fork.resources(rsrc_type) {
+            let mut header = format!("'{}' ({}", type_literal(rsrc_type), entry.id);
+            if let Some(name) = &entry.name {
+                header += &format!(", {}", string_literal(name.as_bytes()));
+            }
+            for attr in attribute_list(entry.attributes) {
+                header += &format!(", {}", attr);
+            }
+            header += ")";
+
+            // Typed rendering is only used if it compiles back to the same data
+            let typed = render_typed(rsrc_type, &entry.data)
+                .filter(|body| compile_body(rsrc_type, body).ok().as_ref() == Some(&entry.data));
+            match typed {
+                Some(body) => out += &format!("resource {} {{\n{}}};\n\n", header, body),
+                None => out += &format!("data {} {{\n{}}};\n\n", header, hex_block(&entry.data))
+            }
+        }
+    }
+    out
+}
+
+// Compile source in the format written by derez into a resource fork
+pub fn rez(source: &str) -> Result<RsrcFork> {
+    let mut parser = Parser::new(source)?;
+    let mut fork = RsrcFork::new();
+    while !parser.at_end() {
+        let offset = parser.offset();
+        let typed = match parser.ident()?.as_str() {
+            "data" => false,
+            "resource" => true,
+            other => return Err(parser.error_at(offset, "'data' or 'resource'", other))
+        };
+        let rsrc_type = parser.rsrc_type()?;
+        parser.punct('(')?;
+        let id = parser.integer(-0x8000, 0x7fff)? as i16;
+        let mut name = None;
+        let mut attributes = RsrcAttributes::default();
+        while parser.eat_punct(',') {
+            if let Some(s) = parser.eat_string() {
+                if s.len() > 255 {
+                    return Err(parser.error("name of at most 255 bytes", s.len()));
+                }
+                name = Some(PString::from(&s[..]));
+            } else if parser.peek_number() {
+                attributes = RsrcAttributes(parser.integer(0, 0xff)? as u8);
+            } else {
+                let offset = parser.offset();
+                let keyword = parser.ident()?.to_lowercase();
+                match ATTRIBUTES.iter().find(|(k, _, _)| *k == keyword) {
+                    Some((_, flag, true)) => attributes.0 |= flag,
+                    Some((_, flag, false)) => attributes.0 &= !flag,
+                    None => return Err(parser.error_at(offset, "resource attribute", keyword))
+                }
+            }
+        }
+        parser.punct(')')?;
+        parser.punct('{')?;
+        let data = if typed {
+            parser.typed_body(&rsrc_type)?
+        } else {
+            parser.data_body()?
+        };
+        parser.punct('}')?;
+        parser.punct(';')?;
+
+        fork.add(rsrc_type.clone(), id, name, data).map_err(|err| err.in_field("Rez", "resource", offset as u64))?;
+        fork.set_attributes(&rsrc_type, id, attributes)?;
+    }
+    Ok(fork)
+}
+
+fn attribute_list(attributes: RsrcAttributes) -> Vec<String> {
+    // Flags without a Rez keyword need the attributes as a number
+    if attributes.0 & !0x7c != 0 {
+        return vec![format!("${:02X}", attributes.0)];
+    }
+    ATTRIBUTES.iter()
+        .filter(|(_, flag, set)| *set && attributes.contains(*flag))
+        .map(|(k, _, _)| k.to_string())
+        .collect()
+}
+
+fn escape_char(b: u8, quote: char) -> String {
+    let c = Encoding::MacRoman.decode_char(b);
+    match c {
+        '\\' => String::from("\\\\"),
+        '\r' => String::from("\\n"),
+        '\t' => String::from("\\t"),
+        c if c == quote => format!("\\{}", c),
+        // Only characters that map back to the same byte are kept as is
+        c if !c.is_control() && Encoding::MacRoman.encode_char(c) == Some(b) => c.to_string(),
+        _ => format!("\\0x{:02X}", b)
+    }
+}
+
+fn string_literal(data: &[u8]) -> String {
+    let body: String = data.iter().map(|b| escape_char(*b, '"')).collect();
+    format!("\"{}\"", body)
+}
+
+fn type_literal(rsrc_type: &OSType) -> String {
+    rsrc_type.0.iter().map(|b| escape_char(*b, '\'')).collect()
+}
+
+fn hex_block(data: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in data.chunks(HEX_LINE_LEN) {
+        let hex: Vec<String> = chunk.chunks(2)
+            .map(|w| w.iter().map(|b| format!("{:02X}", b)).collect())
+            .collect();
+        let ascii: String = chunk.iter()
+            .map(|b| if *b >= 0x20 && *b <= 0x7e && *b != b'*' { *b as char } else { '.' })
+            .collect();
+        out += &format!("\t{:<44}/* {} */\n", format!("$\"{}\"", hex.join(" ")), ascii);
+    }
+    out
+}
+
+// Split data into Pascal strings, all data must be used
+fn pstrings(mut data: &[u8], count: usize) -> Option<Vec<&[u8]>> {
+    let mut strings = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = *data.first()? as usize;
+        strings.push(data.get(1..1 + len)?);
+        data = &data[1 + len..];
+    }
+    if data.is_empty() {
+        Some(strings)
+    } else {
+        None
+    }
+}
+
+// Fields of known types, as laid out in Types.r and SysTypes.r
+fn render_typed(rsrc_type: &OSType, data: &[u8]) -> Option<String> {
+    if *rsrc_type == STR {
+        let s = pstrings(data, 1)?;
+        Some(format!("\t{}\n", string_literal(s[0])))
+    } else if *rsrc_type == STR_LIST {
+        if data.len() < 2 {
+            return None;
+        }
+        let count = u16::from_be_bytes([data[0], data[1]]) as usize;
+        let strings: Vec<String> = pstrings(&data[2..], count)?.iter()
+            .map(|s| format!("\t\t{}", string_literal(s)))
+            .collect();
+        if strings.is_empty() {
+            Some(String::from("\t{\n\t}\n"))
+        } else {
+            Some(format!("\t{{\n{}\n\t}}\n", strings.join(",\n")))
+        }
+    } else if *rsrc_type == TEXT {
+        // One string per line, Rez concatenates adjacent strings
+        if data.is_empty() {
+            return Some(String::from("\t\"\"\n"));
+        }
+        let lines: String = data.split_inclusive(|b| *b == b'\r')
+            .map(|line| format!("\t{}\n", string_literal(line)))
+            .collect();
+        Some(lines)
+    } else if *rsrc_type == VERS {
+        if data.len() < 6 {
+            return None;
+        }
+        let strings = pstrings(&data[6..], 2)?;
+        let stage = match VERS_STAGES.iter().find(|(_, v)| *v == data[2]) {
+            Some((name, _)) => name.to_string(),
+            None => format!("0x{:02X}", data[2])
+        };
+        Some(format!(
+            "\t0x{:02X}, 0x{:02X}, {}, 0x{:02X},\n\t{},\n\t{},\n\t{}\n",
+            data[0], data[1], stage, data[3],
+            i16::from_be_bytes([data[4], data[5]]),
+            string_literal(strings[0]),
+            string_literal(strings[1])
+        ))
+    } else {
+        None
+    }
+}
+
+fn compile_body(rsrc_type: &OSType, body: &str) -> Result<Vec<u8>> {
+    let mut parser = Parser::new(body)?;
+    let data = parser.typed_body(rsrc_type)?;
+    if !parser.at_end() {
+        return Err(parser.error("end of resource", "more fields"));
+    }
+    Ok(data)
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    String(Vec<u8>),
+    Hex(Vec<u8>),
+    Type(Vec<u8>),
+    Punct(char)
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "'{}'", s),
+            Token::Number(n) => write!(f, "{}", n),
+            Token::String(s) => write!(f, "{}", string_literal(s)),
+            Token::Hex(_) => write!(f, "hex string"),
+            Token::Type(t) => write!(f, "'{}'", Encoding::MacRoman.decode(t)),
+            Token::Punct(c) => write!(f, "'{}'", c)
+        }
+    }
+}
+
+fn lex_error(offset: usize, expected: &str, actual: impl std::fmt::Display) -> Error {
+    Error::invalid_value(offset as u64, "Rez", "source", expected, actual)
+}
+
+fn lex_escape(chars: &[(usize, char)], pos: &mut usize) -> Result<u8> {
+    let (offset, c) = chars[*pos];
+    *pos += 1;
+    let digits = |pos: &mut usize, radix: u32, max: usize| -> Result<u8> {
+        let mut value = 0u32;
+        let mut count = 0;
+        while count < max && *pos < chars.len() && chars[*pos].1.is_digit(radix) {
+            value = value * radix + chars[*pos].1.to_digit(radix).unwrap_or(0);
+            *pos += 1;
+            count += 1;
+        }
+        if count == 0 || value > 0xff {
+            return Err(lex_error(offset, "valid escape sequence", c));
+        }
+        Ok(value as u8)
+    };
+    match c {
+        // In MPW, \n is a carriage return and \r a line feed
+        'n' => Ok(0x0d),
+        'r' => Ok(0x0a),
+        't' => Ok(0x09),
+        'b' => Ok(0x08),
+        'f' => Ok(0x0c),
+        'v' => Ok(0x0b),
+        '?' => Ok(0x7f),
+        '\\' | '"' | '\'' => Ok(c as u8),
+        '$' => digits(pos, 16, 2),
+        '0' if *pos < chars.len() && (chars[*pos].1 == 'x' || chars[*pos].1 == 'X') => {
+            *pos += 1;
+            digits(pos, 16, 2)
+        },
+        '0'..='7' => {
+            *pos -= 1;
+            digits(pos, 8, 3)
+        },
+        c => Err(lex_error(offset, "valid escape sequence", c))
+    }
+}
+
+fn lex_quoted(chars: &[(usize, char)], pos: &mut usize, quote: char) -> Result<Vec<u8>> {
+    let start = chars[*pos].0;
+    *pos += 1;
+    let mut data = Vec::new();
+    loop {
+        let (offset, c) = *chars.get(*pos).ok_or_else(|| lex_error(start, "closing quote", "end of source"))?;
+        *pos += 1;
+        if c == quote {
+            return Ok(data);
+        } else if c == '\\' && *pos < chars.len() {
+            data.push(lex_escape(chars, pos)?);
+        } else if c == '\n' {
+            return Err(lex_error(start, "closing quote", "end of line"));
+        } else {
+            data.push(Encoding::MacRoman.encode_char(c).ok_or_else(|| lex_error(offset, "Mac Roman character", c))?);
+        }
+    }
+}
+
+fn lex(source: &str) -> Result<Vec<(usize, Token)>> {
+    let chars: Vec<(usize, char)> = source.char_indices().collect();
+    let at = |pos: usize| chars.get(pos).map(|(_, c)| *c);
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let (offset, c) = chars[pos];
+        if c.is_whitespace() {
+            pos += 1;
+        } else if c == '/' && at(pos + 1) == Some('*') {
+            pos += 2;
+            while pos < chars.len() && !(at(pos) == Some('*') && at(pos + 1) == Some('/')) {
+                pos += 1;
+            }
+            if pos >= chars.len() {
+                return Err(lex_error(offset, "end of comment", "end of source"));
+            }
+            pos += 2;
+        } else if c == '/' && at(pos + 1) == Some('/') {
+            while pos < chars.len() && at(pos) != Some('\n') {
+                pos += 1;
+            }
+        } else if c == '"' {
+            tokens.push((offset, Token::String(lex_quoted(&chars, &mut pos, '"')?)));
+        } else if c == '\'' {
+            tokens.push((offset, Token::Type(lex_quoted(&chars, &mut pos, '\'')?)));
+        } else if c == '$' && at(pos + 1) == Some('"') {
+            pos += 2;
+            let mut digits = Vec::new();
+            loop {
+                match at(pos) {
+                    Some('"') => break,
+                    Some(c) if c.is_whitespace() => (),
+                    Some(c) if c.is_ascii_hexdigit() => digits.push(c.to_digit(16).unwrap_or(0) as u8),
+                    Some(c) => return Err(lex_error(chars[pos].0, "hex digit", c)),
+                    None => return Err(lex_error(offset, "closing quote", "end of source"))
+                }
+                pos += 1;
+            }
+            pos += 1;
+            if digits.len() % 2 != 0 {
+                return Err(lex_error(offset, "even number of hex digits", digits.len()));
+            }
+            let data = digits.chunks(2).map(|d| d[0] << 4 | d[1]).collect();
+            tokens.push((offset, Token::Hex(data)));
+        } else if c.is_ascii_digit() || c == '$' || (c == '-' && matches!(at(pos + 1), Some(c) if c.is_ascii_digit() || c == '$')) {
+            let negative = c == '-';
+            if negative {
+                pos += 1;
+            }
+            let (radix, prefix) = match (at(pos), at(pos + 1)) {
+                (Some('$'), _) => (16, 1),
+                (Some('0'), Some('x')) | (Some('0'), Some('X')) => (16, 2),
+                (Some('0'), Some('b')) | (Some('0'), Some('B')) => (2, 2),
+                (Some('0'), Some(c)) if c.is_ascii_digit() => (8, 1),
+                _ => (10, 0)
+            };
+            pos += prefix;
+            let start = pos;
+            while pos < chars.len() && chars[pos].1.is_ascii_alphanumeric() {
+                pos += 1;
+            }
+            let text: String = chars[start..pos].iter().map(|(_, c)| *c).collect();
+            let value = i64::from_str_radix(&text, radix)
+                .map_err(|_| lex_error(offset, "number", &text))?;
+            tokens.push((offset, Token::Number(if negative { -value } else { value })));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = pos;
+            while pos < chars.len() && (chars[pos].1.is_ascii_alphanumeric() || chars[pos].1 == '_') {
+                pos += 1;
+            }
+            tokens.push((offset, Token::Ident(chars[start..pos].iter().map(|(_, c)| *c).collect())));
+        } else if "(){},;".contains(c) {
+            tokens.push((offset, Token::Punct(c)));
+            pos += 1;
+        } else {
+            return Err(lex_error(offset, "valid token", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Parser> {
+        Ok(Parser {
+            tokens: lex(source)?,
+            pos: 0,
+            end: source.len()
+        })
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(offset, _)| *offset)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn error_at(&self, offset: usize, expected: &str, actual: impl std::fmt::Display) -> Error {
+        lex_error(offset, expected, actual)
+    }
+
+    fn error(&self, expected: &str, actual: impl std::fmt::Display) -> Error {
+        self.error_at(self.offset(), expected, actual)
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        match self.peek() {
+            Some(token) => self.error(expected, token),
+            None => self.error(expected, "end of source")
+        }
+    }
+
+    fn next(&mut self, expected: &str) -> Result<Token> {
+        let token = self.peek().cloned().ok_or_else(|| self.unexpected(expected))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(Token::Ident(s)) => {
+                let s = s.clone();
+                self.pos += 1;
+                Ok(s)
+            },
+            _ => Err(self.unexpected("identifier"))
+        }
+    }
+
+    fn punct(&mut self, c: char) -> Result<()> {
+        if self.eat_punct(c) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", c)))
+        }
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek_number(&self) -> bool {
+        matches!(self.peek(), Some(Token::Number(_)))
+    }
+
+    fn integer(&mut self, min: i64, max: i64) -> Result<i64> {
+        let offset = self.offset();
+        match self.next("number")? {
+            Token::Number(n) if n >= min && n <= max => Ok(n),
+            Token::Number(n) => Err(self.error_at(offset, &format!("number in {}..={}", min, max), n)),
+            token => Err(self.error_at(offset, "number", token))
+        }
+    }
+
+    fn rsrc_type(&mut self) -> Result<OSType> {
+        let offset = self.offset();
+        match self.next("resource type")? {
+            Token::Type(t) if t.len() == 4 => Ok(OSType([t[0], t[1], t[2], t[3]])),
+            token => Err(self.error_at(offset, "4 character resource type", token))
+        }
+    }
+
+    // Adjacent strings are concatenated
+    fn eat_string(&mut self) -> Option<Vec<u8>> {
+        let mut data = None;
+        while let Some(Token::String(s)) = self.peek() {
+            data.get_or_insert_with(Vec::new).extend_from_slice(s);
+            self.pos += 1;
+        }
+        data
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>> {
+        self.eat_string().ok_or_else(|| self.unexpected("string"))
+    }
+
+    fn pstring(&mut self, data: &mut Vec<u8>) -> Result<()> {
+        let offset = self.offset();
+        let s = self.string()?;
+        if s.len() > 255 {
+            return Err(self.error_at(offset, "string of at most 255 bytes", s.len()));
+        }
+        data.push(s.len() as u8);
+        data.extend_from_slice(&s);
+        Ok(())
+    }
+
+    fn data_body(&mut self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Token::Hex(d)) | Some(Token::String(d)) => data.extend_from_slice(d),
+                _ => return Ok(data)
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn typed_body(&mut self, rsrc_type: &OSType) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        if *rsrc_type == STR {
+            self.pstring(&mut data)?;
+        } else if *rsrc_type == STR_LIST {
+            data.extend_from_slice(&[0, 0]);
+            let mut count = 0u16;
+            self.punct('{')?;
+            while !self.eat_punct('}') {
+                if count == 0xffff {
+                    return Err(self.error("at most 65535 strings", "more"));
+                }
+                self.pstring(&mut data)?;
+                count += 1;
+                if !self.eat_punct(',') {
+                    self.punct('}')?;
+                    break;
+                }
+            }
+            data[0..2].copy_from_slice(&count.to_be_bytes());
+        } else if *rsrc_type == TEXT {
+            data = self.string()?;
+        } else if *rsrc_type == VERS {
+            data.push(self.integer(0, 0xff)? as u8);
+            self.punct(',')?;
+            data.push(self.integer(0, 0xff)? as u8);
+            self.punct(',')?;
+            let stage = match self.peek() {
+                Some(Token::Ident(s)) => {
+                    let offset = self.offset();
+                    let s = s.clone();
+                    self.pos += 1;
+                    VERS_STAGES.iter().find(|(name, _)| *name == s)
+                        .map(|(_, v)| *v)
+                        .ok_or_else(|| self.error_at(offset, "release stage", s))?
+                },
+                _ => self.integer(0, 0xff)? as u8
+            };
+            data.push(stage);
+            self.punct(',')?;
+            data.push(self.integer(0, 0xff)? as u8);
+            self.punct(',')?;
+            data.extend_from_slice(&(self.integer(-0x8000, 0xffff)? as u16).to_be_bytes());
+            self.punct(',')?;
+            self.pstring(&mut data)?;
+            self.punct(',')?;
+            self.pstring(&mut data)?;
+        } else {
+            return Err(self.error("type with a known layout, or 'data'", rsrc_type));
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{derez, rez};
+    use crate::filesys::rsrc::{RsrcAttributes, RsrcFork};
+    use crate::types::{OSType, PString};
+
+    fn sample() -> RsrcFork {
+        let mut fork = RsrcFork::new();
+        fork.add(OSType::from(b"STR "), 128, Some(PString::from("Caf\u{e9} \"1\"")), b"\x05Hello".to_vec()).unwrap();
+        fork.add(OSType::from(b"STR#"), 129, None, b"\x00\x02\x03One\x00".to_vec()).unwrap();
+        fork.add(OSType::from(b"TEXT"), 130, None, b"Line 1\rLine\t2\\\r".to_vec()).unwrap();
+        fork.add(OSType::from(b"vers"), 1, None, b"\x01\x20\x60\x03\x00\x00\x031.2\x04v1.2".to_vec()).unwrap();
+        fork.add(OSType::from(b"ICN#"), -4064, None, (0..40).collect()).unwrap();
+        // Trailing data after the string doesn't fit the typed layout
+        fork.add(OSType::from(b"STR "), 131, None, b"\x01A\xff".to_vec()).unwrap();
+        fork.add(OSType([b'a', b'\'', 0, 0xa5]), 0, None, vec![]).unwrap();
+        fork.set_attributes(&OSType::from(b"STR "), 128, RsrcAttributes(RsrcAttributes::PURGEABLE | RsrcAttributes::LOCKED)).unwrap();
+        fork.set_attributes(&OSType::from(b"ICN#"), -4064, RsrcAttributes(RsrcAttributes::COMPRESSED)).unwrap();
+        fork
+    }
+
+    #[test]
+    fn derez_text() {
+        let text = derez(&sample());
+        assert!(text.contains("resource 'STR ' (128, \"Caf\u{e9} \\\"1\\\"\", purgeable, locked) {\n\t\"Hello\"\n};\n"));
+        assert!(text.contains("resource 'STR#' (129) {\n\t{\n\t\t\"One\",\n\t\t\"\"\n\t}\n};\n"));
+        assert!(text.contains("\t\"Line 1\\n\"\n\t\"Line\\t2\\\\\\n\"\n"));
+        assert!(text.contains("\t0x01, 0x20, beta, 0x03,\n\t0,\n\t\"1.2\",\n\t\"v1.2\"\n"));
+        assert!(text.contains("data 'ICN#' (-4064, $01) {\n\t$\"0001 0203 0405 0607 0809 0A0B 0C0D 0E0F\"  /* ................ */\n"));
+        assert!(text.contains("data 'STR ' (131) {\n\t$\"0141 FF\""));
+        assert!(text.contains("data 'a\\'\\0x00\u{2022}' (0) {\n};\n"));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let fork = sample();
+        let compiled = rez(&derez(&fork)).unwrap();
+        assert_eq!(compiled.to_bytes().unwrap(), fork.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn all_bytes_roundtrip() {
+        let mut fork = RsrcFork::new();
+        let data: Vec<u8> = (0..=255).collect();
+        fork.add(OSType::from(b"TEXT"), 128, Some(PString::from(&data[1..])), data).unwrap();
+        let text = derez(&fork);
+        assert!(text.starts_with("resource 'TEXT'"));
+        assert_eq!(rez(&text).unwrap().to_bytes().unwrap(), fork.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn rez_source() {
+        let fork = rez("
+            // Comments and Rez number syntax
+            data 'abcd' (0x10, \"x\" \"y\", sysheap, preload, nonpreload) {
+                $\"01 02\" $\"0304\"    /* { */
+                \"\\$41\\101\\0x41\"
+            };
+            resource 'STR#' (-1) { { \"a\", \"b\", } };
+            resource 'vers' (2) { $1, 0, 0x80, 0, -1, \"\", \"\" };
+        ").unwrap();
+        let entry = fork.get(&OSType::from(b"abcd"), 16).unwrap();
+        assert_eq!(entry.data, b"\x01\x02\x03\x04AAA".to_vec());
+        assert_eq!(entry.name, Some(PString::from("xy")));
+        assert_eq!(entry.attributes.0, RsrcAttributes::SYS_HEAP);
+        assert_eq!(fork.get(&OSType::from(b"STR#"), -1).unwrap().data, b"\x00\x02\x01a\x01b".to_vec());
+        assert_eq!(fork.get(&OSType::from(b"vers"), 2).unwrap().data, b"\x01\x00\x80\x00\xff\xff\x00\x00".to_vec());
+    }
+
+    #[test]
+    fn rez_errors() {
+        assert!(rez("data 'abc' (1) {};").is_err());
+        assert!(rez("data 'abcd' (1) { $\"123\" };").is_err());
+        assert!(rez("data 'abcd' (40000) {};").is_err());
+        assert!(rez("data 'abcd' (1, bogus) {};").is_err());
+        assert!(rez("resource 'abcd' (1) { 1 };").is_err());
+        assert!(rez("data 'abcd' (1) {}").is_err());
+        assert!(rez("data 'abcd' (1) {}; data 'abcd' (1) {};").is_err());
+        assert!(rez("data 'abcd' (1) { \"abc }; ").is_err());
+    }
+}
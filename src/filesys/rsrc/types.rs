@@ -3,6 +3,7 @@ use crate::serialization::{
     SerialReadStorage
 };
 use crate::types::OSType;
+use crate::Result;
 
 #[derive(Debug)]
 #[derive(SerialRead)]
@@ -20,9 +21,9 @@ pub struct RsrcData {
 }
 
 impl SerialRead for RsrcData {
-    fn read(rdr: &mut SerialReadStorage) -> std::io::Result<RsrcData> {
+    fn read(rdr: &mut SerialReadStorage) -> Result<RsrcData> {
         let len = u32::read(rdr)?;
-        let data = rdr.sub_reader(rdr.pos(), len as u64);
+        let data = rdr.sub_reader(rdr.pos(), len as u64)?;
         Ok(RsrcData { len, data })
     }
 }
@@ -32,8 +33,11 @@ impl SerialRead for RsrcData {
 pub struct RsrcMapHeader {
     #[pad(22)]
     pub attributes : u16,
-    pub type_list_offset : i16,
-    pub name_list_offset : i16
+    pub type_list_offset : u16,
+    pub name_list_offset : u16,
+    #[seek(type_list_offset)]
+    #[count_prefix_minus_one(i16)]
+    pub type_refs : Vec<RsrcTypeRef>
 }
 
 #[derive(Debug)]
@@ -53,12 +57,80 @@ pub struct RsrcRef {
 }
 
 impl SerialRead for RsrcRef {
-    fn read(rdr: &mut SerialReadStorage) -> std::io::Result<RsrcRef> {
+    fn read(rdr: &mut SerialReadStorage) -> Result<RsrcRef> {
         let id = rdr.read_i16()?;
         let name_offset = rdr.read_i16()?;
         let attributes = rdr.read_u8()?;
         let data_offset = rdr.read_u24()? as i32;
-        rdr.pad(4);
+        rdr.pad(4)?;
         Ok(RsrcRef {id, name_offset, attributes, data_offset})
     }
-}
\ No newline at end of file
+}
+// Resource attribute flags, from the reference list entry
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+pub struct RsrcAttributes (pub u8);
+
+impl RsrcAttributes {
+    pub const SYS_HEAP: u8 = 0x40;   // resSysHeap:   {read into system heap}
+    pub const PURGEABLE: u8 = 0x20;  // resPurgeable: {purgeable}
+    pub const LOCKED: u8 = 0x10;     // resLocked:    {locked}
+    pub const PROTECTED: u8 = 0x08;  // resProtected: {protected}
+    pub const PRELOAD: u8 = 0x04;    // resPreload:   {read in at open}
+    pub const CHANGED: u8 = 0x02;    // resChanged:   {write to resource file}
+    pub const COMPRESSED: u8 = 0x01; // resExtended:  {compressed, System 7.1 and later}
+
+    pub fn contains(&self, flags: u8) -> bool {
+        self.0 & flags == flags
+    }
+
+    pub fn sys_heap(&self) -> bool {
+        self.contains(RsrcAttributes::SYS_HEAP)
+    }
+
+    pub fn purgeable(&self) -> bool {
+        self.contains(RsrcAttributes::PURGEABLE)
+    }
+
+    pub fn locked(&self) -> bool {
+        self.contains(RsrcAttributes::LOCKED)
+    }
+
+    pub fn protected(&self) -> bool {
+        self.contains(RsrcAttributes::PROTECTED)
+    }
+
+    pub fn preload(&self) -> bool {
+        self.contains(RsrcAttributes::PRELOAD)
+    }
+
+    pub fn changed(&self) -> bool {
+        self.contains(RsrcAttributes::CHANGED)
+    }
+
+    pub fn compressed(&self) -> bool {
+        self.contains(RsrcAttributes::COMPRESSED)
+    }
+}
+
+impl std::fmt::Debug for RsrcAttributes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = [
+            (RsrcAttributes::SYS_HEAP, "sysHeap"),
+            (RsrcAttributes::PURGEABLE, "purgeable"),
+            (RsrcAttributes::LOCKED, "locked"),
+            (RsrcAttributes::PROTECTED, "protected"),
+            (RsrcAttributes::PRELOAD, "preload"),
+            (RsrcAttributes::CHANGED, "changed"),
+            (RsrcAttributes::COMPRESSED, "compressed"),
+        ];
+        let set: Vec<&str> = names.iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect();
+        write!(f, "RsrcAttributes(${:02x}: {})", self.0, set.join(", "))
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashSet;
+
+use crate::types::{
+    OSType,
+    PString
+};
+
+use super::{
+    RsrcAttributes,
+    RsrcFork
+};
+
+// Consistency checks of a resource fork, done on the raw fork data without
+// trusting any offset or count, so it works on forks RsrcMap can't read.
+
+const HEADER_LEN: u64 = 16;
+
+// Header copy, next map handle, file reference number, attributes, type list
+// offset and name list offset
+const MAP_HEADER_LEN: u64 = 28;
+
+const TYPE_REF_LEN: u64 = 8;
+const RSRC_REF_LEN: u64 = 12;
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum Severity {
+    // The fork is inconsistent, but all resources can be read
+    Warning,
+    // Some or all resources can't be read
+    Error
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub enum Problem {
+    // Fork is smaller than the header
+    TooSmall { size: u64 },
+    // Data or map area from the header is outside the fork
+    AreaOutOfBounds { area: &'static str, offset: i64, len: u64, size: u64 },
+    AreasOverlap,
+    // The map starts with a copy of the header, which differs
+    MapHeaderMismatch,
+    TypeListOutOfBounds { offset: u64, count: u64 },
+    DuplicateType { rsrc_type: OSType },
+    RefListOutOfBounds { rsrc_type: OSType, offset: u64, count: u64 },
+    NameOutOfBounds { rsrc_type: OSType, id: i16, offset: u64 },
+    DataOutOfBounds { rsrc_type: OSType, id: i16, offset: u64 },
+    DataOverlap { rsrc_type: OSType, id: i16, other_type: OSType, other_id: i16 },
+    DuplicateId { rsrc_type: OSType, id: i16 }
+}
+
+impl Problem {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Problem::MapHeaderMismatch |
+            Problem::DuplicateType { .. } |
+            Problem::NameOutOfBounds { .. } |
+            Problem::DataOverlap { .. } |
+            Problem::DuplicateId { .. } => Severity::Warning,
+            _ => Severity::Error
+        }
+    }
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::TooSmall { size } =>
+                write!(f, "fork of {} bytes is too small for a header", size),
+            Problem::AreaOutOfBounds { area, offset, len, size } =>
+                write!(f, "{} area of {} bytes at offset {} exceeds fork size {}", area, len, offset, size),
+            Problem::AreasOverlap =>
+                write!(f, "data and map areas overlap"),
+            Problem::MapHeaderMismatch =>
+                write!(f, "header copy in map differs from header"),
+            Problem::TypeListOutOfBounds { offset, count } =>
+                write!(f, "type list of {} types at map offset {} exceeds map", count, offset),
+            Problem::DuplicateType { rsrc_type } =>
+                write!(f, "type {} is listed more than once", rsrc_type),
+            Problem::RefListOutOfBounds { rsrc_type, offset, count } =>
+                write!(f, "reference list of {} {} resources at map offset {} exceeds map", count, rsrc_type, offset),
+            Problem::NameOutOfBounds { rsrc_type, id, offset } =>
+                write!(f, "name of {} {} at map offset {} exceeds map", rsrc_type, id, offset),
+            Problem::DataOutOfBounds { rsrc_type, id, offset } =>
+                write!(f, "data of {} {} at data offset {} exceeds data area", rsrc_type, id, offset),
+            Problem::DataOverlap { rsrc_type, id, other_type, other_id } =>
+                write!(f, "data of {} {} overlaps {} {}", rsrc_type, id, other_type, other_id),
+            Problem::DuplicateId { rsrc_type, id } =>
+                write!(f, "{} {} is listed more than once", rsrc_type, id)
+        }
+    }
+}
+
+#[derive(Debug)]
+#[derive(Default)]
+pub struct ValidationReport {
+    pub problems: Vec<Problem>,
+    // References found in the map
+    pub resources: usize,
+    // Resources with readable data, which salvage keeps
+    pub salvageable: usize
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    // All resources can be read, even if the fork has inconsistencies
+    pub fn is_readable(&self) -> bool {
+        !self.problems.iter().any(|p| p.severity() == Severity::Error)
+    }
+}
+
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} resources, {} salvageable, {} problems", self.resources, self.salvageable, self.problems.len())?;
+        for problem in self.problems.iter() {
+            writeln!(f, "    {:?}: {}", problem.severity(), problem)?;
+        }
+        Ok(())
+    }
+}
+
+fn be(data: &[u8], pos: u64, len: u64) -> Option<u32> {
+    let end = pos.checked_add(len)?;
+    let bytes = data.get(pos as usize..end as usize)?;
+    Some(bytes.iter().fold(0, |v, b| v << 8 | *b as u32))
+}
+
+fn within(pos: u64, len: u64, end: u64) -> bool {
+    pos.checked_add(len).is_some_and(|e| e <= end)
+}
+
+struct Salvaged {
+    rsrc_type: OSType,
+    id: i16,
+    name: Option<PString>,
+    attributes: RsrcAttributes,
+    data: Vec<u8>
+}
+
+fn walk(data: &[u8]) -> (ValidationReport, u16, Vec<Salvaged>) {
+    let mut report = ValidationReport::default();
+    let mut salvaged = Vec::new();
+    let size = data.len() as u64;
+    let header = (be(data, 0, 4), be(data, 4, 4), be(data, 8, 4), be(data, 12, 4));
+    let (data_offset, map_offset, data_len, map_len) = match header {
+        (Some(d), Some(m), Some(dl), Some(ml)) => (d as i32 as i64, m as i32 as i64, dl as u64, ml as u64),
+        _ => {
+            report.problems.push(Problem::TooSmall { size });
+            return (report, 0, salvaged);
+        }
+    };
+
+    // Map area, nothing can be found without it
+    if map_offset < HEADER_LEN as i64 || !within(map_offset as u64, map_len, size) || map_len < MAP_HEADER_LEN + 2 {
+        report.problems.push(Problem::AreaOutOfBounds { area: "map", offset: map_offset, len: map_len, size });
+        return (report, 0, salvaged);
+    }
+    let map = &data[map_offset as usize..(map_offset as u64 + map_len) as usize];
+
+    // Data area, clamped to the fork so resources inside it can be salvaged
+    let data_area = if data_offset < HEADER_LEN as i64 || data_offset as u64 > size {
+        report.problems.push(Problem::AreaOutOfBounds { area: "data", offset: data_offset, len: data_len, size });
+        &data[0..0]
+    } else {
+        if !within(data_offset as u64, data_len, size) {
+            report.problems.push(Problem::AreaOutOfBounds { area: "data", offset: data_offset, len: data_len, size });
+        }
+        let end = (data_offset as u64).saturating_add(data_len).min(size);
+        &data[data_offset as usize..end as usize]
+    };
+    if !data_area.is_empty() {
+        let data_end = data_offset + data_area.len() as i64;
+        if data_offset < map_offset + map.len() as i64 && map_offset < data_end {
+            report.problems.push(Problem::AreasOverlap);
+        }
+    }
+    if map[0..HEADER_LEN as usize] != data[0..HEADER_LEN as usize] {
+        report.problems.push(Problem::MapHeaderMismatch);
+    }
+
+    let map_size = map.len() as u64;
+    let attributes = be(map, 22, 2).unwrap_or(0) as u16;
+    let type_list = be(map, 24, 2).unwrap_or(0) as u64;
+    let name_list = be(map, 26, 2).unwrap_or(0) as u64;
+
+    let mut type_count = match be(map, type_list, 2) {
+        Some(count) => (count as u16 as i16 as i64 + 1).max(0) as u64,
+        None => {
+            report.problems.push(Problem::TypeListOutOfBounds { offset: type_list, count: 0 });
+            return (report, attributes, salvaged);
+        }
+    };
+    if !within(type_list + 2, type_count * TYPE_REF_LEN, map_size) {
+        report.problems.push(Problem::TypeListOutOfBounds { offset: type_list, count: type_count });
+        type_count = map_size.saturating_sub(type_list + 2) / TYPE_REF_LEN;
+    }
+
+    let mut seen_types: HashSet<u32> = HashSet::new();
+    let mut seen_ids: HashSet<(u32, i16)> = HashSet::new();
+    let mut ranges: Vec<(u64, u64, OSType, i16)> = Vec::new();
+    for t in 0..type_count {
+        let pos = type_list + 2 + t * TYPE_REF_LEN;
+        let rsrc_type = OSType::from(be(map, pos, 4).unwrap_or(0));
+        let mut count = be(map, pos + 4, 2).unwrap_or(0) as u64 + 1;
+        let ref_list = type_list + be(map, pos + 6, 2).unwrap_or(0) as u64;
+        if !seen_types.insert(rsrc_type.as_u32()) {
+            report.problems.push(Problem::DuplicateType { rsrc_type: rsrc_type.clone() });
+        }
+        if !within(ref_list, count * RSRC_REF_LEN, map_size) {
+            report.problems.push(Problem::RefListOutOfBounds { rsrc_type: rsrc_type.clone(), offset: ref_list, count });
+            count = map_size.saturating_sub(ref_list) / RSRC_REF_LEN;
+        }
+
+        for r in 0..count {
+            let pos = ref_list + r * RSRC_REF_LEN;
+            let id = be(map, pos, 2).unwrap_or(0) as u16 as i16;
+            let name_offset = be(map, pos + 2, 2).unwrap_or(0) as u16 as i16;
+            let attr = be(map, pos + 4, 1).unwrap_or(0) as u8;
+            let rsrc_offset = be(map, pos + 5, 3).unwrap_or(0) as u64;
+            report.resources += 1;
+
+            let duplicate = !seen_ids.insert((rsrc_type.as_u32(), id));
+            if duplicate {
+                report.problems.push(Problem::DuplicateId { rsrc_type: rsrc_type.clone(), id });
+            }
+
+            let name = if name_offset >= 0 {
+                let name_pos = name_list + name_offset as u64;
+                match be(map, name_pos, 1) {
+                    Some(len) if within(name_pos + 1, len as u64, map_size) => {
+                        let start = name_pos as usize + 1;
+                        Some(PString::from(&map[start..start + len as usize]))
+                    },
+                    _ => {
+                        report.problems.push(Problem::NameOutOfBounds { rsrc_type: rsrc_type.clone(), id, offset: name_pos });
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+
+            let data_size = data_area.len() as u64;
+            match be(data_area, rsrc_offset, 4) {
+                Some(len) if within(rsrc_offset + 4, len as u64, data_size) => {
+                    let start = rsrc_offset + 4;
+                    let end = start + len as u64;
+                    ranges.push((rsrc_offset, end, rsrc_type.clone(), id));
+                    report.salvageable += 1;
+                    if !duplicate {
+                        salvaged.push(Salvaged {
+                            rsrc_type: rsrc_type.clone(),
+                            id,
+                            name,
+                            attributes: RsrcAttributes(attr),
+                            data: data_area[start as usize..end as usize].to_vec()
+                        });
+                    }
+                },
+                _ => report.problems.push(Problem::DataOutOfBounds { rsrc_type: rsrc_type.clone(), id, offset: rsrc_offset })
+            }
+        }
+    }
+
+    // Each resource should have data of its own
+    ranges.sort_by_key(|r| (r.0, r.1));
+    let mut last: Option<&(u64, u64, OSType, i16)> = None;
+    for range in ranges.iter() {
+        if let Some(prev) = last {
+            if range.0 < prev.1 {
+                report.problems.push(Problem::DataOverlap {
+                    rsrc_type: range.2.clone(),
+                    id: range.3,
+                    other_type: prev.2.clone(),
+                    other_id: prev.3
+                });
+            }
+        }
+        if last.is_none_or(|prev| range.1 > prev.1) {
+            last = Some(range);
+        }
+    }
+
+    (report, attributes, salvaged)
+}
+
+// Check a complete resource fork
+pub fn validate(data: &[u8]) -> ValidationReport {
+    walk(data).0
+}
+
+// Rebuild a fork from all resources with readable data. Of resources listed
+// more than once, the first is kept.
+pub fn salvage(data: &[u8]) -> (RsrcFork, ValidationReport) {
+    let (report, attributes, salvaged) = walk(data);
+    let mut fork = RsrcFork::new();
+    fork.attributes = attributes;
+    for s in salvaged {
+        if fork.add(s.rsrc_type.clone(), s.id, s.name, s.data).is_ok() {
+            let _ = fork.set_attributes(&s.rsrc_type, s.id, s.attributes);
+        }
+    }
+    (fork, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{salvage, validate, Problem, Severity};
+    use crate::filesys::rsrc::{Rsrc, RsrcFork};
+    use crate::serialization::SerialAdaptor;
+    use crate::types::{OSType, PString};
+    use std::io::Cursor;
+
+    fn sample() -> Vec<u8> {
+        let mut fork = RsrcFork::new();
+        fork.add(OSType::from(b"STR "), 128, Some(PString::from("one")), vec![1, 2, 3]).unwrap();
+        fork.add(OSType::from(b"STR "), 129, None, vec![4, 5]).unwrap();
+        fork.add(OSType::from(b"ICN#"), 128, None, vec![6; 8]).unwrap();
+        fork.to_bytes().unwrap()
+    }
+
+    // Offsets of the sample: data at 256, map after 3 resources of 4 + 3, 2
+    // and 8 bytes, reference list entries after the map header and 2 types
+    const MAP: usize = 256 + 7 + 6 + 12;
+    const REFS: usize = MAP + 28 + 2 + 16;
+
+    #[test]
+    fn valid() {
+        let report = validate(&sample());
+        assert!(report.is_valid());
+        assert_eq!((report.resources, report.salvageable), (3, 3));
+    }
+
+    #[test]
+    fn damaged() {
+        assert_eq!(validate(&[0; 8]).problems, vec![Problem::TooSmall { size: 8 }]);
+
+        // Map outside the fork
+        let mut data = sample();
+        data[7] = 0xff;
+        assert!(matches!(validate(&data).problems[0], Problem::AreaOutOfBounds { area: "map", .. }));
+
+        // Length of the second resource too large, and a bad name offset
+        let mut data = sample();
+        data[256 + 7] = 0x10;
+        data[REFS + 3] = 0x40;
+        let report = validate(&data);
+        assert!(!report.is_readable());
+        assert_eq!(report.salvageable, 2);
+        assert!(report.problems.contains(&Problem::DataOutOfBounds { rsrc_type: OSType::from(b"STR "), id: 129, offset: 7 }));
+        assert!(report.problems.iter().any(|p| matches!(p, Problem::NameOutOfBounds { id: 128, .. })));
+
+        // Data shared by two resources, a duplicate id and a bad header copy
+        let mut data = sample();
+        data[MAP] = 1;
+        data[REFS + 12 + 7] = 0;
+        data[REFS + 12 + 1] = 128;
+        let report = validate(&data);
+        assert!(report.is_readable());
+        assert!(report.problems.iter().all(|p| p.severity() == Severity::Warning));
+        assert!(report.problems.contains(&Problem::DuplicateId { rsrc_type: OSType::from(b"STR "), id: 128 }));
+        assert!(report.problems.iter().any(|p| matches!(p, Problem::DataOverlap { .. })));
+        assert!(report.problems.contains(&Problem::MapHeaderMismatch));
+    }
+
+    #[test]
+    fn rebuild() {
+        let mut data = sample();
+        data[256 + 7] = 0x10;
+        let (fork, report) = salvage(&data);
+        assert_eq!(report.salvageable, 2);
+        let rebuilt = fork.to_bytes().unwrap();
+        assert!(validate(&rebuilt).is_valid());
+        let rsrc = Rsrc::new(SerialAdaptor::new(Cursor::new(rebuilt))).unwrap();
+        assert_eq!(rsrc.open(OSType::from(b"STR "), 128).unwrap().to_vec(), vec![1, 2, 3]);
+        assert_eq!(rsrc.name(OSType::from(b"STR "), 128).unwrap(), Some(PString::from("one")));
+        assert!(rsrc.open(OSType::from(b"STR "), 129).is_err());
+        assert_eq!(rsrc.open(OSType::from(b"ICN#"), 128).unwrap().to_vec(), vec![6; 8]);
+    }
+}
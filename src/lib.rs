@@ -3,10 +3,14 @@ extern crate marmelade_derive;
 extern crate r68k_emu;
 extern crate r68k_tools;
 
+mod error;
+
 pub mod filesys;
 pub mod serialization;
 pub mod types;
 pub mod phy;
 pub mod toolbox;
 
-pub mod tools;
\ No newline at end of file
+pub mod tools;
+
+pub use error::{Error, Result};
\ No newline at end of file
@@ -0,0 +1,860 @@
+use std::cell::Cell;
+use std::io::{BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+use r68k_emu::ram::AddressBus;
+
+use crate::toolbox::trap_name;
+use crate::tools::disasm;
+
+use super::{
+    Phy,
+    TrapHandler,
+    trace
+};
+use super::mem::WatchMem;
+use super::mem::watch::{
+    Watchpoint,
+    WatchHit,
+    WATCH_READ,
+    WATCH_WRITE
+};
+
+// Interactive debugger for the emulation, stopping on breakpoints, A-line
+// traps and watched memory accesses. Memory must be wrapped in WatchMem for
+// the watchpoints.
+
+type DebugPhy<M, T> = Phy<WatchMem<M>, T>;
+
+// Condition code bits, as kept by the r68k core
+const XFLAG_SET: u32 = 0x100;
+const NFLAG_SET: u32 = 0x80;
+const VFLAG_SET: u32 = 0x80;
+const CFLAG_SET: u32 = 0x100;
+
+// Longest 68000 instruction
+const MAX_INSTRUCTION_LEN: u32 = 10;
+
+// Opcodes returning from a subroutine
+const RTS: u16 = 0x4e75;
+const RTD: u16 = 0x4e74;
+const RTR: u16 = 0x4e77;
+
+thread_local! {
+    static IN_EMULATION: Cell<bool> = const { Cell::new(false) };
+}
+
+static SILENCE_FAULTS: Once = Once::new();
+
+// Run part of the emulation, which panics on faults such as accesses to
+// unmapped memory. Faults are reported by the debugger, so the panic message
+// is silenced while emulating. The hook is installed once for the process,
+// and other panics still go to the previous hook.
+fn catch_fault<R>(f: impl FnOnce() -> R) -> std::thread::Result<R> {
+    SILENCE_FAULTS.call_once(|| {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !IN_EMULATION.with(|emulating| emulating.get()) {
+                hook(info);
+            }
+        }));
+    });
+    let outer = IN_EMULATION.with(|emulating| emulating.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    IN_EMULATION.with(|emulating| emulating.set(outer));
+    result
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum Register {
+    Pc,
+    D(usize),
+    A(usize),
+    X,
+    N,
+    Z,
+    V,
+    C
+}
+
+impl Register {
+    pub fn from_name(name: &str) -> Option<Register> {
+        let name = name.to_lowercase();
+        let index = |s: &str| s.parse::<usize>().ok().filter(|i| *i < 8);
+        match name.as_str() {
+            "pc" => Some(Register::Pc),
+            "sp" => Some(Register::A(7)),
+            "x" => Some(Register::X),
+            "n" => Some(Register::N),
+            "z" => Some(Register::Z),
+            "v" => Some(Register::V),
+            "c" => Some(Register::C),
+            _ if name.starts_with('d') => index(&name[1..]).map(Register::D),
+            _ if name.starts_with('a') => index(&name[1..]).map(Register::A),
+            _ => None
+        }
+    }
+
+    pub fn get<M: AddressBus, T: TrapHandler>(&self, phy: &Phy<M, T>) -> u32 {
+        let core = &phy.core;
+        match self {
+            Register::Pc => core.pc,
+            Register::D(index) => core.dar[*index],
+            Register::A(index) => core.dar[8 + *index],
+            Register::X => (core.x_flag & XFLAG_SET != 0) as u32,
+            Register::N => (core.n_flag & NFLAG_SET != 0) as u32,
+            Register::Z => (core.not_z_flag == 0) as u32,
+            Register::V => (core.v_flag & VFLAG_SET != 0) as u32,
+            Register::C => (core.c_flag & CFLAG_SET != 0) as u32
+        }
+    }
+
+    pub fn set<M: AddressBus, T: TrapHandler>(&self, phy: &mut Phy<M, T>, value: u32) {
+        let core = &mut phy.core;
+        let flag = |set: u32| if value != 0 { set } else { 0 };
+        match self {
+            Register::Pc => core.pc = value,
+            Register::D(index) => core.dar[*index] = value,
+            Register::A(index) => core.dar[8 + *index] = value,
+            Register::X => core.x_flag = flag(XFLAG_SET),
+            Register::N => core.n_flag = flag(NFLAG_SET),
+            Register::Z => core.not_z_flag = (value == 0) as u32,
+            Register::V => core.v_flag = flag(VFLAG_SET),
+            Register::C => core.c_flag = flag(CFLAG_SET)
+        }
+    }
+}
+
+// Value in a command, a register or a hexadecimal number. Register names take
+// precedence, so numbers like $a0 need a prefix.
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub enum Operand {
+    Number(u32),
+    Register(Register)
+}
+
+impl Operand {
+    pub fn parse(s: &str) -> Result<Operand, String> {
+        if let Some(register) = Register::from_name(s) {
+            return Ok(Operand::Register(register));
+        }
+        let digits = s.strip_prefix('$')
+            .or_else(|| s.strip_prefix("0x"))
+            .unwrap_or(s);
+        u32::from_str_radix(digits, 16)
+            .map(Operand::Number)
+            .map_err(|_| format!("Not a register or hexadecimal number: {}", s))
+    }
+
+    pub fn value<M: AddressBus, T: TrapHandler>(&self, phy: &Phy<M, T>) -> u32 {
+        match self {
+            Operand::Number(value) => *value,
+            Operand::Register(register) => register.get(phy)
+        }
+    }
+}
+
+// Where to stop, an address or an A-line trap by name as written in MPW,
+// such as _GetResource
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub enum Location {
+    Address(Operand),
+    Trap(String)
+}
+
+impl Location {
+    pub fn parse(s: &str) -> Result<Location, String> {
+        match s.strip_prefix('_') {
+            Some(name) => match trap_by_name(name) {
+                Some(name) => Ok(Location::Trap(name.to_string())),
+                None => Err(format!("Unknown trap: {}", s))
+            },
+            None => Operand::parse(s).map(Location::Address)
+        }
+    }
+}
+
+// Name of a trap as in the trap table, searched case insensitive
+fn trap_by_name(name: &str) -> Option<&'static str> {
+    (0xa000..=0xafffu16)
+        .filter_map(trap_name)
+        .find(|trap| trap.eq_ignore_ascii_case(name))
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub enum Command {
+    Continue,
+    Step(u32),
+    // Step over subroutine calls
+    Next,
+    // Run until the current subroutine returns
+    Finish,
+    Break(Location),
+    Watch(Operand, u32, u32),
+    // Remove breakpoints and watchpoints at a location, or all
+    Clear(Option<Location>),
+    List,
+    Registers,
+    Set(Register, Operand),
+    Examine(Operand, u32),
+    Poke(Operand, Vec<u8>),
+    Disassemble(Option<Operand>, u32),
+    Trace(bool),
+    Help,
+    Quit
+}
+
+fn parse_count(arg: Option<&&str>, default: u32) -> Result<u32, String> {
+    match arg {
+        Some(arg) => arg.parse::<u32>().map_err(|_| format!("Not a count: {}", arg)),
+        None => Ok(default)
+    }
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (name, args) = match words.split_first() {
+            Some((name, args)) => (name.to_lowercase(), args),
+            None => return Err(String::from("No command"))
+        };
+        let arg = |index: usize| args.get(index).ok_or_else(|| format!("Missing argument to {}", name));
+        let command = match name.as_str() {
+            "c" | "continue" => Command::Continue,
+            "s" | "step" => Command::Step(parse_count(args.first(), 1)?),
+            "n" | "next" => Command::Next,
+            "f" | "finish" => Command::Finish,
+            "b" | "break" => Command::Break(Location::parse(arg(0)?)?),
+            "w" | "watch" => {
+                let access = match args.get(2).map(|a| a.to_lowercase()).as_deref() {
+                    None | Some("rw") => WATCH_READ | WATCH_WRITE,
+                    Some("r") => WATCH_READ,
+                    Some("w") => WATCH_WRITE,
+                    Some(access) => return Err(format!("Access should be r, w or rw: {}", access))
+                };
+                Command::Watch(Operand::parse(arg(0)?)?, parse_count(args.get(1), 4)?, access)
+            },
+            "clear" => Command::Clear(args.first().map(|arg| Location::parse(arg)).transpose()?),
+            "l" | "list" => Command::List,
+            "r" | "regs" => Command::Registers,
+            "set" => {
+                let register = Register::from_name(arg(0)?).ok_or_else(|| format!("Unknown register: {}", args[0]))?;
+                Command::Set(register, Operand::parse(arg(1)?)?)
+            },
+            "x" | "examine" => Command::Examine(Operand::parse(arg(0)?)?, parse_count(args.get(1), 64)?),
+            "poke" => {
+                let bytes = args.iter().skip(1)
+                    .map(|byte| u8::from_str_radix(byte.trim_start_matches('$'), 16).map_err(|_| format!("Not a byte: {}", byte)))
+                    .collect::<Result<Vec<u8>, String>>()?;
+                if bytes.is_empty() {
+                    return Err(String::from("Missing bytes to poke"));
+                }
+                Command::Poke(Operand::parse(arg(0)?)?, bytes)
+            },
+            "d" | "dis" => Command::Disassemble(args.first().map(|arg| Operand::parse(arg)).transpose()?, parse_count(args.get(1), 10)?),
+            "trace" => match args.first().map(|a| a.to_lowercase()).as_deref() {
+                Some("on") => Command::Trace(true),
+                Some("off") => Command::Trace(false),
+                _ => return Err(String::from("Use trace on or trace off"))
+            },
+            "h" | "help" | "?" => Command::Help,
+            "q" | "quit" => Command::Quit,
+            _ => return Err(format!("Unknown command: {}, try help", name))
+        };
+        Ok(command)
+    }
+}
+
+const HELP: &str = "\
+c, continue               Run until a breakpoint, watchpoint or halt
+s, step [count]           Execute instructions
+n, next                   Execute an instruction, stepping over subroutine calls
+f, finish                 Run until the current subroutine returns
+b, break <addr|_Trap>     Stop at an address, or before an A-line trap
+w, watch <addr> [len] [r|w|rw]
+                          Stop after data accesses to memory, default 4 bytes
+clear [addr|_Trap]        Remove breakpoints and watchpoints, or all
+l, list                   List breakpoints and watchpoints
+r, regs                   Show registers
+set <reg> <value>         Set a register, such as d0, a7, pc, or a flag x n z v c
+x, examine <addr> [len]   Show memory
+poke <addr> <byte>...     Write bytes to memory
+d, dis [addr] [count]     Disassemble, around PC by default
+trace on|off              Print each instruction executed
+q, quit                   Leave the debugger
+Numbers are hexadecimal, and registers can be used as addresses. An empty
+line repeats step and next.";
+
+// Why execution stopped
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub enum Stop {
+    Stepped,
+    Finished,
+    Breakpoint(u32),
+    // Trap word about to be executed
+    Trap(u16),
+    Watchpoint(Vec<WatchHit>),
+    // The trap handler halted the CPU
+    Halted,
+    // The emulation panicked, for example on an access to unmapped memory.
+    // The core state may be inconsistent, so execution can't continue.
+    Fault(String)
+}
+
+pub struct Debugger {
+    breakpoints: Vec<u32>,
+    trap_breakpoints: Vec<String>,
+    trace: bool,
+    fault: Option<String>
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger {
+            breakpoints: vec![],
+            trap_breakpoints: vec![],
+            trace: false,
+            fault: None
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, address: u32) {
+        if !self.breakpoints.contains(&address) {
+            self.breakpoints.push(address);
+        }
+    }
+
+    // Stop before executing a trap, by name without the leading '_'
+    pub fn add_trap_breakpoint(&mut self, name: &str) {
+        if !self.trap_breakpoints.iter().any(|trap| trap.eq_ignore_ascii_case(name)) {
+            self.trap_breakpoints.push(name.to_string());
+        }
+    }
+
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    // Bytes of memory, bypassing watchpoints. Reading stops at unmapped
+    // memory, which makes the memory bus panic.
+    pub fn peek<M: AddressBus, T: TrapHandler>(&self, phy: &DebugPhy<M, T>, address: u32, len: u32) -> Vec<u8> {
+        let mut bytes = vec![];
+        for offset in 0..len {
+            let addr = address.wrapping_add(offset);
+            match catch_fault(|| phy.core.mem.peek_byte(addr)) {
+                Ok(byte) => bytes.push(byte),
+                Err(_) => break
+            }
+        }
+        bytes
+    }
+
+    // Write bytes, returning the number written before reaching unmapped
+    // memory
+    pub fn poke<M: AddressBus, T: TrapHandler>(&self, phy: &mut DebugPhy<M, T>, address: u32, bytes: &[u8]) -> usize {
+        for (offset, byte) in bytes.iter().enumerate() {
+            let addr = address.wrapping_add(offset as u32);
+            if catch_fault(|| phy.core.mem.poke_byte(addr, *byte)).is_err() {
+                return offset;
+            }
+        }
+        bytes.len()
+    }
+
+    fn word_at<M: AddressBus, T: TrapHandler>(&self, phy: &DebugPhy<M, T>, address: u32) -> Option<u16> {
+        match self.peek(phy, address, 2)[..] {
+            [high, low] => Some((high as u16) << 8 | low as u16),
+            _ => None
+        }
+    }
+
+    // Instructions starting at an address, as address, length and text
+    pub fn disassemble<M: AddressBus, T: TrapHandler>(&self, phy: &DebugPhy<M, T>, address: u32, count: u32) -> Vec<(u32, u32, String)> {
+        let code = self.peek(phy, address, count * MAX_INSTRUCTION_LEN);
+        let mut lines = vec![];
+        let mut offset = 0;
+        while (offset as usize) < code.len() && (lines.len() as u32) < count {
+            let inst = disasm::decode(&code, offset);
+            lines.push((address.wrapping_add(offset), inst.len, inst.text));
+            offset += inst.len.max(1);
+        }
+        lines
+    }
+
+    // Instructions can't be decoded backwards, so disassembly before PC
+    // starts as far back as possible while still reaching PC exactly, within
+    // the given number of instructions
+    fn disassemble_around<M: AddressBus, T: TrapHandler>(&self, phy: &DebugPhy<M, T>, before: u32, after: u32) -> Vec<(u32, u32, String)> {
+        let pc = phy.core.pc;
+        for back in (1..=before * MAX_INSTRUCTION_LEN / 2).rev().map(|words| words * 2) {
+            let mut lines = self.disassemble(phy, pc.wrapping_sub(back), before + 1);
+            if let Some(index) = lines.iter().position(|(address, _, _)| *address == pc) {
+                lines.truncate(index);
+                lines.extend(self.disassemble(phy, pc, after));
+                return lines;
+            }
+        }
+        self.disassemble(phy, pc, after)
+    }
+
+    fn check_breakpoints<M: AddressBus, T: TrapHandler>(&self, phy: &DebugPhy<M, T>) -> Option<Stop> {
+        let pc = phy.core.pc;
+        if self.breakpoints.contains(&pc) {
+            return Some(Stop::Breakpoint(pc));
+        }
+        if self.trap_breakpoints.is_empty() {
+            return None;
+        }
+        let word = self.word_at(phy, pc)?;
+        let name = trap_name(word)?;
+        if self.trap_breakpoints.iter().any(|trap| trap.eq_ignore_ascii_case(name)) {
+            Some(Stop::Trap(word))
+        } else {
+            None
+        }
+    }
+
+    // Execute one instruction, returning why execution should stop, if so
+    fn execute<M: AddressBus, T: TrapHandler>(&mut self, phy: &mut DebugPhy<M, T>) -> Option<Stop> {
+        if let Some(fault) = &self.fault {
+            return Some(Stop::Fault(fault.clone()));
+        }
+        if phy.is_stopped() {
+            return Some(Stop::Halted);
+        }
+        if self.trace {
+            trace::print_core_line(phy);
+        }
+        if let Err(payload) = catch_fault(|| phy.step()) {
+            let fault = payload.downcast_ref::<String>().cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| String::from("emulation panicked"));
+            self.fault = Some(fault.clone());
+            return Some(Stop::Fault(fault));
+        }
+        let hits = phy.core.mem.take_hits();
+        if !hits.is_empty() {
+            return Some(Stop::Watchpoint(hits));
+        }
+        if phy.is_stopped() {
+            return Some(Stop::Halted);
+        }
+        None
+    }
+
+    // Execute until done returns a stop after an instruction, given the
+    // opcode of the instruction executed, or until another stop. The first
+    // instruction is executed even if there is a breakpoint on it, to be able
+    // to continue from a breakpoint.
+    fn run_until<M: AddressBus, T: TrapHandler>(&mut self, phy: &mut DebugPhy<M, T>, done: impl Fn(&DebugPhy<M, T>, Option<u16>) -> Option<Stop>) -> Stop {
+        let mut first = true;
+        loop {
+            if !first {
+                if let Some(stop) = self.check_breakpoints(phy) {
+                    return stop;
+                }
+            }
+            first = false;
+            let opcode = self.word_at(phy, phy.core.pc);
+            if let Some(stop) = self.execute(phy) {
+                return stop;
+            }
+            if let Some(stop) = done(phy, opcode) {
+                return stop;
+            }
+        }
+    }
+
+    pub fn cont<M: AddressBus, T: TrapHandler>(&mut self, phy: &mut DebugPhy<M, T>) -> Stop {
+        self.run_until(phy, |_, _| None)
+    }
+
+    pub fn step<M: AddressBus, T: TrapHandler>(&mut self, phy: &mut DebugPhy<M, T>) -> Stop {
+        self.run_until(phy, |_, _| Some(Stop::Stepped))
+    }
+
+    // Step, but run subroutines called by JSR and BSR to completion. A-line
+    // traps are emulated in a single step anyway.
+    pub fn next<M: AddressBus, T: TrapHandler>(&mut self, phy: &mut DebugPhy<M, T>) -> Stop {
+        let pc = phy.core.pc;
+        let is_call = match self.word_at(phy, pc) {
+            Some(word) => word & 0xffc0 == 0x4e80 || word & 0xff00 == 0x6100,
+            None => false
+        };
+        if !is_call {
+            return self.step(phy);
+        }
+        let len = self.disassemble(phy, pc, 1).first().map(|(_, len, _)| *len).unwrap_or(2);
+        let return_pc = pc.wrapping_add(len);
+        // A recursive call reaches the return address deeper in the stack
+        let sp = phy.core.dar[15];
+        self.run_until(phy, |phy, _| {
+            if phy.core.pc == return_pc && phy.core.dar[15] >= sp {
+                Some(Stop::Stepped)
+            } else {
+                None
+            }
+        })
+    }
+
+    // Returning pops the return address, leaving the stack pointer above
+    // where it was anywhere in the body of the subroutine. The epilogue also
+    // pops the stack, by UNLK and MOVEM, so only returns are checked.
+    pub fn finish<M: AddressBus, T: TrapHandler>(&mut self, phy: &mut DebugPhy<M, T>) -> Stop {
+        let sp = phy.core.dar[15];
+        self.run_until(phy, |phy, opcode| match opcode {
+            Some(RTS) | Some(RTD) | Some(RTR) if phy.core.dar[15] > sp => Some(Stop::Finished),
+            _ => None
+        })
+    }
+
+    fn print_registers<M: AddressBus, T: TrapHandler>(&self, phy: &DebugPhy<M, T>, out: &mut impl Write) -> std::io::Result<()> {
+        let core = &phy.core;
+        let flags: String = [(Register::X, 'X'), (Register::N, 'N'), (Register::Z, 'Z'), (Register::V, 'V'), (Register::C, 'C')].iter()
+            .map(|(flag, name)| if flag.get(phy) != 0 { *name } else { name.to_ascii_lowercase() })
+            .collect();
+        writeln!(out, "PC {:08x}  IR {:04x}  {}  {}  {:?}", core.pc, core.ir, flags,
+            if core.s_flag != 0 { "supervisor" } else { "user" }, core.processing_state)?;
+        for (prefix, base) in [("D", 0), ("A", 8)].iter() {
+            let regs: Vec<String> = (0..8).map(|i| format!("{:08x}", core.dar[base + i])).collect();
+            writeln!(out, "{}0-7 {}  {}", prefix, regs[0..4].join(" "), regs[4..8].join(" "))?;
+        }
+        Ok(())
+    }
+
+    fn print_memory<M: AddressBus, T: TrapHandler>(&self, phy: &DebugPhy<M, T>, address: u32, len: u32, out: &mut impl Write) -> std::io::Result<()> {
+        let bytes = self.peek(phy, address, len);
+        for (index, chunk) in bytes.chunks(16).enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let text: String = chunk.iter().map(|b| if (0x20..0x7f).contains(b) { *b as char } else { '.' }).collect();
+            writeln!(out, "{:08x}  {:47}  {}", address.wrapping_add(index as u32 * 16), hex.join(" "), text)?;
+        }
+        if (bytes.len() as u32) < len {
+            writeln!(out, "{:08x}  unmapped", address.wrapping_add(bytes.len() as u32))?;
+        }
+        Ok(())
+    }
+
+    fn print_disassembly<M: AddressBus, T: TrapHandler>(&self, phy: &DebugPhy<M, T>, lines: &[(u32, u32, String)], out: &mut impl Write) -> std::io::Result<()> {
+        for (address, len, text) in lines {
+            let marker = if *address == phy.core.pc { "=>" } else if self.breakpoints.contains(address) { " *" } else { "  " };
+            let code: Vec<String> = self.peek(phy, *address, *len).iter().map(|b| format!("{:02x}", b)).collect();
+            writeln!(out, "{} {:08x}  {:20}  {}", marker, address, code.join(""), text)?;
+        }
+        Ok(())
+    }
+
+    fn print_stop<M: AddressBus, T: TrapHandler>(&self, phy: &DebugPhy<M, T>, stop: &Stop, out: &mut impl Write) -> std::io::Result<()> {
+        match stop {
+            Stop::Stepped | Stop::Finished => (),
+            Stop::Breakpoint(address) => writeln!(out, "Breakpoint at {:08x}", address)?,
+            Stop::Trap(word) => writeln!(out, "Trap {}", disasm::decode(&word.to_be_bytes(), 0).text)?,
+            Stop::Watchpoint(hits) => for hit in hits {
+                let value = format!("{:0width$x}", hit.value, width = hit.size as usize * 2);
+                if hit.write {
+                    writeln!(out, "Watchpoint: mem[{:08x}] = {}", hit.address, value)?;
+                } else {
+                    writeln!(out, "Watchpoint: {} = mem[{:08x}]", value, hit.address)?;
+                }
+            },
+            Stop::Halted => writeln!(out, "CPU halted")?,
+            Stop::Fault(fault) => writeln!(out, "Emulation stopped: {}", fault)?
+        }
+        let lines = self.disassemble(phy, phy.core.pc, 1);
+        self.print_disassembly(phy, &lines, out)
+    }
+
+    fn print_list(&self, watchpoints: &[Watchpoint], out: &mut impl Write) -> std::io::Result<()> {
+        for address in self.breakpoints.iter() {
+            writeln!(out, "break {:08x}", address)?;
+        }
+        for trap in self.trap_breakpoints.iter() {
+            writeln!(out, "break _{}", trap)?;
+        }
+        for watch in watchpoints {
+            let access = match (watch.access & WATCH_READ != 0, watch.access & WATCH_WRITE != 0) {
+                (true, true) => "rw",
+                (true, false) => "r",
+                _ => "w"
+            };
+            writeln!(out, "watch {:08x} {} {}", watch.address, watch.len, access)?;
+        }
+        Ok(())
+    }
+
+    // Run a command, returning false to quit
+    pub fn command<M: AddressBus, T: TrapHandler>(&mut self, phy: &mut DebugPhy<M, T>, command: &Command, out: &mut impl Write) -> std::io::Result<bool> {
+        match command {
+            Command::Continue => {
+                let stop = self.cont(phy);
+                self.print_stop(phy, &stop, out)?;
+            },
+            Command::Step(count) => {
+                for _ in 0..*count {
+                    let stop = self.step(phy);
+                    if stop != Stop::Stepped {
+                        self.print_stop(phy, &stop, out)?;
+                        return Ok(true);
+                    }
+                }
+                self.print_stop(phy, &Stop::Stepped, out)?;
+            },
+            Command::Next => {
+                let stop = self.next(phy);
+                self.print_stop(phy, &stop, out)?;
+            },
+            Command::Finish => {
+                let stop = self.finish(phy);
+                self.print_stop(phy, &stop, out)?;
+            },
+            Command::Break(Location::Address(address)) => self.add_breakpoint(address.value(phy)),
+            Command::Break(Location::Trap(name)) => self.add_trap_breakpoint(name),
+            Command::Watch(address, len, access) => {
+                let address = address.value(phy);
+                phy.core.mem.watch(Watchpoint { address, len: *len, access: *access });
+            },
+            Command::Clear(None) => {
+                self.breakpoints.clear();
+                self.trap_breakpoints.clear();
+                let watched: Vec<u32> = phy.core.mem.watchpoints().iter().map(|watch| watch.address).collect();
+                for address in watched {
+                    phy.core.mem.unwatch(address);
+                }
+            },
+            Command::Clear(Some(Location::Address(address))) => {
+                let address = address.value(phy);
+                let count = self.breakpoints.len();
+                self.breakpoints.retain(|bp| *bp != address);
+                if !phy.core.mem.unwatch(address) && self.breakpoints.len() == count {
+                    writeln!(out, "Nothing at {:08x}", address)?;
+                }
+            },
+            Command::Clear(Some(Location::Trap(name))) => self.trap_breakpoints.retain(|trap| !trap.eq_ignore_ascii_case(name)),
+            Command::List => self.print_list(phy.core.mem.watchpoints(), out)?,
+            Command::Registers => self.print_registers(phy, out)?,
+            Command::Set(register, value) => {
+                let value = value.value(phy);
+                register.set(phy, value);
+            },
+            Command::Examine(address, len) => self.print_memory(phy, address.value(phy), *len, out)?,
+            Command::Poke(address, bytes) => {
+                let address = address.value(phy);
+                let written = self.poke(phy, address, bytes);
+                if written < bytes.len() {
+                    writeln!(out, "Unmapped memory at {:08x}", address.wrapping_add(written as u32))?;
+                }
+            },
+            Command::Disassemble(address, count) => {
+                let lines = match address {
+                    Some(address) => self.disassemble(phy, address.value(phy), *count),
+                    None => self.disassemble_around(phy, count / 2, count - count / 2)
+                };
+                self.print_disassembly(phy, &lines, out)?;
+            },
+            Command::Trace(trace) => self.trace = *trace,
+            Command::Help => writeln!(out, "{}", HELP)?,
+            Command::Quit => return Ok(false)
+        }
+        Ok(true)
+    }
+
+    // Read commands until quit or end of input
+    pub fn repl<M: AddressBus, T: TrapHandler>(&mut self, phy: &mut DebugPhy<M, T>, input: &mut impl BufRead, out: &mut impl Write) -> std::io::Result<()> {
+        self.print_stop(phy, &Stop::Stepped, out)?;
+        let mut last: Option<Command> = None;
+        loop {
+            write!(out, "> ")?;
+            out.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let command = if line.trim().is_empty() {
+                match &last {
+                    Some(command) => Ok(command.clone()),
+                    None => continue
+                }
+            } else {
+                Command::parse(&line)
+            };
+            let command = match command {
+                Ok(command) => command,
+                Err(err) => {
+                    writeln!(out, "{}", err)?;
+                    continue;
+                }
+            };
+            if !self.command(phy, &command, out)? {
+                return Ok(());
+            }
+            last = match command {
+                Command::Step(_) | Command::Next => Some(command),
+                _ => None
+            };
+        }
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, Debugger, Location, Operand, Register, Stop, IN_EMULATION};
+    use crate::phy::{Core, Phy, TrapHandler, TrapResult};
+    use crate::phy::mem::{MuxMem, RAM, WatchMem};
+    use crate::phy::prefix::Prefix;
+    use crate::phy::mem::watch::{Watchpoint, WatchHit, WATCH_READ, WATCH_WRITE};
+    use r68k_emu::ram::{AddressBus, SUPERVISOR_DATA, SUPERVISOR_PROGRAM};
+
+    // Halts on any A-line trap
+    struct Exit;
+
+    impl TrapHandler for Exit {
+        fn line_1010_emualtion(&mut self, _core: &mut impl Core, _ir: u16, _pc: u32) -> TrapResult {
+            TrapResult::Halt
+        }
+    }
+
+    #[test]
+    fn run() {
+        let program = [
+            0x70, 0x01,             // 100 MOVEQ #1, D0
+            0x61, 0x06,             // 102 BSR.S $10a
+            0x21, 0xc0, 0x00, 0x80, // 104 MOVE.L D0, $80.W
+            0xa9, 0xf4,             // 108 _ExitToShell
+            0x70, 0x02,             // 10a MOVEQ #2, D0
+            0x4e, 0x75              // 10c RTS
+        ];
+        let mut ram = vec![0; 0x200];
+        ram[0x100..0x100 + program.len()].copy_from_slice(&program);
+        let mut phy = Phy::new(WatchMem::new(RAM::from(ram)), Exit);
+        phy.core.dar[15] = 0x200;
+        phy.core.jump(0x100);
+
+        let mut debugger = Debugger::new();
+        debugger.add_breakpoint(0x102);
+        assert_eq!(debugger.cont(&mut phy), Stop::Breakpoint(0x102));
+        assert_eq!(debugger.step(&mut phy), Stop::Stepped);
+        assert_eq!((phy.core.pc, phy.core.dar[15]), (0x10a, 0x1fc));
+        assert_eq!(debugger.finish(&mut phy), Stop::Finished);
+        assert_eq!((phy.core.pc, phy.core.dar[0], phy.core.dar[15]), (0x104, 2, 0x200));
+
+        Register::Pc.set(&mut phy, 0x102);
+        Register::D(0).set(&mut phy, 1);
+        assert_eq!(debugger.next(&mut phy), Stop::Stepped);
+        assert_eq!((phy.core.pc, phy.core.dar[0]), (0x104, 2));
+
+        phy.core.mem.watch(Watchpoint { address: 0x80, len: 4, access: WATCH_WRITE });
+        assert_eq!(debugger.cont(&mut phy), Stop::Watchpoint(vec![
+            WatchHit { address: 0x80, size: 4, value: 2, write: true }
+        ]));
+        assert_eq!(phy.core.pc, 0x108);
+        assert_eq!(debugger.peek(&phy, 0x80, 4), vec![0, 0, 0, 2]);
+
+        phy.core.mem.unwatch(0x80);
+        Register::Pc.set(&mut phy, 0x104);
+        debugger.add_trap_breakpoint("ExitToShell");
+        assert_eq!(debugger.cont(&mut phy), Stop::Trap(0xa9f4));
+        assert_eq!(debugger.cont(&mut phy), Stop::Halted);
+    }
+
+    #[test]
+    fn finish_frame() {
+        let program = [
+            0x61, 0x06,             // 100 BSR.S $108
+            0x21, 0xc0, 0x00, 0x80, // 102 MOVE.L D0, $80.W
+            0xa9, 0xf4,             // 106 _ExitToShell
+            0x4e, 0x56, 0xff, 0xf8, // 108 LINK A6, #-8
+            0x48, 0xe7, 0x18, 0x00, // 10c MOVEM.L D3-D4, -(SP)
+            0x70, 0x03,             // 110 MOVEQ #3, D0
+            0x4c, 0xdf, 0x00, 0x18, // 112 MOVEM.L (SP)+, D3-D4
+            0x4e, 0x5e,             // 116 UNLK A6
+            0x4e, 0x75              // 118 RTS
+        ];
+        let mut ram = vec![0; 0x200];
+        ram[0x100..0x100 + program.len()].copy_from_slice(&program);
+        let mut phy = Phy::new(WatchMem::new(RAM::from(ram)), Exit);
+        phy.core.dar[15] = 0x200;
+        phy.core.jump(0x100);
+
+        // The epilogue pops the saved registers and the frame before the
+        // return
+        let mut debugger = Debugger::new();
+        debugger.add_breakpoint(0x110);
+        assert_eq!(debugger.cont(&mut phy), Stop::Breakpoint(0x110));
+        assert_eq!(phy.core.dar[15], 0x1e8);
+        assert_eq!(debugger.finish(&mut phy), Stop::Finished);
+        assert_eq!((phy.core.pc, phy.core.dar[0], phy.core.dar[15]), (0x102, 3, 0x200));
+    }
+
+    #[test]
+    fn faults() {
+        let mut mem = MuxMem::new();
+        mem.add_prefix(Prefix::new(0x0000_0000, 24), Box::new(RAM::from(vec![0x42; 0x100])));
+        let mut phy = Phy::new(WatchMem::new(mem), Exit);
+
+        let debugger = Debugger::new();
+        assert_eq!(debugger.peek(&phy, 0xfe, 4), vec![0x42, 0x42]);
+        assert_eq!(debugger.poke(&mut phy, 0xff, &[1, 2]), 1);
+        assert!(!IN_EMULATION.with(|emulating| emulating.get()));
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(Command::parse("s 10"), Ok(Command::Step(10)));
+        assert_eq!(Command::parse("step"), Ok(Command::Step(1)));
+        assert_eq!(Command::parse("b $a0"), Ok(Command::Break(Location::Address(Operand::Number(0xa0)))));
+        assert_eq!(Command::parse("b a0"), Ok(Command::Break(Location::Address(Operand::Register(Register::A(0))))));
+        assert_eq!(Command::parse("break _getresource"), Ok(Command::Break(Location::Trap(String::from("GetResource")))));
+        assert!(Command::parse("break _NoSuchTrap").is_err());
+        assert_eq!(Command::parse("watch 0x904 2 w"), Ok(Command::Watch(Operand::Number(0x904), 2, WATCH_WRITE)));
+        assert_eq!(Command::parse("set SP 10f00000"), Ok(Command::Set(Register::A(7), Operand::Number(0x10f00000))));
+        assert_eq!(Command::parse("set z 1"), Ok(Command::Set(Register::Z, Operand::Number(1))));
+        assert!(Command::parse("set d8 0").is_err());
+        assert_eq!(Command::parse("poke 100 4e 75"), Ok(Command::Poke(Operand::Number(0x100), vec![0x4e, 0x75])));
+        assert_eq!(Command::parse("dis"), Ok(Command::Disassemble(None, 10)));
+        assert_eq!(Command::parse("clear"), Ok(Command::Clear(None)));
+        assert!(Command::parse("x").is_err());
+        assert!(Command::parse("frobnicate").is_err());
+    }
+
+    #[test]
+    fn watch() {
+        let mut mem = WatchMem::new(RAM::new(0x100));
+        mem.watch(Watchpoint { address: 0x10, len: 4, access: WATCH_WRITE });
+        mem.watch(Watchpoint { address: 0x20, len: 1, access: WATCH_READ | WATCH_WRITE });
+        mem.write_long(SUPERVISOR_DATA, 0x0e, 0x12345678);
+        mem.write_long(SUPERVISOR_DATA, 0x14, 0);
+        mem.read_long(SUPERVISOR_DATA, 0x10);
+        mem.read_word(SUPERVISOR_PROGRAM, 0x20);
+        mem.read_byte(SUPERVISOR_DATA, 0x20);
+        assert_eq!(mem.take_hits(), vec![
+            WatchHit { address: 0x0e, size: 4, value: 0x12345678, write: true },
+            WatchHit { address: 0x20, size: 1, value: 0, write: false }
+        ]);
+        assert!(mem.take_hits().is_empty());
+
+        mem.poke_byte(0x10, 0xff);
+        assert_eq!(mem.peek_byte(0x10), 0xff);
+        assert!(mem.take_hits().is_empty());
+        assert!(mem.unwatch(0x10));
+        assert!(!mem.unwatch(0x10));
+        assert_eq!(mem.watchpoints().len(), 1);
+    }
+}
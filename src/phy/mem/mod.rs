@@ -2,8 +2,10 @@ mod ram;
 mod mux;
 mod rc;
 pub mod log;
+pub mod watch;
 
 pub use ram::RAM;
 pub use mux::MuxMem;
 pub use rc::RcMem;
-pub use log::LogMem;
\ No newline at end of file
+pub use log::LogMem;
+pub use watch::WatchMem;
\ No newline at end of file
@@ -0,0 +1,128 @@
+use r68k_emu::ram::{
+    AddressBus,
+    AddressSpace,
+    USER_DATA,
+    SUPERVISOR_DATA
+};
+
+use std::cell::RefCell;
+
+pub const WATCH_READ : u32 = 0x00000001;
+pub const WATCH_WRITE : u32 = 0x00000002;
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Watchpoint {
+    pub address: u32,
+    pub len: u32,
+    pub access: u32
+}
+
+impl Watchpoint {
+    fn overlaps(&self, address: u32, size: u32) -> bool {
+        address < self.address.wrapping_add(self.len) && self.address < address.wrapping_add(size)
+    }
+}
+
+// Data access hitting a watchpoint
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct WatchHit {
+    pub address: u32,
+    // Access size in bytes
+    pub size: u32,
+    pub value: u32,
+    pub write: bool
+}
+
+// Records data accesses to watched memory ranges, for the debugger to stop
+// after the instruction doing them. Program accesses, such as instruction
+// fetches, are never recorded.
+pub struct WatchMem<M:AddressBus> {
+    watchpoints: Vec<Watchpoint>,
+    hits: RefCell<Vec<WatchHit>>,
+    child: M
+}
+
+impl<M:AddressBus> AddressBus for WatchMem<M> {
+    fn read_byte(&self, address_space: AddressSpace, address: u32) -> u32 {
+        let value = self.child.read_byte(address_space, address);
+        self.check(address_space, address, 1, value, false);
+        value
+    }
+    fn read_word(&self, address_space: AddressSpace, address: u32) -> u32 {
+        let value = self.child.read_word(address_space, address);
+        self.check(address_space, address, 2, value, false);
+        value
+    }
+    fn read_long(&self, address_space: AddressSpace, address: u32) -> u32 {
+        let value = self.child.read_long(address_space, address);
+        self.check(address_space, address, 4, value, false);
+        value
+    }
+    fn write_byte(&mut self, address_space: AddressSpace, address: u32, value: u32) {
+        self.check(address_space, address, 1, value, true);
+        self.child.write_byte(address_space, address, value);
+    }
+    fn write_word(&mut self, address_space: AddressSpace, address: u32, value: u32) {
+        self.check(address_space, address, 2, value, true);
+        self.child.write_word(address_space, address, value);
+    }
+    fn write_long(&mut self, address_space: AddressSpace, address: u32, value: u32) {
+        self.check(address_space, address, 4, value, true);
+        self.child.write_long(address_space, address, value);
+    }
+}
+
+impl<M:AddressBus> WatchMem<M> {
+    pub fn new(child: M) -> WatchMem<M> {
+        WatchMem {
+            watchpoints: vec![],
+            hits: RefCell::new(vec![]),
+            child
+        }
+    }
+
+    fn check(&self, address_space: AddressSpace, address: u32, size: u32, value: u32, write: bool) {
+        if address_space != USER_DATA && address_space != SUPERVISOR_DATA {
+            return;
+        }
+        let access = if write { WATCH_WRITE } else { WATCH_READ };
+        if self.watchpoints.iter().any(|watch| watch.access & access != 0 && watch.overlaps(address, size)) {
+            self.hits.borrow_mut().push(WatchHit { address, size, value, write });
+        }
+    }
+
+    pub fn watch(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    // Remove all watchpoints starting at the address, returns if any was found
+    pub fn unwatch(&mut self, address: u32) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints.retain(|watch| watch.address != address);
+        self.watchpoints.len() != count
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    // Hits since the last call
+    pub fn take_hits(&self) -> Vec<WatchHit> {
+        self.hits.replace(vec![])
+    }
+
+    // Access bypassing the watchpoints, for inspecting memory
+    pub fn peek_byte(&self, address: u32) -> u8 {
+        self.child.read_byte(SUPERVISOR_DATA, address) as u8
+    }
+
+    pub fn poke_byte(&mut self, address: u32, value: u8) {
+        self.child.write_byte(SUPERVISOR_DATA, address, value as u32);
+    }
+}
@@ -1,6 +1,7 @@
 pub mod mem;
 pub mod prefix;
 pub mod stackable;
+pub mod debug;
 mod trace;
 
 use r68k_emu::{
@@ -39,15 +40,21 @@ impl<M : AddressBus, T : TrapHandler> Phy<M, T> {
 
     pub fn run(&mut self) -> () {
         trace::print_core_header(&self);
-        loop {
+        while !self.is_stopped() {
             trace::print_core_line(&self);
-            self.core.execute_with_state(1, &mut self.callbacks);
-            if self.core.processing_state == ProcessingState::Halted || self.core.processing_state == ProcessingState::Stopped {
-                break;
-            }
+            self.step();
         }
         trace::print_core(&self);
     }
+
+    // Execute a single instruction
+    pub fn step(&mut self) {
+        self.core.execute_with_state(1, &mut self.callbacks);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.core.processing_state == ProcessingState::Halted || self.core.processing_state == ProcessingState::Stopped
+    }
 }
 
 pub enum TrapResult {
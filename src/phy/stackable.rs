@@ -1,7 +1,17 @@
 use r68k_emu::cpu::Core;
 
 // Add for special core types too
-use crate::types::OSType;
+use crate::types::{
+    OSType,
+    DateTime,
+    Fixed,
+    Fract,
+    Point,
+    Rect,
+    RGBColor,
+    Pattern,
+    Str255
+};
 
 pub trait Stackable : Sized {
     fn stack_push(&self, core: &mut impl Core);
@@ -81,6 +91,110 @@ impl Stackable for OSType {
     }
 }
 
+impl Stackable for DateTime {
+    fn stack_push(&self, core: &mut impl Core) {
+        core.push_32(self.to_mac_u32());
+    }
+    fn stack_pop(core: &mut impl Core) -> Self {
+        DateTime::from_mac_u32(core.pop_32())
+    }
+}
+
+impl Stackable for Fixed {
+    fn stack_push(&self, core: &mut impl Core) {
+        core.push_32(self.to_bits() as u32);
+    }
+    fn stack_pop(core: &mut impl Core) -> Self {
+        Fixed::from_bits(core.pop_32() as i32)
+    }
+}
+
+impl Stackable for Fract {
+    fn stack_push(&self, core: &mut impl Core) {
+        core.push_32(self.to_bits() as u32);
+    }
+    fn stack_pop(core: &mut impl Core) -> Self {
+        Fract::from_bits(core.pop_32() as i32)
+    }
+}
+
+// Structures are pushed in reverse, so they are laid out on the stack the
+// same way as in memory. Note that Pascal calling conventions passes
+// structures larger than 4 bytes by reference, in which case the pointer is
+// on the stack instead.
+
+impl Stackable for Point {
+    fn stack_push(&self, core: &mut impl Core) {
+        self.h.stack_push(core);
+        self.v.stack_push(core);
+    }
+    fn stack_pop(core: &mut impl Core) -> Self {
+        let v = Stackable::stack_pop(core);
+        let h = Stackable::stack_pop(core);
+        Point { v, h }
+    }
+}
+
+impl Stackable for Rect {
+    fn stack_push(&self, core: &mut impl Core) {
+        self.bot_right().stack_push(core);
+        self.top_left().stack_push(core);
+    }
+    fn stack_pop(core: &mut impl Core) -> Self {
+        let top_left = Point::stack_pop(core);
+        let bot_right = Point::stack_pop(core);
+        Rect::new(top_left.h, top_left.v, bot_right.h, bot_right.v)
+    }
+}
+
+impl Stackable for RGBColor {
+    fn stack_push(&self, core: &mut impl Core) {
+        self.blue.stack_push(core);
+        self.green.stack_push(core);
+        self.red.stack_push(core);
+    }
+    fn stack_pop(core: &mut impl Core) -> Self {
+        let red = Stackable::stack_pop(core);
+        let green = Stackable::stack_pop(core);
+        let blue = Stackable::stack_pop(core);
+        RGBColor { red, green, blue }
+    }
+}
+
+impl Stackable for Pattern {
+    fn stack_push(&self, core: &mut impl Core) {
+        for row in self.0.chunks(2).rev() {
+            core.push_16(((row[0] as u16) << 8) | row[1] as u16);
+        }
+    }
+    fn stack_pop(core: &mut impl Core) -> Self {
+        let mut pat = Pattern::default();
+        for row in pat.0.chunks_mut(2) {
+            let word = core.pop_16();
+            row[0] = (word >> 8) as u8;
+            row[1] = word as u8;
+        }
+        pat
+    }
+}
+
+impl Stackable for Str255 {
+    fn stack_push(&self, core: &mut impl Core) {
+        for word in self.to_bytes().chunks(2).rev() {
+            core.push_16(((word[0] as u16) << 8) | word[1] as u16);
+        }
+    }
+    fn stack_pop(core: &mut impl Core) -> Self {
+        let mut data = [0u8; 256];
+        for word in data.chunks_mut(2) {
+            let val = core.pop_16();
+            word[0] = (val >> 8) as u8;
+            word[1] = val as u8;
+        }
+        Str255::from(&data)
+    }
+}
+
 impl Stackable for () {
     fn stack_push(&self, _core: &mut impl Core) {
     }
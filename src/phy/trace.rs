@@ -10,45 +10,16 @@ use r68k_tools::{
     memory::Memory,
 };
 
+use crate::tools::disasm::{
+    format_instruction,
+    ReadMemory
+};
+
 use super::{
     Phy,
     TrapHandler
 };
 
-struct PhyMemory<'phy, M : AddressBus, T : TrapHandler> {
-    phy: &'phy Phy<M, T>
-}
-
-impl<'phy, M : AddressBus, T : TrapHandler> PhyMemory<'phy, M, T> {
-    pub fn new(phy: &'phy Phy<M, T>) -> PhyMemory<'phy, M, T> {
-        PhyMemory { phy: phy }
-    }
-}
-
-impl<'phy, M : AddressBus, T : TrapHandler> Memory for PhyMemory<'phy, M, T> {
-    fn offset(&self) -> u32 {
-        self.phy.core.pc
-    }
-    fn data(&self) -> &[u8] {
-        unimplemented!();
-    }
-    fn read_word(&self, pc: PC) -> u16 {
-        self.phy.core.mem.read_word(SUPERVISOR_PROGRAM, pc.0) as u16
-    }
-    fn read_byte(&self, pc: PC) -> u8 {
-        self.phy.core.mem.read_byte(SUPERVISOR_PROGRAM, pc.0) as u8
-    }
-    fn write_byte(&mut self, _pc: PC, _byte: u8) -> PC {
-        unimplemented!();
-    }
-    fn write_word(&mut self, _pc: PC, _word: u16) -> PC {
-        unimplemented!();
-    }
-    fn write_vec(&mut self, _pc: PC, _bytes: Vec<u8>) -> PC {
-        unimplemented!();
-    }
-}
-
 pub fn print_core_header<M : AddressBus, T : TrapHandler>(_tbx : &Phy<M, T>) {
     println!(
         "        PC...... | IR.. | D0...... D1...... D2...... D3...... D4...... D5...... D6...... D7...... | A0...... A1...... A2...... A3...... A4...... A5...... A6...... A7...... | Next Instruction");
@@ -64,19 +35,10 @@ pub fn print_core_line<M : AddressBus, T : TrapHandler>(tbx : &Phy<M, T>) {
         c.dar[8], c.dar[9], c.dar[10], c.dar[11], c.dar[12], c.dar[13], c.dar[14], c.dar[15],
     );
 
-    let mem = PhyMemory::new(&tbx);
+    let mem = ReadMemory::new(c.pc, |addr| c.mem.read_byte(SUPERVISOR_PROGRAM, addr) as u8);
     print!("${:04x}  ", mem.read_word(PC(c.pc)));
     if let Ok((_, inst)) = disassemble(PC(c.pc), &mem) {
-        let mut inst = format!("{}", inst);
-        if let Some(delim) = inst.find("\t") {
-            let arg = inst.split_off(delim);
-            println!("{:10} {}",
-                inst.trim().to_lowercase(),
-                arg.trim().replace(",", ", ")
-            );
-        } else {
-            println!("{}", inst.trim());
-        }
+        println!("{}", format_instruction(&format!("{}", inst)));
     } else {
         println!("");
     }
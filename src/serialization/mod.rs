@@ -1,5 +1,6 @@
 mod serialaccess;
 mod serialread;
+mod serialwrite;
 
 pub use serialaccess::{
     SerialAdaptor,
@@ -9,4 +10,9 @@ pub use serialaccess::{
 pub use serialread::{
     SerialRead,
     SerialReadStorage
-};
\ No newline at end of file
+};
+
+pub use serialwrite::{
+    SerialWrite,
+    SerialWriteStorage
+};
@@ -2,12 +2,13 @@ use std::io;
 use std::cell::RefCell;
 
 use super::SerialReadStorage;
+use crate::{Error, Result};
 
 pub struct SerialAdaptor<T: io::Read + io::Seek> (RefCell<T>);
 
 pub trait SerialAccess : std::fmt::Debug {
-    fn size(&self) -> io::Result<u64>;
-    fn read(&self, pos : u64, len : u64) -> io::Result<SerialReadStorage>;
+    fn size(&self) -> Result<u64>;
+    fn read(&self, pos : u64, len : u64) -> Result<SerialReadStorage>;
 }
 
 impl<T> SerialAdaptor<T>
@@ -29,11 +30,16 @@ impl<T : std::io::Read + std::io::Seek> std::fmt::Debug for SerialAdaptor<T> {
 impl<T> SerialAccess for SerialAdaptor<T>
 where
 T: io::Read + io::Seek {
-    fn size(&self) -> io::Result<u64> {
+    fn size(&self) -> Result<u64> {
         let mut storage = self.0.borrow_mut();
-        storage.seek(io::SeekFrom::End(0))
+        Ok(storage.seek(io::SeekFrom::End(0))?)
     }
-    fn read(&self, pos : u64, len : u64) -> io::Result<SerialReadStorage> {
+    fn read(&self, pos : u64, len : u64) -> Result<SerialReadStorage> {
+        // Check bounds first, so corrupt lengths doesn't cause huge allocations
+        let size = self.size()?;
+        if pos.checked_add(len).is_none_or(|end| end > size) {
+            return Err(Error::UnexpectedEof { offset: pos, len, size });
+        }
         let mut bufv : Vec<u8> = Vec::with_capacity(len as usize);
         let mut storage = self.0.borrow_mut();
         storage.seek(io::SeekFrom::Start(pos))?;
@@ -1,62 +1,60 @@
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ByteOrder};
 
-use std::io::{
-    Cursor,
-    Seek,
-    SeekFrom
-};
+use crate::{Error, Result};
 
 
 pub trait SerialRead : std::marker::Sized {
-    fn read( rdr : &mut SerialReadStorage ) -> std::io::Result<Self>;
+    fn read( rdr : &mut SerialReadStorage ) -> Result<Self>;
 }
 
 impl SerialRead for u8 {
-    fn read( rdr : &mut SerialReadStorage ) -> std::io::Result<Self> {
+    fn read( rdr : &mut SerialReadStorage ) -> Result<Self> {
         rdr.read_u8()
     }
 }
 
 impl SerialRead for i8 {
-    fn read( rdr : &mut SerialReadStorage ) -> std::io::Result<Self> {
+    fn read( rdr : &mut SerialReadStorage ) -> Result<Self> {
         rdr.read_i8()
     }
 }
 
 impl SerialRead for u16 {
-    fn read( rdr : &mut SerialReadStorage ) -> std::io::Result<Self> {
+    fn read( rdr : &mut SerialReadStorage ) -> Result<Self> {
         rdr.read_u16()
     }
 }
 
 impl SerialRead for i16 {
-    fn read( rdr : &mut SerialReadStorage ) -> std::io::Result<Self> {
+    fn read( rdr : &mut SerialReadStorage ) -> Result<Self> {
         rdr.read_i16()
     }
 }
 
 impl SerialRead for u32 {
-    fn read( rdr : &mut SerialReadStorage ) -> std::io::Result<Self> {
+    fn read( rdr : &mut SerialReadStorage ) -> Result<Self> {
         rdr.read_u32()
     }
 }
 
 impl SerialRead for i32 {
-    fn read( rdr : &mut SerialReadStorage ) -> std::io::Result<Self> {
+    fn read( rdr : &mut SerialReadStorage ) -> Result<Self> {
         rdr.read_i32()
     }
 }
 
 
 pub struct SerialReadStorage {
-    block : Cursor<Vec<u8>>,
+    block : Vec<u8>,
+    pos : u64,
     len_stack : Vec<u64>
 }
 
 impl From<Vec<u8>> for SerialReadStorage {
     fn from(vec : Vec<u8>) -> SerialReadStorage {
         SerialReadStorage {
-            block: Cursor::new(vec),
+            block: vec,
+            pos: 0,
             len_stack: vec![]
         }
     }
@@ -68,88 +66,110 @@ fn pad_to_wordlen(len: u64, wordlen: u64) -> u64 {
 
 impl SerialReadStorage {
     pub fn extend(&mut self, chain: SerialReadStorage) {
-        self.block.get_mut().extend(chain.block.into_inner());
+        self.block.extend(chain.block);
     }
 
     pub fn pos(&self) -> u64 {
-        self.block.position()
+        self.pos
     }
 
-
-    pub fn seek(&mut self, offset : u64) {
-        self.block.seek(SeekFrom::Start(offset as u64)).unwrap();
+    // Seeking to the end is valid, but not past it
+    pub fn seek(&mut self, offset : u64) -> Result<&mut Self> {
+        if offset > self.size() {
+            return Err(Error::UnexpectedEof { offset, len: 0, size: self.size() });
+        }
+        self.pos = offset;
+        Ok(self)
     }
 
     pub fn size(&self) -> u64 {
-        self.block.get_ref().len() as u64
+        self.block.len() as u64
+    }
+
+    pub fn length_start(&mut self, len : u64) -> Result<&mut Self> {
+        self.len_stack.push(self.pos.saturating_add(len));
+        Ok(self)
     }
 
-    pub fn length_start(&mut self, len : u64) -> &mut Self {
-        let cur_pos = self.block.seek(SeekFrom::Current(0)).unwrap();
-        self.len_stack.push(cur_pos + len);
-        self
+    pub fn length_end(&mut self) -> Result<&mut Self> {
+        let pos = self.len_stack.pop()
+            .ok_or(Error::invalid_value(self.pos, "SerialReadStorage", "len_stack", "length_start", "empty"))?;
+        self.seek(pos)
     }
 
-    pub fn length_end(&mut self) -> &mut Self {
-        let pos = self.len_stack.pop().unwrap();
-        self.seek(pos);
-        self
+    pub fn align(&mut self, wordlength : u64) -> Result<&mut Self> {
+        self.seek(pad_to_wordlen(self.pos, wordlength))
+    }
+
+    pub fn pad(&mut self, bytes : i64) -> Result<&mut Self> {
+        let offset = self.pos as i64 + bytes;
+        if offset < 0 {
+            return Err(Error::UnexpectedEof { offset: self.pos, len: bytes.unsigned_abs(), size: self.size() });
+        }
+        self.seek(offset as u64)
     }
 
-    pub fn align(&mut self, wordlength : u64) -> &mut Self {
-        let cur_pos = self.block.seek(SeekFrom::Current(0)).unwrap();
-        self.seek(pad_to_wordlen(cur_pos, wordlength));
-        self
+    // Returns end offset of the range, if within the buffer
+    fn check_range(&self, offset : u64, len : u64) -> Result<u64> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.size() => Ok(end),
+            _ => Err(Error::UnexpectedEof { offset, len, size: self.size() })
+        }
     }
 
-    pub fn pad(&mut self, bytes : i64) -> &mut Self {
-        self.block.seek(SeekFrom::Current(bytes)).unwrap();
-        self
+    fn take(&mut self, len : u64) -> Result<&[u8]> {
+        let start = self.pos;
+        self.pos = self.check_range(start, len)?;
+        Ok(&self.block[start as usize..self.pos as usize])
     }
 
-    pub fn read_u8(&mut self) -> std::io::Result<u8> {
-        self.block.read_u8()
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
     }
-    pub fn read_i8(&mut self) -> std::io::Result<i8> {
-        self.block.read_i8()
+    pub fn read_i8(&mut self) -> Result<i8> {
+        Ok(self.take(1)?[0] as i8)
     }
-    pub fn read_u16(&mut self) -> std::io::Result<u16> {
-        self.block.read_u16::<BigEndian>()
+    pub fn read_u16(&mut self) -> Result<u16> {
+        Ok(BigEndian::read_u16(self.take(2)?))
     }
-    pub fn read_i16(&mut self) -> std::io::Result<i16> {
-        self.block.read_i16::<BigEndian>()
+    pub fn read_i16(&mut self) -> Result<i16> {
+        Ok(BigEndian::read_i16(self.take(2)?))
     }
 
     // Note that this is not
-    pub fn read_u24(&mut self) -> std::io::Result<u32> {
-        self.block.read_u24::<BigEndian>()
+    pub fn read_u24(&mut self) -> Result<u32> {
+        Ok(BigEndian::read_u24(self.take(3)?))
     }
-    pub fn read_i24(&mut self) -> std::io::Result<i32> {
-        self.block.read_i24::<BigEndian>()
+    pub fn read_i24(&mut self) -> Result<i32> {
+        Ok(BigEndian::read_i24(self.take(3)?))
     }
 
-    pub fn read_u32(&mut self) -> std::io::Result<u32> {
-        self.block.read_u32::<BigEndian>()
+    pub fn read_u32(&mut self) -> Result<u32> {
+        Ok(BigEndian::read_u32(self.take(4)?))
+    }
+    pub fn read_i32(&mut self) -> Result<i32> {
+        Ok(BigEndian::read_i32(self.take(4)?))
     }
-    pub fn read_i32(&mut self) -> std::io::Result<i32> {
-        self.block.read_i32::<BigEndian>()
+
+    pub fn read_bytes(&mut self, len : u64) -> Result<Vec<u8>> {
+        Ok(Vec::from(self.take(len)?))
     }
 
-    pub fn sub_reader(&self, offset : u64, len : u64) -> SerialReadStorage {
-        let inner = self.block.get_ref();
-        SerialReadStorage::from(Vec::from(&inner[offset as usize..(offset+len) as usize]))
+    pub fn sub_reader(&self, offset : u64, len : u64) -> Result<SerialReadStorage> {
+        let end = self.check_range(offset, len)?;
+        Ok(SerialReadStorage::from(Vec::from(&self.block[offset as usize..end as usize])))
     }
 
     pub fn to_vec(self) -> Vec<u8> {
-        self.block.into_inner()
+        self.block
     }
 }
 
 impl std::fmt::Debug for SerialReadStorage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let inner = self.block.get_ref();
+        let inner = &self.block;
         let len = inner.len() as usize;
-        let pos = self.block.position() as usize;
+        let pos = std::cmp::min(self.pos as usize, len);
         let start = if pos < 16 { 0 } else { pos-16 };
         let end = if pos+16 > len { len } else { pos+16 };
 
@@ -168,7 +188,7 @@ impl std::fmt::Debug for SerialReadStorage {
 
 #[cfg(test)]
 mod tests {
-    use super::{SerialReadStorage, SerialRead};
+    use super::{SerialReadStorage, SerialRead, Error};
 
     use super::pad_to_wordlen;
     #[test]
@@ -301,4 +321,93 @@ mod tests {
             }
         );
     }
-}
\ No newline at end of file
+
+    #[derive(SerialRead)]
+    #[derive(PartialEq)]
+    #[derive(Debug)]
+    struct TestCounted {
+        count : u8,
+        #[count(count)]
+        a : Vec<u8>,
+        #[count_minus_one(count)]
+        b : Vec<u8>
+    }
+
+    #[test]
+    fn read_counted() {
+        let mut rdr = SerialReadStorage::from(vec![2,1,2,3,4,5]);
+        let actual : TestCounted = SerialRead::read(&mut rdr).unwrap();
+        assert_eq!(
+            actual,
+            TestCounted {
+                count : 2,
+                a : vec![1,2],
+                b : vec![3,4,5]
+            }
+        );
+    }
+
+    #[derive(SerialRead)]
+    #[derive(PartialEq)]
+    #[derive(Debug)]
+    struct TestPrefixed {
+        #[count_prefix(u16)]
+        a : Vec<u8>,
+        #[count_prefix_minus_one(u8)]
+        b : Vec<u16>
+    }
+
+    #[test]
+    fn read_prefixed() {
+        let mut rdr = SerialReadStorage::from(vec![0,2,1,2,1,3,4,5,6]);
+        let actual : TestPrefixed = SerialRead::read(&mut rdr).unwrap();
+        assert_eq!(
+            actual,
+            TestPrefixed {
+                a : vec![1,2],
+                b : vec![0x0304,0x0506]
+            }
+        );
+    }
+
+    #[derive(SerialRead)]
+    #[derive(PartialEq)]
+    #[derive(Debug)]
+    struct TestSeek {
+        offset : u8,
+        #[seek(offset)]
+        a : u8,
+        #[seek(offset as u64 - 2)]
+        #[count(2)]
+        b : Vec<u8>
+    }
+
+    #[test]
+    fn read_seek() {
+        let mut rdr = SerialReadStorage::from(vec![4,1,2,3,4,5]);
+        let actual : TestSeek = SerialRead::read(&mut rdr).unwrap();
+        assert_eq!(
+            actual,
+            TestSeek {
+                offset : 4,
+                a : 4,
+                b : vec![2,3]
+            }
+        );
+    }
+
+    #[test]
+    fn read_count_truncated() {
+        let mut rdr = SerialReadStorage::from(vec![3,1,2]);
+        let actual : crate::Result<TestCounted> = SerialRead::read(&mut rdr);
+        match actual {
+            Err(Error::InField { structure: "TestCounted", field: "a", offset: 1, error }) => {
+                match *error {
+                    Error::UnexpectedEof { offset: 3, len: 1, size: 3 } => (),
+                    err => panic!("unexpected cause {:?}", err)
+                }
+            },
+            res => panic!("unexpected result {:?}", res)
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::{Error, Result};
+
+
+pub trait SerialWrite {
+    fn write( &self, wtr : &mut SerialWriteStorage ) -> Result<()>;
+}
+
+impl SerialWrite for u8 {
+    fn write( &self, wtr : &mut SerialWriteStorage ) -> Result<()> {
+        wtr.write_u8(*self)
+    }
+}
+
+impl SerialWrite for i8 {
+    fn write( &self, wtr : &mut SerialWriteStorage ) -> Result<()> {
+        wtr.write_i8(*self)
+    }
+}
+
+impl SerialWrite for u16 {
+    fn write( &self, wtr : &mut SerialWriteStorage ) -> Result<()> {
+        wtr.write_u16(*self)
+    }
+}
+
+impl SerialWrite for i16 {
+    fn write( &self, wtr : &mut SerialWriteStorage ) -> Result<()> {
+        wtr.write_i16(*self)
+    }
+}
+
+impl SerialWrite for u32 {
+    fn write( &self, wtr : &mut SerialWriteStorage ) -> Result<()> {
+        wtr.write_u32(*self)
+    }
+}
+
+impl SerialWrite for i32 {
+    fn write( &self, wtr : &mut SerialWriteStorage ) -> Result<()> {
+        wtr.write_i32(*self)
+    }
+}
+
+
+// Growable buffer with a write position. Writing past the end extends the
+// buffer, and seeking past the end fills with zeros, so structures with
+// offsets can be written out of order.
+pub struct SerialWriteStorage {
+    block : Vec<u8>,
+    pos : u64
+}
+
+impl SerialWriteStorage {
+    pub fn new() -> SerialWriteStorage {
+        SerialWriteStorage {
+            block: vec![],
+            pos: 0
+        }
+    }
+
+    pub fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn size(&self) -> u64 {
+        self.block.len() as u64
+    }
+
+    pub fn seek(&mut self, offset : u64) -> Result<&mut Self> {
+        if offset > self.size() {
+            self.block.resize(offset as usize, 0);
+        }
+        self.pos = offset;
+        Ok(self)
+    }
+
+    pub fn seek_end(&mut self) -> Result<&mut Self> {
+        let end = self.size();
+        self.seek(end)
+    }
+
+    pub fn align(&mut self, wordlength : u64) -> Result<&mut Self> {
+        let rem = self.pos % wordlength;
+        if rem == 0 {
+            Ok(self)
+        } else {
+            self.pad(wordlength - rem)
+        }
+    }
+
+    // Skip bytes, filling with zeros if past the end
+    pub fn pad(&mut self, bytes : u64) -> Result<&mut Self> {
+        let offset = self.pos + bytes;
+        self.seek(offset)
+    }
+
+    pub fn write_bytes(&mut self, data : &[u8]) -> Result<()> {
+        let start = self.pos as usize;
+        let end = start + data.len();
+        if end > self.block.len() {
+            self.block.resize(end, 0);
+        }
+        self.block[start..end].copy_from_slice(data);
+        self.pos = end as u64;
+        Ok(())
+    }
+
+    pub fn write_u8(&mut self, val : u8) -> Result<()> {
+        self.write_bytes(&[val])
+    }
+    pub fn write_i8(&mut self, val : i8) -> Result<()> {
+        self.write_bytes(&[val as u8])
+    }
+    pub fn write_u16(&mut self, val : u16) -> Result<()> {
+        let mut buf = [0u8; 2];
+        BigEndian::write_u16(&mut buf, val);
+        self.write_bytes(&buf)
+    }
+    pub fn write_i16(&mut self, val : i16) -> Result<()> {
+        self.write_u16(val as u16)
+    }
+
+    // Values that doesn't fit in 24 bits are an error, rather than being
+    // silently truncated
+    pub fn write_u24(&mut self, val : u32) -> Result<()> {
+        if val > 0xffffff {
+            return Err(Error::invalid_value(self.pos, "SerialWriteStorage", "u24", "value below $1000000", format!("${:x}", val)));
+        }
+        let mut buf = [0u8; 3];
+        BigEndian::write_u24(&mut buf, val);
+        self.write_bytes(&buf)
+    }
+
+    pub fn write_u32(&mut self, val : u32) -> Result<()> {
+        let mut buf = [0u8; 4];
+        BigEndian::write_u32(&mut buf, val);
+        self.write_bytes(&buf)
+    }
+    pub fn write_i32(&mut self, val : i32) -> Result<()> {
+        self.write_u32(val as u32)
+    }
+
+    pub fn to_vec(self) -> Vec<u8> {
+        self.block
+    }
+}
+
+impl Default for SerialWriteStorage {
+    fn default() -> SerialWriteStorage {
+        SerialWriteStorage::new()
+    }
+}
+
+impl std::fmt::Debug for SerialWriteStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SerialWriteStorage(pos={}, size={})", self.pos, self.block.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SerialWrite, SerialWriteStorage};
+    use crate::serialization::{SerialRead, SerialReadStorage};
+
+    #[test]
+    fn write_roundtrip() {
+        let mut wtr = SerialWriteStorage::new();
+        0x0102u16.write(&mut wtr).unwrap();
+        (-2i32).write(&mut wtr).unwrap();
+        wtr.write_u24(0x030405).unwrap();
+        assert!(wtr.write_u24(0x1000000).is_err());
+
+        let mut rdr = SerialReadStorage::from(wtr.to_vec());
+        assert_eq!(u16::read(&mut rdr).unwrap(), 0x0102);
+        assert_eq!(i32::read(&mut rdr).unwrap(), -2);
+        assert_eq!(rdr.read_u24().unwrap(), 0x030405);
+    }
+
+    #[test]
+    fn write_out_of_order() {
+        let mut wtr = SerialWriteStorage::new();
+        wtr.seek(4).unwrap();
+        wtr.write_u8(5).unwrap();
+        wtr.align(4).unwrap();
+        assert_eq!(wtr.pos(), 8);
+        wtr.seek(0).unwrap();
+        wtr.write_u16(0xabcd).unwrap();
+        assert_eq!(wtr.to_vec(), vec![0xab, 0xcd, 0, 0, 5, 0, 0, 0]);
+    }
+}
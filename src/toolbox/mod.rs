@@ -1,5 +1,6 @@
 mod traphandler;
 mod segment_loader;
+mod traps;
 
 // The toolbox emulates the functionality of Macintosh Toolbox
 
@@ -15,7 +16,10 @@ mod segment_loader;
 use crate::{
     filesys::{
         hfs::HfsImage,
-        rsrc::Rsrc
+        rsrc::{
+            Rsrc,
+            ResourceChain
+        }
     },
     phy::{
         Phy,
@@ -27,32 +31,48 @@ use crate::{
             LogMem,
             RAM,
             RcMem,
-            log::{
-                LOG_DATA
-            }
+            WatchMem
         }
-    }
+    },
+    types::DateTime
 };
 use std::rc::Rc;
+use std::cell::RefCell;
 use traphandler::ToolboxTrapHandler;
 use segment_loader::SegmentLoader;
 
 pub use r68k_emu::cpu::Core;
 
-type ToolboxPhy = Phy<LogMem<MuxMem>, ToolboxTrapHandler>;
+pub use traps::{
+    trap_name,
+    format_trap,
+    is_toolbox_trap
+};
+
+type ToolboxPhy = Phy<WatchMem<LogMem<MuxMem>>, ToolboxTrapHandler>;
 
 pub struct Toolbox {
     _img: HfsImage,
-    rsrc: Rsrc,
+    rsrc: RefCell<ResourceChain>,
     segment_loader: RcMem<SegmentLoader>
 
 }
 
 impl Toolbox {
-    pub fn new(img: HfsImage, rsrc: Rsrc) -> std::io::Result<Rc<Toolbox>> {
+    pub fn new(img: HfsImage, rsrc: Rsrc) -> crate::Result<Rc<Toolbox>> {
+        let mut chain = ResourceChain::new();
+
+        // Not all images has a System file, in which case only the
+        // application resources are available
+        match chain.open_system_from(&img) {
+            Ok(_) | Err(crate::Error::NotFound) => (),
+            Err(err) => return Err(err)
+        }
+        chain.open(rsrc)?;
+
         let toolbox = Rc::new(Toolbox {
             _img: img,
-            rsrc,
+            rsrc: RefCell::new(chain),
             segment_loader: RcMem::new(SegmentLoader::new())
         });
 
@@ -60,7 +80,8 @@ impl Toolbox {
     }
 
 
-    pub fn into_phy(toolbox: &Rc<Toolbox>) -> std::io::Result<ToolboxPhy> {
+    // Memory accesses are logged according to log_level, see phy::mem::log
+    pub fn into_phy(toolbox: &Rc<Toolbox>, log_level: u32) -> crate::Result<ToolboxPhy> {
         let mut mem = MuxMem::new();
 
         // THe handlers is the main entry point to own the toolbox, since it's not owned back
@@ -75,8 +96,10 @@ impl Toolbox {
             Box::new(toolbox.segment_loader.clone())
         );
 
-        // Globals RAM
-        mem.add_prefix(Prefix::new(0x0000_0000, 20), Box::new(RAM::from(vec![0x00u8; 0x1000])));
+        // Globals RAM, with the Time global read by GetDateTime
+        let mut globals = vec![0x00u8; 0x1000];
+        globals[0x20c..0x210].copy_from_slice(&DateTime::now().to_mac_u32().to_be_bytes());
+        mem.add_prefix(Prefix::new(0x0000_0000, 20), Box::new(RAM::from(globals)));
 
         // Stack
         mem.add_prefix(Prefix::new(0x10f0_0000, 12), Box::new(RAM::new(0x0010_0000)));
@@ -84,7 +107,7 @@ impl Toolbox {
         // Application RAM needs to preceed the jump table, since relative to A5
         mem.add_prefix(Prefix::new(0x1ff0_0000, 12), Box::new(RAM::new(0x0010_0000)));
 
-        let mut phy = Phy::new(LogMem::new(mem, LOG_DATA), handlers);
+        let mut phy = Phy::new(WatchMem::new(LogMem::new(mem, log_level)), handlers);
 
         phy.core.dar = [
             0xd0d0_d0d0, // D0
@@ -10,30 +10,20 @@ use crate::types::{
     OSType
 };
 use crate::serialization::{SerialReadStorage, SerialRead};
+use crate::filesys::rsrc::decode::{
+    JumpTableHeader,
+    SegmentHeader
+};
 
 const SEGMENT_MAX_SIZE : u32 = 0x8000;
 
-#[derive(SerialRead, Default, Clone)]
-struct JumpTableHeader {
-    _above_a5: u32,
-    _below_a5: u32,
-    _length: u32,
-    offset_a5: u32
-}
-
-#[derive(SerialRead, Default)]
-struct SegmentHeader {
-    _offset: u16,
-    _count: u16
-}
-
 enum Header {
     JumpTable(JumpTableHeader),
     Segment(SegmentHeader)
 }
 
 impl Header {
-    fn read(rdr: &mut SerialReadStorage, code_id: i16) -> std::io::Result<Header> {
+    fn read(rdr: &mut SerialReadStorage, code_id: i16) -> crate::Result<Header> {
         if code_id == 0 {
             Ok(Header::JumpTable(SerialRead::read(rdr)?))
         } else {
@@ -111,7 +101,10 @@ impl SegmentLoader {
 
     pub fn load(&mut self, id: i16) -> Option<u32> {
         if let Some(toolbox) = self.toolbox.upgrade() {
-            let name = toolbox.rsrc.name(OSType::from(b"CODE"), id).ok()?.unwrap_or(PString::from("-"));
+            let chain = toolbox.rsrc.borrow();
+            let code_type = OSType::from(b"CODE");
+            let code = chain.get(&code_type, id)?;
+            let name = code.name().cloned().unwrap_or(PString::from("-"));
 
             // See if already loaded
             for (idx, (cur_id,_,  _)) in self.data.iter().enumerate() {
@@ -125,7 +118,7 @@ impl SegmentLoader {
             let idx = self.data.len();
             let address = idx as u32 * SEGMENT_MAX_SIZE + self.address_base;
 
-            let mut data = toolbox.rsrc.open(OSType::from(b"CODE"), id).ok()?;
+            let mut data = code.open().ok()?;
 
             // The header is not included in the content
             let header = Header::read(&mut data, id).ok()?;
@@ -9,7 +9,7 @@ use crate::phy::{
     stackable::Stackable
 };
 
-use crate::types::OSType;
+use crate::types::{DateTime, OSType};
 
 use std::rc::Rc;
 
@@ -79,8 +79,9 @@ impl ToolboxTrapHandler {
     }
 
     fn CurResFile(&mut self, _core: &mut impl Core) -> Option<i16> {
-        println!("CurResFile()");
-        Some(1234)
+        let refnum = self.toolbox.rsrc.borrow().current().unwrap_or(0);
+        println!("CurResFile() = {}", refnum);
+        Some(refnum)
     }
 
     fn invoke_CurResFile(&mut self, core: &mut impl Core) -> TrapResult {
@@ -92,6 +93,23 @@ impl ToolboxTrapHandler {
         }
     }
 
+    fn UseResFile(&mut self, _core: &mut impl Core, refNum: i16) -> Option<()> {
+        println!("UseResFile({})", refNum);
+        // Unknown reference numbers sets ResError, but is not fatal
+        let _ = self.toolbox.rsrc.borrow_mut().use_file(refNum);
+        Some(())
+    }
+
+    fn invoke_UseResFile(&mut self, core: &mut impl Core) -> TrapResult {
+        let arg_1 = Stackable::stack_pop(core);
+        if let Some(result) = self.UseResFile(core, arg_1) {
+            result.stack_replace(core);
+            TrapResult::Continue
+        } else {
+            TrapResult::Halt
+        }
+    }
+
     fn GetTrapAddress(&mut self, core: &mut impl Core) -> TrapResult {
         let dar : &mut [u32; 16] = core.dar();
         // Input: D0 => trap number
@@ -104,6 +122,20 @@ impl ToolboxTrapHandler {
         TrapResult::Continue
     }
 
+    fn ReadDateTime(&mut self, core: &mut impl Core) -> TrapResult {
+        // Input: A0 => address of the long receiving the time
+        // Output: D0 => result code
+        let address = core.dar()[8]; // A0
+        let secs = DateTime::now().to_mac_u32();
+        println!("ReadDateTime(${:08x}) = {}", address, secs);
+
+        if core.write_data_long(address, secs).is_err() {
+            return TrapResult::Halt;
+        }
+        core.dar()[0] = 0; // D0 = noErr
+        TrapResult::Continue
+    }
+
     fn SysError(&mut self, core: &mut impl Core) -> TrapResult {
         println!("SysError code: {}", core.dar()[0] as i32);
         TrapResult::Halt
@@ -162,15 +194,61 @@ impl TrapHandler for ToolboxTrapHandler {
             0xa97b => self.invoke_InitDialogs(core),
             0xa850 => self.do_nothing(core, "InitCursor"),
             0xa063 => self.MaxApplZone(core),
+            0xa039 => self.ReadDateTime(core),
             0xa1ad => self.Gestalt(core),
             0xa260 => self.HFSDispatch(core),
             0xa346 => self.GetTrapAddress(core),
             0xa746 => self.GetTrapAddress(core),
             0xa994 => self.invoke_CurResFile(core),
+            0xa998 => self.invoke_UseResFile(core),
             0xa9c9 => self.SysError(core),
             0xa9f0 => self.invoke_LoadSeg(core),
             0xa9fd => self.invoke_GetScrap(core),
             _ => TrapResult::Unimplemented
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::ToolboxTrapHandler;
+    use crate::filesys::hfs::{HfsBuilder, HfsImage};
+    use crate::filesys::rsrc::{Rsrc, RsrcFork};
+    use crate::phy::{Core, Phy, TrapHandler, TrapResult};
+    use crate::phy::mem::RAM;
+    use crate::serialization::SerialAdaptor;
+    use crate::toolbox::Toolbox;
+    use crate::types::PString;
+    use std::io::Cursor;
+
+    fn rsrc() -> Rsrc {
+        Rsrc::new(SerialAdaptor::new(Cursor::new(RsrcFork::new().to_bytes().unwrap()))).unwrap()
+    }
+
+    #[test]
+    fn dispatch() {
+        let builder = HfsBuilder::new(PString::from("Test")).unwrap();
+        let img = HfsImage::from(SerialAdaptor::new(Cursor::new(builder.build().unwrap()))).unwrap();
+        let toolbox = Toolbox::new(img, rsrc()).unwrap();
+        let app = toolbox.rsrc.borrow().current().unwrap();
+        let other = toolbox.rsrc.borrow_mut().open(rsrc()).unwrap();
+
+        let mut handler = ToolboxTrapHandler::new(toolbox.clone());
+        let mut phy = Phy::new(RAM::from(vec![0; 0x200]), ToolboxTrapHandler::new(toolbox.clone()));
+        phy.core.dar[15] = 0x200;
+
+        // UseResFile(app)
+        phy.core.push_16(app as u16);
+        assert!(matches!(handler.line_1010_emualtion(&mut phy.core, 0xa998, 0x100), TrapResult::Continue));
+        assert_eq!(phy.core.dar[15], 0x200);
+        assert_eq!(toolbox.rsrc.borrow().current(), Some(app));
+
+        // SetWRefCon(window, refCon) is not implemented, and leaves the
+        // stack and resource chain alone
+        toolbox.rsrc.borrow_mut().use_file(other).unwrap();
+        phy.core.push_32(0x1000);
+        phy.core.push_32(0x1234);
+        assert!(matches!(handler.line_1010_emualtion(&mut phy.core, 0xa918, 0x100), TrapResult::Unimplemented));
+        assert_eq!(phy.core.dar[15], 0x1f8);
+        assert_eq!(toolbox.rsrc.borrow().current(), Some(other));
+    }
+}
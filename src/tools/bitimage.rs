@@ -0,0 +1,97 @@
+use super::Image;
+
+// 1 bit image with rows padded to whole words, laid out like a QuickDraw bit
+// map. Set bits are black, the most significant bit is the leftmost pixel.
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub struct BitImage {
+    width: usize,
+    height: usize,
+    row_bytes: usize,
+    data: Vec<u8>
+}
+
+impl BitImage {
+    pub fn new(width: usize, height: usize) -> BitImage {
+        let row_bytes = width.div_ceil(16) * 2;
+        BitImage {
+            width,
+            height,
+            row_bytes,
+            data: vec![0; row_bytes * height]
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn row_bytes(&self) -> usize {
+        self.row_bytes
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    // Pixels outside the image are clear
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        self.data[y * self.row_bytes + x / 8] & (0x80 >> (x % 8)) != 0
+    }
+
+    // Pixels outside the image are ignored
+    pub fn set(&mut self, x: usize, y: usize, set: bool) {
+        if x < self.width && y < self.height {
+            let byte = &mut self.data[y * self.row_bytes + x / 8];
+            if set {
+                *byte |= 0x80 >> (x % 8);
+            } else {
+                *byte &= !(0x80 >> (x % 8));
+            }
+        }
+    }
+
+    // Black on white
+    pub fn to_image(&self) -> Image {
+        let mut image = Image::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let val = if self.get(x, y) { 0x00 } else { 0xff };
+                image.set(x, y, [val, val, val, 0xff]);
+            }
+        }
+        image
+    }
+}
+
+impl std::fmt::Debug for BitImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BitImage({}x{})", self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BitImage;
+
+    #[test]
+    fn bits() {
+        let mut bits = BitImage::new(17, 2);
+        assert_eq!(bits.row_bytes(), 4);
+        bits.set(0, 0, true);
+        bits.set(16, 1, true);
+        bits.set(20, 1, true);
+        assert_eq!(bits.data(), &[0x80, 0, 0, 0, 0, 0, 0x80, 0]);
+        assert!(bits.get(16, 1));
+        bits.set(16, 1, false);
+        assert!(!bits.get(16, 1));
+        assert_eq!(bits.to_image().get(0, 0), Some([0, 0, 0, 0xff]));
+    }
+}
@@ -0,0 +1,202 @@
+use crate::types::{
+    Pattern,
+    Point,
+    Rect,
+    Region,
+    RGBColor
+};
+
+use super::Image;
+
+// Transfer modes, as passed to PenMode and CopyBits
+pub const SRC_COPY: u16 = 0;
+pub const SRC_OR: u16 = 1;
+pub const SRC_XOR: u16 = 2;
+pub const SRC_BIC: u16 = 3;
+pub const PAT_COPY: u16 = 8;
+pub const PAT_OR: u16 = 9;
+pub const PAT_XOR: u16 = 10;
+pub const PAT_BIC: u16 = 11;
+
+// How pixels are drawn: the pattern selects between the foreground and
+// background color, combined with the existing pixels by the transfer mode
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Ink {
+    pub pattern: Pattern,
+    pub fore: RGBColor,
+    pub back: RGBColor,
+    pub mode: u16
+}
+
+impl Ink {
+    pub fn new(pattern: Pattern, mode: u16) -> Ink {
+        Ink {
+            pattern,
+            fore: RGBColor::new(0, 0, 0),
+            back: RGBColor::new(0xffff, 0xffff, 0xffff),
+            mode
+        }
+    }
+}
+
+// Drawing surface in QuickDraw coordinates, backed by an RGBA image. Only
+// the basic transfer modes are supported, arithmetic modes are drawn as copy.
+pub struct Canvas {
+    image: Image,
+    bounds: Rect,
+    clip: Option<Region>
+}
+
+impl Canvas {
+    // Starts out white, as an erased port
+    pub fn new(bounds: Rect) -> Canvas {
+        let width = bounds.width().max(0) as usize;
+        let height = bounds.height().max(0) as usize;
+        let mut image = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                image.set(x, y, [0xff, 0xff, 0xff, 0xff]);
+            }
+        }
+        Canvas {
+            image,
+            bounds,
+            clip: None
+        }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    pub fn set_clip(&mut self, clip: Option<Region>) {
+        self.clip = clip;
+    }
+
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    fn pixel_pos(&self, h: i32, v: i32) -> Option<(usize, usize)> {
+        let x = h - self.bounds.left as i32;
+        let y = v - self.bounds.top as i32;
+        if x < 0 || y < 0 || x >= self.image.width() as i32 || y >= self.image.height() as i32 {
+            return None;
+        }
+        if let Some(clip) = &self.clip {
+            if !clip.contains(h, v) {
+                return None;
+            }
+        }
+        Some((x as usize, y as usize))
+    }
+
+    // Draws a single pixel where the source is set or clear, according to
+    // the transfer mode
+    pub fn plot(&mut self, h: i32, v: i32, set: bool, ink: &Ink) {
+        let (x, y) = match self.pixel_pos(h, v) {
+            Some(pos) => pos,
+            None => return
+        };
+        let set = set != (ink.mode & 4 != 0);
+        let color = match (ink.mode & 3, set) {
+            (0, true) => Some(ink.fore),
+            (0, false) => Some(ink.back),
+            (1, true) => Some(ink.fore),
+            (2, true) => {
+                let [r, g, b, _] = self.image.get(x, y).unwrap_or([0; 4]);
+                Some(RGBColor::from_rgb8(!r, !g, !b))
+            },
+            (3, true) => Some(ink.back),
+            _ => None
+        };
+        if let Some(color) = color {
+            self.image.set_color(x, y, color);
+        }
+    }
+
+    // Draws a color pixel, as from a pixel map. For modes other than copy,
+    // white pixels are treated as clear and others as set.
+    pub fn plot_color(&mut self, h: i32, v: i32, color: RGBColor, ink: &Ink) {
+        if ink.mode & 7 == SRC_COPY {
+            if let Some((x, y)) = self.pixel_pos(h, v) {
+                self.image.set_color(x, y, color);
+            }
+        } else {
+            let set = color.to_rgb8() != [0xff, 0xff, 0xff];
+            let ink = Ink { fore: color, ..ink.clone() };
+            self.plot(h, v, set, &ink);
+        }
+    }
+
+    // Fills the pixels inside a shape with the pattern of the ink
+    pub fn fill(&mut self, bounds: Rect, ink: &Ink, inside: impl Fn(i32, i32) -> bool) {
+        let area = match bounds.intersection(&self.bounds) {
+            Some(area) => area,
+            None => return
+        };
+        for v in area.top as i32..area.bottom as i32 {
+            for h in area.left as i32..area.right as i32 {
+                if inside(h, v) {
+                    self.plot(h, v, ink.pattern.is_set(h, v), ink);
+                }
+            }
+        }
+    }
+
+    // Line drawn with a rectangular pen hanging below and to the right of
+    // each point. Only the part of the pen on the canvas is drawn.
+    pub fn line(&mut self, from: Point, to: Point, pen_size: Point, ink: &Ink) {
+        let bounds = self.bounds;
+        let (mut h, mut v) = (from.h as i32, from.v as i32);
+        let (h1, v1) = (to.h as i32, to.v as i32);
+        let dh = (h1 - h).abs();
+        let dv = -(v1 - v).abs();
+        let sh = if h < h1 { 1 } else { -1 };
+        let sv = if v < v1 { 1 } else { -1 };
+        let mut err = dh + dv;
+        loop {
+            let (left, right) = (h.max(bounds.left as i32), (h + pen_size.h as i32).min(bounds.right as i32));
+            let (top, bottom) = (v.max(bounds.top as i32), (v + pen_size.v as i32).min(bounds.bottom as i32));
+            for pv in top..bottom {
+                for ph in left..right {
+                    self.plot(ph, pv, ink.pattern.is_set(ph, pv), ink);
+                }
+            }
+            if h == h1 && v == v1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dv {
+                err += dv;
+                h += sh;
+            }
+            if e2 <= dh {
+                err += dh;
+                v += sv;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draw() {
+        let mut canvas = Canvas::new(Rect::new(10, 10, 14, 14));
+        let black = Ink::new(Pattern::BLACK, PAT_COPY);
+        canvas.line(Point::new(10, 10), Point::new(13, 13), Point::new(1, 1), &black);
+        canvas.set_clip(Some(Region::from_rect(Rect::new(10, 10, 14, 11))));
+        canvas.fill(Rect::new(0, 0, 20, 20), &Ink::new(Pattern::BLACK, PAT_XOR), |_, _| true);
+
+        let image = canvas.into_image();
+        assert_eq!(image.get(0, 0), Some([0xff, 0xff, 0xff, 0xff]));
+        assert_eq!(image.get(1, 0), Some([0, 0, 0, 0xff]));
+        assert_eq!(image.get(1, 1), Some([0, 0, 0, 0xff]));
+        assert_eq!(image.get(2, 1), Some([0xff, 0xff, 0xff, 0xff]));
+        assert_eq!(image.get(3, 3), Some([0, 0, 0, 0xff]));
+    }
+}
@@ -0,0 +1,190 @@
+use r68k_tools::{
+    PC,
+    disassembler::disassemble,
+    memory::Memory
+};
+
+use crate::toolbox::format_trap;
+
+// Read only view of memory for the r68k disassembler, reading bytes through
+// a function such as a buffer lookup or the emulated address bus. Writes are
+// ignored, and there is no contiguous data to return.
+pub struct ReadMemory<F: Fn(u32) -> u8> {
+    offset: u32,
+    read: F
+}
+
+impl<F: Fn(u32) -> u8> ReadMemory<F> {
+    pub fn new(offset: u32, read: F) -> ReadMemory<F> {
+        ReadMemory { offset, read }
+    }
+}
+
+impl<F: Fn(u32) -> u8> Memory for ReadMemory<F> {
+    fn offset(&self) -> u32 {
+        self.offset
+    }
+    fn data(&self) -> &[u8] {
+        &[]
+    }
+    fn read_word(&self, pc: PC) -> u16 {
+        (self.read_byte(pc) as u16) << 8 | self.read_byte(pc + 1) as u16
+    }
+    fn read_byte(&self, pc: PC) -> u8 {
+        (self.read)(pc.0)
+    }
+    fn write_byte(&mut self, pc: PC, _byte: u8) -> PC {
+        pc
+    }
+    fn write_word(&mut self, pc: PC, _word: u16) -> PC {
+        pc
+    }
+    fn write_vec(&mut self, pc: PC, _bytes: Vec<u8>) -> PC {
+        pc
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Instruction {
+    pub offset: u32,
+    pub len: u32,
+    pub text: String
+}
+
+// The r68k disassembler separates mnemonic and operands with a tab
+pub fn format_instruction(inst: &str) -> String {
+    let mut inst = inst.to_string();
+    if let Some(delim) = inst.find('\t') {
+        let arg = inst.split_off(delim);
+        format!("{:10} {}", inst.trim().to_lowercase(), arg.trim().replace(",", ", "))
+    } else {
+        inst.trim().to_string()
+    }
+}
+
+pub fn read_word(code: &[u8], offset: u32) -> Option<u16> {
+    let offset = offset as usize;
+    let bytes = code.get(offset..offset + 2)?;
+    Some((bytes[0] as u16) << 8 | bytes[1] as u16)
+}
+
+// Decode one instruction. A-line traps are shown by name, and words that
+// can't be decoded as data.
+pub fn decode(code: &[u8], offset: u32) -> Instruction {
+    let word = match read_word(code, offset) {
+        Some(word) => word,
+        None => return Instruction {
+            offset,
+            len: (code.len() as u32).saturating_sub(offset),
+            text: format!("{:10} ${:02x}", "dc.b", code.get(offset as usize).unwrap_or(&0))
+        }
+    };
+    if word & 0xf000 == 0xa000 {
+        let text = format_trap(word).unwrap_or_else(|| format!("{:10} ${:04x}", "dc.w", word));
+        return Instruction { offset, len: 2, text };
+    }
+    let mem = ReadMemory::new(0, |addr| *code.get(addr as usize).unwrap_or(&0));
+    match disassemble(PC(offset), &mem) {
+        Ok((next, inst)) if next.0 > offset && next.0 as usize <= code.len() => Instruction {
+            offset,
+            len: next.0 - offset,
+            text: format_instruction(&format!("{}", inst))
+        },
+        _ => Instruction {
+            offset,
+            len: 2,
+            text: format!("{:10} ${:04x}", "dc.w", word)
+        }
+    }
+}
+
+// Instructions which may be followed by a MacsBug symbol: RTS, JMP (A0) and
+// RTD #n
+pub fn is_procedure_end(code: &[u8], offset: u32) -> bool {
+    matches!(read_word(code, offset), Some(0x4e75) | Some(0x4ed0) | Some(0x4e74))
+}
+
+fn is_symbol_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'%' || c == b'.'
+}
+
+// MacsBug procedure name, which compilers put after the last instruction of a
+// procedure (MacsBug Reference, appendix D). Returns the name and the length
+// of the symbol, including padding and the constant data following it.
+pub fn macsbug_symbol(code: &[u8], offset: u32) -> Option<(String, u32)> {
+    let start = offset as usize;
+    let first = *code.get(start)?;
+    let (name, end) = if (0x80..0xa0).contains(&first) {
+        // Variable length, with the length in the first byte or following it
+        let (len, name_start) = if first == 0x80 {
+            (*code.get(start + 1)? as usize, start + 2)
+        } else {
+            ((first & 0x7f) as usize, start + 1)
+        };
+        let name = code.get(name_start..name_start + len)?;
+        if len == 0 || !name.iter().all(|c| is_symbol_char(*c)) {
+            return None;
+        }
+        let mut end = name_start + len;
+        end += end & 1;
+        let constants = read_word(code, end as u32)? as usize;
+        end += 2 + constants;
+        end += end & 1;
+        if end > code.len() {
+            return None;
+        }
+        (name.to_vec(), end)
+    } else if first >= 0xa0 {
+        // Fixed length, 16 characters if the second also has the high bit set
+        let len = if *code.get(start + 1)? >= 0x80 { 16 } else { 8 };
+        let mut name: Vec<u8> = code.get(start..start + len)?.to_vec();
+        name[0] &= 0x7f;
+        name[1] &= 0x7f;
+        while name.last() == Some(&b' ') {
+            name.pop();
+        }
+        if !name.iter().all(|c| is_symbol_char(*c)) {
+            return None;
+        }
+        (name, start + len)
+    } else {
+        return None;
+    };
+    match name.first() {
+        Some(c) if !c.is_ascii_digit() => (),
+        _ => return None
+    }
+    Some((String::from_utf8_lossy(&name).into_owned(), (end - start) as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, format_instruction, macsbug_symbol};
+
+    #[test]
+    fn symbols() {
+        // Variable length with constant data
+        let code = b"\x84Main\x00\x00\x02\xff\xff\x4e\x75";
+        assert_eq!(macsbug_symbol(code, 0), Some((String::from("Main"), 10)));
+        // Long variable length, padded
+        let code = b"\x80\x03Foo\x00\x00\x00";
+        assert_eq!(macsbug_symbol(code, 0), Some((String::from("Foo"), 8)));
+        // Fixed length
+        assert_eq!(macsbug_symbol(b"\xc4OIT    ", 0), Some((String::from("DOIT"), 8)));
+        assert_eq!(macsbug_symbol(b"\xc4\xcfITWITHLONGNAME", 0), Some((String::from("DOITWITHLONGNAME"), 16)));
+        // Not symbols
+        assert_eq!(macsbug_symbol(b"\x4e\x75", 0), None);
+        assert_eq!(macsbug_symbol(b"\x84Ma", 0), None);
+        assert_eq!(macsbug_symbol(b"\x84M\x01in\x00\x00", 0), None);
+    }
+
+    #[test]
+    fn traps() {
+        let inst = decode(b"\x4e\x75\xa9\xf4\xa0", 2);
+        assert_eq!((inst.offset, inst.len, inst.text.as_str()), (2, 2, "_ExitToShell"));
+        assert_eq!(decode(b"\xab\xff", 0).text, "dc.w       $abff");
+        assert_eq!(decode(b"\x4e\x75\xa0", 2).text, "dc.b       $a0");
+        assert_eq!(format_instruction("MOVE.L\tD0,(A7)"), "move.l     D0, (A7)");
+    }
+}
@@ -0,0 +1,79 @@
+use crate::types::RGBColor;
+
+use super::png;
+
+// 8 bit RGBA image, rows from top to bottom. Pixels default to transparent.
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    data: Vec<u8>
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            data: vec![0; width * height * 4]
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // RGBA, 4 bytes per pixel
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let idx = (y * self.width + x) * 4;
+        let mut pixel = [0u8; 4];
+        pixel.copy_from_slice(&self.data[idx..idx + 4]);
+        Some(pixel)
+    }
+
+    // Pixels outside the image are ignored
+    pub fn set(&mut self, x: usize, y: usize, pixel: [u8; 4]) {
+        if x < self.width && y < self.height {
+            let idx = (y * self.width + x) * 4;
+            self.data[idx..idx + 4].copy_from_slice(&pixel);
+        }
+    }
+
+    pub fn set_color(&mut self, x: usize, y: usize, color: RGBColor) {
+        let [r, g, b] = color.to_rgb8();
+        self.set(x, y, [r, g, b, 0xff]);
+    }
+
+    // Makes pixels transparent where the mask function returns false
+    pub fn apply_mask(&mut self, mask: impl Fn(usize, usize) -> bool) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !mask(x, y) {
+                    self.set(x, y, [0; 4]);
+                }
+            }
+        }
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        png::encode(self)
+    }
+}
+
+impl std::fmt::Debug for Image {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Image({}x{})", self.width, self.height)
+    }
+}
@@ -0,0 +1,232 @@
+// MACE (Macintosh Audio Compression/Expansion) decoder, for 'MAC3' and
+// 'MAC6' compressed sampled sounds.
+//
+// MACE 3:1 packs six 8 bit samples into two bytes, MACE 6:1 into a single
+// byte. Each byte holds three codes of 3, 2 and 3 bits, which select a step
+// from tables scaled by an adaptive index. Channels are interleaved per
+// packet. Output samples are 16 bit, with the low byte a copy of the high
+// byte as the original decoder produced 8 bit samples.
+
+// Index adjustment by code, for 3 bit and 2 bit codes
+const STEP_INDEX_3: [i32; 8] = [-13, 8, 76, 222, 222, 76, 8, -13];
+const STEP_INDEX_2: [i32; 4] = [-18, 140, 140, -18];
+
+// Positive steps for 3 bit codes 0-3 by index, codes 4-7 are the negated
+// steps in reverse order
+const STEPS_3: [[i16; 4]; 128] = [
+    [37, 116, 206, 330], [39, 121, 216, 346], [41, 127, 225, 361], [42, 132, 235, 377],
+    [44, 137, 245, 392], [46, 144, 256, 410], [48, 150, 267, 428], [51, 157, 280, 449],
+    [53, 165, 293, 470], [55, 172, 306, 490], [58, 179, 319, 511], [60, 187, 333, 534],
+    [63, 195, 348, 557], [66, 205, 364, 583], [69, 214, 380, 609], [72, 223, 396, 635],
+    [75, 233, 414, 663], [79, 244, 433, 694], [82, 254, 453, 725], [86, 265, 472, 756],
+    [90, 278, 495, 792], [94, 290, 516, 826], [98, 303, 538, 862], [102, 316, 562, 901],
+    [107, 331, 588, 942], [112, 345, 614, 983], [117, 361, 641, 1027], [122, 377, 670, 1074],
+    [127, 394, 701, 1123], [133, 411, 732, 1172], [139, 430, 764, 1224], [145, 449, 799, 1280],
+    [152, 469, 835, 1337], [159, 490, 872, 1397], [166, 512, 911, 1459], [173, 535, 951, 1523],
+    [181, 558, 993, 1590], [189, 584, 1038, 1662], [197, 610, 1085, 1738], [206, 637, 1133, 1815],
+    [215, 665, 1183, 1895], [225, 695, 1237, 1980], [235, 726, 1291, 2068], [246, 759, 1349, 2161],
+    [257, 792, 1409, 2257], [268, 828, 1472, 2357], [280, 865, 1538, 2463], [293, 903, 1606, 2572],
+    [306, 944, 1678, 2688], [319, 986, 1753, 2807], [334, 1030, 1832, 2933], [349, 1076, 1914, 3065],
+    [364, 1124, 1999, 3202], [380, 1174, 2088, 3344], [398, 1227, 2182, 3494], [415, 1281, 2278, 3649],
+    [434, 1339, 2380, 3811], [453, 1398, 2486, 3982], [473, 1461, 2598, 4160], [495, 1526, 2714, 4346],
+    [517, 1594, 2835, 4540], [540, 1665, 2961, 4741], [564, 1740, 3093, 4953], [589, 1818, 3232, 5175],
+    [615, 1898, 3375, 5405], [643, 1984, 3527, 5647], [671, 2072, 3683, 5898], [701, 2164, 3848, 6161],
+    [733, 2261, 4020, 6438], [766, 2362, 4199, 6724], [800, 2467, 4386, 7024], [836, 2578, 4583, 7339],
+    [873, 2692, 4786, 7664], [912, 2813, 5000, 8008], [952, 2938, 5223, 8364], [995, 3070, 5457, 8739],
+    [1039, 3207, 5701, 9129], [1086, 3350, 5956, 9537], [1134, 3499, 6220, 9960], [1185, 3655, 6497, 10404],
+    [1238, 3818, 6788, 10869], [1293, 3989, 7091, 11355], [1351, 4166, 7407, 11861], [1411, 4352, 7738, 12390],
+    [1474, 4547, 8084, 12946], [1540, 4750, 8444, 13522], [1609, 4962, 8821, 14126], [1680, 5183, 9215, 14756],
+    [1756, 5415, 9626, 15415], [1834, 5657, 10057, 16104], [1916, 5909, 10505, 16822], [2001, 6173, 10975, 17574],
+    [2091, 6448, 11463, 18356], [2184, 6736, 11974, 19175], [2282, 7037, 12510, 20032], [2383, 7351, 13068, 20926],
+    [2490, 7679, 13652, 21861], [2601, 8021, 14260, 22834], [2717, 8380, 14897, 23854], [2838, 8753, 15561, 24918],
+    [2965, 9144, 16256, 26031], [3097, 9553, 16982, 27193], [3236, 9979, 17740, 28407], [3380, 10424, 18532, 29675],
+    [3531, 10890, 19359, 30999], [3688, 11375, 20222, 32382], [3853, 11883, 21125, 32767], [4025, 12414, 22069, 32767],
+    [4205, 12967, 23053, 32767], [4392, 13546, 24082, 32767], [4589, 14151, 25157, 32767], [4793, 14783, 26280, 32767],
+    [5007, 15442, 27452, 32767], [5231, 16132, 28678, 32767], [5464, 16851, 29957, 32767], [5708, 17603, 31294, 32767],
+    [5963, 18389, 32691, 32767], [6229, 19210, 32767, 32767], [6507, 20067, 32767, 32767], [6797, 20963, 32767, 32767],
+    [7101, 21899, 32767, 32767], [7418, 22876, 32767, 32767], [7749, 23897, 32767, 32767], [8095, 24964, 32767, 32767],
+    [8456, 26078, 32767, 32767], [8833, 27242, 32767, 32767], [9228, 28457, 32767, 32767], [9639, 29727, 32767, 32767]
+];
+
+// Positive steps for 2 bit codes 0-1 by index
+const STEPS_2: [[i16; 2]; 128] = [
+    [64, 216], [67, 226], [70, 236], [74, 246], [77, 257], [80, 268], [84, 280], [88, 294],
+    [92, 307], [96, 321], [100, 334], [104, 350], [109, 365], [114, 382], [119, 399], [124, 416],
+    [130, 434], [136, 454], [142, 475], [148, 495], [155, 519], [162, 541], [169, 564], [176, 590],
+    [185, 617], [193, 644], [201, 673], [210, 703], [220, 735], [230, 767], [240, 801], [251, 838],
+    [262, 876], [274, 914], [286, 955], [299, 997], [312, 1041], [326, 1089], [341, 1138], [356, 1188],
+    [372, 1241], [388, 1297], [406, 1354], [424, 1415], [443, 1478], [462, 1544], [483, 1613], [505, 1684],
+    [527, 1760], [551, 1838], [576, 1921], [601, 2006], [628, 2097], [656, 2190], [686, 2288], [716, 2389],
+    [748, 2496], [781, 2607], [816, 2724], [853, 2846], [891, 2973], [930, 3104], [972, 3243], [1016, 3389],
+    [1061, 3539], [1108, 3698], [1158, 3862], [1209, 4035], [1264, 4216], [1320, 4403], [1379, 4599], [1441, 4806],
+    [1505, 5019], [1572, 5244], [1642, 5477], [1715, 5722], [1792, 5978], [1872, 6245], [1955, 6522], [2043, 6813],
+    [2134, 7118], [2229, 7436], [2329, 7767], [2432, 8114], [2541, 8477], [2655, 8855], [2773, 9250], [2897, 9663],
+    [3026, 10094], [3162, 10546], [3303, 11016], [3450, 11508], [3604, 12020], [3765, 12556], [3933, 13118], [4108, 13703],
+    [4292, 14315], [4483, 14953], [4683, 15621], [4892, 16318], [5111, 17046], [5339, 17807], [5577, 18602], [5826, 19433],
+    [6086, 20300], [6358, 21205], [6642, 22152], [6938, 23141], [7248, 24173], [7571, 25252], [7909, 26380], [8262, 27557],
+    [8631, 28786], [9016, 30072], [9419, 31413], [9839, 32767], [10278, 32767], [10737, 32767], [11216, 32767], [11717, 32767],
+    [12240, 32767], [12786, 32767], [13356, 32767], [13953, 32767], [14576, 32767], [15226, 32767], [15906, 32767], [16615, 32767]
+];
+
+#[derive(Default)]
+struct Channel {
+    index: i32,
+    factor: i32,
+    level: i32,
+    previous: i32,
+    prev2: i32
+}
+
+impl Channel {
+    // Step for a code of the given width in bits, adapting the index
+    fn step(&mut self, code: u8, width: usize) -> i32 {
+        let row = ((self.index & 0x7f0) >> 4) as usize;
+        let code = code as usize;
+        let (step, adjust) = if width == 3 {
+            let step = if code < 4 { STEPS_3[row][code] as i32 } else { -1 - STEPS_3[row][7 - code] as i32 };
+            (step, STEP_INDEX_3[code])
+        } else {
+            let step = if code < 2 { STEPS_2[row][code] as i32 } else { -1 - STEPS_2[row][3 - code] as i32 };
+            (step, STEP_INDEX_2[code])
+        };
+        self.index = (self.index + adjust - (self.index >> 5)).max(0);
+        step
+    }
+
+    fn mace3(&mut self, code: u8, width: usize) -> i16 {
+        let current = clip(self.step(code, width) + self.level);
+        self.level = current - (current >> 3);
+        widen(current)
+    }
+
+    fn mace6(&mut self, code: u8, width: usize) -> [i16; 2] {
+        let step = self.step(code, width);
+        if (self.previous ^ step) >= 0 {
+            self.factor = (self.factor + 506).min(32767);
+        } else {
+            self.factor = if self.factor - 314 < -32768 { -32767 } else { self.factor - 314 };
+        }
+        let current = clip(step + self.level);
+        self.level = (current * self.factor) >> 15;
+        let current = current >> 1;
+        let out = [
+            widen(self.previous + self.prev2 - ((self.prev2 - current) >> 2)),
+            widen(self.previous + current + ((self.prev2 - current) >> 2))
+        ];
+        self.prev2 = self.previous;
+        self.previous = current;
+        out
+    }
+}
+
+// Clipping of the original decoder, which is one off for negative values
+fn clip(val: i32) -> i32 {
+    if val > 32767 {
+        32767
+    } else if val < -32768 {
+        -32767
+    } else {
+        val
+    }
+}
+
+// The high byte is the 8 bit sample
+fn widen(val: i32) -> i16 {
+    ((val & 0xff00) | ((val >> 8) & 0xff)) as i16
+}
+
+// Decodes 'MAC3' data, two bytes per channel for every six samples
+pub fn decode_mace3(data: &[u8], channels: usize) -> Vec<i16> {
+    let channels = channels.max(1);
+    let mut state: Vec<Channel> = (0..channels).map(|_| Channel::default()).collect();
+    let mut out = Vec::with_capacity(data.len() * 3);
+    for packet in data.chunks_exact(channels * 2) {
+        let mut samples = vec![[0i16; 6]; channels];
+        for (ch, chd) in state.iter_mut().enumerate() {
+            for k in 0..2 {
+                let byte = packet[ch * 2 + k];
+                let codes = [(byte & 7, 3), ((byte >> 3) & 3, 2), (byte >> 5, 3)];
+                for (l, (code, width)) in codes.iter().enumerate() {
+                    samples[ch][k * 3 + l] = chd.mace3(*code, *width);
+                }
+            }
+        }
+        for i in 0..6 {
+            out.extend(samples.iter().map(|s| s[i]));
+        }
+    }
+    out
+}
+
+// Decodes 'MAC6' data, one byte per channel for every six samples
+pub fn decode_mace6(data: &[u8], channels: usize) -> Vec<i16> {
+    let channels = channels.max(1);
+    let mut state: Vec<Channel> = (0..channels).map(|_| Channel::default()).collect();
+    let mut out = Vec::with_capacity(data.len() * 6);
+    for packet in data.chunks_exact(channels) {
+        let mut samples = vec![[0i16; 6]; channels];
+        for (ch, chd) in state.iter_mut().enumerate() {
+            let byte = packet[ch];
+            let codes = [(byte >> 5, 3), ((byte >> 3) & 3, 2), (byte & 7, 3)];
+            for (l, (code, width)) in codes.iter().enumerate() {
+                let pair = chd.mace6(*code, *width);
+                samples[ch][l * 2..l * 2 + 2].copy_from_slice(&pair);
+            }
+        }
+        for i in 0..6 {
+            out.extend(samples.iter().map(|s| s[i]));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables() {
+        // Steps grow with the index, saturating at the largest sample
+        for rows in STEPS_3.windows(2) {
+            assert!(rows[0].iter().zip(rows[1].iter()).all(|(a, b)| a <= b));
+        }
+        for rows in STEPS_2.windows(2) {
+            assert!(rows[0][0] < rows[1][0] && rows[0][1] <= rows[1][1]);
+        }
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(decode_mace3(&[0x00, 0x00, 0x00], 1).len(), 6);
+        assert_eq!(decode_mace3(&[0x00; 8], 2).len(), 24);
+        assert_eq!(decode_mace6(&[0x00, 0x00], 2).len(), 12);
+
+        // Repeated largest positive steps ramp up, and negative steps down
+        let up = decode_mace3(&[0x6b; 64], 1);
+        assert!(up[0] > 0 && up.windows(2).take(8).all(|w| w[0] <= w[1]));
+        let down = decode_mace3(&[0x94; 64], 1);
+        assert!(down[0] < 0 && down.windows(2).take(8).all(|w| w[0] >= w[1]));
+
+        // Stereo channels are decoded independently
+        let stereo = decode_mace6(&[0x00, 0xff, 0x00, 0xff], 2);
+        let left = decode_mace6(&[0x00, 0x00], 1);
+        let right = decode_mace6(&[0xff, 0xff], 1);
+        let interleaved: Vec<i16> = left.iter().zip(right.iter()).flat_map(|(l, r)| vec![*l, *r]).collect();
+        assert_eq!(stereo, interleaved);
+    }
+
+    // Output of the reference decoder for the same data
+    #[test]
+    fn known_answer() {
+        let data = [0x6b, 0x6b, 0x6b, 0x6b, 0x94, 0x12, 0xe7, 0x3c];
+        assert_eq!(decode_mace3(&data, 1), vec![
+            257, 514, 1285, 2570, 4112, 7196, 12593, 17733, 29041, 32639, 32639, 32639,
+            -3856, -3856, -3856, -2828, -2828, -2571, -2314, -1800, -1543, -1800, -1543, -1286
+        ]);
+        assert_eq!(decode_mace6(&data[..4], 1), vec![
+            0, 0, 0, 257, 257, 514, 771, 1028, 1285, 1542, 1799, 2313,
+            3341, 4369, 5397, 6425, 7967, 10023, 13107, 17476, 21074, 24415, 26985, 28784
+        ]);
+        assert_eq!(decode_mace6(&data[4..], 1)[..6], [-1, -1, -1, -258, -258, -515]);
+    }
+}
@@ -1 +1,16 @@
-pub mod hexdump;
\ No newline at end of file
+pub mod hexdump;
+pub mod image;
+pub mod png;
+pub mod packbits;
+pub mod canvas;
+pub mod pcm;
+pub mod wav;
+pub mod mace;
+pub mod bitimage;
+pub mod disasm;
+pub mod json;
+
+pub use image::Image;
+pub use pcm::Pcm;
+pub use bitimage::BitImage;
+pub use json::Json;
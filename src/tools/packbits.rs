@@ -0,0 +1,86 @@
+use crate::{Error, Result};
+
+// PackBits run length encoding, as used by UnpackBits and in PICT pixel data.
+//
+// Each run starts with a signed flag byte. A positive flag n is followed by
+// n+1 literal units, a negative flag -n by a single unit repeated n+1 times.
+// A flag of -128 is ignored. The unit is a byte, or a 16 bit word for 16 bit
+// pixel data.
+
+pub fn unpack(data: &[u8], unit: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() * 2);
+    let mut pos = 0;
+    while pos < data.len() {
+        let flag = data[pos] as i8;
+        pos += 1;
+        let (len, repeat) = match flag {
+            -128 => continue,
+            flag if flag < 0 => (unit, 1 - flag as isize),
+            flag => ((flag as usize + 1) * unit, 1)
+        };
+        let run = data.get(pos..pos + len)
+            .ok_or(Error::UnexpectedEof { offset: pos as u64, len: len as u64, size: data.len() as u64 })?;
+        for _ in 0..repeat {
+            out.extend_from_slice(run);
+        }
+        pos += len;
+    }
+    Ok(out)
+}
+
+// Byte oriented PackBits, with runs of three or more bytes repeated
+pub fn pack(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut pos = 0;
+    while pos < data.len() {
+        let run = data[pos..].iter().take(128).take_while(|b| **b == data[pos]).count();
+        if run >= 3 {
+            out.push((1 - run as isize) as u8);
+            out.push(data[pos]);
+            pos += run;
+            continue;
+        }
+        // Literal up to the next run of three
+        let start = pos;
+        while pos < data.len() && pos - start < 128 {
+            if pos + 2 < data.len() && data[pos] == data[pos + 1] && data[pos] == data[pos + 2] {
+                break;
+            }
+            pos += 1;
+        }
+        out.push((pos - start - 1) as u8);
+        out.extend_from_slice(&data[start..pos]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pack, unpack};
+
+    #[test]
+    fn unpack_bytes() {
+        // Example from Apple Technical Note TN1023
+        let packed = [
+            0xfe, 0xaa, 0x02, 0x80, 0x00, 0x2a, 0xfd, 0xaa, 0x03, 0x80, 0x00, 0x2a, 0x22, 0xf7, 0xaa
+        ];
+        let unpacked = [
+            0xaa, 0xaa, 0xaa, 0x80, 0x00, 0x2a, 0xaa, 0xaa, 0xaa, 0xaa, 0x80, 0x00, 0x2a, 0x22,
+            0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa
+        ];
+        assert_eq!(unpack(&packed, 1).unwrap(), unpacked.to_vec());
+        assert_eq!(unpack(&pack(&unpacked), 1).unwrap(), unpacked.to_vec());
+        assert!(unpack(&[0x02, 0x01], 1).is_err());
+    }
+
+    #[test]
+    fn unpack_words() {
+        assert_eq!(unpack(&[0xff, 0x12, 0x34, 0x00, 0x56, 0x78], 2).unwrap(), vec![0x12, 0x34, 0x12, 0x34, 0x56, 0x78]);
+    }
+
+    #[test]
+    fn pack_long() {
+        let data: Vec<u8> = (0..300).map(|i| if i < 200 { 7 } else { i as u8 }).collect();
+        assert_eq!(unpack(&pack(&data), 1).unwrap(), data);
+    }
+}
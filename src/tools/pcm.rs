@@ -0,0 +1,39 @@
+use super::wav;
+
+// Linear PCM audio with 16 bit signed samples, interleaved by channel. The
+// sample size is the resolution of the source, 8 bit sounds are saved as
+// such.
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub struct Pcm {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub sample_size: u16,
+    pub samples: Vec<i16>
+}
+
+impl Pcm {
+    pub fn new(channels: u16, sample_rate: u32, sample_size: u16, samples: Vec<i16>) -> Pcm {
+        Pcm {
+            channels,
+            sample_rate,
+            sample_size,
+            samples
+        }
+    }
+
+    // Number of samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    pub fn to_wav(&self) -> crate::Result<Vec<u8>> {
+        wav::encode(self)
+    }
+}
+
+impl std::fmt::Debug for Pcm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Pcm({} channels, {} Hz, {} bit, {} frames)", self.channels, self.sample_rate, self.sample_size, self.frames())
+    }
+}
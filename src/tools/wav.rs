@@ -0,0 +1,81 @@
+use std::convert::TryFrom;
+
+use crate::{Error, Result};
+
+use super::pcm::Pcm;
+
+// WAV writer for uncompressed PCM. 8 bit samples are stored unsigned, 16 bit
+// samples signed little endian.
+
+const WAVE_FORMAT_PCM: u16 = 1;
+
+fn write_chunk(out: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(chunk_type);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    // Chunks are aligned to words
+    if !data.len().is_multiple_of(2) {
+        out.push(0);
+    }
+}
+
+// Fails when the format can't be represented in the header, such as with
+// too many channels
+pub fn encode(pcm: &Pcm) -> Result<Vec<u8>> {
+    let bytes_per_sample: u32 = if pcm.sample_size <= 8 { 1 } else { 2 };
+    let block_align = pcm.channels as u32 * bytes_per_sample;
+    let byte_rate = pcm.sample_rate.checked_mul(block_align);
+    let (block_align, byte_rate) = match (u16::try_from(block_align), byte_rate) {
+        (Ok(block_align), Some(byte_rate)) => (block_align, byte_rate),
+        _ => return Err(Error::invalid_value(0, "Pcm", "channels", "fitting a WAV header", pcm.channels))
+    };
+
+    let mut format = vec![];
+    format.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+    format.extend_from_slice(&pcm.channels.to_le_bytes());
+    format.extend_from_slice(&pcm.sample_rate.to_le_bytes());
+    format.extend_from_slice(&byte_rate.to_le_bytes());
+    format.extend_from_slice(&block_align.to_le_bytes());
+    format.extend_from_slice(&(bytes_per_sample as u16 * 8).to_le_bytes());
+
+    let mut data = Vec::with_capacity(pcm.samples.len() * bytes_per_sample as usize);
+    for sample in pcm.samples.iter() {
+        if bytes_per_sample == 1 {
+            data.push(((*sample >> 8) as u8) ^ 0x80);
+        } else {
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+    }
+
+    let mut body = Vec::from(&b"WAVE"[..]);
+    write_chunk(&mut body, b"fmt ", &format);
+    write_chunk(&mut body, b"data", &data);
+    let mut out = vec![];
+    write_chunk(&mut out, b"RIFF", &body);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encode;
+    use crate::tools::pcm::Pcm;
+
+    #[test]
+    fn encode_8bit() {
+        let wav = encode(&Pcm::new(1, 22254, 8, vec![0, -0x8000, 0x7f00])).unwrap();
+        assert_eq!(&wav[0..12], b"RIFF\x28\x00\x00\x00WAVE");
+        assert_eq!(&wav[12..20], b"fmt \x10\x00\x00\x00");
+        assert_eq!(&wav[20..36], &[1, 0, 1, 0, 0xee, 0x56, 0, 0, 0xee, 0x56, 0, 0, 1, 0, 8, 0]);
+        assert_eq!(&wav[36..48], b"data\x03\x00\x00\x00\x80\x00\xff\x00");
+        assert_eq!(wav.len(), 48);
+    }
+
+    #[test]
+    fn encode_16bit() {
+        let wav = encode(&Pcm::new(2, 44100, 16, vec![1, -2])).unwrap();
+        assert_eq!(&wav[32..36], &[4, 0, 16, 0]);
+        assert_eq!(&wav[36..48], b"data\x04\x00\x00\x00\x01\x00\xfe\xff");
+        assert!(encode(&Pcm::new(0x8000, 44100, 16, vec![])).is_err());
+        assert!(encode(&Pcm::new(0x4000, 0x20000, 16, vec![])).is_err());
+    }
+}
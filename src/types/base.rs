@@ -1,7 +1,11 @@
-use crate::serialization::{SerialReadStorage, SerialRead};
-use chrono::NaiveDateTime;
+use crate::serialization::{SerialReadStorage, SerialRead, SerialWriteStorage, SerialWrite};
+use crate::{Error, Result};
+use std::convert::TryFrom;
+
+use super::Encoding;
 
 #[derive(PartialEq)]
+#[derive(Clone)]
 #[derive(SerialRead)]
 pub struct OSType (pub [u8;4]);
 
@@ -12,6 +16,12 @@ impl OSType {
     }
 }
 
+impl SerialWrite for OSType {
+    fn write(&self, wtr : &mut SerialWriteStorage) -> Result<()> {
+        wtr.write_bytes(&self.0)
+    }
+}
+
 impl std::fmt::Debug for OSType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "\'{}{}{}{}\'",
@@ -40,10 +50,14 @@ impl From<&[u8; 4]> for OSType {
     }
 }
 
-impl From<&[u8]> for OSType {
-    fn from(b: &[u8]) -> OSType {
-        assert_eq!(b.len(), 4);
-        OSType([b[0], b[1], b[2], b[3]])
+impl TryFrom<&[u8]> for OSType {
+    type Error = Error;
+
+    fn try_from(b: &[u8]) -> Result<OSType> {
+        if b.len() != 4 {
+            return Err(Error::invalid_value(0, "OSType", "0", "4 bytes", format!("{} bytes", b.len())));
+        }
+        Ok(OSType([b[0], b[1], b[2], b[3]]))
     }
 }
 
@@ -64,27 +78,55 @@ impl From<OSType> for u32 {
     }
 }
 
+// Pascal string, kept as raw bytes since the encoding depends on the script
+// system that wrote it. Conversions without explicit encoding uses Mac Roman.
 #[derive(PartialEq)]
 #[derive(PartialOrd)]
 #[derive(Clone)]
 pub struct PString (Vec<u8>);
 
+impl PString {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn decode(&self, encoding: Encoding) -> String {
+        encoding.decode(&self.0)
+    }
+
+    pub fn encode(s: &str, encoding: Encoding) -> Result<PString> {
+        let data = encoding.encode(s)?;
+        if data.len() > 255 {
+            return Err(Error::invalid_value(0, "PString", "len", "at most 255 bytes", data.len()));
+        }
+        Ok(PString(data))
+    }
+}
+
 impl std::fmt::Debug for PString {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = String::from(String::from_utf8_lossy(&self.0));
+        let s = self.decode(Encoding::MacRoman);
         std::fmt::Debug::fmt(&s, f)
     }
 }
 
 impl std::fmt::Display for PString {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = String::from(String::from_utf8_lossy(&self.0));
+        let s = self.decode(Encoding::MacRoman);
         std::fmt::Display::fmt(&s, f)
     }
 }
 
 impl SerialRead for PString {
-    fn read(rdr : &mut SerialReadStorage ) -> std::io::Result<PString> {
+    fn read(rdr : &mut SerialReadStorage ) -> Result<PString> {
         let len : u8 = SerialRead::read(rdr)?;
         let mut data = Vec::with_capacity(len as usize);
         for _ in 0..len {
@@ -97,24 +139,79 @@ impl SerialRead for PString {
     }
 }
 
+impl SerialWrite for PString {
+    fn write(&self, wtr : &mut SerialWriteStorage) -> Result<()> {
+        if self.0.len() > 255 {
+            return Err(Error::invalid_value(wtr.pos(), "PString", "len", "at most 255 bytes", self.0.len()));
+        }
+        wtr.write_u8(self.0.len() as u8)?;
+        wtr.write_bytes(&self.0)
+    }
+}
+
+// Characters not available in Mac Roman are replaced by '?', use
+// PString::encode to detect that
 impl From<&str> for PString {
     fn from(s: &str) -> PString {
-        PString(Vec::from(s))
+        PString(Encoding::MacRoman.encode_lossy(s))
+    }
+}
+
+impl From<&[u8]> for PString {
+    fn from(b: &[u8]) -> PString {
+        PString(Vec::from(b))
     }
 }
 
 impl From<&PString> for String {
     fn from(s: &PString) -> String {
-        String::from(String::from_utf8_lossy(&s.0[..]))
+        s.decode(Encoding::MacRoman)
     }
 }
 
+// Str255 as stored in records, always occupying 256 bytes regardless of the
+// length of the string
+#[derive(PartialEq)]
+#[derive(Clone)]
 #[derive(Debug)]
-pub struct DateTime (NaiveDateTime);
+pub struct Str255 (pub PString);
+
+impl Str255 {
+    // The string followed by zero padding, as laid out in memory. Strings
+    // longer than 255 bytes are truncated.
+    pub fn to_bytes(&self) -> [u8; 256] {
+        let mut data = [0u8; 256];
+        let bytes = &self.0.as_bytes()[..self.0.len().min(255)];
+        data[0] = bytes.len() as u8;
+        data[1..=bytes.len()].copy_from_slice(bytes);
+        data
+    }
+}
 
-impl SerialRead for DateTime {
-    fn read(rdr:&mut SerialReadStorage) -> std::io::Result<DateTime> {
-        let val : u32 = SerialRead::read(rdr)?;
-        Ok( DateTime (NaiveDateTime::from_timestamp(val as i64 - 2082844800i64, 0)))
+impl SerialRead for Str255 {
+    fn read(rdr : &mut SerialReadStorage ) -> Result<Str255> {
+        let offset = rdr.pos();
+        let str = PString::read(rdr)?;
+        rdr.seek(offset + 256)?;
+        Ok(Str255(str))
+    }
+}
+
+impl From<&[u8; 256]> for Str255 {
+    fn from(b: &[u8; 256]) -> Str255 {
+        let len = b[0] as usize;
+        Str255(PString::from(&b[1..=len]))
+    }
+}
+
+impl From<PString> for Str255 {
+    fn from(s: PString) -> Str255 {
+        Str255(s)
+    }
+}
+
+impl std::fmt::Display for Str255 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
     }
 }
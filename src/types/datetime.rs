@@ -0,0 +1,170 @@
+use crate::serialization::{SerialReadStorage, SerialRead, SerialWriteStorage, SerialWrite};
+use crate::{Error, Result};
+
+use chrono::{
+    NaiveDate,
+    NaiveDateTime,
+    Duration,
+    FixedOffset,
+    Local,
+    Utc,
+    TimeZone,
+    Timelike
+};
+
+fn mac_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1904, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap()
+}
+
+// Seconds since 1904-01-01 00:00:00, in local time. Classic Mac OS has no
+// notion of timezones, so the time is kept as the wall clock time of the
+// system that wrote it. Converting to an absolute time requires knowing the
+// timezone offset of that system, for example the volume it is stored on.
+//
+// On disk the time is an unsigned 32 bit value, which wraps in February
+// 2040. Times outside of that range can still be represented here, but
+// wraps when converted back to 32 bits.
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(PartialOrd)]
+#[derive(Ord)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Hash)]
+pub struct DateTime (NaiveDateTime);
+
+impl DateTime {
+    pub fn from_mac_u32(secs: u32) -> DateTime {
+        DateTime(mac_epoch() + Duration::seconds(secs as i64))
+    }
+
+    pub fn to_mac_u32(&self) -> u32 {
+        self.mac_secs() as u32
+    }
+
+    pub fn from_mac_secs(secs: i64) -> Result<DateTime> {
+        // Duration::seconds panics outside of +/- i64::MAX milliseconds
+        if secs.checked_abs().is_none_or(|s| s > i64::MAX / 1000) {
+            return Err(Error::invalid_value(0, "DateTime", "0", "time within supported range", secs));
+        }
+        mac_epoch().checked_add_signed(Duration::seconds(secs))
+            .map(DateTime)
+            .ok_or_else(|| Error::invalid_value(0, "DateTime", "0", "time within supported range", secs))
+    }
+
+    pub fn mac_secs(&self) -> i64 {
+        (self.0 - mac_epoch()).num_seconds()
+    }
+
+    // Fractions of seconds are dropped, since they can't be represented
+    pub fn from_naive(time: NaiveDateTime) -> DateTime {
+        DateTime(time.with_nanosecond(0).unwrap_or(time))
+    }
+
+    pub fn naive(&self) -> NaiveDateTime {
+        self.0
+    }
+
+    pub fn from_utc(time: &chrono::DateTime<Utc>, offset: FixedOffset) -> DateTime {
+        DateTime::from_naive(time.with_timezone(&offset).naive_local())
+    }
+
+    pub fn to_utc(&self, offset: FixedOffset) -> chrono::DateTime<Utc> {
+        Utc.from_utc_datetime(&(self.0 - Duration::seconds(offset.local_minus_utc() as i64)))
+    }
+
+    pub fn from_local(time: &chrono::DateTime<Local>) -> DateTime {
+        DateTime::from_naive(time.naive_local())
+    }
+
+    // Times that are ambiguous in the local timezone, due to daylight saving
+    // time, resolves to the earliest. Times skipped by daylight saving time
+    // are treated as UTC.
+    pub fn to_local(&self) -> chrono::DateTime<Local> {
+        Local.from_local_datetime(&self.0)
+            .earliest()
+            .unwrap_or_else(|| Local.from_utc_datetime(&self.0))
+    }
+
+    // Current time according to the local clock, as GetDateTime would return
+    pub fn now() -> DateTime {
+        DateTime::from_local(&Local::now())
+    }
+}
+
+impl From<u32> for DateTime {
+    fn from(secs: u32) -> DateTime {
+        DateTime::from_mac_u32(secs)
+    }
+}
+
+impl From<DateTime> for u32 {
+    fn from(time: DateTime) -> u32 {
+        time.to_mac_u32()
+    }
+}
+
+impl SerialRead for DateTime {
+    fn read(rdr:&mut SerialReadStorage) -> Result<DateTime> {
+        let val : u32 = SerialRead::read(rdr)?;
+        Ok(DateTime::from_mac_u32(val))
+    }
+}
+
+impl SerialWrite for DateTime {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        wtr.write_u32(self.to_mac_u32())
+    }
+}
+
+impl std::fmt::Debug for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DateTime").field(&self.0).finish()
+    }
+}
+
+impl std::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.format("%Y-%m-%d %H:%M:%S"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DateTime;
+    use crate::serialization::{SerialReadStorage, SerialRead};
+    use chrono::{FixedOffset, Utc, TimeZone};
+
+    #[test]
+    fn mac_epoch_range() {
+        assert_eq!(DateTime::from_mac_u32(0).to_string(), "1904-01-01 00:00:00");
+        assert_eq!(DateTime::from_mac_u32(2082844800).to_string(), "1970-01-01 00:00:00");
+        assert_eq!(DateTime::from_mac_u32(0xffffffff).to_string(), "2040-02-06 06:28:15");
+    }
+
+    #[test]
+    fn wrap_2040() {
+        let after = DateTime::from_mac_secs(0x100000000 + 10).unwrap();
+        assert_eq!(after.to_string(), "2040-02-06 06:28:26");
+        assert_eq!(after.mac_secs(), 0x100000000 + 10);
+        assert_eq!(after.to_mac_u32(), 10);
+        assert!(after > DateTime::from_mac_u32(0xffffffff));
+        assert!(DateTime::from_mac_secs(i64::MAX).is_err());
+    }
+
+    #[test]
+    fn utc_offset() {
+        let cet = FixedOffset::east_opt(3600).unwrap();
+        let utc = Utc.from_utc_datetime(&DateTime::from_mac_u32(2082844800).naive());
+        let local = DateTime::from_utc(&utc, cet);
+        assert_eq!(local.to_string(), "1970-01-01 01:00:00");
+        assert_eq!(local.to_utc(cet), utc);
+    }
+
+    #[test]
+    fn read_datetime() {
+        let mut rdr = SerialReadStorage::from(vec![0xa6, 0x1e, 0x6e, 0x80]);
+        let val: DateTime = SerialRead::read(&mut rdr).unwrap();
+        assert_eq!(u32::from(val), 0xa61e6e80);
+    }
+}
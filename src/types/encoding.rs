@@ -0,0 +1,293 @@
+use crate::{Error, Result};
+
+// Classic Mac OS stores text in 8 bit encodings, selected by the script system
+// used when the text was written. The lower half is always ASCII, the upper
+// half is given by the tables below, taken from the Unicode mapping tables
+// published by Apple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    MacRoman,
+    MacCentralEurRoman,
+    MacCyrillic,
+    MacGreek,
+    MacTurkish,
+    MacIcelandic,
+    MacCroatian,
+    MacRomanian
+}
+
+impl Encoding {
+    // Encoding for a Script Manager script code, as stored in for example
+    // fdScript. Regional variants of the roman script can't be determined
+    // from the script code only.
+    pub fn from_script(script: i16) -> Option<Encoding> {
+        match script {
+            0 => Some(Encoding::MacRoman),
+            6 => Some(Encoding::MacGreek),
+            7 => Some(Encoding::MacCyrillic),
+            29 => Some(Encoding::MacCentralEurRoman),
+            _ => None
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        let name = name.to_lowercase().replace(['-', '_'], "");
+        match name.as_str() {
+            "macroman" | "roman" => Some(Encoding::MacRoman),
+            "maccentraleurroman" | "maccentraleurope" | "centraleurroman" | "maclatin2" => Some(Encoding::MacCentralEurRoman),
+            "maccyrillic" | "cyrillic" => Some(Encoding::MacCyrillic),
+            "macgreek" | "greek" => Some(Encoding::MacGreek),
+            "macturkish" | "turkish" => Some(Encoding::MacTurkish),
+            "macicelandic" | "maciceland" | "icelandic" => Some(Encoding::MacIcelandic),
+            "maccroatian" | "croatian" => Some(Encoding::MacCroatian),
+            "macromanian" | "romanian" => Some(Encoding::MacRomanian),
+            _ => None
+        }
+    }
+
+    fn table(&self) -> &'static [char; 128] {
+        match self {
+            Encoding::MacRoman => &MAC_ROMAN,
+            Encoding::MacCentralEurRoman => &MAC_CENTRAL_EUR_ROMAN,
+            Encoding::MacCyrillic => &MAC_CYRILLIC,
+            Encoding::MacGreek => &MAC_GREEK,
+            Encoding::MacTurkish => &MAC_TURKISH,
+            Encoding::MacIcelandic => &MAC_ICELANDIC,
+            Encoding::MacCroatian => &MAC_CROATIAN,
+            Encoding::MacRomanian => &MAC_ROMANIAN
+        }
+    }
+
+    pub fn decode_char(&self, byte: u8) -> char {
+        if byte < 0x80 {
+            byte as char
+        } else {
+            self.table()[(byte - 0x80) as usize]
+        }
+    }
+
+    pub fn encode_char(&self, c: char) -> Option<u8> {
+        if (c as u32) < 0x80 {
+            Some(c as u8)
+        } else {
+            self.table().iter()
+                .position(|tc| *tc == c)
+                .map(|idx| idx as u8 + 0x80)
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        bytes.iter().map(|b| self.decode_char(*b)).collect()
+    }
+
+    pub fn encode(&self, s: &str) -> Result<Vec<u8>> {
+        s.chars().enumerate().map(|(idx, c)| {
+            self.encode_char(c).ok_or_else(|| Error::invalid_value(
+                idx as u64,
+                "str",
+                "char",
+                format!("character in {:?}", self),
+                format!("{:?}", c)
+            ))
+        }).collect()
+    }
+
+    // Characters that can't be represented are replaced with '?'
+    pub fn encode_lossy(&self, s: &str) -> Vec<u8> {
+        s.chars().map(|c| self.encode_char(c).unwrap_or(b'?')).collect()
+    }
+}
+
+const MAC_ROMAN : [char; 128] = [
+    '\u{00c4}', '\u{00c5}', '\u{00c7}', '\u{00c9}', '\u{00d1}', '\u{00d6}', '\u{00dc}', '\u{00e1}', // 80
+    '\u{00e0}', '\u{00e2}', '\u{00e4}', '\u{00e3}', '\u{00e5}', '\u{00e7}', '\u{00e9}', '\u{00e8}', // 88
+    '\u{00ea}', '\u{00eb}', '\u{00ed}', '\u{00ec}', '\u{00ee}', '\u{00ef}', '\u{00f1}', '\u{00f3}', // 90
+    '\u{00f2}', '\u{00f4}', '\u{00f6}', '\u{00f5}', '\u{00fa}', '\u{00f9}', '\u{00fb}', '\u{00fc}', // 98
+    '\u{2020}', '\u{00b0}', '\u{00a2}', '\u{00a3}', '\u{00a7}', '\u{2022}', '\u{00b6}', '\u{00df}', // A0
+    '\u{00ae}', '\u{00a9}', '\u{2122}', '\u{00b4}', '\u{00a8}', '\u{2260}', '\u{00c6}', '\u{00d8}', // A8
+    '\u{221e}', '\u{00b1}', '\u{2264}', '\u{2265}', '\u{00a5}', '\u{00b5}', '\u{2202}', '\u{2211}', // B0
+    '\u{220f}', '\u{03c0}', '\u{222b}', '\u{00aa}', '\u{00ba}', '\u{03a9}', '\u{00e6}', '\u{00f8}', // B8
+    '\u{00bf}', '\u{00a1}', '\u{00ac}', '\u{221a}', '\u{0192}', '\u{2248}', '\u{2206}', '\u{00ab}', // C0
+    '\u{00bb}', '\u{2026}', '\u{00a0}', '\u{00c0}', '\u{00c3}', '\u{00d5}', '\u{0152}', '\u{0153}', // C8
+    '\u{2013}', '\u{2014}', '\u{201c}', '\u{201d}', '\u{2018}', '\u{2019}', '\u{00f7}', '\u{25ca}', // D0
+    '\u{00ff}', '\u{0178}', '\u{2044}', '\u{20ac}', '\u{2039}', '\u{203a}', '\u{fb01}', '\u{fb02}', // D8
+    '\u{2021}', '\u{00b7}', '\u{201a}', '\u{201e}', '\u{2030}', '\u{00c2}', '\u{00ca}', '\u{00c1}', // E0
+    '\u{00cb}', '\u{00c8}', '\u{00cd}', '\u{00ce}', '\u{00cf}', '\u{00cc}', '\u{00d3}', '\u{00d4}', // E8
+    '\u{f8ff}', '\u{00d2}', '\u{00da}', '\u{00db}', '\u{00d9}', '\u{0131}', '\u{02c6}', '\u{02dc}', // F0
+    '\u{00af}', '\u{02d8}', '\u{02d9}', '\u{02da}', '\u{00b8}', '\u{02dd}', '\u{02db}', '\u{02c7}', // F8
+];
+
+const MAC_CENTRAL_EUR_ROMAN : [char; 128] = [
+    '\u{00c4}', '\u{0100}', '\u{0101}', '\u{00c9}', '\u{0104}', '\u{00d6}', '\u{00dc}', '\u{00e1}', // 80
+    '\u{0105}', '\u{010c}', '\u{00e4}', '\u{010d}', '\u{0106}', '\u{0107}', '\u{00e9}', '\u{0179}', // 88
+    '\u{017a}', '\u{010e}', '\u{00ed}', '\u{010f}', '\u{0112}', '\u{0113}', '\u{0116}', '\u{00f3}', // 90
+    '\u{0117}', '\u{00f4}', '\u{00f6}', '\u{00f5}', '\u{00fa}', '\u{011a}', '\u{011b}', '\u{00fc}', // 98
+    '\u{2020}', '\u{00b0}', '\u{0118}', '\u{00a3}', '\u{00a7}', '\u{2022}', '\u{00b6}', '\u{00df}', // A0
+    '\u{00ae}', '\u{00a9}', '\u{2122}', '\u{0119}', '\u{00a8}', '\u{2260}', '\u{0123}', '\u{012e}', // A8
+    '\u{012f}', '\u{012a}', '\u{2264}', '\u{2265}', '\u{012b}', '\u{0136}', '\u{2202}', '\u{2211}', // B0
+    '\u{0142}', '\u{013b}', '\u{013c}', '\u{013d}', '\u{013e}', '\u{0139}', '\u{013a}', '\u{0145}', // B8
+    '\u{0146}', '\u{0143}', '\u{00ac}', '\u{221a}', '\u{0144}', '\u{0147}', '\u{2206}', '\u{00ab}', // C0
+    '\u{00bb}', '\u{2026}', '\u{00a0}', '\u{0148}', '\u{0150}', '\u{00d5}', '\u{0151}', '\u{014c}', // C8
+    '\u{2013}', '\u{2014}', '\u{201c}', '\u{201d}', '\u{2018}', '\u{2019}', '\u{00f7}', '\u{25ca}', // D0
+    '\u{014d}', '\u{0154}', '\u{0155}', '\u{0158}', '\u{2039}', '\u{203a}', '\u{0159}', '\u{0156}', // D8
+    '\u{0157}', '\u{0160}', '\u{201a}', '\u{201e}', '\u{0161}', '\u{015a}', '\u{015b}', '\u{00c1}', // E0
+    '\u{0164}', '\u{0165}', '\u{00cd}', '\u{017d}', '\u{017e}', '\u{016a}', '\u{00d3}', '\u{00d4}', // E8
+    '\u{016b}', '\u{016e}', '\u{00da}', '\u{016f}', '\u{0170}', '\u{0171}', '\u{0172}', '\u{0173}', // F0
+    '\u{00dd}', '\u{00fd}', '\u{0137}', '\u{017b}', '\u{0141}', '\u{017c}', '\u{0122}', '\u{02c7}', // F8
+];
+
+const MAC_CYRILLIC : [char; 128] = [
+    '\u{0410}', '\u{0411}', '\u{0412}', '\u{0413}', '\u{0414}', '\u{0415}', '\u{0416}', '\u{0417}', // 80
+    '\u{0418}', '\u{0419}', '\u{041a}', '\u{041b}', '\u{041c}', '\u{041d}', '\u{041e}', '\u{041f}', // 88
+    '\u{0420}', '\u{0421}', '\u{0422}', '\u{0423}', '\u{0424}', '\u{0425}', '\u{0426}', '\u{0427}', // 90
+    '\u{0428}', '\u{0429}', '\u{042a}', '\u{042b}', '\u{042c}', '\u{042d}', '\u{042e}', '\u{042f}', // 98
+    '\u{2020}', '\u{00b0}', '\u{0490}', '\u{00a3}', '\u{00a7}', '\u{2022}', '\u{00b6}', '\u{0406}', // A0
+    '\u{00ae}', '\u{00a9}', '\u{2122}', '\u{0402}', '\u{0452}', '\u{2260}', '\u{0403}', '\u{0453}', // A8
+    '\u{221e}', '\u{00b1}', '\u{2264}', '\u{2265}', '\u{0456}', '\u{00b5}', '\u{0491}', '\u{0408}', // B0
+    '\u{0404}', '\u{0454}', '\u{0407}', '\u{0457}', '\u{0409}', '\u{0459}', '\u{040a}', '\u{045a}', // B8
+    '\u{0458}', '\u{0405}', '\u{00ac}', '\u{221a}', '\u{0192}', '\u{2248}', '\u{2206}', '\u{00ab}', // C0
+    '\u{00bb}', '\u{2026}', '\u{00a0}', '\u{040b}', '\u{045b}', '\u{040c}', '\u{045c}', '\u{0455}', // C8
+    '\u{2013}', '\u{2014}', '\u{201c}', '\u{201d}', '\u{2018}', '\u{2019}', '\u{00f7}', '\u{201e}', // D0
+    '\u{040e}', '\u{045e}', '\u{040f}', '\u{045f}', '\u{2116}', '\u{0401}', '\u{0451}', '\u{044f}', // D8
+    '\u{0430}', '\u{0431}', '\u{0432}', '\u{0433}', '\u{0434}', '\u{0435}', '\u{0436}', '\u{0437}', // E0
+    '\u{0438}', '\u{0439}', '\u{043a}', '\u{043b}', '\u{043c}', '\u{043d}', '\u{043e}', '\u{043f}', // E8
+    '\u{0440}', '\u{0441}', '\u{0442}', '\u{0443}', '\u{0444}', '\u{0445}', '\u{0446}', '\u{0447}', // F0
+    '\u{0448}', '\u{0449}', '\u{044a}', '\u{044b}', '\u{044c}', '\u{044d}', '\u{044e}', '\u{20ac}', // F8
+];
+
+const MAC_GREEK : [char; 128] = [
+    '\u{00c4}', '\u{00b9}', '\u{00b2}', '\u{00c9}', '\u{00b3}', '\u{00d6}', '\u{00dc}', '\u{0385}', // 80
+    '\u{00e0}', '\u{00e2}', '\u{00e4}', '\u{0384}', '\u{00a8}', '\u{00e7}', '\u{00e9}', '\u{00e8}', // 88
+    '\u{00ea}', '\u{00eb}', '\u{00a3}', '\u{2122}', '\u{00ee}', '\u{00ef}', '\u{2022}', '\u{00bd}', // 90
+    '\u{2030}', '\u{00f4}', '\u{00f6}', '\u{00a6}', '\u{20ac}', '\u{00f9}', '\u{00fb}', '\u{00fc}', // 98
+    '\u{2020}', '\u{0393}', '\u{0394}', '\u{0398}', '\u{039b}', '\u{039e}', '\u{03a0}', '\u{00df}', // A0
+    '\u{00ae}', '\u{00a9}', '\u{03a3}', '\u{03aa}', '\u{00a7}', '\u{2260}', '\u{00b0}', '\u{00b7}', // A8
+    '\u{0391}', '\u{00b1}', '\u{2264}', '\u{2265}', '\u{00a5}', '\u{0392}', '\u{0395}', '\u{0396}', // B0
+    '\u{0397}', '\u{0399}', '\u{039a}', '\u{039c}', '\u{03a6}', '\u{03ab}', '\u{03a8}', '\u{03a9}', // B8
+    '\u{03ac}', '\u{039d}', '\u{00ac}', '\u{039f}', '\u{03a1}', '\u{2248}', '\u{03a4}', '\u{00ab}', // C0
+    '\u{00bb}', '\u{2026}', '\u{00a0}', '\u{03a5}', '\u{03a7}', '\u{0386}', '\u{0388}', '\u{0153}', // C8
+    '\u{2013}', '\u{2015}', '\u{201c}', '\u{201d}', '\u{2018}', '\u{2019}', '\u{00f7}', '\u{0389}', // D0
+    '\u{038a}', '\u{038c}', '\u{038e}', '\u{03ad}', '\u{03ae}', '\u{03af}', '\u{03cc}', '\u{038f}', // D8
+    '\u{03cd}', '\u{03b1}', '\u{03b2}', '\u{03c8}', '\u{03b4}', '\u{03b5}', '\u{03c6}', '\u{03b3}', // E0
+    '\u{03b7}', '\u{03b9}', '\u{03be}', '\u{03ba}', '\u{03bb}', '\u{03bc}', '\u{03bd}', '\u{03bf}', // E8
+    '\u{03c0}', '\u{03ce}', '\u{03c1}', '\u{03c3}', '\u{03c4}', '\u{03b8}', '\u{03c9}', '\u{03c2}', // F0
+    '\u{03c7}', '\u{03c5}', '\u{03b6}', '\u{03ca}', '\u{03cb}', '\u{0390}', '\u{03b0}', '\u{00ad}', // F8
+];
+
+const MAC_TURKISH : [char; 128] = [
+    '\u{00c4}', '\u{00c5}', '\u{00c7}', '\u{00c9}', '\u{00d1}', '\u{00d6}', '\u{00dc}', '\u{00e1}', // 80
+    '\u{00e0}', '\u{00e2}', '\u{00e4}', '\u{00e3}', '\u{00e5}', '\u{00e7}', '\u{00e9}', '\u{00e8}', // 88
+    '\u{00ea}', '\u{00eb}', '\u{00ed}', '\u{00ec}', '\u{00ee}', '\u{00ef}', '\u{00f1}', '\u{00f3}', // 90
+    '\u{00f2}', '\u{00f4}', '\u{00f6}', '\u{00f5}', '\u{00fa}', '\u{00f9}', '\u{00fb}', '\u{00fc}', // 98
+    '\u{2020}', '\u{00b0}', '\u{00a2}', '\u{00a3}', '\u{00a7}', '\u{2022}', '\u{00b6}', '\u{00df}', // A0
+    '\u{00ae}', '\u{00a9}', '\u{2122}', '\u{00b4}', '\u{00a8}', '\u{2260}', '\u{00c6}', '\u{00d8}', // A8
+    '\u{221e}', '\u{00b1}', '\u{2264}', '\u{2265}', '\u{00a5}', '\u{00b5}', '\u{2202}', '\u{2211}', // B0
+    '\u{220f}', '\u{03c0}', '\u{222b}', '\u{00aa}', '\u{00ba}', '\u{03a9}', '\u{00e6}', '\u{00f8}', // B8
+    '\u{00bf}', '\u{00a1}', '\u{00ac}', '\u{221a}', '\u{0192}', '\u{2248}', '\u{2206}', '\u{00ab}', // C0
+    '\u{00bb}', '\u{2026}', '\u{00a0}', '\u{00c0}', '\u{00c3}', '\u{00d5}', '\u{0152}', '\u{0153}', // C8
+    '\u{2013}', '\u{2014}', '\u{201c}', '\u{201d}', '\u{2018}', '\u{2019}', '\u{00f7}', '\u{25ca}', // D0
+    '\u{00ff}', '\u{0178}', '\u{011e}', '\u{011f}', '\u{0130}', '\u{0131}', '\u{015e}', '\u{015f}', // D8
+    '\u{2021}', '\u{00b7}', '\u{201a}', '\u{201e}', '\u{2030}', '\u{00c2}', '\u{00ca}', '\u{00c1}', // E0
+    '\u{00cb}', '\u{00c8}', '\u{00cd}', '\u{00ce}', '\u{00cf}', '\u{00cc}', '\u{00d3}', '\u{00d4}', // E8
+    '\u{f8ff}', '\u{00d2}', '\u{00da}', '\u{00db}', '\u{00d9}', '\u{f8a0}', '\u{02c6}', '\u{02dc}', // F0
+    '\u{00af}', '\u{02d8}', '\u{02d9}', '\u{02da}', '\u{00b8}', '\u{02dd}', '\u{02db}', '\u{02c7}', // F8
+];
+
+const MAC_ICELANDIC : [char; 128] = [
+    '\u{00c4}', '\u{00c5}', '\u{00c7}', '\u{00c9}', '\u{00d1}', '\u{00d6}', '\u{00dc}', '\u{00e1}', // 80
+    '\u{00e0}', '\u{00e2}', '\u{00e4}', '\u{00e3}', '\u{00e5}', '\u{00e7}', '\u{00e9}', '\u{00e8}', // 88
+    '\u{00ea}', '\u{00eb}', '\u{00ed}', '\u{00ec}', '\u{00ee}', '\u{00ef}', '\u{00f1}', '\u{00f3}', // 90
+    '\u{00f2}', '\u{00f4}', '\u{00f6}', '\u{00f5}', '\u{00fa}', '\u{00f9}', '\u{00fb}', '\u{00fc}', // 98
+    '\u{00dd}', '\u{00b0}', '\u{00a2}', '\u{00a3}', '\u{00a7}', '\u{2022}', '\u{00b6}', '\u{00df}', // A0
+    '\u{00ae}', '\u{00a9}', '\u{2122}', '\u{00b4}', '\u{00a8}', '\u{2260}', '\u{00c6}', '\u{00d8}', // A8
+    '\u{221e}', '\u{00b1}', '\u{2264}', '\u{2265}', '\u{00a5}', '\u{00b5}', '\u{2202}', '\u{2211}', // B0
+    '\u{220f}', '\u{03c0}', '\u{222b}', '\u{00aa}', '\u{00ba}', '\u{03a9}', '\u{00e6}', '\u{00f8}', // B8
+    '\u{00bf}', '\u{00a1}', '\u{00ac}', '\u{221a}', '\u{0192}', '\u{2248}', '\u{2206}', '\u{00ab}', // C0
+    '\u{00bb}', '\u{2026}', '\u{00a0}', '\u{00c0}', '\u{00c3}', '\u{00d5}', '\u{0152}', '\u{0153}', // C8
+    '\u{2013}', '\u{2014}', '\u{201c}', '\u{201d}', '\u{2018}', '\u{2019}', '\u{00f7}', '\u{25ca}', // D0
+    '\u{00ff}', '\u{0178}', '\u{2044}', '\u{20ac}', '\u{00d0}', '\u{00f0}', '\u{00de}', '\u{00fe}', // D8
+    '\u{00fd}', '\u{00b7}', '\u{201a}', '\u{201e}', '\u{2030}', '\u{00c2}', '\u{00ca}', '\u{00c1}', // E0
+    '\u{00cb}', '\u{00c8}', '\u{00cd}', '\u{00ce}', '\u{00cf}', '\u{00cc}', '\u{00d3}', '\u{00d4}', // E8
+    '\u{f8ff}', '\u{00d2}', '\u{00da}', '\u{00db}', '\u{00d9}', '\u{0131}', '\u{02c6}', '\u{02dc}', // F0
+    '\u{00af}', '\u{02d8}', '\u{02d9}', '\u{02da}', '\u{00b8}', '\u{02dd}', '\u{02db}', '\u{02c7}', // F8
+];
+
+const MAC_CROATIAN : [char; 128] = [
+    '\u{00c4}', '\u{00c5}', '\u{00c7}', '\u{00c9}', '\u{00d1}', '\u{00d6}', '\u{00dc}', '\u{00e1}', // 80
+    '\u{00e0}', '\u{00e2}', '\u{00e4}', '\u{00e3}', '\u{00e5}', '\u{00e7}', '\u{00e9}', '\u{00e8}', // 88
+    '\u{00ea}', '\u{00eb}', '\u{00ed}', '\u{00ec}', '\u{00ee}', '\u{00ef}', '\u{00f1}', '\u{00f3}', // 90
+    '\u{00f2}', '\u{00f4}', '\u{00f6}', '\u{00f5}', '\u{00fa}', '\u{00f9}', '\u{00fb}', '\u{00fc}', // 98
+    '\u{2020}', '\u{00b0}', '\u{00a2}', '\u{00a3}', '\u{00a7}', '\u{2022}', '\u{00b6}', '\u{00df}', // A0
+    '\u{00ae}', '\u{0160}', '\u{2122}', '\u{00b4}', '\u{00a8}', '\u{2260}', '\u{017d}', '\u{00d8}', // A8
+    '\u{221e}', '\u{00b1}', '\u{2264}', '\u{2265}', '\u{2206}', '\u{00b5}', '\u{2202}', '\u{2211}', // B0
+    '\u{220f}', '\u{0161}', '\u{222b}', '\u{00aa}', '\u{00ba}', '\u{03a9}', '\u{017e}', '\u{00f8}', // B8
+    '\u{00bf}', '\u{00a1}', '\u{00ac}', '\u{221a}', '\u{0192}', '\u{2248}', '\u{0106}', '\u{00ab}', // C0
+    '\u{010c}', '\u{2026}', '\u{00a0}', '\u{00c0}', '\u{00c3}', '\u{00d5}', '\u{0152}', '\u{0153}', // C8
+    '\u{0110}', '\u{2014}', '\u{201c}', '\u{201d}', '\u{2018}', '\u{2019}', '\u{00f7}', '\u{25ca}', // D0
+    '\u{f8ff}', '\u{00a9}', '\u{2044}', '\u{20ac}', '\u{2039}', '\u{203a}', '\u{00c6}', '\u{00bb}', // D8
+    '\u{2013}', '\u{00b7}', '\u{201a}', '\u{201e}', '\u{2030}', '\u{00c2}', '\u{0107}', '\u{00c1}', // E0
+    '\u{010d}', '\u{00c8}', '\u{00cd}', '\u{00ce}', '\u{00cf}', '\u{00cc}', '\u{00d3}', '\u{00d4}', // E8
+    '\u{0111}', '\u{00d2}', '\u{00da}', '\u{00db}', '\u{00d9}', '\u{0131}', '\u{02c6}', '\u{02dc}', // F0
+    '\u{00af}', '\u{03c0}', '\u{00cb}', '\u{02da}', '\u{00b8}', '\u{00ca}', '\u{00e6}', '\u{02c7}', // F8
+];
+
+const MAC_ROMANIAN : [char; 128] = [
+    '\u{00c4}', '\u{00c5}', '\u{00c7}', '\u{00c9}', '\u{00d1}', '\u{00d6}', '\u{00dc}', '\u{00e1}', // 80
+    '\u{00e0}', '\u{00e2}', '\u{00e4}', '\u{00e3}', '\u{00e5}', '\u{00e7}', '\u{00e9}', '\u{00e8}', // 88
+    '\u{00ea}', '\u{00eb}', '\u{00ed}', '\u{00ec}', '\u{00ee}', '\u{00ef}', '\u{00f1}', '\u{00f3}', // 90
+    '\u{00f2}', '\u{00f4}', '\u{00f6}', '\u{00f5}', '\u{00fa}', '\u{00f9}', '\u{00fb}', '\u{00fc}', // 98
+    '\u{2020}', '\u{00b0}', '\u{00a2}', '\u{00a3}', '\u{00a7}', '\u{2022}', '\u{00b6}', '\u{00df}', // A0
+    '\u{00ae}', '\u{00a9}', '\u{2122}', '\u{00b4}', '\u{00a8}', '\u{2260}', '\u{0102}', '\u{0218}', // A8
+    '\u{221e}', '\u{00b1}', '\u{2264}', '\u{2265}', '\u{00a5}', '\u{00b5}', '\u{2202}', '\u{2211}', // B0
+    '\u{220f}', '\u{03c0}', '\u{222b}', '\u{00aa}', '\u{00ba}', '\u{03a9}', '\u{0103}', '\u{0219}', // B8
+    '\u{00bf}', '\u{00a1}', '\u{00ac}', '\u{221a}', '\u{0192}', '\u{2248}', '\u{2206}', '\u{00ab}', // C0
+    '\u{00bb}', '\u{2026}', '\u{00a0}', '\u{00c0}', '\u{00c3}', '\u{00d5}', '\u{0152}', '\u{0153}', // C8
+    '\u{2013}', '\u{2014}', '\u{201c}', '\u{201d}', '\u{2018}', '\u{2019}', '\u{00f7}', '\u{25ca}', // D0
+    '\u{00ff}', '\u{0178}', '\u{2044}', '\u{20ac}', '\u{2039}', '\u{203a}', '\u{021a}', '\u{021b}', // D8
+    '\u{2021}', '\u{00b7}', '\u{201a}', '\u{201e}', '\u{2030}', '\u{00c2}', '\u{00ca}', '\u{00c1}', // E0
+    '\u{00cb}', '\u{00c8}', '\u{00cd}', '\u{00ce}', '\u{00cf}', '\u{00cc}', '\u{00d3}', '\u{00d4}', // E8
+    '\u{f8ff}', '\u{00d2}', '\u{00da}', '\u{00db}', '\u{00d9}', '\u{0131}', '\u{02c6}', '\u{02dc}', // F0
+    '\u{00af}', '\u{02d8}', '\u{02d9}', '\u{02da}', '\u{00b8}', '\u{02dd}', '\u{02db}', '\u{02c7}', // F8
+];
+
+#[cfg(test)]
+mod tests {
+    use super::Encoding;
+
+    #[test]
+    fn decode_mac_roman() {
+        assert_eq!(Encoding::MacRoman.decode(b"\xc4 Applications"), "ƒ Applications");
+        assert_eq!(Encoding::MacRoman.decode(b"\xa9\xaa\xa5\x8e"), "©™•é");
+        assert_eq!(Encoding::MacRoman.decode(b"plain"), "plain");
+    }
+
+    #[test]
+    fn encode_mac_roman() {
+        assert_eq!(Encoding::MacRoman.encode("ƒ Applications").unwrap(), b"\xc4 Applications");
+        assert!(Encoding::MacRoman.encode("日本").is_err());
+        assert_eq!(Encoding::MacRoman.encode_lossy("a日b"), b"a?b");
+    }
+
+    #[test]
+    fn round_trip_all() {
+        let bytes : Vec<u8> = (0..=255).collect();
+        for enc in [
+            Encoding::MacRoman,
+            Encoding::MacCentralEurRoman,
+            Encoding::MacCyrillic,
+            Encoding::MacGreek,
+            Encoding::MacTurkish,
+            Encoding::MacIcelandic,
+            Encoding::MacCroatian,
+            Encoding::MacRomanian
+        ].iter() {
+            assert_eq!(enc.encode(&enc.decode(&bytes)).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn decode_cyrillic() {
+        assert_eq!(Encoding::MacCyrillic.decode(b"\x8c\xe0\xf0"), "Мар");
+    }
+}
@@ -0,0 +1,265 @@
+use crate::serialization::{SerialReadStorage, SerialRead};
+use std::ops::{Add, Sub, Mul, Div, Neg};
+
+// Results that doesn't fit are pinned to the largest representable value,
+// the same way FixMul/FixDiv/FracMul/FracDiv does in the Toolbox.
+fn pin(val: i64) -> i32 {
+    val.max(i32::MIN as i64).min(i32::MAX as i64) as i32
+}
+
+// Division of numbers with the given fraction bits. Division by zero gives
+// the largest value with the sign of the dividend.
+fn pinned_div(num: i32, den: i32, shift: u32) -> i32 {
+    if den == 0 {
+        if num < 0 { i32::MIN } else { i32::MAX }
+    } else {
+        pin(((num as i64) << shift) / den as i64)
+    }
+}
+
+fn round_shift(val: i64, shift: u32) -> i64 {
+    (val + (1 << (shift - 1))) >> shift
+}
+
+// Fixed point number, 16 bit integer part and 16 bit fraction
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(PartialOrd)]
+#[derive(Ord)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+#[derive(SerialRead)]
+pub struct Fixed (i32);
+
+impl Fixed {
+    pub const ONE: Fixed = Fixed(1 << 16);
+
+    pub fn from_bits(bits: i32) -> Fixed {
+        Fixed(bits)
+    }
+
+    pub fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    pub fn from_f64(val: f64) -> Fixed {
+        Fixed(pin((val * 65536.0).round() as i64))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 65536.0
+    }
+
+    // FixRound
+    pub fn round(self) -> i16 {
+        round_shift(self.0 as i64, 16) as i16
+    }
+
+    pub fn trunc(self) -> i16 {
+        (self.0 >> 16) as i16
+    }
+
+    // Fix2Frac
+    pub fn to_fract(self) -> Fract {
+        Fract(pin((self.0 as i64) << 14))
+    }
+}
+
+impl From<i16> for Fixed {
+    fn from(val: i16) -> Fixed {
+        Fixed((val as i32) << 16)
+    }
+}
+
+impl From<Fixed> for f64 {
+    fn from(val: Fixed) -> f64 {
+        val.to_f64()
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+    fn add(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+    fn sub(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+    fn neg(self) -> Fixed {
+        Fixed(self.0.wrapping_neg())
+    }
+}
+
+// FixMul
+impl Mul for Fixed {
+    type Output = Fixed;
+    fn mul(self, rhs: Fixed) -> Fixed {
+        Fixed(pin(round_shift(self.0 as i64 * rhs.0 as i64, 16)))
+    }
+}
+
+// FixDiv
+impl Div for Fixed {
+    type Output = Fixed;
+    fn div(self, rhs: Fixed) -> Fixed {
+        Fixed(pinned_div(self.0, rhs.0, 16))
+    }
+}
+
+impl std::fmt::Debug for Fixed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Fixed({})", self.to_f64())
+    }
+}
+
+impl std::fmt::Display for Fixed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.to_f64(), f)
+    }
+}
+
+// Fixed point number, 2 bit integer part and 30 bit fraction, covering -2 to 2
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(PartialOrd)]
+#[derive(Ord)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+#[derive(SerialRead)]
+pub struct Fract (i32);
+
+impl Fract {
+    pub const ONE: Fract = Fract(1 << 30);
+
+    pub fn from_bits(bits: i32) -> Fract {
+        Fract(bits)
+    }
+
+    pub fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    pub fn from_f64(val: f64) -> Fract {
+        Fract(pin((val * 1073741824.0).round() as i64))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 1073741824.0
+    }
+
+    // Frac2Fix
+    pub fn to_fixed(self) -> Fixed {
+        Fixed(round_shift(self.0 as i64, 14) as i32)
+    }
+}
+
+impl From<Fract> for f64 {
+    fn from(val: Fract) -> f64 {
+        val.to_f64()
+    }
+}
+
+impl Add for Fract {
+    type Output = Fract;
+    fn add(self, rhs: Fract) -> Fract {
+        Fract(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl Sub for Fract {
+    type Output = Fract;
+    fn sub(self, rhs: Fract) -> Fract {
+        Fract(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl Neg for Fract {
+    type Output = Fract;
+    fn neg(self) -> Fract {
+        Fract(self.0.wrapping_neg())
+    }
+}
+
+// FracMul
+impl Mul for Fract {
+    type Output = Fract;
+    fn mul(self, rhs: Fract) -> Fract {
+        Fract(pin(round_shift(self.0 as i64 * rhs.0 as i64, 30)))
+    }
+}
+
+// FracDiv
+impl Div for Fract {
+    type Output = Fract;
+    fn div(self, rhs: Fract) -> Fract {
+        Fract(pinned_div(self.0, rhs.0, 30))
+    }
+}
+
+impl std::fmt::Debug for Fract {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Fract({})", self.to_f64())
+    }
+}
+
+impl std::fmt::Display for Fract {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.to_f64(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fixed, Fract};
+    use crate::serialization::{SerialReadStorage, SerialRead};
+
+    #[test]
+    fn fixed_arith() {
+        let a = Fixed::from_f64(1.5);
+        let b = Fixed::from(2);
+        assert_eq!(a.to_bits(), 0x00018000);
+        assert_eq!((a + b).to_f64(), 3.5);
+        assert_eq!((a - b).to_f64(), -0.5);
+        assert_eq!((a * b).to_f64(), 3.0);
+        assert_eq!((a / b).to_f64(), 0.75);
+        assert_eq!((-a).round(), -1);
+        assert_eq!(a.round(), 2);
+        assert_eq!(a.trunc(), 1);
+    }
+
+    #[test]
+    fn fixed_pinned() {
+        let big = Fixed::from(0x4000);
+        assert_eq!((big * big).to_bits(), i32::MAX);
+        assert_eq!((-big * big).to_bits(), i32::MIN);
+        assert_eq!((big / Fixed::default()).to_bits(), i32::MAX);
+        assert_eq!((-big / Fixed::default()).to_bits(), i32::MIN);
+    }
+
+    #[test]
+    fn fract_arith() {
+        let half = Fract::from_f64(0.5);
+        assert_eq!(half.to_bits(), 0x20000000);
+        assert_eq!((half * half).to_f64(), 0.25);
+        assert_eq!((half / Fract::ONE).to_f64(), 0.5);
+        assert_eq!(half.to_fixed(), Fixed::from_f64(0.5));
+        assert_eq!(Fixed::from_f64(-0.25).to_fract(), Fract::from_f64(-0.25));
+    }
+
+    #[test]
+    fn fixed_read() {
+        let mut rdr = SerialReadStorage::from(vec![0xff, 0xff, 0x80, 0x00]);
+        let val: Fixed = SerialRead::read(&mut rdr).unwrap();
+        assert_eq!(val.to_f64(), -0.5);
+    }
+}
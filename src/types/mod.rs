@@ -1,3 +1,11 @@
 mod base;
+mod datetime;
+mod encoding;
+mod fixed;
+mod quickdraw;
 
-pub use base::*;
\ No newline at end of file
+pub use base::*;
+pub use datetime::DateTime;
+pub use encoding::Encoding;
+pub use fixed::*;
+pub use quickdraw::*;
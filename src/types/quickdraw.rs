@@ -0,0 +1,467 @@
+use crate::serialization::{SerialReadStorage, SerialRead, SerialWriteStorage, SerialWrite};
+use crate::{Error, Result};
+use super::Fixed;
+use std::ops::{Add, Sub};
+
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+#[derive(Debug)]
+#[derive(SerialRead)]
+pub struct Point {
+    pub v: i16, // INTEGER;     {vertical coordinate}
+    pub h: i16  // INTEGER;     {horizontal coordinate}
+}
+
+impl Point {
+    // Argument order as SetPt
+    pub fn new(h: i16, v: i16) -> Point {
+        Point { v, h }
+    }
+}
+
+impl SerialWrite for Point {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        wtr.write_i16(self.v)?;
+        wtr.write_i16(self.h)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+    fn add(self, rhs: Point) -> Point {
+        Point::new(self.h.wrapping_add(rhs.h), self.v.wrapping_add(rhs.v))
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+    fn sub(self, rhs: Point) -> Point {
+        Point::new(self.h.wrapping_sub(rhs.h), self.v.wrapping_sub(rhs.v))
+    }
+}
+
+// Coordinates are on the grid lines between pixels, so the bottom and right
+// edges are not part of the rectangle
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+#[derive(Debug)]
+#[derive(SerialRead)]
+pub struct Rect {
+    pub top: i16,    // INTEGER;
+    pub left: i16,   // INTEGER;
+    pub bottom: i16, // INTEGER;
+    pub right: i16   // INTEGER;
+}
+
+impl SerialWrite for Rect {
+    fn write(&self, wtr: &mut SerialWriteStorage) -> Result<()> {
+        wtr.write_i16(self.top)?;
+        wtr.write_i16(self.left)?;
+        wtr.write_i16(self.bottom)?;
+        wtr.write_i16(self.right)
+    }
+}
+
+impl Rect {
+    // Argument order as SetRect
+    pub fn new(left: i16, top: i16, right: i16, bottom: i16) -> Rect {
+        Rect { top, left, bottom, right }
+    }
+
+    // Pt2Rect, the smallest rectangle enclosing both points
+    pub fn from_points(a: Point, b: Point) -> Rect {
+        Rect::new(a.h.min(b.h), a.v.min(b.v), a.h.max(b.h), a.v.max(b.v))
+    }
+
+    pub fn top_left(&self) -> Point {
+        Point::new(self.left, self.top)
+    }
+
+    pub fn bot_right(&self) -> Point {
+        Point::new(self.right, self.bottom)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.right as i32 - self.left as i32
+    }
+
+    pub fn height(&self) -> i32 {
+        self.bottom as i32 - self.top as i32
+    }
+
+    // EmptyRect
+    pub fn is_empty(&self) -> bool {
+        self.bottom <= self.top || self.right <= self.left
+    }
+
+    // PtInRect
+    pub fn contains(&self, pt: Point) -> bool {
+        pt.h >= self.left && pt.h < self.right && pt.v >= self.top && pt.v < self.bottom
+    }
+
+    // OffsetRect
+    pub fn offset(&self, dh: i16, dv: i16) -> Rect {
+        Rect::new(
+            self.left.wrapping_add(dh),
+            self.top.wrapping_add(dv),
+            self.right.wrapping_add(dh),
+            self.bottom.wrapping_add(dv)
+        )
+    }
+
+    // InsetRect, negative values grows the rectangle. Like the Toolbox, an
+    // empty result is returned as (0,0,0,0)
+    pub fn inset(&self, dh: i16, dv: i16) -> Rect {
+        let res = Rect::new(
+            self.left.saturating_add(dh),
+            self.top.saturating_add(dv),
+            self.right.saturating_sub(dh),
+            self.bottom.saturating_sub(dv)
+        );
+        if res.is_empty() { Rect::default() } else { res }
+    }
+
+    // SectRect, None if the rectangles doesn't intersect
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let res = Rect::new(
+            self.left.max(other.left),
+            self.top.max(other.top),
+            self.right.min(other.right),
+            self.bottom.min(other.bottom)
+        );
+        if res.is_empty() { None } else { Some(res) }
+    }
+
+    // UnionRect, empty rectangles are not ignored, same as the Toolbox
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect::new(
+            self.left.min(other.left),
+            self.top.min(other.top),
+            self.right.max(other.right),
+            self.bottom.max(other.bottom)
+        )
+    }
+}
+
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+#[derive(Debug)]
+#[derive(SerialRead)]
+pub struct RGBColor {
+    pub red: u16,   // INTEGER;     {red component}
+    pub green: u16, // INTEGER;     {green component}
+    pub blue: u16   // INTEGER;     {blue component}
+}
+
+impl RGBColor {
+    pub fn new(red: u16, green: u16, blue: u16) -> RGBColor {
+        RGBColor { red, green, blue }
+    }
+
+    // Expand 8 bit components by repeating the byte, so 0xff maps to 0xffff
+    pub fn from_rgb8(red: u8, green: u8, blue: u8) -> RGBColor {
+        RGBColor::new(red as u16 * 0x101, green as u16 * 0x101, blue as u16 * 0x101)
+    }
+
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        [(self.red >> 8) as u8, (self.green >> 8) as u8, (self.blue >> 8) as u8]
+    }
+
+    // Components scaled to 0.0 - 1.0
+    pub fn to_f64(&self) -> [f64; 3] {
+        [
+            self.red as f64 / 65535.0,
+            self.green as f64 / 65535.0,
+            self.blue as f64 / 65535.0
+        ]
+    }
+}
+
+// 8x8 pixel bit pattern, one byte per row with the leftmost pixel in the
+// most significant bit
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+#[derive(Debug)]
+#[derive(SerialRead)]
+pub struct Pattern (pub [u8; 8]);
+
+impl Pattern {
+    pub const WHITE: Pattern = Pattern([0x00; 8]);
+    pub const BLACK: Pattern = Pattern([0xff; 8]);
+    pub const GRAY: Pattern = Pattern([0xaa, 0x55, 0xaa, 0x55, 0xaa, 0x55, 0xaa, 0x55]);
+
+    // Patterns are aligned to the coordinate system, so any coordinate is
+    // valid and wraps around
+    pub fn is_set(&self, h: i32, v: i32) -> bool {
+        let row = self.0[(v & 7) as usize];
+        (row >> (7 - (h & 7))) & 1 != 0
+    }
+}
+
+// Header of a 1 bit bitmap, the pixel data is stored separately
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(SerialRead)]
+#[allow(non_snake_case)] // This struct comes from old Mac structs
+pub struct BitMap {
+    pub baseAddr: u32,  // Ptr;         {pointer to bit image}
+    pub rowBytes: u16,  // INTEGER;     {row width}
+    pub bounds: Rect    // Rect;        {boundary rectangle}
+}
+
+// Header of a color pixel image, the pixel data and color table are stored
+// separately
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(SerialRead)]
+#[allow(non_snake_case)] // This struct comes from old Mac structs
+pub struct PixMap {
+    pub baseAddr: u32,    // Ptr;         {pixel image}
+    pub rowBytes: u16,    // INTEGER;     {flags, and row width}
+    pub bounds: Rect,     // Rect;        {boundary rectangle}
+    pub pmVersion: i16,   // INTEGER;     {PixMap record version number}
+    pub packType: i16,    // INTEGER;     {packing format}
+    pub packSize: i32,    // LONGINT;     {size of data in packed state}
+    pub hRes: Fixed,      // Fixed;       {horizontal resolution}
+    pub vRes: Fixed,      // Fixed;       {vertical resolution}
+    pub pixelType: i16,   // INTEGER;     {format of pixel image}
+    pub pixelSize: i16,   // INTEGER;     {physical bits per pixel}
+    pub cmpCount: i16,    // INTEGER;     {logical components per pixel}
+    pub cmpSize: i16,     // INTEGER;     {logical bits per component}
+    pub planeBytes: i32,  // LONGINT;     {offset to next plane}
+    pub pmTable: u32,     // CTabHandle;  {handle to color table}
+    pub pmReserved: i32   // LONGINT;     {reserved}
+}
+
+impl PixMap {
+    // The two high bits of rowBytes are flags
+    pub fn row_bytes(&self) -> usize {
+        (self.rowBytes & 0x3fff) as usize
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+#[derive(SerialRead)]
+#[allow(non_snake_case)] // This struct comes from old Mac structs
+pub struct ColorSpec {
+    pub value: i16,     // INTEGER;     {index or other value}
+    pub rgb: RGBColor   // RGBColor;    {true color}
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(SerialRead)]
+#[allow(non_snake_case)] // This struct comes from old Mac structs
+pub struct ColorTable {
+    pub ctSeed: i32,   // LONGINT;     {unique identifier from table}
+    pub ctFlags: u16,  // INTEGER;     {flags}
+    pub ctSize: i16,   // INTEGER;     {number of entries minus 1}
+    #[count_minus_one(ctSize)]
+    pub ctTable: Vec<ColorSpec> // cSpecArray;  {array of ColorSpec records}
+}
+
+impl ColorTable {
+    // Device color tables are indexed by position, others by the value field
+    pub fn lookup(&self, index: u8) -> Option<RGBColor> {
+        if self.ctFlags & 0x8000 != 0 {
+            self.ctTable.get(index as usize).map(|spec| spec.rgb)
+        } else {
+            self.ctTable.iter().find(|spec| spec.value == index as i16).map(|spec| spec.rgb)
+        }
+    }
+}
+
+// Region in the QuickDraw format, a bounding box and for non-rectangular
+// regions a list of inversion points per scanline. A pixel is inside the
+// region if an odd number of inversion points are above and to the left of
+// it, including itself.
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Region {
+    pub bounds: Rect,
+    scanlines: Vec<(i16, Vec<i16>)>
+}
+
+// Terminates each scanline, and the list of scanlines
+const REGION_END: i16 = 0x7fff;
+
+impl Region {
+    pub fn from_rect(rect: Rect) -> Region {
+        Region {
+            bounds: rect,
+            scanlines: vec![]
+        }
+    }
+
+    pub fn is_rect(&self) -> bool {
+        self.scanlines.is_empty()
+    }
+
+    // PtInRgn, in 32 bit coordinates to avoid overflow when drawing
+    pub fn contains(&self, h: i32, v: i32) -> bool {
+        let b = &self.bounds;
+        if h < b.left as i32 || h >= b.right as i32 || v < b.top as i32 || v >= b.bottom as i32 {
+            return false;
+        }
+        if self.scanlines.is_empty() {
+            return true;
+        }
+        let mut inside = false;
+        for (line_v, hs) in self.scanlines.iter() {
+            if *line_v as i32 > v {
+                break;
+            }
+            for line_h in hs {
+                if *line_h as i32 <= h {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+}
+
+impl SerialRead for Region {
+    fn read(rdr: &mut SerialReadStorage) -> Result<Region> {
+        let offset = rdr.pos();
+        let size = rdr.read_u16()?;
+        let bounds = Rect::read(rdr)?;
+        let end = offset + size as u64;
+        let mut scanlines = vec![];
+        if size > 10 {
+            loop {
+                let v = rdr.read_i16()?;
+                if v == REGION_END {
+                    break;
+                }
+                let mut hs = vec![];
+                loop {
+                    let h = rdr.read_i16()?;
+                    if h == REGION_END {
+                        break;
+                    }
+                    hs.push(h);
+                }
+                scanlines.push((v, hs));
+                if rdr.pos() >= end {
+                    return Err(Error::invalid_value(offset, "Region", "rgnSize", "size including the end marker", size));
+                }
+            }
+        }
+        rdr.seek(end.max(rdr.pos()))?;
+        Ok(Region {
+            bounds,
+            scanlines
+        })
+    }
+}
+
+// Polygon, the first and last points are usually the same for a closed
+// polygon
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(SerialRead)]
+#[allow(non_snake_case)] // This struct comes from old Mac structs
+pub struct Polygon {
+    pub polySize: u16,   // INTEGER;     {size in bytes}
+    pub polyBBox: Rect,  // Rect;        {bounding rectangle}
+    #[count((polySize as usize).saturating_sub(10) / 4)]
+    pub polyPoints: Vec<Point> // ARRAY[0..0] OF Point {vertices of polygon}
+}
+
+impl Polygon {
+    // Even-odd rule, with the pixel at (h, v) sampled at its center
+    pub fn contains(&self, h: i32, v: i32) -> bool {
+        let (x, y) = (h as f64 + 0.5, v as f64 + 0.5);
+        let pts = &self.polyPoints;
+        let mut inside = false;
+        for i in 0..pts.len() {
+            let a = pts[i];
+            let b = pts[(i + 1) % pts.len()];
+            let (ax, ay, bx, by) = (a.h as f64, a.v as f64, b.h as f64, b.v as f64);
+            if (ay > y) != (by > y) && x < ax + (y - ay) * (bx - ax) / (by - ay) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Point, Rect, RGBColor, Pattern, Region};
+    use crate::serialization::{SerialReadStorage, SerialRead};
+
+    #[test]
+    fn rect_read() {
+        let mut rdr = SerialReadStorage::from(vec![0, 1, 0, 2, 0, 3, 0, 4]);
+        let rect: Rect = SerialRead::read(&mut rdr).unwrap();
+        assert_eq!(rect, Rect::new(2, 1, 4, 3));
+        assert_eq!(rect.top_left(), Point::new(2, 1));
+        assert_eq!(rect.bot_right(), Point::new(4, 3));
+    }
+
+    #[test]
+    fn rect_ops() {
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(5, 5, 20, 15);
+        assert_eq!(a.intersection(&b), Some(Rect::new(5, 5, 10, 10)));
+        assert_eq!(a.intersection(&Rect::new(10, 0, 20, 10)), None);
+        assert_eq!(a.union(&b), Rect::new(0, 0, 20, 15));
+        assert_eq!(a.inset(2, 3), Rect::new(2, 3, 8, 7));
+        assert_eq!(a.inset(-1, -1), Rect::new(-1, -1, 11, 11));
+        assert_eq!(a.inset(5, 1), Rect::default());
+        assert_eq!(a.offset(1, -1), Rect::new(1, -1, 11, 9));
+        assert!(a.contains(Point::new(0, 9)));
+        assert!(!a.contains(Point::new(10, 0)));
+        assert_eq!(Rect::from_points(Point::new(4, 1), Point::new(1, 3)), Rect::new(1, 1, 4, 3));
+    }
+
+    #[test]
+    fn color_pattern() {
+        let c = RGBColor::from_rgb8(0xff, 0x80, 0x00);
+        assert_eq!(c, RGBColor::new(0xffff, 0x8080, 0x0000));
+        assert_eq!(c.to_rgb8(), [0xff, 0x80, 0x00]);
+        assert_eq!(c.to_f64()[0], 1.0);
+        assert!(Pattern::GRAY.is_set(0, 0));
+        assert!(!Pattern::GRAY.is_set(1, 0));
+        assert!(Pattern::GRAY.is_set(-1, 1));
+    }
+
+    #[test]
+    fn region() {
+        // L shaped region, 4x4 with the top right 2x2 quadrant cut out
+        let mut rdr = SerialReadStorage::from(vec![
+            0, 36, 0, 0, 0, 0, 0, 4, 0, 4,
+            0, 0, 0, 0, 0, 2, 0x7f, 0xff,
+            0, 2, 0, 2, 0, 4, 0x7f, 0xff,
+            0, 4, 0, 0, 0, 4, 0x7f, 0xff,
+            0x7f, 0xff
+        ]);
+        let rgn = Region::read(&mut rdr).unwrap();
+        assert_eq!(rdr.pos(), 36);
+        assert!(!rgn.is_rect());
+        assert!(rgn.contains(0, 0));
+        assert!(rgn.contains(1, 1));
+        assert!(!rgn.contains(2, 0));
+        assert!(rgn.contains(3, 3));
+        assert!(!rgn.contains(4, 3));
+        assert!(Region::from_rect(Rect::new(0, 0, 2, 2)).contains(1, 1));
+    }
+}